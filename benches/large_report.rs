@@ -0,0 +1,122 @@
+//! Measures the memory and serialisation time of a report with 100k findings, like a large
+//! monorepo produces, both as a `Report` and with its strings interned in a `CompactReport`.
+//! Run with `cargo bench --bench large_report`.
+use doc_panic_checker::ast_walker::FindingKind;
+use doc_panic_checker::report::{CompactReport, FileReport, Finding, Report};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+const FILES: usize = 1_000;
+const FINDINGS_PER_FILE: usize = 100;
+
+/// Builds the report the way an analysis does, pushing findings and formatting their strings
+fn synthetic_report() -> Report {
+    let files = (0..FILES)
+        .map(|file| {
+            let mut findings = vec![];
+            for i in 0..FINDINGS_PER_FILE {
+                let mut ident = String::new();
+                ident.push_str(&format!("module_{}::nested::", file % 20));
+                ident.push_str(&format!("Type{} < T > :: method_{}", file, i));
+                findings.push(Finding {
+                    ident,
+                    kind: FindingKind::UndocumentedPanic,
+                    start_line: i * 10 + 1,
+                    end_line: i * 10 + 8,
+                    panic_line: i * 10 + 4,
+                    context: vec![
+                        format!("    let value_{} = input.get({});", i, i),
+                        format!("    let parsed = value_{}.parse().unwrap();", i),
+                        "    parsed".to_string(),
+                    ],
+                    underline: Some(17..43),
+                    ..Default::default()
+                });
+            }
+            FileReport {
+                path: PathBuf::from(format!("src/module_{}/file_{}.rs", file % 20, file)),
+                symlink_target: None,
+                findings,
+            }
+        })
+        .collect();
+    Report::new(files)
+}
+
+/// Counts the bytes currently allocated so the report's heap can be measured, whichever way its
+/// strings are stored
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocated() -> f64 {
+    ALLOCATED.load(Ordering::Relaxed) as f64 / 1e6
+}
+
+fn time<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = f();
+    println!(
+        "{:<32} {:>8.1}ms",
+        name,
+        start.elapsed().as_secs_f64() * 1000.0
+    );
+    result
+}
+
+fn main() -> io::Result<()> {
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    let heap = || allocated() - baseline as f64 / 1e6;
+    let mut report = time("build", synthetic_report);
+    let built = heap();
+    time("shrink_to_fit", || report.shrink_to_fit());
+    let shrunk = heap();
+    let compact = time("compact", || CompactReport::from(report.clone()));
+    drop(report);
+    println!(
+        "{:<32} {:>8.1}MB -> {:.1}MB, {:.1}MB compacted",
+        "heap",
+        built,
+        shrunk,
+        heap()
+    );
+
+    let path = std::env::temp_dir().join(format!(
+        "doc_panic_checker_large_report-{}.json",
+        std::process::id()
+    ));
+    time("json, unbuffered", || {
+        serde_json::to_writer_pretty(File::create(&path)?, &compact).map_err(io::Error::from)
+    })?;
+    time("json, buffered", || {
+        let mut writer = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer_pretty(&mut writer, &compact)?;
+        writer.flush()
+    })?;
+    let report = time("expand", || compact.into_report());
+    time("json, buffered, expanded", || {
+        let mut writer = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer_pretty(&mut writer, &report)?;
+        writer.flush()
+    })?;
+    std::fs::remove_file(&path)
+}
@@ -0,0 +1,79 @@
+//! Records details of the build which `doc_panic_checker info` prints for bug reports
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let manifest_dir = Path::new(&manifest_dir);
+
+    println!(
+        "cargo:rustc-env=DOC_PANIC_CHECKER_GIT_HASH={}",
+        git_hash(manifest_dir).unwrap_or_else(|| "unknown".to_string())
+    );
+    println!(
+        "cargo:rustc-env=DOC_PANIC_CHECKER_TARGET={}",
+        env::var("TARGET").unwrap_or_default()
+    );
+
+    // The lock file is in the workspace root, which may be above the package
+    let lock = manifest_dir
+        .ancestors()
+        .map(|x| x.join("Cargo.lock"))
+        .find(|x| x.is_file());
+    let locked = lock
+        .as_ref()
+        .and_then(|x| fs::read_to_string(x).ok())
+        .unwrap_or_default();
+    for (package, var) in &[("syn", "SYN"), ("proc-macro2", "PROC_MACRO2")] {
+        println!(
+            "cargo:rustc-env=DOC_PANIC_CHECKER_{}_VERSION={}",
+            var,
+            locked_version(&locked, package).unwrap_or("unknown")
+        );
+    }
+
+    // A path which doesn't exist would rerun the script on every build
+    let mut watched = vec![manifest_dir.join("build.rs")];
+    watched.extend(lock);
+    let head = manifest_dir.join(".git").join("HEAD");
+    if let Some(branch) = fs::read_to_string(&head)
+        .ok()
+        .and_then(|x| x.trim().strip_prefix("ref: ").map(String::from))
+    {
+        watched.push(manifest_dir.join(".git").join(branch));
+    }
+    watched.push(head);
+    for path in watched.iter().filter(|x| x.exists()) {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+}
+
+/// The commit being built, from git or from the `.cargo_vcs_info.json` cargo writes into a
+/// published crate
+fn git_hash(manifest_dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .current_dir(manifest_dir)
+        .output()
+        .ok()
+        .filter(|x| x.status.success());
+    if let Some(hash) = output.and_then(|x| String::from_utf8(x.stdout).ok()) {
+        return Some(hash.trim().to_string()).filter(|x| !x.is_empty());
+    }
+    let vcs_info = fs::read_to_string(manifest_dir.join(".cargo_vcs_info.json")).ok()?;
+    let sha1 = vcs_info.split("\"sha1\"").nth(1)?;
+    let hash = sha1.split('"').nth(1)?;
+    Some(hash.chars().take(7).collect())
+}
+
+/// The version of a package in a `Cargo.lock`, the first one if there are several
+fn locked_version<'a>(locked: &'a str, package: &str) -> Option<&'a str> {
+    let name = format!("name = \"{}\"", package);
+    let mut lines = locked.lines().skip_while(|x| *x != name).skip(1);
+    lines
+        .next()?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')
+}
@@ -0,0 +1,23 @@
+//! Using the checker from another tool, e.g. a lint driver which reports the findings as its own
+//! diagnostics. Run with `cargo run --example embed --no-default-features`.
+use doc_panic_checker::ast_walker::Options;
+use doc_panic_checker::findings_for_source;
+use std::path::Path;
+
+const SOURCE: &str = r#"
+/// Parses the port from the config
+pub fn port(config: &str) -> u16 {
+    config.trim().parse().unwrap()
+}
+"#;
+
+fn main() {
+    let findings = findings_for_source(SOURCE, Path::new("src/config.rs"), &Options::default());
+    for finding in &findings {
+        println!(
+            "src/config.rs:{}: `{}` can panic without documenting it",
+            finding.panic_line, finding.ident
+        );
+    }
+    assert_eq!(findings.len(), 1);
+}
@@ -1,42 +1,671 @@
+use crate::density::count_public_items;
+use crate::doc_sections::{misplaced_section, prose_lines, section_body};
+use crate::imports::ImportMap;
+use crate::panic_visitor::{
+    cfg_predicates, dyn_dispatch_calls, find_panics, is_delegation, is_placeholder, type_name,
+    Conversion, Helpers, PanicSite, CHANNEL_SEND, COLLECT_PANIC, DEFERRED_CLOSURE,
+    DYNAMIC_DISPATCH, ENV_VAR, FILE_IO, FOREIGN_NON_EXHAUSTIVE, HELD_ACROSS_AWAIT, IN_UNSAFE_BLOCK,
+    MUTEX_POISON, NEAR_UNSAFE, OPAQUE_FALLBACK, OPTION_LOOKUP, PRECONDITIONS, SORT_CLOSURE_PANIC,
+    STD_PRECONDITION, UTF8_DECODE, WILDCARD_MATCH_ARM, WRITE_UNWRAP,
+};
+use crate::report::{Applicability, Suggestion};
+use crate::snippets::{extract, LineColRange, Snippet};
+use crate::surface::Surface;
 use proc_macro2::Span;
 use quote::ToTokens;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt;
 use std::fs::File;
 use std::io::{self, Read};
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
 use syn::spanned::Spanned;
 use syn::*;
+use tracing::debug;
+
+pub use crate::panic_visitor::{
+    Lookup, PanicKind, Precondition, DEFERRED_ITERATOR, LOOKUPS, STD_PANICS,
+};
+
+/// Tag for items marked `#[track_caller]`, whose panics are reported at the caller's location
+pub const TRACK_CALLER: &str = "track-caller";
+/// Tag for functions whose body only forwards their parameters to another call
+pub const DELEGATION: &str = "delegation";
+/// Tag for a `todo!` or `unimplemented!` in a function with other logic, an unfinished branch
+/// rather than a placeholder for the whole function
+pub const UNFINISHED_BRANCH: &str = "unfinished-branch";
+/// Tag for functions which only panic when some `cfg`, such as a feature, is enabled or disabled
+pub const CFG_GATED: &str = "cfg-gated";
+/// Tag for types whose const generic parameter defaults can panic, which fails compilation for
+/// users relying on the default
+pub const CONST_GENERIC_DEFAULT: &str = "const-generic-default";
+/// Tag for `extern "C"` functions which can panic, unwinding out of them is undefined behaviour
+pub const FFI_UB: &str = "FFI UB";
+/// Attributes recorded on findings to help triage them. `non_exhaustive` is recorded for methods
+/// of a type marked with it in the same file.
+pub const TRIAGE_ATTRIBUTES: &[&str] = &[
+    "must_use",
+    "inline",
+    "non_exhaustive",
+    "track_caller",
+    "deprecated",
+];
+/// Tag for items which can end the process with `process::exit` or `process::abort`
+pub const PROCESS_EXIT: &str = "PROCESS EXIT";
+
+/// The note on a `PanicInFallibleFn`
+const FALLIBLE_NOTE: &str =
+    "(returns a `Result`, so callers already handle errors, return an `Err` instead of panicking)";
 
 #[derive(Clone)]
 pub struct AstWalker {
     filename: PathBuf,
     source_code: String,
+    options: Options,
+}
+
+/// Settings for which checks the `AstWalker` runs
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Flag public functions taking closures or function pointers which don't document that
+    /// panics in them propagate to the caller
+    pub check_callback_propagation: bool,
+    /// Don't accept a trait's own documentation as documenting panics in its default methods
+    pub ignore_trait_docs: bool,
+    /// The file is the root of a binary target so its `fn main` is the program entry point
+    pub binary_root: bool,
+    /// Check `fn main` in binary roots, whether or not it's public
+    pub check_binaries: bool,
+    /// Report panics in the `fn main` of binary roots like any other finding rather than as
+    /// `EntryPoint` findings
+    pub strict_main: bool,
+    /// Only check items reachable from outside the crate, reporting them by their shortest
+    /// public path
+    pub surface: Option<Arc<Surface>>,
+    /// Tag panics about being on the wrong thread in types which manually implement `Send`
+    pub check_send_wrappers: bool,
+    /// Check `Deref` and `DerefMut` impls, which are called implicitly by auto-deref
+    pub check_deref: bool,
+    /// Check `Clone` impls, which shouldn't panic
+    pub check_clone: bool,
+    /// Check `Default` impls, which shouldn't panic
+    pub check_default: bool,
+    /// Report `From` impls which can panic as contract violations even if they're documented, as
+    /// conversions should be infallible or use `TryFrom`
+    pub check_type_conversions: bool,
+    /// Report `TryFrom` impls which can panic as contract violations even if they're documented,
+    /// as they should return an error instead
+    pub check_try_from: bool,
+    /// Report panics in functions returning a `Result`, and in `FromStr` and `TryFrom` impls, as
+    /// `PanicInFallibleFn` even if they're documented
+    pub check_fallible_fns: bool,
+    /// Check `io::Write` impls, which should return errors rather than panic
+    pub check_io_write: bool,
+    /// Check `io::Read` impls, which should return errors rather than panic
+    pub check_io_read: bool,
+    /// The crate is `#![no_std]`, so findings note that a panic halts or resets the target
+    pub no_std: bool,
+    /// The release profile sets `panic = "abort"`, so findings note that a panic can't be caught
+    pub panic_abort: bool,
+    /// Check `Index` and `IndexMut` impls, which are called implicitly by indexing
+    pub check_index: bool,
+    /// Never report `Index` and `IndexMut` impls, even with `check_index` or as blanket impls
+    pub ignore_index_impls: bool,
+    /// Check `IntoIterator` impls, which are called implicitly by `for` loops
+    pub check_into_iter: bool,
+    /// Check `Add`, `Sub`, `Mul`, `Div` and `Rem` impls, which are called implicitly by the
+    /// arithmetic operators
+    pub check_operators: bool,
+    /// Tag unwrapping the result of `write!` and `writeln!`, suggesting the error is returned
+    pub check_format_writes: bool,
+    /// Tag unwrapping the result of sending on a channel, which panics once the receiver is
+    /// dropped
+    pub check_channel_sends: bool,
+    /// Tag unwrapping the result of locking a `Mutex` or `RwLock`, which panics if another thread
+    /// panicked while holding the lock
+    pub check_lock_poison: bool,
+    /// Tag unwrapping the result of `std::env::var`, which panics when the variable isn't set
+    pub check_env_vars: bool,
+    /// Tag unwrapping the result of `File::open`, `fs::read` or `fs::read_to_string`, which
+    /// panics when the file is missing or can't be read
+    pub check_file_io: bool,
+    /// Tag unwrapping inside the `map` closure of an iterator chain which is collected into a
+    /// plain collection, like `.map(|x| x.parse().unwrap()).collect::<Vec<_>>()`
+    pub check_iterator_collect: bool,
+    /// Tag panics inside the closure given to `sort_by`, `sort_by_key` or their unstable
+    /// versions
+    pub check_sort_keys: bool,
+    /// The order the documentation sections like `# Panics` should come in, after the summary.
+    /// Items with sections out of this order are reported, empty to not check it.
+    pub doc_section_order: Vec<String>,
+    /// Tag unwrapping the `Option` from a lookup like `get`, `first` or `next`, with a hint for
+    /// what to use instead
+    pub check_option_lookups: bool,
+    /// Note when a `const fn` can panic, which only happens at runtime as a panic in a const
+    /// context fails to compile
+    pub check_const_fn: bool,
+    /// Note when a safe function panics inside one of its `unsafe` blocks
+    pub check_unsafe_blocks: bool,
+    /// Count calling a method on a `dyn Trait` parameter or local as a panic if the trait has a
+    /// default method in the same file which can panic
+    pub check_dynamic_dispatch: bool,
+    /// Count calls to std methods which panic unless their arguments meet a precondition, like
+    /// `split_at` or `copy_from_slice`, as panics
+    pub check_std_panics: bool,
+    /// The entries of `STD_PANICS` not to count with `check_std_panics`, e.g. because the crate
+    /// has its own type with a `split_at` method
+    pub ignore_std_panics: Vec<String>,
+    /// Check the defaults of public types' const generic parameters, like
+    /// `struct Buf<const N: usize = { size() }>`, for calls to `const fn`s in the same file which
+    /// can panic
+    pub check_const_generic_defaults: bool,
+    /// Note the `#[cfg]` predicates enabling the panics behind a cfg on the item or the
+    /// statements around them, like `cfg(not(feature = "safe-mode"))`
+    pub check_cfg_features: bool,
+    /// Count passing a function as the fallback of `unwrap_or_else` or similar as a panic, and
+    /// tag `todo!` and `unimplemented!` in functions with other logic as unfinished branches
+    pub pedantic: bool,
+    /// The file is `cargo expand` output, so the impls derive macros leave behind are skipped as
+    /// the user didn't write them
+    pub expanded_source: bool,
+    /// Report `todo!` and `unimplemented!` in public items as release blockers, whatever their
+    /// docs say
+    pub release_blockers: bool,
+    /// Check `Hash` impls, which are called implicitly by hash based collections
+    pub check_hash: bool,
+    /// Check `PartialOrd` and `Ord` impls, which are called implicitly when sorting and comparing
+    pub check_ord: bool,
+    /// Check serde `Serialize` and `Deserialize` impls, which should return errors not panic
+    pub check_serde: bool,
+    /// Check `Display` impls of types which implement `Error`, where a panic hides the error
+    pub check_display_error: bool,
+    /// Check blanket trait impls like `impl<T: Bound> Trait for T`
+    pub check_blanket_impls: bool,
+    /// Report `# Panics` sections with fewer lines of explanation than expected
+    pub require_panic_details: bool,
+    /// The fewest lines of explanation a `# Panics` section should have, always at least one
+    pub min_panic_doc_lines: usize,
+    /// Lines of explanation expected for each different kind of panic in the item
+    pub panic_doc_lines_per_kind: usize,
+    /// Don't count `process::exit` and `process::abort` as panics
+    pub ignore_process_exit: bool,
+    /// Don't count unwrapping the result of locking a `Mutex` or `RwLock` as a panic
+    pub ignore_mutex_poison: bool,
+    /// Don't count unwrapping the result of these lookups, like `get` or `first`, as a panic
+    pub ignore_lookups: Vec<Lookup>,
+    /// Check `extern "C"` functions, whatever their visibility, as a panic unwinding out of them
+    /// is undefined behaviour
+    pub check_ffi: bool,
+    /// Check the top level `fn main` of every file, not only binary roots, suggesting it returns
+    /// a `Result` instead of panicking
+    pub check_main: bool,
+    /// Also report items which document their panics, as `DocumentedPanic` notes, for tools which
+    /// need every panicking item rather than only the problems
+    pub report_documented: bool,
+    /// Name the type built by free constructor functions like `fn new_foo() -> Foo`, as unlike
+    /// `Foo::new` their name alone doesn't say
+    pub check_struct_new: bool,
+    /// Count calls to private functions and methods in the same file which can panic as panics
+    /// in the caller
+    pub follow_private_calls: bool,
+    /// The edition the source is written in, for syntax which depends on it
+    pub edition: Edition,
+    /// Accept a module's own docs, like the `//!` comments at the top of a crate root, as
+    /// documenting panics in the items directly inside it
+    pub inherit_module_docs: bool,
+    /// Parse every file and check every item, even if their source has nothing which looks like
+    /// a panic
+    pub no_prefilter: bool,
+    /// Paths of functions from other crates which count as panics, like a fatal error helper,
+    /// matched by their last segments
+    pub panicking_fns: Vec<String>,
+    /// Paths of functions which never count as panics, even built in ones like
+    /// `std::process::exit`. These take precedence over `panicking_fns`.
+    pub allowed_fns: Vec<String>,
+}
+
+impl Options {
+    /// Whether files and items are skipped without checking them if their source has nothing
+    /// which looks like a panic. This misses things the text search can't see, like calls to
+    /// `panicking_fns`, so it's off when asked, when there are panicking functions or when
+    /// checking something other than the items' own panics.
+    pub fn prefilter(&self) -> bool {
+        !(self.no_prefilter
+            || self.check_callback_propagation
+            || self.surface.is_some()
+            || !self.panicking_fns.is_empty()
+            || !self.doc_section_order.is_empty()
+            || self.check_std_panics
+            || self.pedantic)
+    }
+}
+
+/// The names of the editions `Edition` can be parsed from
+pub const EDITIONS: &[&str] = &["2015", "2018", "2021", "2024"];
+
+/// A Rust edition
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Edition {
+    E2015,
+    E2018,
+    #[default]
+    E2021,
+    E2024,
+}
+
+impl FromStr for Edition {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "2015" => Ok(Edition::E2015),
+            "2018" => Ok(Edition::E2018),
+            "2021" => Ok(Edition::E2021),
+            "2024" => Ok(Edition::E2024),
+            _ => Err(format!(
+                "expected one of {}, got `{}`",
+                EDITIONS.join(", "),
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A trait whose methods are called implicitly by the language or std, and how to report panics
+/// in them
+struct ImplicitTrait {
+    methods: &'static [&'static str],
+    /// `ImplicitCall` if documenting the panics on the impl is enough, `ContractViolation` if
+    /// they shouldn't panic at all
+    kind: FindingKind,
+    tag: Option<&'static str>,
+    note: &'static str,
+    /// Count `unreachable!` as well, for methods which shouldn't panic even on a broken invariant
+    unreachable: bool,
+}
+
+/// Facts about the whole file which affect how individual items are checked
+#[derive(Default)]
+struct FileIndex {
+    /// Names of the types with an `unsafe impl Send`
+    send_types: HashSet<String>,
+    /// Names of the types with an `impl Error`
+    error_types: HashSet<String>,
+    /// Names of the types marked `#[non_exhaustive]`
+    non_exhaustive_types: HashSet<String>,
+    /// Names of the enums defined in the file
+    enums: HashSet<String>,
+    imports: ImportMap,
+    /// Names panicking macros and functions were imported as, which the textual checks for
+    /// panics need to look for as well
+    panic_renames: Vec<String>,
+    /// Private functions which can panic, as `name` or `Type::name` for methods, only filled in
+    /// when following private calls
+    panicking_helpers: HashSet<String>,
+    /// The `From` and `TryFrom` impls and whether each can panic, only filled in when following
+    /// private calls
+    conversions: HashMap<Conversion, bool>,
+    /// Names of the traits with a default method which can panic, only filled in when checking
+    /// dynamic dispatch
+    panicking_traits: HashSet<String>,
+    /// `const fn`s which can panic whatever their visibility, as `name` or `Type::name` for
+    /// methods, only filled in when checking const generic defaults
+    panicking_const_fns: HashSet<String>,
+}
+
+impl FileIndex {
+    fn new(items: &[Item]) -> Self {
+        let imports = ImportMap::new(items);
+        let mut index = Self {
+            panic_renames: imports.renames_of(&[
+                "panic",
+                "panic_any",
+                "todo",
+                "unimplemented",
+                "exit",
+                "abort",
+                "unreachable",
+            ]),
+            imports,
+            ..Default::default()
+        };
+        index.add_items(items);
+        index
+    }
+
+    fn add_items(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Mod(m) => {
+                    if let Some((_, items)) = &m.content {
+                        self.add_items(items);
+                    }
+                }
+                Item::Impl(i) if i.unsafety.is_some() => {
+                    let is_send = i
+                        .trait_
+                        .as_ref()
+                        .and_then(|(_, path, _)| path.segments.last())
+                        .map(|x| x.ident == "Send")
+                        .unwrap_or(false);
+                    if is_send {
+                        self.send_types.extend(type_name(&i.self_ty));
+                    }
+                }
+                Item::Impl(i) if i.trait_.as_ref().is_some_and(|(_, p, _)| is_error_trait(p)) => {
+                    self.error_types.extend(type_name(&i.self_ty));
+                }
+                Item::Struct(s) if is_non_exhaustive(&s.attrs) => {
+                    self.non_exhaustive_types.insert(s.ident.to_string());
+                }
+                Item::Enum(e) => {
+                    if is_non_exhaustive(&e.attrs) {
+                        self.non_exhaustive_types.insert(e.ident.to_string());
+                    }
+                    self.enums.insert(e.ident.to_string());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Why an item was reported
+#[derive(Copy, Clone, Debug, Default, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingKind {
+    /// The item contains something which can panic and the docs don't mention it
+    #[default]
+    UndocumentedPanic,
+    /// The item calls user provided code and doesn't document that panics propagate
+    CallbackPropagation,
+    /// A trait method which the language calls implicitly can panic and the impl doesn't
+    /// document it
+    ImplicitCall,
+    /// A trait method can panic when the trait's contract says it shouldn't, documenting it
+    /// doesn't help
+    ContractViolation,
+    /// The `fn main` of a binary or example can panic, which is usually deliberate
+    EntryPoint,
+    /// The item has a `# Panics` section but it doesn't say much
+    ThinPanicDocs,
+    /// The item documents that it can panic, reported when auditing docs alone
+    DocumentedPanic,
+    /// The item's name suggests it can panic but it has no `# Panics` section, reported when
+    /// auditing docs alone
+    UndocumentedRisk,
+    /// An `extern "C"` function can panic, unwinding out of it is undefined behaviour so
+    /// documenting it doesn't help
+    ForeignUnwind,
+    /// The item contains `todo!` or `unimplemented!`, which shouldn't be released whatever the
+    /// docs say
+    ReleaseBlocker,
+    /// A wildcard match arm panics on an enum which looks like it's from another crate, so it's
+    /// probably `#[non_exhaustive]` and the panic fires when that crate adds a variant
+    ForeignNonExhaustive,
+    /// The item calls a function the user declared as panicking without documenting it
+    ConfiguredPanicFn,
+    /// The sections of the item's docs, like `# Panics`, aren't in the configured order
+    DocSectionOrder,
+    /// A function returning a `Result` can panic, it should return an error instead so
+    /// documenting the panic doesn't help
+    PanicInFallibleFn,
+}
+
+impl FindingKind {
+    /// Stable identifier for the kind of finding, used as the rule id in SARIF output
+    pub fn code(&self) -> &'static str {
+        match self {
+            FindingKind::UndocumentedPanic => "undocumented-panic",
+            FindingKind::CallbackPropagation => "callback-propagation",
+            FindingKind::ImplicitCall => "implicit-call",
+            FindingKind::ContractViolation => "contract-violation",
+            FindingKind::EntryPoint => "entry-point",
+            FindingKind::ThinPanicDocs => "thin-panic-docs",
+            FindingKind::DocumentedPanic => "documented-panic",
+            FindingKind::UndocumentedRisk => "undocumented-risk",
+            FindingKind::ForeignUnwind => "foreign-unwind",
+            FindingKind::ReleaseBlocker => "release-blocker",
+            FindingKind::ForeignNonExhaustive => "foreign-non-exhaustive",
+            FindingKind::ConfiguredPanicFn => "configured-panic-fn",
+            FindingKind::DocSectionOrder => "doc-section-order",
+            FindingKind::PanicInFallibleFn => "panic-in-fallible-fn",
+        }
+    }
+
+    /// How serious the finding is, using SARIF's names for the levels
+    pub fn level(&self) -> &'static str {
+        match self {
+            FindingKind::ImplicitCall
+            | FindingKind::ContractViolation
+            | FindingKind::ForeignUnwind
+            | FindingKind::ReleaseBlocker
+            | FindingKind::ForeignNonExhaustive => "error",
+            FindingKind::EntryPoint | FindingKind::DocumentedPanic => "note",
+            _ => "warning",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            FindingKind::UndocumentedPanic => {
+                "Public item can panic without a \"Panics\" section in its documentation"
+            }
+            FindingKind::CallbackPropagation => {
+                "Public item takes a callback without documenting that panics in it propagate"
+            }
+            FindingKind::ImplicitCall => {
+                "Implicitly called trait method can panic without its impl documenting it"
+            }
+            FindingKind::ContractViolation => {
+                "Trait method can panic although the trait's contract says it shouldn't"
+            }
+            FindingKind::EntryPoint => "Entry point of a binary or example can panic",
+            FindingKind::ThinPanicDocs => {
+                "The \"Panics\" section of the documentation doesn't explain each way it can panic"
+            }
+            FindingKind::DocumentedPanic => "Public item documents that it can panic",
+            FindingKind::UndocumentedRisk => {
+                "Public item's name suggests it can panic but it has no \"Panics\" section"
+            }
+            FindingKind::ForeignUnwind => {
+                "Function called over FFI can panic, unwinding out of it is undefined behaviour"
+            }
+            FindingKind::ReleaseBlocker => {
+                "Public item contains `todo!` or `unimplemented!` which shouldn't be released"
+            }
+            FindingKind::ForeignNonExhaustive => {
+                "Wildcard match arm panics on an enum from another crate, which probably fires \
+                 when the crate adds a variant"
+            }
+            FindingKind::ConfiguredPanicFn => {
+                "Public item calls a function declared as panicking without a \"Panics\" section \
+                 in its documentation"
+            }
+            FindingKind::DocSectionOrder => {
+                "Public item's documentation sections aren't in the configured order"
+            }
+            FindingKind::PanicInFallibleFn => {
+                "Function returning a `Result` can panic where it could return an error"
+            }
+        }
+    }
 }
 
+#[derive(Clone)]
 pub struct PanicLocation {
     ident: String,
     span: Span,
+    /// Span of the first expression which can panic, or the item if there isn't one
+    panic_span: Span,
+    /// The line before, of and after the start of `panic_span`
+    context: Snippet,
+    kind: FindingKind,
+    /// How many expressions directly in the item can panic
+    panic_sites: usize,
+    /// The different kinds of those expressions, in the order they're first found
+    panic_kinds: Vec<PanicKind>,
+    /// Attributes on the item which help triage it, see `TRIAGE_ATTRIBUTES`
+    attributes: Vec<String>,
+    tags: Vec<String>,
+    notes: Vec<String>,
+    /// What to do about the first panic, from its kind
+    fix_hint: Option<String>,
+    /// Adds a stub `# Panics` section to the item's docs
+    suggestion: Option<Suggestion>,
 }
 
-impl fmt::Display for PanicLocation {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "{} {}:{}",
-            self.ident.replace(" ", ""),
-            self.span.start().line,
-            self.span.end().line
-        )
+impl PanicLocation {
+    /// The fully qualified name of the item which can panic
+    pub fn ident(&self) -> &str {
+        &self.ident
+    }
+
+    /// The first and last line of the item which can panic
+    pub fn lines(&self) -> (usize, usize) {
+        (self.span.start().line, self.span.end().line)
+    }
+
+    /// The line the first panic is on
+    pub fn panic_line(&self) -> usize {
+        self.panic_span.start().line
+    }
+
+    /// The source code around the panic line, with the first panic underlined
+    pub fn context(&self) -> &Snippet {
+        &self.context
+    }
+
+    pub fn kind(&self) -> FindingKind {
+        self.kind
+    }
+
+    /// How many expressions directly in the item can panic, this is 0 for callback propagation
+    pub fn panic_sites(&self) -> usize {
+        self.panic_sites
+    }
+
+    /// The different ways the item can panic
+    pub fn panic_kinds(&self) -> &[PanicKind] {
+        &self.panic_kinds
+    }
+
+    /// Attributes on the item which help triage it, like `must_use`
+    pub fn attributes(&self) -> &[String] {
+        &self.attributes
+    }
+
+    /// Short machine readable labels describing the context of the panics
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Explanations to help the user understand the finding
+    pub fn notes(&self) -> &[String] {
+        &self.notes
+    }
+
+    /// What to do about the first panic, like replacing an `unwrap` with `?`
+    pub fn fix_hint(&self) -> Option<&str> {
+        self.fix_hint.as_deref()
+    }
+
+    pub fn suggestion(&self) -> Option<&Suggestion> {
+        self.suggestion.as_ref()
     }
 }
 
+const PANICKY_WORDS: &[&str] = &[
+    "panic",
+    "unwrap",
+    "expect",
+    "todo",
+    "unimplemented",
+    "exit",
+    "abort",
+    "unreachable",
+];
+
 fn contains_panicky_words(source_code: &str) -> bool {
-    let panicky_words = &["panic", "unwrap", "expect", "todo", "unimplemented"];
+    contains_any(source_code, PANICKY_WORDS)
+}
+
+fn contains_any(source_code: &str, panicky_words: &[impl AsRef<str>]) -> bool {
     source_code
         .lines()
         .map(|x| x.trim_start())
         .filter(|trimmed| !trimmed.starts_with("///") || !trimmed.starts_with("//"))
-        .any(|x| panicky_words.iter().any(|panik| x.contains(panik)))
+        .any(|x| panicky_words.iter().any(|panik| x.contains(panik.as_ref())))
+}
+
+/// The name of a module in idents. Sibling modules with the same name, usually alternatives
+/// behind different `#[cfg]`s, are told apart by their cfg or else their position, like
+/// `imp[cfg(unix)]` or `imp[2]`.
+fn module_name(module: &ItemMod, items: &[Item]) -> String {
+    let siblings = items
+        .iter()
+        .filter_map(|x| match x {
+            Item::Mod(m) if m.ident == module.ident => Some(m),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if siblings.len() < 2 {
+        return module.ident.to_string();
+    }
+    let cfg = module
+        .attrs
+        .iter()
+        .find(|x| x.path.is_ident("cfg"))
+        .map(|x| x.tokens.to_string().split_whitespace().collect::<String>());
+    match cfg {
+        Some(cfg) => format!("{}[cfg{}]", module.ident, cfg),
+        None => {
+            let position = siblings
+                .iter()
+                .position(|x| std::ptr::eq(*x, module))
+                .unwrap_or_default();
+            format!("{}[{}]", module.ident, position + 1)
+        }
+    }
+}
+
+/// The text of the inner doc attributes, like `//!` comments, lowercased like
+/// `AstWalker::find_doc_comment`
+fn inner_docs(attrs: &[Attribute]) -> String {
+    attrs
+        .iter()
+        .filter(|x| matches!(x.style, AttrStyle::Inner(_)) && x.path.is_ident("doc"))
+        .filter_map(|x| match x.parse_meta() {
+            Ok(Meta::NameValue(MetaNameValue {
+                lit: Lit::Str(s), ..
+            })) => Some(s.value()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .to_lowercase()
+}
+
+fn mentions_no_std(token: &proc_macro2::TokenTree) -> bool {
+    match token {
+        proc_macro2::TokenTree::Ident(x) => x == "no_std",
+        proc_macro2::TokenTree::Group(x) => x.stream().into_iter().any(|x| mentions_no_std(&x)),
+        _ => false,
+    }
 }
 
 fn warns_about_panics(comment: &str) -> bool {
@@ -53,44 +682,216 @@ impl AstWalker {
         Ok(Self::new_with_source(filename, source_code))
     }
 
-    fn new_with_source(filename: PathBuf, source_code: String) -> Self {
+    /// Walks source which has already been read, like generated code or a file from an archive.
+    /// `filename` is only used to describe the findings
+    pub fn new_with_source(filename: PathBuf, source_code: impl Into<String>) -> Self {
         Self {
             filename,
-            source_code,
+            source_code: source_code.into(),
+            options: Options::default(),
         }
     }
 
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn source_code(&self) -> &str {
+        &self.source_code
+    }
+
+    /// Finds all the panic locations in the file, a file which can't be parsed has none
     pub fn process(&self) -> Vec<PanicLocation> {
         let mut result = vec![];
-        if contains_panicky_words(&self.source_code) {
-            if let Ok(file) = parse_file(&self.source_code) {
-                self.process_items(&file.items, None, &mut result);
+        // Already logged
+        let _ = self.process_with(|x| result.push(x));
+        result
+    }
+
+    /// Calls `on_violation` with each panic location as soon as it's found, returning the number
+    /// of public items checked. Fails if the file can't be parsed, files without anything which
+    /// could panic are only parsed to count their items and aren't an error if that fails.
+    pub fn process_with(&self, mut on_violation: impl FnMut(PanicLocation)) -> Result<usize> {
+        if self.options.prefilter() && !contains_panicky_words(&self.source_code) {
+            let file = parse_file(&self.source_code);
+            return Ok(file.map_or(0, |x| count_public_items(&x.items)));
+        }
+        match parse_file(&self.source_code) {
+            Ok(file) => {
+                let mut index = FileIndex::new(&file.items);
+                if self.options.follow_private_calls {
+                    self.add_panicking_helpers(&file.items, &mut index);
+                }
+                if self.options.check_dynamic_dispatch {
+                    self.add_panicking_traits(&file.items, &mut index);
+                }
+                if self.options.check_const_generic_defaults {
+                    add_panicking_const_fns(&file.items, &mut index);
+                }
+                let on_violation = &mut |mut location: PanicLocation| {
+                    if location.kind != FindingKind::DocumentedPanic && location.fix_hint.is_none()
+                    {
+                        location.fix_hint = location
+                            .panic_kinds
+                            .first()
+                            .and_then(|x| x.fix_hint())
+                            .map(String::from);
+                    }
+                    on_violation(self.note_panic_strategy(location))
+                };
+                self.process_items(&file.items, &file.attrs, None, &index, on_violation);
+                Ok(count_public_items(&file.items))
+            }
+            Err(e) => {
+                debug!(
+                    "Unable to parse {} as edition {}: {}",
+                    self.filename.display(),
+                    self.options.edition,
+                    e
+                );
+                Err(e)
             }
         }
-        result
     }
 
+    /// Returns true if the file's inner docs, like the `//!` comments of a crate root, mention
+    /// panics. Files which can't be parsed don't.
+    pub fn docs_mention_panics(&self) -> bool {
+        parse_file(&self.source_code)
+            .map(|x| warns_about_panics(&inner_docs(&x.attrs)))
+            .unwrap_or(false)
+    }
+
+    /// Says what a panic does to the running program when it's more than an unwind
+    fn note_panic_strategy(&self, mut location: PanicLocation) -> PanicLocation {
+        if matches!(
+            location.kind,
+            FindingKind::DocumentedPanic
+                | FindingKind::CallbackPropagation
+                | FindingKind::DocSectionOrder
+        ) {
+            return location;
+        }
+        if self.options.no_std {
+            location
+                .notes
+                .push("(panics halt or reset on this no_std target)".to_string());
+        } else if self.options.panic_abort {
+            location
+                .notes
+                .push("(panics abort the process under the release profile)".to_string());
+        }
+        location
+    }
+
+    /// The number of items left behind by derive macros in `cargo expand` output, which are
+    /// skipped with `Options::expanded_source`. Files which can't be parsed have none.
+    pub fn derive_residue(&self) -> usize {
+        fn count(items: &[Item]) -> usize {
+            items
+                .iter()
+                .map(|item| match item {
+                    Item::Mod(m) if is_public(&m.vis) => {
+                        m.content.as_ref().map_or(0, |(_, items)| count(items))
+                    }
+                    _ => is_derive_residue(item) as usize,
+                })
+                .sum()
+        }
+        parse_file(&self.source_code)
+            .map(|x| count(&x.items))
+            .unwrap_or(0)
+    }
+
+    /// Returns true if the file has a `#![no_std]` attribute, including under a `cfg_attr` like
+    /// `#![cfg_attr(not(test), no_std)]`. Files which can't be parsed don't.
+    pub fn is_no_std(&self) -> bool {
+        parse_file(&self.source_code)
+            .map(|x| {
+                x.attrs.iter().any(|attr| {
+                    attr.path.is_ident("no_std")
+                        || (attr.path.is_ident("cfg_attr")
+                            && attr.tokens.clone().into_iter().any(|x| mentions_no_std(&x)))
+                })
+            })
+            .unwrap_or(false)
+    }
+
+    /// Checks the items of a module, `inner_attrs` are the module's own attributes like `//!`
+    /// docs
     fn process_items(
         &self,
         items: &[Item],
+        inner_attrs: &[Attribute],
         namespace: Option<String>,
-        result: &mut Vec<PanicLocation>,
+        index: &FileIndex,
+        on_violation: &mut dyn FnMut(PanicLocation),
     ) {
+        let module_documents_panics =
+            self.options.inherit_module_docs && warns_about_panics(&inner_docs(inner_attrs));
         for item in items.iter() {
-            if !self.span_has_panics(item.span()) {
+            if self.options.prefilter() && !self.span_has_panics(item.span(), &index.panic_renames)
+            {
+                continue;
+            }
+            if let Item::Mod(ref i) = *item {
+                if is_public(&i.vis) {
+                    let name = module_name(i, items);
+                    self.process_module(i, &name, namespace.as_ref(), index, on_violation);
+                }
                 continue;
             }
+            // Only items directly in the module inherit its docs, not those in submodules
+            let on_violation = &mut |location: PanicLocation| {
+                let location = if module_documents_panics {
+                    self.inherit_module_docs(location)
+                } else {
+                    Some(location)
+                };
+                if let Some(location) = location {
+                    on_violation(location);
+                }
+            };
             match *item {
-                Item::Mod(ref i) if is_public(&i.vis) => {
-                    self.process_module(i, namespace.as_ref(), result)
+                _ if self.options.expanded_source && is_derive_residue(item) => {}
+                Item::Fn(ref i) if self.options.check_ffi && is_foreign_abi(&i.sig) => {
+                    self.process_foreign_fn(i, namespace.as_ref(), index, on_violation)
                 }
-                Item::Fn(ref i) if is_public(&i.vis) => {
-                    self.process_fn(i, namespace.as_ref(), result)
+                Item::Fn(ref i) if self.should_check_fn(i, namespace.is_none()) => {
+                    let entry_point = self.is_entry_point(i, namespace.is_none());
+                    self.process_fn(i, namespace.as_ref(), entry_point, index, on_violation)
                 }
                 Item::Trait(ref i) if is_public(&i.vis) => {
-                    self.process_trait(i, namespace.as_ref(), result)
+                    self.process_trait(i, namespace.as_ref(), index, on_violation)
+                }
+                Item::Impl(ref i) => self.process_impl(i, namespace.as_ref(), index, on_violation),
+                Item::Struct(ref i)
+                    if self.options.check_const_generic_defaults && is_public(&i.vis) =>
+                {
+                    let location = self.check_const_defaults(
+                        &i.ident,
+                        &i.generics,
+                        i.span(),
+                        &i.attrs,
+                        namespace.as_ref(),
+                        index,
+                    );
+                    location.into_iter().for_each(&mut *on_violation);
+                }
+                Item::Enum(ref i)
+                    if self.options.check_const_generic_defaults && is_public(&i.vis) =>
+                {
+                    let location = self.check_const_defaults(
+                        &i.ident,
+                        &i.generics,
+                        i.span(),
+                        &i.attrs,
+                        namespace.as_ref(),
+                        index,
+                    );
+                    location.into_iter().for_each(&mut *on_violation);
                 }
-                Item::Impl(ref i) => self.process_impl(i, namespace.as_ref(), result),
                 Item::Macro(ref _i) => {}
                 Item::Macro2(ref i) if is_public(&i.vis) => {}
                 _ => {}
@@ -98,19 +899,37 @@ impl AstWalker {
         }
     }
 
+    /// Public functions are checked, except for the entry point of a binary which is checked
+    /// whether or not it's public if the user asks for it
+    fn should_check_fn(&self, func: &ItemFn, top_level: bool) -> bool {
+        if self.is_entry_point(func, top_level) {
+            self.options.check_binaries || self.options.check_main
+        } else {
+            is_public(&func.vis)
+        }
+    }
+
+    fn is_entry_point(&self, func: &ItemFn, top_level: bool) -> bool {
+        top_level
+            && (self.options.binary_root || self.options.check_main)
+            && func.sig.ident == "main"
+    }
+
     fn process_module(
         &self,
         module: &ItemMod,
+        name: &str,
         namespace: Option<&String>,
-        result: &mut Vec<PanicLocation>,
+        index: &FileIndex,
+        on_violation: &mut dyn FnMut(PanicLocation),
     ) {
         if let Some(items) = &module.content {
             let ident = if let Some(namespace) = namespace {
-                format!("{}::{}", namespace, module.ident)
+                format!("{}::{}", namespace, name)
             } else {
-                format!("{}", module.ident)
+                name.to_string()
             };
-            self.process_items(&items.1, Some(ident), result);
+            self.process_items(&items.1, &module.attrs, Some(ident), index, on_violation);
         }
     }
 
@@ -118,35 +937,658 @@ impl AstWalker {
         &self,
         func: &ItemFn,
         namespace: Option<&String>,
-        result: &mut Vec<PanicLocation>,
+        entry_point: bool,
+        index: &FileIndex,
+        on_violation: &mut dyn FnMut(PanicLocation),
     ) {
-        if !self.span_has_panics(func.block.span()) {
+        let mut sites = self.find_panics(&func.block, index, None, namespace.map(String::as_str));
+        sites.extend(self.dyn_dispatch_sites(&func.sig, &func.block, index));
+        let has_panics = !sites.is_empty();
+        let name = func.sig.ident.to_string();
+        let ident = match self.item_ident(namespace, &name, &name, false) {
+            Some(ident) => ident,
+            None => return,
+        };
+        if let Some(location) = self.check_section_order(&ident, func.span()) {
+            on_violation(location);
+        }
+        if !(has_panics || self.takes_callback(&func.sig)) {
             return;
         }
         let comment = self.find_doc_comment(func.span());
-        let ident = if let Some(namespace) = namespace {
-            format!("{}::{}", namespace, func.sig.ident)
+        let location = if has_panics {
+            self.check_docs(
+                &comment,
+                &ident,
+                func.span(),
+                &func.attrs,
+                &func.sig,
+                &sites,
+            )
+            .map(|mut location| {
+                let entry_point = entry_point && location.kind == FindingKind::UndocumentedPanic;
+                // Unwrapping everything in `main` is normal for examples and small tools so
+                // it's reported at a lower severity unless asked otherwise
+                if entry_point && !self.options.strict_main {
+                    location.kind = FindingKind::EntryPoint;
+                }
+                // Documenting panics in `main` doesn't help anyone, returning the error does
+                if entry_point {
+                    location.notes.push(
+                        "(return `anyhow::Result<()>` from `main` and use `?` instead of \
+                             panicking)"
+                            .to_string(),
+                    );
+                    location.suggestion = self.result_main_suggestion(&func.sig);
+                }
+                location
+            })
+        } else {
+            self.check_callback_docs(&comment, &ident, func.span())
+        };
+        if let Some(mut location) = location {
+            if self.options.check_struct_new {
+                if let Some(ty) = constructed_type(&func.sig) {
+                    location
+                        .notes
+                        .push(format!("(free fn constructing `{}`)", ty));
+                }
+            }
+            let location = self.note_const_fn(location, &func.sig);
+            let location = self.note_unsafe_blocks(location, &func.sig, &sites);
+            let location = self.note_unfinished(location, &func.block, &sites, index);
+            on_violation(tag_delegation(location, &func.sig, &func.block));
+        }
+    }
+
+    /// Checks a function which is called from other languages. Unwinding out of it is undefined
+    /// behaviour so any panic is reported whatever the docs say, unless it's inside a
+    /// `catch_unwind`.
+    fn process_foreign_fn(
+        &self,
+        func: &ItemFn,
+        namespace: Option<&String>,
+        index: &FileIndex,
+        on_violation: &mut dyn FnMut(PanicLocation),
+    ) {
+        let caught = catch_unwind_spans(&func.block);
+        let sites = self
+            .find_panics(&func.block, index, None, namespace.map(String::as_str))
+            .into_iter()
+            .filter(|site| !caught.iter().any(|x| contains_span(*x, site.span)))
+            .collect::<Vec<_>>();
+        let site = match sites.first() {
+            Some(site) => site,
+            None => return,
+        };
+        let ident = match namespace {
+            Some(namespace) => format!("{}::{}", namespace, func.sig.ident),
+            None => func.sig.ident.to_string(),
+        };
+        on_violation(PanicLocation {
+            span: func.span(),
+            panic_span: site.span,
+            context: self.context(site.span),
+            ident,
+            kind: FindingKind::ForeignUnwind,
+            panic_sites: sites.len(),
+            panic_kinds: distinct_kinds(&sites),
+            attributes: triage_attributes(&func.attrs),
+            tags: vec![FFI_UB.to_string()],
+            notes: vec![
+                "(unwinding into the caller is undefined behaviour, wrap the body in \
+                 `std::panic::catch_unwind` or remove the panic)"
+                    .to_string(),
+            ],
+            fix_hint: None,
+            suggestion: None,
+        });
+    }
+
+    /// Creates a finding for an item containing panics unless the docs mention them
+    fn check_docs(
+        &self,
+        comment: &str,
+        ident: &str,
+        span: Span,
+        attrs: &[Attribute],
+        sig: &Signature,
+        sites: &[PanicSite],
+    ) -> Option<PanicLocation> {
+        let blockers = sites
+            .iter()
+            .filter(|x| matches!(x.kind, PanicKind::Todo | PanicKind::Unimplemented))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut location = if self.options.release_blockers && !blockers.is_empty() {
+            // Documenting a `todo!` doesn't make it ready to release, so the docs are ignored
+            let mut location = self.check_doc_comment("", ident, span, attrs, &blockers)?;
+            location.kind = FindingKind::ReleaseBlocker;
+            location.suggestion = None;
+            location.notes.push(
+                "(`todo!` and `unimplemented!` shouldn't be released, documenting them doesn't \
+                 help)"
+                    .to_string(),
+            );
+            location
+        } else if self.options.check_fallible_fns && returns_result(sig) {
+            // The caller is already handling errors, so one more is better than a documented panic
+            let mut location = self.check_doc_comment("", ident, span, attrs, sites)?;
+            location.kind = FindingKind::PanicInFallibleFn;
+            location.suggestion = None;
+            location.notes.push(FALLIBLE_NOTE.to_string());
+            location
         } else {
-            func.sig.ident.to_string()
+            self.check_doc_comment(comment, ident, span, attrs, sites)?
         };
-        self.check_docs(&comment, &ident, func.span(), result);
+        location.attributes = triage_attributes(attrs);
+        Some(location)
     }
 
-    fn check_docs(&self, comment: &str, ident: &str, span: Span, result: &mut Vec<PanicLocation>) {
-        if !warns_about_panics(comment) {
-            result.push(PanicLocation {
-                span: span,
+    fn check_doc_comment(
+        &self,
+        comment: &str,
+        ident: &str,
+        span: Span,
+        attrs: &[Attribute],
+        sites: &[PanicSite],
+    ) -> Option<PanicLocation> {
+        if warns_about_panics(comment) {
+            self.check_panic_details(comment, ident, span, sites)
+                .or_else(|| self.documented_panic(ident, span, sites))
+        } else {
+            let mut tags = vec![];
+            let mut notes = vec![];
+            // Panic helpers are marked `#[track_caller]` so the panic message points at whoever
+            // called them, they still need documenting but are deliberate
+            let track_caller = attrs.iter().any(|x| x.path.is_ident("track_caller"));
+            if track_caller {
+                tags.push(TRACK_CALLER.to_string());
+            }
+            let foreign_enum = sites
+                .iter()
+                .filter(|x| x.tags.contains(&FOREIGN_NON_EXHAUSTIVE))
+                .find_map(|x| x.matched_enum.as_ref());
+            // The tags with a note which doesn't depend on the panics, each added when it's turned
+            // on and any of the panics has it
+            let fixed: &[(&str, bool, &str)] = &[
+                (
+                    DEFERRED_CLOSURE,
+                    true,
+                    "(panics when the returned closure is invoked, not when the function is called)",
+                ),
+                (
+                    DEFERRED_ITERATOR,
+                    true,
+                    "(panics while the caller iterates over the returned iterator, not when the \
+                     function is called)",
+                ),
+                (
+                    PROCESS_EXIT,
+                    true,
+                    "(exits the process without unwinding, callers can't catch or recover from it)",
+                ),
+                (
+                    MUTEX_POISON,
+                    self.options.check_lock_poison,
+                    "(unwrapping a lock panics if another thread panicked while holding it, handle \
+                     the `PoisonError` explicitly)",
+                ),
+                (
+                    WRITE_UNWRAP,
+                    self.options.check_format_writes,
+                    "(unwraps the result of `write!`, return the error with `?` instead)",
+                ),
+                (
+                    CHANNEL_SEND,
+                    self.options.check_channel_sends,
+                    "(unwrapping `send` panics once the receiver is dropped, handle it with \
+                     `if let Err(_) = sender.send(value)` or return the error with `?`)",
+                ),
+                (
+                    ENV_VAR,
+                    self.options.check_env_vars,
+                    "(panics when the environment variable isn't set, fall back to a default with \
+                     `.unwrap_or_else(|_| default)` or return a `Result`)",
+                ),
+                (
+                    FILE_IO,
+                    self.options.check_file_io,
+                    "(panics when the file is missing or can't be read, propagate the error with \
+                     `?` or handle it like `fs::read_to_string(path).unwrap_or_else(|e| \
+                     handle_error(e))`)",
+                ),
+                (
+                    COLLECT_PANIC,
+                    self.options.check_iterator_collect,
+                    "(panics on the first error while collecting, map to a `Result` and collect \
+                     with `.collect::<Result<Vec<_>, _>>()?` instead)",
+                ),
+                (
+                    SORT_CLOSURE_PANIC,
+                    self.options.check_sort_keys,
+                    "(panics inside a sort closure, consider validating the elements before \
+                     sorting or comparing `Result`s with `sort_by`)",
+                ),
+                (
+                    UTF8_DECODE,
+                    true,
+                    "(panics when the bytes aren't valid UTF-8, return the error with \
+                     `String::from_utf8(bytes)?` or replace invalid bytes with \
+                     `String::from_utf8_lossy(&bytes)`)",
+                ),
+                (
+                    DYNAMIC_DISPATCH,
+                    true,
+                    "(may panic: calls a trait object whose trait has a default method which can \
+                     panic, whether it does depends on the implementor)",
+                ),
+                (
+                    OPAQUE_FALLBACK,
+                    true,
+                    "(passes a function as the fallback of `unwrap_or_else` or similar, which \
+                     isn't checked and may panic)",
+                ),
+                (
+                    HELD_ACROSS_AWAIT,
+                    true,
+                    "(a lock guard which can panic is held across an `.await`)",
+                ),
+                (
+                    WILDCARD_MATCH_ARM,
+                    foreign_enum.is_none(),
+                    "(a wildcard match arm panics, list the remaining variants so the compiler \
+                     checks them or return an error instead)",
+                ),
+            ];
+            for &(tag, enabled, note) in fixed {
+                // Process exits are a kind of panic rather than a tag
+                let found = sites.iter().any(|x| {
+                    x.tags.contains(&tag)
+                        || (tag == PROCESS_EXIT && x.kind == PanicKind::ProcessExit)
+                });
+                if enabled && found {
+                    tags.push(tag.to_string());
+                    notes.push(note.to_string());
+                }
+            }
+            let lookups = sites
+                .iter()
+                .filter_map(|x| x.lookup)
+                .collect::<BTreeSet<_>>();
+            if self.options.check_option_lookups && !lookups.is_empty() {
+                tags.push(OPTION_LOOKUP.to_string());
+                notes.extend(lookups.iter().map(|x| x.hint().to_string()));
+            }
+            let preconditions = preconditions(sites);
+            if !preconditions.is_empty() {
+                tags.push(STD_PRECONDITION.to_string());
+                notes.extend(preconditions.iter().map(|x| x.note()));
+            }
+            if self.options.check_cfg_features {
+                let conditions = cfg_conditions(attrs, sites);
+                if !conditions.is_empty() {
+                    tags.push(CFG_GATED.to_string());
+                    notes.extend(conditions.iter().map(|x| format!("({})", x)));
+                }
+            }
+            let mut kind = FindingKind::UndocumentedPanic;
+            if let Some(path) = foreign_enum {
+                kind = FindingKind::ForeignNonExhaustive;
+                tags.push(FOREIGN_NON_EXHAUSTIVE.to_string());
+                notes.push(format!(
+                    "(a wildcard match arm on `{}` panics, it looks like a `#[non_exhaustive]` \
+                     enum from another crate so this fires when a variant is added, return an \
+                     error or log it instead)",
+                    path.join("::")
+                ));
+            }
+            let configured = sites.iter().find_map(|x| x.configured_fn.as_ref());
+            if let Some(path) = configured {
+                if kind == FindingKind::UndocumentedPanic {
+                    kind = FindingKind::ConfiguredPanicFn;
+                }
+                notes.push(format!(
+                    "(calls `{}`, which is declared as panicking)",
+                    path
+                ));
+            }
+            // Panics next to unsafe code are often checking what it returned, the reader needs to
+            // know what the unsafe code relies on to know when it fails
+            let near_unsafe = sites.iter().any(|x| x.tags.contains(&NEAR_UNSAFE));
+            if near_unsafe {
+                tags.push(NEAR_UNSAFE.to_string());
+                notes.push(
+                    "(panics next to `unsafe` code, document the invariant it relies on as well)"
+                        .to_string(),
+                );
+            }
+            let panic_span = sites.first().map(|x| x.span).unwrap_or(span);
+            // The precondition says more than the generic hint for the kind
+            let fix_hint = sites
+                .first()
+                .filter(|x| x.kind == PanicKind::PreconditionPanic)
+                .and_then(|x| x.precondition)
+                .map(|x| {
+                    format!(
+                        "`{}` {}, check it before the call or document it",
+                        x.name, x.condition
+                    )
+                });
+            let mut suggestion = self.panics_stub(span, kind, track_caller, near_unsafe);
+            add_preconditions(&mut suggestion, &preconditions);
+            Some(PanicLocation {
+                span,
+                panic_span,
+                context: self.context(panic_span),
                 ident: ident.to_string(),
-            });
+                kind,
+                panic_sites: sites.len(),
+                panic_kinds: distinct_kinds(sites),
+                attributes: vec![],
+                tags,
+                notes,
+                fix_hint,
+                suggestion: Some(suggestion),
+            })
+        }
+    }
+
+    fn note_const_fn(&self, mut location: PanicLocation, sig: &Signature) -> PanicLocation {
+        if self.options.check_const_fn && sig.constness.is_some() && location.panic_sites > 0 {
+            location.notes.push(
+                "(const fn, panics at runtime when called outside a const context)".to_string(),
+            );
+        }
+        location
+    }
+
+    /// An `unsafe fn` already tells the caller to read its docs, a safe one panicking inside an
+    /// `unsafe` block is easier to miss
+    fn note_unsafe_blocks(
+        &self,
+        mut location: PanicLocation,
+        sig: &Signature,
+        sites: &[PanicSite],
+    ) -> PanicLocation {
+        if self.options.check_unsafe_blocks
+            && sig.unsafety.is_none()
+            && sites.iter().any(|x| x.tags.contains(&IN_UNSAFE_BLOCK))
+        {
+            location.notes.push(
+                "(panic inside unsafe block — callers cannot catch this with safe code)"
+                    .to_string(),
+            );
+        }
+        location
+    }
+
+    /// A `todo!` in a function with other logic is easy to mistake for handled, unlike one which
+    /// is the whole body
+    fn note_unfinished(
+        &self,
+        mut location: PanicLocation,
+        block: &Block,
+        sites: &[PanicSite],
+        index: &FileIndex,
+    ) -> PanicLocation {
+        if self.options.pedantic
+            && sites
+                .iter()
+                .any(|x| matches!(x.kind, PanicKind::Todo | PanicKind::Unimplemented))
+            && !is_placeholder(block, &index.imports)
+        {
+            location.tags.push(UNFINISHED_BRANCH.to_string());
+            location.notes.push(
+                "(`todo!` or `unimplemented!` next to other logic, an unfinished branch which \
+                 callers can reach)"
+                    .to_string(),
+            );
+        }
+        location
+    }
+
+    /// Creates a finding if the item's doc sections aren't in the order the user asked for
+    fn check_section_order(&self, ident: &str, span: Span) -> Option<PanicLocation> {
+        if self.options.doc_section_order.is_empty() {
+            return None;
+        }
+        let comment = self.find_doc_comment(span);
+        let misplaced = misplaced_section(&comment, &self.options.doc_section_order)?;
+        let note = match misplaced.before {
+            Some(before) => format!(
+                "(`# {}` should come before `# {}`, the expected order is {})",
+                misplaced.section,
+                before,
+                self.options.doc_section_order.join(", ")
+            ),
+            None => format!("(`# {}` should come after the summary)", misplaced.section),
+        };
+        Some(PanicLocation {
+            span,
+            panic_span: span,
+            context: self.context(span),
+            ident: ident.to_string(),
+            kind: FindingKind::DocSectionOrder,
+            panic_sites: 0,
+            panic_kinds: vec![],
+            attributes: vec![],
+            tags: vec![],
+            notes: vec![note],
+            fix_hint: None,
+            suggestion: None,
+        })
+    }
+
+    /// Counts an undocumented panic as documented by the module's docs, as a note if those are
+    /// being reported
+    fn inherit_module_docs(&self, mut location: PanicLocation) -> Option<PanicLocation> {
+        if location.kind != FindingKind::UndocumentedPanic {
+            return Some(location);
+        }
+        if !self.options.report_documented {
+            return None;
+        }
+        location.kind = FindingKind::DocumentedPanic;
+        location.suggestion = None;
+        location
+            .notes
+            .push("(the panics are described in the module docs)".to_string());
+        Some(location)
+    }
+
+    /// Creates a note for an item which documents its panics if those are being reported
+    fn documented_panic(
+        &self,
+        ident: &str,
+        span: Span,
+        sites: &[PanicSite],
+    ) -> Option<PanicLocation> {
+        if !self.options.report_documented {
+            return None;
+        }
+        let panic_span = sites.first().map(|x| x.span).unwrap_or(span);
+        Some(PanicLocation {
+            span,
+            panic_span,
+            context: self.context(panic_span),
+            ident: ident.to_string(),
+            kind: FindingKind::DocumentedPanic,
+            panic_sites: sites.len(),
+            panic_kinds: distinct_kinds(sites),
+            attributes: vec![],
+            tags: vec![],
+            notes: vec![],
+            fix_hint: None,
+            suggestion: None,
+        })
+    }
+
+    /// Creates a finding if details are required and the `# Panics` section has too few lines of
+    /// explanation for the different kinds of panic in the item
+    fn check_panic_details(
+        &self,
+        comment: &str,
+        ident: &str,
+        span: Span,
+        sites: &[PanicSite],
+    ) -> Option<PanicLocation> {
+        if !self.options.require_panic_details {
+            return None;
+        }
+        let lines = prose_lines(&section_body(comment, "panics")?);
+        let kinds = distinct_kinds(sites);
+        let expected = (kinds.len() * self.options.panic_doc_lines_per_kind)
+            .max(self.options.min_panic_doc_lines)
+            .max(1);
+        if lines >= expected {
+            return None;
+        }
+        let panic_span = sites.first().map(|x| x.span).unwrap_or(span);
+        Some(PanicLocation {
+            span,
+            panic_span,
+            context: self.context(panic_span),
+            ident: ident.to_string(),
+            kind: FindingKind::ThinPanicDocs,
+            panic_sites: sites.len(),
+            panic_kinds: distinct_kinds(sites),
+            attributes: vec![],
+            tags: vec![],
+            notes: vec![format!(
+                "(the `# Panics` section has {} lines of explanation, expected at least {})",
+                lines, expected
+            )],
+            fix_hint: None,
+            suggestion: None,
+        })
+    }
+
+    /// Creates a finding for an item taking a callback unless the docs mention panics
+    fn check_callback_docs(&self, comment: &str, ident: &str, span: Span) -> Option<PanicLocation> {
+        if warns_about_panics(comment) {
+            None
+        } else {
+            Some(PanicLocation {
+                span,
+                panic_span: span,
+                context: self.context(span),
+                ident: ident.to_string(),
+                kind: FindingKind::CallbackPropagation,
+                panic_sites: 0,
+                panic_kinds: vec![],
+                attributes: vec![],
+                tags: vec![],
+                notes: vec!["(panics in callbacks propagate to the caller)".to_string()],
+                fix_hint: None,
+                suggestion: Some(self.panics_stub(
+                    span,
+                    FindingKind::CallbackPropagation,
+                    false,
+                    false,
+                )),
+            })
+        }
+    }
+
+    /// Returns true if callback checking is enabled and the function takes a closure or function
+    /// pointer as an argument
+    fn takes_callback(&self, sig: &Signature) -> bool {
+        if !self.options.check_callback_propagation {
+            return false;
+        }
+        let mut callback_generics = vec![];
+        for param in sig.generics.type_params() {
+            if param.bounds.iter().any(is_fn_trait_bound) {
+                callback_generics.push(param.ident.clone());
+            }
+        }
+        if let Some(where_clause) = &sig.generics.where_clause {
+            for predicate in where_clause.predicates.iter() {
+                if let WherePredicate::Type(t) = predicate {
+                    if let Type::Path(ref p) = t.bounded_ty {
+                        if let Some(ident) = p.path.get_ident() {
+                            if t.bounds.iter().any(is_fn_trait_bound) {
+                                callback_generics.push(ident.clone());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        sig.inputs.iter().any(|arg| match arg {
+            FnArg::Typed(t) => is_callback_type(&t.ty, &callback_generics),
+            FnArg::Receiver(_) => false,
+        })
+    }
+
+    /// Checks the defaults of a type's const generic parameters, a panic in one is a compile
+    /// error for anyone using the default
+    fn check_const_defaults(
+        &self,
+        ident: &Ident,
+        generics: &Generics,
+        span: Span,
+        attrs: &[Attribute],
+        namespace: Option<&String>,
+        index: &FileIndex,
+    ) -> Option<PanicLocation> {
+        let helpers = Helpers {
+            panicking: Some(&index.panicking_const_fns),
+            module: namespace.map(String::as_str),
+            panicking_fns: &self.options.panicking_fns,
+            allowed_fns: &self.options.allowed_fns,
+            ..Default::default()
+        };
+        let mut sites = vec![];
+        let mut params = vec![];
+        for param in generics.const_params() {
+            let default = match &param.default {
+                Some(Expr::Block(block)) => block.block.clone(),
+                Some(expr) => Block {
+                    brace_token: Default::default(),
+                    stmts: vec![Stmt::Expr(expr.clone())],
+                },
+                None => continue,
+            };
+            let found = find_panics(&default, &index.imports, helpers);
+            if !found.is_empty() {
+                params.push(format!("`{}`", param.ident));
+                sites.extend(found);
+            }
+        }
+        if sites.is_empty() {
+            return None;
         }
+        let name = ident.to_string();
+        let ident = self.item_ident(namespace, &name, &name, true)?;
+        let comment = self.find_doc_comment(span);
+        let mut location = self.check_doc_comment(&comment, &ident, span, attrs, &sites)?;
+        location.tags.push(CONST_GENERIC_DEFAULT.to_string());
+        location.notes.push(format!(
+            "(the default of const parameter {} can panic, which fails to compile for users \
+             relying on it)",
+            params.join(", ")
+        ));
+        Some(location)
     }
 
     fn process_trait(
         &self,
         item_trait: &ItemTrait,
         namespace: Option<&String>,
-        result: &mut Vec<PanicLocation>,
+        index: &FileIndex,
+        on_violation: &mut dyn FnMut(PanicLocation),
     ) {
+        // Traits often describe how their default methods behave in the trait level docs, which
+        // is also where a reader will look first.
+        let trait_documents_panics = !self.options.ignore_trait_docs
+            && warns_about_panics(&self.find_doc_comment(item_trait.span()));
+        let trait_name = item_trait.ident.to_string();
+        let trait_ident = match self.item_ident(namespace, &trait_name, &trait_name, false) {
+            Some(ident) => ident,
+            None => return,
+        };
         for default_method in item_trait
             .items
             .iter()
@@ -157,17 +1599,39 @@ impl AstWalker {
             } else {
                 unreachable!()
             };
-            if !self.span_has_panics(method.default.as_ref().unwrap().span()) {
+            let sites = self.find_panics(
+                method.default.as_ref().unwrap(),
+                index,
+                None,
+                namespace.map(String::as_str),
+            );
+            if sites.is_empty() {
                 continue;
             }
             let comment = self.find_doc_comment(method.span());
-            let ident = if let Some(namespace) = namespace {
-                format!("{}::{}::{}", namespace, item_trait.ident, method.sig.ident)
-            } else {
-                format!("{}::{}", item_trait.ident, method.sig.ident)
-            };
+            let ident = format!("{}::{}", trait_ident, method.sig.ident);
 
-            self.check_docs(&comment, &ident, method.span(), result);
+            if trait_documents_panics && !warns_about_panics(&comment) {
+                debug!(
+                    "Panics in {} are documented on the trait {}",
+                    ident, item_trait.ident
+                );
+                if let Some(location) = self.documented_panic(&ident, method.span(), &sites) {
+                    on_violation(location);
+                }
+                continue;
+            }
+            if let Some(location) = self.check_docs(
+                &comment,
+                &ident,
+                method.span(),
+                &method.attrs,
+                &method.sig,
+                &sites,
+            ) {
+                let body = method.default.as_ref().unwrap();
+                on_violation(tag_delegation(location, &method.sig, body));
+            }
         }
     }
 
@@ -175,8 +1639,50 @@ impl AstWalker {
         &self,
         imp: &ItemImpl,
         namespace: Option<&String>,
-        result: &mut Vec<PanicLocation>,
+        index: &FileIndex,
+        on_violation: &mut dyn FnMut(PanicLocation),
     ) {
+        if self.options.ignore_index_impls && is_index_impl(imp) {
+            return;
+        }
+        if self.options.check_blanket_impls && is_blanket_impl(imp) {
+            self.process_blanket_impl(imp, namespace, index, on_violation);
+            return;
+        }
+        let self_ty = imp.self_ty.to_token_stream().to_string();
+        let type_name = type_name(&imp.self_ty).unwrap_or_else(|| self_ty.clone());
+        let send_wrapper =
+            self.options.check_send_wrappers && index.send_types.contains(&type_name);
+        let self_ident = match self.item_ident(namespace, &type_name, &self_ty, true) {
+            Some(ident) => ident,
+            None => return,
+        };
+        let non_exhaustive = index.non_exhaustive_types.contains(&type_name);
+        let on_violation = &mut |mut location: PanicLocation| {
+            if non_exhaustive && !location.attributes.iter().any(|x| x == "non_exhaustive") {
+                location.attributes.push("non_exhaustive".to_string());
+                location
+                    .attributes
+                    .sort_by_key(|x| TRIAGE_ATTRIBUTES.iter().position(|a| a == x));
+            }
+            on_violation(location)
+        };
+        let trait_path = imp
+            .trait_
+            .as_ref()
+            .map(|(_, path, _)| index.imports.resolve(path));
+        if let Some(implicit) = trait_path.and_then(|x| self.implicit_trait(&x, &type_name, index))
+        {
+            // A conversion is reported by its trait, as there can be several for the type
+            let self_ident = match &imp.trait_ {
+                Some((_, path, _)) if conversion(imp, &index.imports).is_some() => {
+                    format!("< {} as {} >", self_ident, path.to_token_stream())
+                }
+                _ => self_ident,
+            };
+            self.process_implicit_impl(imp, &self_ident, namespace, &implicit, index, on_violation);
+            return;
+        }
         for method in imp
             .items
             .iter()
@@ -187,19 +1693,362 @@ impl AstWalker {
             } else {
                 unreachable!()
             };
-            if !self.span_has_panics(method.block.span()) {
+            let mut sites = self.find_panics(
+                &method.block,
+                index,
+                Some(&type_name),
+                namespace.map(String::as_str),
+            );
+            sites.extend(self.dyn_dispatch_sites(&method.sig, &method.block, index));
+            let has_panics = !sites.is_empty();
+            let ident = format!("{}::{}", self_ident, method.sig.ident);
+            if let Some(location) = self.check_section_order(&ident, method.span()) {
+                on_violation(location);
+            }
+            if !(has_panics || self.takes_callback(&method.sig)) {
                 continue;
             }
             let comment = self.find_doc_comment(method.span());
-            let self_ty = imp.self_ty.to_token_stream().to_string();
-            let ident = if let Some(namespace) = namespace {
-                format!("{}::{}::{}", namespace, self_ty, method.sig.ident)
+
+            let location = if has_panics {
+                self.check_docs(
+                    &comment,
+                    &ident,
+                    method.span(),
+                    &method.attrs,
+                    &method.sig,
+                    &sites,
+                )
+                .map(|mut location| {
+                    if send_wrapper && sites.iter().any(is_threading_panic) {
+                        location.tags.push("THREADING".to_string());
+                        location
+                            .notes
+                            .push("(panics if used from the wrong thread)".to_string());
+                    }
+                    location
+                })
             } else {
-                format!("{}::{}", self_ty, method.sig.ident)
+                self.check_callback_docs(&comment, &ident, method.span())
             };
-
-            self.check_docs(&comment, &ident, method.span(), result);
-        }
+            if let Some(mut location) = location {
+                if self.options.check_struct_new && is_constructor(&method.sig, &type_name) {
+                    location.tags.push("CONSTRUCTOR".to_string());
+                    location.notes.push(format!("(constructs `{}`)", type_name));
+                }
+                let location = self.note_const_fn(location, &method.sig);
+                let location = self.note_unsafe_blocks(location, &method.sig, &sites);
+                let location = self.note_unfinished(location, &method.block, &sites, index);
+                on_violation(tag_delegation(location, &method.sig, &method.block));
+            }
+        }
+    }
+
+    /// Checks every method of a blanket impl, any type meeting the bounds gets these methods
+    /// whether its author knows about the impl or not. The panics can be documented on the impl
+    /// or the method.
+    fn process_blanket_impl(
+        &self,
+        imp: &ItemImpl,
+        namespace: Option<&String>,
+        index: &FileIndex,
+        on_violation: &mut dyn FnMut(PanicLocation),
+    ) {
+        let trait_name = match imp.trait_.as_ref().and_then(|(_, p, _)| p.segments.last()) {
+            Some(segment) => segment.ident.to_string(),
+            None => return,
+        };
+        let trait_ident = match self.item_ident(namespace, &trait_name, &trait_name, true) {
+            Some(ident) => ident,
+            None => return,
+        };
+        let impl_comment = self.find_doc_comment(imp.span());
+        for item in imp.items.iter() {
+            let method = match item {
+                ImplItem::Method(m) => m,
+                _ => continue,
+            };
+            let sites = self.find_panics(&method.block, index, None, namespace.map(String::as_str));
+            if sites.is_empty() {
+                continue;
+            }
+            let comment = format!("{}\n{}", impl_comment, self.find_doc_comment(method.span()));
+            let ident = format!("{}::{}", trait_ident, method.sig.ident);
+            if let Some(mut location) = self.check_docs(
+                &comment,
+                &ident,
+                method.span(),
+                &method.attrs,
+                &method.sig,
+                &sites,
+            ) {
+                location.tags.push("BLANKET IMPL".to_string());
+                location.notes.push(format!(
+                    "(implemented for every type meeting the bounds on `{}`)",
+                    imp.self_ty.to_token_stream()
+                ));
+                on_violation(location);
+            }
+        }
+    }
+
+    /// How to check an impl of the trait for the type if it's called implicitly and is being
+    /// checked
+    fn implicit_trait(
+        &self,
+        trait_path: &[String],
+        type_name: &str,
+        index: &FileIndex,
+    ) -> Option<ImplicitTrait> {
+        let trait_name = trait_path.last().map(String::as_str).unwrap_or_default();
+        match trait_name {
+            "Deref" | "DerefMut" if self.options.check_deref => Some(ImplicitTrait {
+                methods: &["deref", "deref_mut"],
+                kind: FindingKind::ImplicitCall,
+                tag: None,
+                note: "(implicit via auto-deref)",
+                unreachable: false,
+            }),
+            "Index" | "IndexMut" if self.options.check_index => Some(ImplicitTrait {
+                methods: &["index", "index_mut"],
+                kind: FindingKind::ImplicitCall,
+                tag: None,
+                note: "(panics on an invalid key or index when indexing, document which are valid \
+                       in a `# Panics` section)",
+                unreachable: false,
+            }),
+            "IntoIterator" if self.options.check_into_iter => Some(ImplicitTrait {
+                methods: &["into_iter"],
+                kind: FindingKind::ImplicitCall,
+                tag: None,
+                note: "(called implicitly by for loop)",
+                unreachable: false,
+            }),
+            "From" if self.options.check_type_conversions => Some(ImplicitTrait {
+                methods: &["from"],
+                kind: FindingKind::ContractViolation,
+                tag: Some("FROM IMPL"),
+                note: "(called implicitly by `.into()` and by `?` when converting errors, `From` \
+                       conversions shouldn't fail so implement `TryFrom` and return an error \
+                       instead)",
+                unreachable: false,
+            }),
+            "TryFrom" if self.options.check_try_from => Some(ImplicitTrait {
+                methods: &["try_from"],
+                kind: FindingKind::ContractViolation,
+                tag: Some("TRY_FROM CONTRACT"),
+                note: "(called by `.try_into()` and `try_from`, whose callers handle an `Err` \
+                       rather than a panic, remove the panic and return an error)",
+                unreachable: false,
+            }),
+            "TryFrom" | "FromStr" if self.options.check_fallible_fns => Some(ImplicitTrait {
+                methods: &["try_from", "from_str"],
+                kind: FindingKind::PanicInFallibleFn,
+                tag: None,
+                note: FALLIBLE_NOTE,
+                unreachable: false,
+            }),
+            // Always checked, a caller converting with `.into()` or `?` can't see that it panics
+            "From" | "TryFrom" => Some(ImplicitTrait {
+                methods: &["from", "try_from"],
+                kind: FindingKind::ImplicitCall,
+                tag: None,
+                note: if trait_name == "From" {
+                    "(called implicitly by `.into()` and by `?` when converting errors)"
+                } else {
+                    "(called by `.try_into()`, callers expect an `Err` rather than a panic)"
+                },
+                unreachable: false,
+            }),
+            "Add" | "Sub" | "Mul" | "Div" | "Rem" if self.options.check_operators => {
+                Some(ImplicitTrait {
+                    methods: &["add", "sub", "mul", "div", "rem"],
+                    kind: FindingKind::ImplicitCall,
+                    tag: Some("OPERATOR OVERLOAD"),
+                    note: match trait_name {
+                        "Add" => {
+                            "(called implicitly by the `+` operator, `a + b` doesn't look like \
+                                  it can panic)"
+                        }
+                        "Sub" => {
+                            "(called implicitly by the `-` operator, `a - b` doesn't look like \
+                                  it can panic)"
+                        }
+                        "Mul" => {
+                            "(called implicitly by the `*` operator, `a * b` doesn't look like \
+                                  it can panic)"
+                        }
+                        "Div" => {
+                            "(called implicitly by the `/` operator, `a / b` doesn't look like \
+                                  it can panic)"
+                        }
+                        _ => {
+                            "(called implicitly by the `%` operator, `a % b` doesn't look like it \
+                              can panic)"
+                        }
+                    },
+                    unreachable: false,
+                })
+            }
+            "Default" if self.options.check_default => Some(ImplicitTrait {
+                methods: &["default"],
+                kind: FindingKind::ContractViolation,
+                tag: Some("DEFAULT CONTRACT"),
+                note: "(called implicitly by `..Default::default()`, derived `Default` impls and \
+                       `unwrap_or_default`, return a safe value or don't implement `Default`)",
+                unreachable: false,
+            }),
+            // `fmt::Write` is allowed to fail without saying why so it's left alone
+            "Write" if self.options.check_io_write && !trait_path.iter().any(|x| x == "fmt") => {
+                Some(ImplicitTrait {
+                    methods: &["write", "flush", "write_all"],
+                    kind: FindingKind::ContractViolation,
+                    tag: Some("IO CONTRACT"),
+                    note: "(writers are used by `write!`, `println!` and logging which expect an \
+                           error rather than a panic, return `Err(io::Error::new(..))` instead)",
+                    unreachable: false,
+                })
+            }
+            "Read" if self.options.check_io_read => Some(ImplicitTrait {
+                methods: &["read", "read_to_end"],
+                kind: FindingKind::ContractViolation,
+                tag: Some("IO CONTRACT"),
+                note: "(readers are expected to return an error rather than panic, return \
+                       `Err(io::Error::new(..))` instead)",
+                unreachable: false,
+            }),
+            "Clone" if self.options.check_clone => Some(ImplicitTrait {
+                methods: &["clone", "clone_from"],
+                kind: FindingKind::ContractViolation,
+                tag: Some("CLONE CONTRACT VIOLATION"),
+                note: "(`Clone` implementations shouldn't panic)",
+                unreachable: false,
+            }),
+            "Hash" if self.options.check_hash => Some(ImplicitTrait {
+                methods: &["hash"],
+                kind: FindingKind::ContractViolation,
+                tag: Some("HASH CONTRACT"),
+                note: "(called implicitly by hash maps and sets, use `if let` instead of \
+                       unwrapping or check the state when it's created)",
+                unreachable: false,
+            }),
+            "PartialOrd" | "Ord" if self.options.check_ord => Some(ImplicitTrait {
+                methods: &["partial_cmp", "cmp"],
+                kind: FindingKind::ContractViolation,
+                tag: Some("ORDERING CONTRACT"),
+                note: "(called implicitly by comparison operators, sorting and ordered \
+                       collections)",
+                unreachable: false,
+            }),
+            "Serialize" | "Deserialize" if self.options.check_serde => Some(ImplicitTrait {
+                methods: &["serialize", "deserialize"],
+                kind: FindingKind::ContractViolation,
+                tag: Some("SERDE CONTRACT"),
+                note: "(return an error with `serde::ser::Error::custom` or \
+                       `serde::de::Error::custom` instead)",
+                unreachable: false,
+            }),
+            "Display"
+                if self.options.check_display_error && index.error_types.contains(type_name) =>
+            {
+                Some(ImplicitTrait {
+                    methods: &["fmt"],
+                    kind: FindingKind::ContractViolation,
+                    tag: Some("ERROR DISPLAY"),
+                    note: "(error messages are formatted when logging, propagating errors and \
+                           reporting test failures, a panic there hides the original error)",
+                    unreachable: true,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks a trait impl whose methods are called implicitly. Someone reading code which calls
+    /// them won't see a method call to look up, so the panics have to be documented on the impl
+    /// block rather than the methods.
+    fn process_implicit_impl(
+        &self,
+        imp: &ItemImpl,
+        self_ident: &str,
+        namespace: Option<&String>,
+        implicit: &ImplicitTrait,
+        index: &FileIndex,
+        on_violation: &mut dyn FnMut(PanicLocation),
+    ) {
+        if implicit.kind == FindingKind::ImplicitCall
+            && warns_about_panics(&self.find_doc_comment(imp.span()))
+        {
+            return;
+        }
+        let self_type = type_name(&imp.self_ty);
+        let module = namespace.map(String::as_str);
+        for item in imp.items.iter() {
+            let method = match item {
+                ImplItem::Method(m) if implicit.methods.iter().any(|x| m.sig.ident == x) => m,
+                _ => continue,
+            };
+            let sites = if implicit.unreachable {
+                self.find_all_panics(&method.block, index, self_type.as_deref(), module)
+            } else {
+                self.find_panics(&method.block, index, self_type.as_deref(), module)
+            };
+            if let Some(site) = sites.first() {
+                let span = method.span();
+                on_violation(PanicLocation {
+                    span,
+                    panic_span: site.span,
+                    context: self.context(site.span),
+                    ident: format!("{}::{}", self_ident, method.sig.ident),
+                    kind: implicit.kind,
+                    panic_sites: sites.len(),
+                    panic_kinds: distinct_kinds(&sites),
+                    attributes: triage_attributes(&method.attrs),
+                    tags: implicit.tag.iter().map(|x| x.to_string()).collect(),
+                    notes: vec![implicit.note.to_string()],
+                    fix_hint: None,
+                    suggestion: Some(self.panics_stub(imp.span(), implicit.kind, false, false)),
+                });
+            }
+        }
+    }
+
+    /// The name to report an item by, `name` is used to look the item up while `display` is what
+    /// gets shown otherwise. Returns `None` if only the public surface of the crate is being
+    /// checked and the item isn't part of it.
+    fn item_ident(
+        &self,
+        namespace: Option<&String>,
+        name: &str,
+        display: &str,
+        is_type: bool,
+    ) -> Option<String> {
+        match &self.options.surface {
+            Some(surface) => {
+                let mut module = surface.module_path(&self.filename)?.to_vec();
+                // Any alternative versions of a module have the same public path
+                module.extend(
+                    namespace
+                        .iter()
+                        .flat_map(|x| x.split("::"))
+                        .map(|x| x.split('[').next().unwrap_or(x).to_string()),
+                );
+                if is_type {
+                    surface.public_type_path(&module, name)
+                } else {
+                    surface.public_path(&module, name)
+                }
+            }
+            None => match namespace {
+                Some(namespace) => Some(format!("{}::{}", namespace, display)),
+                None => Some(display.to_string()),
+            },
+        }
+    }
+
+    /// Gets the line before, the line of and the line after the start of the span
+    fn context(&self, span: Span) -> Snippet {
+        extract(&self.source_code, LineColRange::from(span).first_line(), 1)
     }
 
     fn find_doc_comment(&self, span: Span) -> String {
@@ -208,8 +2057,8 @@ impl AstWalker {
         let lines = self.source_code.lines().collect::<Vec<&str>>();
 
         let mut doc_comment = vec![];
-        for i in start..end {
-            let trimmed = lines[i].trim();
+        for line in &lines[start..end] {
+            let trimmed = line.trim();
             if trimmed.starts_with("///") {
                 doc_comment.push(trimmed);
             } else {
@@ -219,14 +2068,611 @@ impl AstWalker {
         doc_comment.join("\n").to_lowercase()
     }
 
-    fn span_has_panics(&self, span: Span) -> bool {
+    /// Creates an insertion of a `# Panics` section at the end of the item's doc comment, or
+    /// above the item if it doesn't have one. The section has to stay in the run of `///` lines
+    /// starting the item for `find_doc_comment` to see it.
+    fn panics_stub(
+        &self,
+        span: Span,
+        kind: FindingKind,
+        track_caller: bool,
+        near_unsafe: bool,
+    ) -> Suggestion {
+        let lines = self.source_code.split_inclusive('\n').collect::<Vec<_>>();
+        let first = span.start().line - 1;
+        let doc_lines = lines[first..]
+            .iter()
+            .take_while(|x| x.trim_start().starts_with("///"))
+            .count();
+        let line = first + doc_lines;
+        let indent = lines
+            .get(first)
+            .map(|x| &x[..x.len() - x.trim_start().len()])
+            .unwrap_or_default();
+        let body = match kind {
+            FindingKind::UndocumentedPanic
+            | FindingKind::EntryPoint
+            | FindingKind::ThinPanicDocs
+            | FindingKind::DocumentedPanic
+            | FindingKind::UndocumentedRisk
+            | FindingKind::ConfiguredPanicFn
+            | FindingKind::DocSectionOrder => "Describe the conditions under which this panics.",
+            FindingKind::CallbackPropagation => {
+                "Panics in the provided callback propagate to the caller."
+            }
+            FindingKind::ImplicitCall => {
+                "Describe the conditions under which the implicitly called methods panic."
+            }
+            FindingKind::ContractViolation
+            | FindingKind::ForeignUnwind
+            | FindingKind::ReleaseBlocker
+            | FindingKind::ForeignNonExhaustive
+            | FindingKind::PanicInFallibleFn => {
+                "Describe the conditions under which this panics, ideally change it so it can't."
+            }
+        };
+        let mut stub = vec![];
+        if doc_lines > 0 {
+            stub.push("");
+        }
+        stub.extend_from_slice(&["# Panics", "", body]);
+        if track_caller {
+            stub.extend_from_slice(&[
+                "",
+                "The panic is reported at the location this was called from.",
+            ]);
+        }
+        if near_unsafe {
+            stub.extend_from_slice(&[
+                "",
+                "Describe the invariant the `unsafe` code relies on which the panic checks.",
+            ]);
+        }
+        let suggested_replacement = stub
+            .iter()
+            .map(|x| {
+                format!(
+                    "{}///{}{}\n",
+                    indent,
+                    if x.is_empty() { "" } else { " " },
+                    x
+                )
+            })
+            .collect::<String>();
+        let byte_start = lines[..line].iter().map(|x| x.len()).sum();
+        Suggestion {
+            byte_start,
+            byte_end: byte_start,
+            line_start: line + 1,
+            line_end: line + 1,
+            column_start: 1,
+            column_end: 1,
+            suggested_replacement,
+            suggestion_applicability: Applicability::MaybeIncorrect,
+        }
+    }
+
+    /// Finds everything in a function body which can panic, or exit the process unless that's
+    /// being ignored
+    fn find_panics(
+        &self,
+        block: &Block,
+        index: &FileIndex,
+        self_type: Option<&str>,
+        module: Option<&str>,
+    ) -> Vec<PanicSite> {
+        let mut sites = self.find_all_panics(block, index, self_type, module);
+        sites.retain(|x| x.kind != PanicKind::Unreachable);
+        sites
+    }
+
+    /// Like `find_panics` but counting `unreachable!` too
+    fn find_all_panics(
+        &self,
+        block: &Block,
+        index: &FileIndex,
+        self_type: Option<&str>,
+        module: Option<&str>,
+    ) -> Vec<PanicSite> {
+        let preconditions = if self.options.check_std_panics {
+            PRECONDITIONS
+                .iter()
+                .filter(|x| !self.options.ignore_std_panics.iter().any(|y| y == x.name))
+                .collect()
+        } else {
+            vec![]
+        };
+        let helpers = Helpers {
+            panicking: Some(&index.panicking_helpers),
+            conversions: Some(&index.conversions),
+            self_type,
+            module,
+            panicking_fns: &self.options.panicking_fns,
+            allowed_fns: &self.options.allowed_fns,
+            preconditions: &preconditions,
+            opaque_fallbacks: self.options.pedantic,
+        };
+        let mut sites = find_panics(block, &index.imports, helpers);
+        if self.options.ignore_process_exit {
+            sites.retain(|x| x.kind != PanicKind::ProcessExit);
+        }
+        if self.options.ignore_mutex_poison {
+            sites.retain(|x| !x.tags.contains(&MUTEX_POISON));
+        }
+        if !self.options.ignore_lookups.is_empty() {
+            sites.retain(|x| {
+                x.lookup
+                    .is_none_or(|lookup| !self.options.ignore_lookups.contains(&lookup))
+            });
+        }
+        for site in &mut sites {
+            if site
+                .matched_enum
+                .as_ref()
+                .is_some_and(|x| is_foreign_enum(x, index))
+            {
+                site.tags.push(FOREIGN_NON_EXHAUSTIVE);
+            }
+        }
+        sites
+    }
+
+    /// Adds `-> anyhow::Result<()>` to the signature of a `fn main` which doesn't return anything
+    fn result_main_suggestion(&self, sig: &Signature) -> Option<Suggestion> {
+        if !matches!(sig.output, ReturnType::Default) {
+            return None;
+        }
+        let end = sig.paren_token.span.end();
+        let lines = self.source_code.split_inclusive('\n').collect::<Vec<_>>();
+        let line = lines.get(end.line - 1)?;
+        let column = line
+            .char_indices()
+            .nth(end.column)
+            .map(|(i, _)| i)
+            .unwrap_or(line.len());
+        let byte_start = lines[..end.line - 1].iter().map(|x| x.len()).sum::<usize>() + column;
+        Some(Suggestion {
+            byte_start,
+            byte_end: byte_start,
+            line_start: end.line,
+            line_end: end.line,
+            column_start: end.column + 1,
+            column_end: end.column + 1,
+            suggested_replacement: " -> anyhow::Result<()>".to_string(),
+            suggestion_applicability: Applicability::MaybeIncorrect,
+        })
+    }
+
+    /// Calls to trait objects whose trait has a default method which can panic
+    fn dyn_dispatch_sites(
+        &self,
+        sig: &Signature,
+        block: &Block,
+        index: &FileIndex,
+    ) -> Vec<PanicSite> {
+        if index.panicking_traits.is_empty() {
+            return vec![];
+        }
+        dyn_dispatch_calls(sig, block, &index.panicking_traits)
+    }
+
+    /// Finds the traits with a default method which can panic. Their names are added to the words
+    /// which suggest an item can panic, as calling them through a trait object mentions them.
+    fn add_panicking_traits(&self, items: &[Item], index: &mut FileIndex) {
+        let mut traits = vec![];
+        self.panicking_traits(items, None, index, &mut traits);
+        index.panic_renames.extend(traits.iter().cloned());
+        index.panicking_traits.extend(traits);
+    }
+
+    fn panicking_traits(
+        &self,
+        items: &[Item],
+        module: Option<&str>,
+        index: &FileIndex,
+        traits: &mut Vec<String>,
+    ) {
+        for item in items {
+            match item {
+                Item::Mod(m) => {
+                    if let Some((_, content)) = &m.content {
+                        let module = submodule(module, m, items);
+                        self.panicking_traits(content, Some(&module), index, traits);
+                    }
+                }
+                Item::Trait(t) => {
+                    let panics = t.items.iter().any(|x| match x {
+                        TraitItem::Method(m) => m
+                            .default
+                            .as_ref()
+                            .is_some_and(|x| !self.find_panics(x, index, None, module).is_empty()),
+                        _ => false,
+                    });
+                    if panics {
+                        traits.push(t.ident.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Finds the private functions and methods which can panic, directly or through calls to each
+    /// other. Their names are added to the words which suggest an item can panic.
+    fn add_panicking_helpers(&self, items: &[Item], index: &mut FileIndex) {
+        let mut private = vec![];
+        private_fns(items, &mut private);
+        let mut conversions = vec![];
+        conversion_impls(items, None, &index.imports, &mut conversions);
+        index.conversions = conversions.iter().map(|x| (x.0.clone(), false)).collect();
+        loop {
+            let found = private
+                .iter()
+                .filter(|x| !index.panicking_helpers.contains(&x.name))
+                .filter(|x| {
+                    !self
+                        .find_panics(x.block, index, x.self_type.as_deref(), x.module.as_deref())
+                        .is_empty()
+                })
+                .map(|x| x.name.clone())
+                .collect::<Vec<_>>();
+            // Conversions can call helpers and the other way round
+            let converted = conversions
+                .iter()
+                .filter(|(x, _, _)| !index.conversions[x])
+                .filter(|(x, module, block)| {
+                    !self
+                        .find_panics(block, index, Some(&x.target), module.as_deref())
+                        .is_empty()
+                })
+                .map(|x| x.0.clone())
+                .collect::<Vec<_>>();
+            if found.is_empty() && converted.is_empty() {
+                break;
+            }
+            index.panicking_helpers.extend(found);
+            index
+                .conversions
+                .extend(converted.into_iter().map(|x| (x, true)));
+        }
+        let mut names = index
+            .panicking_helpers
+            .iter()
+            .map(|x| x.rsplit("::").next().unwrap_or(x).to_string())
+            .collect::<Vec<_>>();
+        if index.conversions.values().any(|x| *x) {
+            // Covers `try_from` and `try_into` too
+            names.extend(["from".to_string(), "into".to_string()]);
+        }
+        names.sort();
+        names.dedup();
+        index.panic_renames.extend(names);
+    }
+
+    fn span_has_panics(&self, span: Span, renames: &[String]) -> bool {
         let start = span.start().line - 1;
         let end = (span.end().line - 1) - start;
         self.source_code
             .lines()
             .skip(start)
-            .take(end)
-            .any(contains_panicky_words)
+            .take(end + 1)
+            .any(|x| contains_panicky_words(x) || contains_any(x, renames))
+    }
+}
+
+/// Finds the `const fn`s which can panic, including through other `const fn`s in the file, so
+/// calls to them in const generic defaults can be checked
+fn add_panicking_const_fns(items: &[Item], index: &mut FileIndex) {
+    let mut const_fns = vec![];
+    collect_fns(
+        items,
+        None,
+        &|_, sig| sig.constness.is_some(),
+        &mut const_fns,
+    );
+    loop {
+        let found = const_fns
+            .iter()
+            .filter(|x| !index.panicking_const_fns.contains(&x.name))
+            .filter(|x| {
+                let helpers = Helpers {
+                    panicking: Some(&index.panicking_const_fns),
+                    self_type: x.self_type.as_deref(),
+                    module: x.module.as_deref(),
+                    ..Default::default()
+                };
+                !find_panics(x.block, &index.imports, helpers).is_empty()
+            })
+            .map(|x| x.name.clone())
+            .collect::<Vec<_>>();
+        if found.is_empty() {
+            break;
+        }
+        index.panicking_const_fns.extend(found);
+    }
+    let names = index
+        .panicking_const_fns
+        .iter()
+        .map(|x| x.rsplit("::").next().unwrap_or(x).to_string())
+        .collect::<Vec<_>>();
+    index.panic_renames.extend(names);
+}
+
+/// A function or method which is only reported through what calls it, usually because it isn't
+/// public
+struct PrivateFn<'a> {
+    /// `name` for free functions and `Type::name` for methods, after the path of the inline
+    /// module they're in so functions with the same name in different modules aren't mixed up
+    name: String,
+    block: &'a Block,
+    self_type: Option<String>,
+    /// The inline module the function is in, calls in its body are resolved from here
+    module: Option<String>,
+}
+
+/// The private free functions and inherent methods in the items, including those in modules
+fn private_fns<'a>(items: &'a [Item], result: &mut Vec<PrivateFn<'a>>) {
+    collect_fns(items, None, &|vis, _| !is_public(vis), result);
+}
+
+/// The path of an inline module in `module`, as it's written in idents
+fn submodule(module: Option<&str>, m: &ItemMod, items: &[Item]) -> String {
+    let name = module_name(m, items);
+    match module {
+        Some(module) => format!("{}::{}", module, name),
+        None => name,
+    }
+}
+
+/// The free functions and inherent methods in the items which `keep` accepts, including those in
+/// modules
+fn collect_fns<'a>(
+    items: &'a [Item],
+    module: Option<&str>,
+    keep: &dyn Fn(&Visibility, &Signature) -> bool,
+    result: &mut Vec<PrivateFn<'a>>,
+) {
+    let qualified = |name: String| match module {
+        Some(module) => format!("{}::{}", module, name),
+        None => name,
+    };
+    for item in items {
+        match item {
+            Item::Mod(m) => {
+                if let Some((_, content)) = &m.content {
+                    let module = submodule(module, m, items);
+                    collect_fns(content, Some(&module), keep, result);
+                }
+            }
+            Item::Fn(f) if keep(&f.vis, &f.sig) => result.push(PrivateFn {
+                name: qualified(f.sig.ident.to_string()),
+                block: &f.block,
+                self_type: None,
+                module: module.map(String::from),
+            }),
+            Item::Impl(i) if i.trait_.is_none() => {
+                let self_type = match type_name(&i.self_ty) {
+                    Some(ty) => ty,
+                    None => continue,
+                };
+                for method in &i.items {
+                    match method {
+                        ImplItem::Method(m) if keep(&m.vis, &m.sig) => result.push(PrivateFn {
+                            name: qualified(format!("{}::{}", self_type, m.sig.ident)),
+                            block: &m.block,
+                            self_type: Some(self_type.clone()),
+                            module: module.map(String::from),
+                        }),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The `From` and `TryFrom` impls in the items, including those in modules, with the module
+/// they're in and the body of their conversion
+fn conversion_impls<'a>(
+    items: &'a [Item],
+    module: Option<&str>,
+    imports: &ImportMap,
+    result: &mut Vec<(Conversion, Option<String>, &'a Block)>,
+) {
+    for item in items {
+        match item {
+            Item::Mod(m) => {
+                if let Some((_, content)) = &m.content {
+                    let module = submodule(module, m, items);
+                    conversion_impls(content, Some(&module), imports, result);
+                }
+            }
+            Item::Impl(i) => {
+                let conversion = match conversion(i, imports) {
+                    Some(conversion) => conversion,
+                    None => continue,
+                };
+                let body = i.items.iter().find_map(|x| match x {
+                    ImplItem::Method(m) if m.sig.ident == "from" || m.sig.ident == "try_from" => {
+                        Some(&m.block)
+                    }
+                    _ => None,
+                });
+                if let Some(body) = body {
+                    result.push((conversion, module.map(String::from), body));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The types converted between if it's a `From` or `TryFrom` impl
+fn conversion(imp: &ItemImpl, imports: &ImportMap) -> Option<Conversion> {
+    let (_, path, _) = imp.trait_.as_ref()?;
+    let fallible = match imports.resolve(path).last()?.as_str() {
+        "From" => false,
+        "TryFrom" => true,
+        _ => return None,
+    };
+    let source = match &path.segments.last()?.arguments {
+        PathArguments::AngleBracketed(args) => args.args.iter().find_map(|x| match x {
+            GenericArgument::Type(ty) => type_name(ty),
+            _ => None,
+        })?,
+        _ => return None,
+    };
+    Some(Conversion {
+        source,
+        target: type_name(&imp.self_ty)?,
+        fallible,
+    })
+}
+
+fn is_non_exhaustive(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|x| x.path.is_ident("non_exhaustive"))
+}
+
+/// The attributes in `TRIAGE_ATTRIBUTES` which are on the item
+fn triage_attributes(attrs: &[Attribute]) -> Vec<String> {
+    TRIAGE_ATTRIBUTES
+        .iter()
+        .filter(|name| attrs.iter().any(|x| x.path.is_ident(name)))
+        .map(|x| x.to_string())
+        .collect()
+}
+
+/// Returns true for `Error`, `std::error::Error` and the like but not other traits called
+/// `Error` such as `serde::de::Error`
+fn is_error_trait(path: &Path) -> bool {
+    let segments = path.segments.iter().rev().take(2).collect::<Vec<_>>();
+    match segments.as_slice() {
+        [last] => last.ident == "Error",
+        [last, module] => last.ident == "Error" && module.ident == "error",
+        _ => false,
+    }
+}
+
+/// The distinct preconditions of the std calls in the sites, in the order they're called
+fn preconditions(sites: &[PanicSite]) -> Vec<&'static Precondition> {
+    let mut preconditions = vec![];
+    for precondition in sites.iter().filter_map(|x| x.precondition) {
+        if !preconditions.contains(&precondition) {
+            preconditions.push(precondition);
+        }
+    }
+    preconditions
+}
+
+/// Lists the preconditions at the end of a `# Panics` stub, as they're a starting point for
+/// describing when the item panics
+fn add_preconditions(stub: &mut Suggestion, preconditions: &[&Precondition]) {
+    if preconditions.is_empty() {
+        return;
+    }
+    let last = stub
+        .suggested_replacement
+        .lines()
+        .last()
+        .unwrap_or_default();
+    let prefix = last[..last.find("///").unwrap_or(0) + 3].to_string();
+    stub.suggested_replacement
+        .push_str(&format!("{}\n{} It calls:\n{}\n", prefix, prefix, prefix));
+    for precondition in preconditions {
+        stub.suggested_replacement.push_str(&format!(
+            "{} - `{}`, which {}\n",
+            prefix, precondition.name, precondition.condition
+        ));
+    }
+}
+
+/// The conditions under which the sites panic, from the `#[cfg]`s on the item and the statements
+/// around each site, like `cfg(not(feature = "safe-mode"))`. Sites which are always compiled
+/// aren't included.
+fn cfg_conditions(attrs: &[Attribute], sites: &[PanicSite]) -> Vec<String> {
+    let item = cfg_predicates(attrs);
+    let mut conditions = vec![];
+    for site in sites {
+        let predicates = item.iter().chain(&site.cfg).cloned().collect::<Vec<_>>();
+        let condition = match predicates.as_slice() {
+            [] => continue,
+            [predicate] => format!("cfg({})", predicate),
+            _ => format!("cfg(all({}))", predicates.join(", ")),
+        };
+        if !conditions.contains(&condition) {
+            conditions.push(condition);
+        }
+    }
+    conditions
+}
+
+/// The different kinds of panic in the sites, in the order they're first found
+fn distinct_kinds(sites: &[PanicSite]) -> Vec<PanicKind> {
+    let mut kinds = vec![];
+    for site in sites {
+        if !kinds.contains(&site.kind) {
+            kinds.push(site.kind);
+        }
+    }
+    kinds
+}
+
+/// Tags a finding for a function which only forwards its parameters to another call, these are
+/// often reported for the panics of what they call and can be skipped to cut down the noise
+fn tag_delegation(mut location: PanicLocation, sig: &Signature, block: &Block) -> PanicLocation {
+    if is_delegation(sig, block) {
+        location.tags.push(DELEGATION.to_string());
+    }
+    location
+}
+
+/// Returns true if the function uses a non-Rust ABI like `extern "C"`, a bare `extern` is "C"
+fn is_foreign_abi(sig: &Signature) -> bool {
+    match &sig.abi {
+        Some(abi) => abi.name.as_ref().is_none_or(|x| x.value() != "Rust"),
+        None => false,
+    }
+}
+
+/// The spans of the `catch_unwind` calls in a block, panics inside them don't unwind any further
+fn catch_unwind_spans(block: &Block) -> Vec<Span> {
+    struct Finder(Vec<Span>);
+    impl<'ast> visit::Visit<'ast> for Finder {
+        fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+            if let Expr::Path(p) = &*call.func {
+                if p.path
+                    .segments
+                    .last()
+                    .is_some_and(|x| x.ident == "catch_unwind")
+                {
+                    self.0.push(call.span());
+                }
+            }
+            visit::visit_expr_call(self, call);
+        }
+    }
+    let mut finder = Finder(vec![]);
+    visit::Visit::visit_block(&mut finder, block);
+    finder.0
+}
+
+/// Returns true if `inner` is within `outer`
+fn contains_span(outer: Span, inner: Span) -> bool {
+    let start = |x: Span| (x.start().line, x.start().column);
+    let end = |x: Span| (x.end().line, x.end().column);
+    start(outer) <= start(inner) && end(inner) <= end(outer)
+}
+
+/// Guesses whether an enum comes from another crate, its path names a module which isn't part of
+/// this crate and there's no enum with its name in the file
+fn is_foreign_enum(path: &[String], index: &FileIndex) -> bool {
+    match path {
+        [first, .., name] => {
+            !matches!(first.as_str(), "crate" | "self" | "super" | "Self")
+                && !index.enums.contains(name)
+        }
+        _ => false,
     }
 }
 
@@ -234,9 +2680,220 @@ fn is_public(visibility: &Visibility) -> bool {
     matches!(visibility, &Visibility::Public(_))
 }
 
+/// Returns true for what derive macros leave in `cargo expand` output, impls marked
+/// `#[automatically_derived]` and the `#[doc(hidden)] const _: () = { .. };` blocks crates like
+/// serde wrap their impls in
+fn is_derive_residue(item: &Item) -> bool {
+    match item {
+        Item::Impl(i) => i
+            .attrs
+            .iter()
+            .any(|x| x.path.is_ident("automatically_derived")),
+        Item::Const(c) => c.attrs.iter().any(|x| {
+            x.path.is_ident("doc")
+                && matches!(x.parse_meta(), Ok(Meta::List(list))
+                    if list.nested.iter().any(|x| matches!(x, NestedMeta::Meta(Meta::Path(p)) if p.is_ident("hidden"))))
+        }),
+        _ => false,
+    }
+}
+
+/// Returns true for trait impls like `impl<T: Display> Trait for T` which apply to every type
+/// meeting some bounds
+fn is_blanket_impl(imp: &ItemImpl) -> bool {
+    let param = match &*imp.self_ty {
+        Type::Path(p) if p.qself.is_none() => p.path.get_ident(),
+        _ => None,
+    };
+    imp.trait_.is_some()
+        && param
+            .map(|x| imp.generics.type_params().any(|p| p.ident == *x))
+            .unwrap_or(false)
+}
+
+/// Returns true for an impl of `Index` or `IndexMut`
+fn is_index_impl(imp: &ItemImpl) -> bool {
+    imp.trait_
+        .as_ref()
+        .and_then(|(_, path, _)| path.segments.last())
+        .is_some_and(|x| x.ident == "Index" || x.ident == "IndexMut")
+}
+
+/// Returns true if the function returns a `Result` of any kind, like `io::Result<()>`
+fn returns_result(sig: &Signature) -> bool {
+    match &sig.output {
+        ReturnType::Type(_, ty) => type_name(ty).is_some_and(|x| x == "Result"),
+        ReturnType::Default => false,
+    }
+}
+
+/// The type a function named `new` or `new_*` returns, looking inside a `Result`, `Option` or
+/// smart pointer
+fn constructed_type(sig: &Signature) -> Option<String> {
+    let name = sig.ident.to_string();
+    if name != "new" && !name.starts_with("new_") {
+        return None;
+    }
+    returned_type(sig).filter(|x| x != "Self")
+}
+
+/// Returns true if an associated function of `type_name` builds one, it has no receiver and
+/// returns `Self` or the type, maybe inside a `Result`, `Option` or smart pointer
+fn is_constructor(sig: &Signature, type_name: &str) -> bool {
+    sig.receiver().is_none() && returned_type(sig).is_some_and(|x| x == "Self" || x == type_name)
+}
+
+/// The type a function returns, looking inside a `Result`, `Option` or smart pointer
+fn returned_type(sig: &Signature) -> Option<String> {
+    let mut ty = match &sig.output {
+        ReturnType::Type(_, ty) => &**ty,
+        ReturnType::Default => return None,
+    };
+    while let Type::Path(p) = ty {
+        let last = p.path.segments.last()?;
+        let wrapper = ["Result", "Option", "Box", "Rc", "Arc"]
+            .iter()
+            .any(|x| last.ident == x);
+        let inner = match &last.arguments {
+            PathArguments::AngleBracketed(args) if wrapper => {
+                args.args.iter().find_map(|x| match x {
+                    GenericArgument::Type(ty) => Some(ty),
+                    _ => None,
+                })
+            }
+            _ => None,
+        };
+        match inner {
+            Some(inner) => ty = inner,
+            None => break,
+        }
+    }
+    type_name(ty)
+}
+
+/// A panic which says it's about threads, used by types which check which thread they're on
+/// at runtime to uphold a manual `Send` implementation
+fn is_threading_panic(site: &PanicSite) -> bool {
+    site.kind == PanicKind::Panic
+        && site
+            .message
+            .as_ref()
+            .map(|x| x.to_lowercase())
+            .map(|x| x.contains("thread") || x.contains("not send"))
+            .unwrap_or(false)
+}
+
+fn is_fn_trait_bound(bound: &TypeParamBound) -> bool {
+    match bound {
+        TypeParamBound::Trait(t) => t
+            .path
+            .segments
+            .last()
+            .map(|x| x.ident == "Fn" || x.ident == "FnMut" || x.ident == "FnOnce")
+            .unwrap_or(false),
+        TypeParamBound::Lifetime(_) => false,
+    }
+}
+
+/// Returns true if the type is a closure or function pointer, looking through references and
+/// generic arguments so `Box<dyn Fn()>` and `Option<F>` are caught
+fn is_callback_type(ty: &Type, callback_generics: &[Ident]) -> bool {
+    match ty {
+        Type::BareFn(_) => true,
+        Type::ImplTrait(t) => t.bounds.iter().any(is_fn_trait_bound),
+        Type::TraitObject(t) => t.bounds.iter().any(is_fn_trait_bound),
+        Type::Reference(r) => is_callback_type(&r.elem, callback_generics),
+        Type::Paren(p) => is_callback_type(&p.elem, callback_generics),
+        Type::Group(g) => is_callback_type(&g.elem, callback_generics),
+        Type::Path(p) if p.qself.is_none() => {
+            if let Some(ident) = p.path.get_ident() {
+                return callback_generics.contains(ident);
+            }
+            p.path.segments.iter().any(|seg| match &seg.arguments {
+                PathArguments::AngleBracketed(args) => args.args.iter().any(|arg| match arg {
+                    GenericArgument::Type(t) => is_callback_type(t, callback_generics),
+                    _ => false,
+                }),
+                _ => false,
+            })
+        }
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::report::{compact_ident, Finding};
+
+    #[test]
+    fn panic_lines() {
+        let code = r#"
+pub fn explicit(x: u8) {
+    let y = x + 1;
+    println!("{}", y);
+    panic!("boom");
+}
+
+pub fn chained(v: Vec<u8>) -> u8 {
+    v.iter()
+        .copied()
+        .max()
+        .unwrap()
+}
+
+pub struct Thing(Option<u8>);
+
+impl Thing {
+    pub fn get(&self) -> u8 {
+        let a = 1;
+        self.0
+            .expect("set")
+            + a
+    }
+}
+
+pub fn closure() -> Vec<u8> {
+    vec![Some(1)].into_iter().map(|x| {
+        x.unwrap()
+    }).collect()
+}
+
+impl std::ops::Deref for Thing {
+    type Target = u8;
+    fn deref(&self) -> &u8 {
+        let _ = 1;
+        todo!()
+    }
+}
+"#;
+        let options = Options {
+            check_deref: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+            .with_options(options)
+            .process();
+        // The item's span covers the whole item for finding its docs, the panic line is where the
+        // panic is, not the opening brace
+        let lines = panik
+            .iter()
+            .map(|x| (x.ident(), x.lines(), x.panic_line()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            &[
+                ("explicit", (2, 6), 5),
+                ("chained", (8, 13), 12),
+                ("Thing::get", (18, 23), 21),
+                ("closure", (26, 30), 28),
+                ("Thing::deref", (34, 37), 36),
+            ]
+        );
+        // A method call spanning several lines is underlined on the line of the call
+        assert_eq!(panik[1].context().lines[1], "        .unwrap()");
+        assert_eq!(panik[1].context().underline, 9..15);
+    }
 
     #[test]
     fn undocumented_panics() {
@@ -245,14 +2902,23 @@ mod tests {
                 pub fn foobar() {
                     panic!("mwhahahahaha");
                 }
-            "#
-        .to_string();
+            "#;
 
         let ast_walker = AstWalker::new_with_source(PathBuf::from("bad_code.rs"), naughty_code);
 
         let panik = ast_walker.process();
         assert_eq!(panik.len(), 1);
         assert_eq!(panik[0].ident, "foobar");
+        assert_eq!(panik[0].panic_line(), 4);
+        assert_eq!(
+            panik[0].context().lines,
+            &[
+                "                pub fn foobar() {",
+                "                    panic!(\"mwhahahahaha\");",
+                "                }",
+            ]
+        );
+        assert_eq!(panik[0].context().underline, 20..42);
 
         let naughty_code = r#"
             pub mod baz {
@@ -260,8 +2926,7 @@ mod tests {
                 pub fn foobar() {
                     panic!("mwhahahahaha");
                 }
-            }"#
-        .to_string();
+            }"#;
 
         let ast_walker = AstWalker::new_with_source(PathBuf::from("bad_code.rs"), naughty_code);
 
@@ -294,10 +2959,2706 @@ mod tests {
                         println!("You should implement me ya know");
                     }
                 }
-            "#
-        .to_string();
+            "#;
 
         let ast_walker = AstWalker::new_with_source(PathBuf::from("good.rs"), good_code);
         assert!(ast_walker.process().is_empty());
     }
+
+    #[test]
+    fn deferred_closure_panics() {
+        let code = r#"
+            pub struct Hooks {
+                pub on_error: Box<dyn Fn(&str)>,
+            }
+
+            impl Hooks {
+                pub fn new() -> Self {
+                    Self {
+                        on_error: Box::new(|e| panic!("{}", e)),
+                    }
+                }
+            }
+        "#;
+
+        let ast_walker = AstWalker::new_with_source(PathBuf::from("hooks.rs"), code);
+        let panik = ast_walker.process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(panik[0].ident, "Hooks::new");
+        assert_eq!(panik[0].tags, &["deferred-closure"]);
+        assert!(panik[0].notes[0].contains("closure is invoked"));
+    }
+
+    #[test]
+    fn deferred_iterator_panics() {
+        let code = r#"
+            pub struct Ids {
+                raw: Vec<String>,
+            }
+
+            impl Ids {
+                pub fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+                    self.raw.iter().map(|s| s.parse().unwrap())
+                }
+
+                pub fn sorted(&self) -> Vec<u32> {
+                    let mut ids = self.raw.iter().map(|s| s.parse().unwrap()).collect::<Vec<_>>();
+                    ids.sort();
+                    ids
+                }
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("ids.rs"), code).process();
+        assert_eq!(panik.len(), 2);
+        assert_eq!(panik[0].tags, &[DEFERRED_ITERATOR]);
+        assert!(panik[0].notes[0].contains("while the caller iterates"));
+        assert!(panik[1].tags.is_empty());
+    }
+
+    #[test]
+    fn trait_level_docs() {
+        let code = r#"
+            pub trait MethodDocs {
+                /// # Panics
+                ///
+                /// Always panics unless overridden
+                fn capacity(&self) -> usize {
+                    unimplemented!()
+                }
+            }
+
+            /// Default implementations panic unless `capacity` is overridden
+            pub trait TraitDocs {
+                fn capacity(&self) -> usize {
+                    unimplemented!()
+                }
+            }
+
+            /// A trait with no mention of what happens
+            pub trait NoDocs {
+                fn capacity(&self) -> usize {
+                    unimplemented!()
+                }
+            }
+        "#;
+
+        let ast_walker = AstWalker::new_with_source(PathBuf::from("traits.rs"), code);
+        let panik = ast_walker.process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(panik[0].ident, "NoDocs::capacity");
+
+        let options = Options {
+            ignore_trait_docs: true,
+            ..Default::default()
+        };
+        let ast_walker =
+            AstWalker::new_with_source(PathBuf::from("traits.rs"), code).with_options(options);
+        let idents = ast_walker
+            .process()
+            .iter()
+            .map(|x| x.ident.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(idents, &["TraitDocs::capacity", "NoDocs::capacity"]);
+    }
+
+    #[test]
+    fn binary_roots() {
+        let code = r#"
+            fn main() {
+                let x: i32 = std::env::args().nth(1).unwrap().parse().unwrap();
+                println!("{}", x);
+            }
+
+            pub fn helper() {
+                todo!()
+            }
+
+            pub mod cli {
+                pub fn main() {
+                    todo!()
+                }
+            }
+        "#;
+
+        let idents = |options: Options| {
+            AstWalker::new_with_source(PathBuf::from("src/bin/foo.rs"), code)
+                .with_options(options)
+                .process()
+                .iter()
+                .map(|x| x.ident.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let binary_root = Options {
+            binary_root: true,
+            ..Default::default()
+        };
+        assert_eq!(idents(binary_root.clone()), &["helper", "cli::main"]);
+
+        let check_binaries = Options {
+            check_binaries: true,
+            ..binary_root
+        };
+        assert_eq!(
+            idents(check_binaries.clone()),
+            &["main", "helper", "cli::main"]
+        );
+
+        let kinds = |options: Options| {
+            AstWalker::new_with_source(PathBuf::from("examples/foo.rs"), code)
+                .with_options(options)
+                .process()
+                .iter()
+                .map(|x| x.kind)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            kinds(check_binaries.clone()),
+            &[
+                FindingKind::EntryPoint,
+                FindingKind::UndocumentedPanic,
+                FindingKind::UndocumentedPanic
+            ]
+        );
+        let strict_main = Options {
+            strict_main: true,
+            ..check_binaries
+        };
+        assert!(kinds(strict_main)
+            .iter()
+            .all(|x| *x == FindingKind::UndocumentedPanic));
+    }
+
+    #[test]
+    fn suggestions_document_panics() {
+        let code = r#"
+/// Does foo things
+#[inline]
+pub fn foo(x: Option<i32>) -> i32 {
+    x.unwrap()
+}
+
+pub mod inner {
+    pub fn bar(f: impl Fn()) {
+        f();
+        panic!();
+    }
+}
+
+pub struct Baz;
+
+impl Baz {
+    #[must_use]
+    pub fn with(&self, f: impl Fn()) {
+        f()
+    }
+}
+"#;
+        let options = Options {
+            check_callback_propagation: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("fix.rs"), code)
+            .with_options(options.clone())
+            .process();
+        assert_eq!(panik.len(), 3);
+
+        let first = panik[0].suggestion().unwrap();
+        assert_eq!(first.line_start, 3);
+        assert_eq!(
+            first.suggestion_applicability,
+            Applicability::MaybeIncorrect
+        );
+        assert_eq!(
+            first.suggested_replacement,
+            "///\n/// # Panics\n///\n/// Describe the conditions under which this panics.\n"
+        );
+        assert!(panik[1]
+            .suggestion()
+            .unwrap()
+            .suggested_replacement
+            .starts_with("    /// # Panics\n"));
+
+        // Apply from the end of the file backwards so the earlier offsets stay valid
+        let mut fixed = code.to_string();
+        for location in panik.iter().rev() {
+            let fix = location.suggestion().unwrap();
+            fixed.replace_range(fix.byte_start..fix.byte_end, &fix.suggested_replacement);
+        }
+        let panik = AstWalker::new_with_source(PathBuf::from("fix.rs"), fixed)
+            .with_options(options)
+            .process();
+        assert!(panik.is_empty());
+    }
+
+    #[test]
+    fn track_caller_helpers() {
+        let code = r#"
+            #[track_caller]
+            pub fn expect_positive(x: i32) -> i32 {
+                assert!(x > 0);
+                if x < 0 { panic!("negative") }
+                x
+            }
+
+            pub struct Foo;
+
+            impl Foo {
+                #[inline]
+                pub fn get(&self) -> i32 {
+                    None.unwrap()
+                }
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("caller.rs"), code).process();
+        assert_eq!(panik.len(), 2);
+        assert_eq!(panik[0].tags, &[TRACK_CALLER]);
+        assert!(panik[0]
+            .suggestion()
+            .unwrap()
+            .suggested_replacement
+            .contains("reported at the location this was called from"));
+        assert!(panik[1].tags.is_empty());
+        assert!(!panik[1]
+            .suggestion()
+            .unwrap()
+            .suggested_replacement
+            .contains("called from"));
+    }
+
+    #[test]
+    fn deref_impls() {
+        let code = r#"
+            pub struct Lazy(Option<String>);
+
+            impl Deref for Lazy {
+                type Target = String;
+                fn deref(&self) -> &String {
+                    self.0.as_ref().unwrap()
+                }
+            }
+
+            impl DerefMut for Lazy {
+                fn deref_mut(&mut self) -> &mut String {
+                    self.0.as_mut().expect("initialised")
+                }
+            }
+
+            /// # Panics
+            ///
+            /// Panics if the value hasn't been initialised
+            impl std::ops::Deref for Documented {
+                type Target = String;
+                fn deref(&self) -> &String {
+                    self.0.as_ref().unwrap()
+                }
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("deref.rs"), code);
+        assert!(walker.process().is_empty());
+
+        let options = Options {
+            check_deref: true,
+            ..Default::default()
+        };
+        let panik = walker.with_options(options).process();
+        assert_eq!(panik.len(), 2);
+        assert_eq!(panik[0].ident, "Lazy::deref");
+        assert_eq!(panik[0].kind, FindingKind::ImplicitCall);
+        assert_eq!(panik[0].notes, &["(implicit via auto-deref)"]);
+        assert_eq!(panik[1].ident, "Lazy::deref_mut");
+    }
+
+    #[test]
+    fn index_impls() {
+        let code = r#"
+            pub struct Registry(HashMap<String, Entry>);
+
+            impl<'a> Index<&'a str> for Registry {
+                type Output = Entry;
+                fn index(&self, name: &'a str) -> &Entry {
+                    self.0.get(name).expect("no such entry")
+                }
+            }
+
+            impl std::ops::IndexMut<&str> for Registry {
+                fn index_mut(&mut self, name: &str) -> &mut Entry {
+                    self.0.get_mut(name).unwrap()
+                }
+            }
+
+            /// # Panics
+            ///
+            /// Panics if the id is past the end of the table
+            impl Index<Id> for Table {
+                type Output = Row;
+                fn index(&self, id: Id) -> &Row {
+                    self.rows.get(id.0).unwrap()
+                }
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("index.rs"), code);
+        assert!(walker.process().is_empty());
+
+        let options = Options {
+            check_index: true,
+            ..Default::default()
+        };
+        let panik = walker.with_options(options).process();
+        assert_eq!(panik.len(), 2);
+        assert_eq!(panik[0].ident, "Registry::index");
+        assert_eq!(panik[0].kind, FindingKind::ImplicitCall);
+        assert!(panik[0].notes[0].starts_with("(panics on an invalid key"));
+        assert_eq!(panik[1].ident, "Registry::index_mut");
+
+        // Ignoring them wins over checking them, including as blanket impls
+        let blanket = r#"
+            impl<T: AsRef<[u8]>> Index<usize> for T {
+                type Output = u8;
+                fn index(&self, i: usize) -> &u8 {
+                    self.as_ref().get(i).unwrap()
+                }
+            }
+        "#;
+        for code in [code, blanket] {
+            let options = Options {
+                check_index: true,
+                check_blanket_impls: true,
+                ignore_index_impls: true,
+                ..Default::default()
+            };
+            let walker = AstWalker::new_with_source(PathBuf::from("index.rs"), code);
+            assert!(walker.with_options(options).process().is_empty());
+        }
+        let options = Options {
+            check_blanket_impls: true,
+            ..Default::default()
+        };
+        let walker = AstWalker::new_with_source(PathBuf::from("index.rs"), blanket);
+        assert_eq!(walker.with_options(options).process().len(), 1);
+    }
+
+    #[test]
+    fn configured_panic_fns() {
+        let code = r#"
+            use our_telemetry::fatal;
+
+            pub fn load(path: &str) -> Config {
+                match read(path) {
+                    Ok(config) => config,
+                    Err(e) => fatal(e),
+                }
+            }
+
+            /// # Panics
+            ///
+            /// If the config can't be saved
+            pub fn save(config: &Config) {
+                if write(config).is_err() {
+                    fatal("unable to save");
+                }
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("lib.rs"), code);
+        assert!(walker.process().is_empty());
+
+        let options = Options {
+            panicking_fns: vec!["our_telemetry::fatal".to_string()],
+            ..Default::default()
+        };
+        assert!(!options.prefilter());
+        let panik = walker.with_options(options).process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(panik[0].ident, "load");
+        assert_eq!(panik[0].kind, FindingKind::ConfiguredPanicFn);
+        assert_eq!(
+            panik[0].notes,
+            ["(calls `our_telemetry::fatal`, which is declared as panicking)"]
+        );
+    }
+
+    #[test]
+    fn fix_hints() {
+        let code = r#"
+            pub fn port(s: &str) -> u16 {
+                s.parse().unwrap()
+            }
+
+            pub fn check(x: u8) {
+                if x > 9 {
+                    panic!("too big");
+                }
+                x.checked_add(1).expect("fits");
+            }
+
+            pub fn later() {
+                unimplemented!()
+            }
+
+            pub fn exit() {
+                std::process::exit(1)
+            }
+
+            /// # Panics
+            ///
+            /// If it isn't a port
+            pub fn documented(s: &str) -> u16 {
+                s.parse().unwrap()
+            }
+        "#;
+        let hints = AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+            .with_options(Options {
+                report_documented: true,
+                ..Default::default()
+            })
+            .process()
+            .iter()
+            .map(|x| x.fix_hint().map(String::from))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            hints,
+            [
+                Some("consider using ? or match".to_string()),
+                Some("add '# Panics\n\nPanics when <condition>.' to the doc comment".to_string()),
+                Some("implement the function or replace with Result::Err".to_string()),
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn panic_strategy_notes() {
+        let no_std =
+            |code: &str| AstWalker::new_with_source(PathBuf::from("lib.rs"), code).is_no_std();
+        assert!(no_std("#![no_std]\npub fn f() {}"));
+        assert!(no_std("#![cfg_attr(not(test), no_std)]\npub fn f() {}"));
+        assert!(!no_std(
+            "#![cfg_attr(docsrs, feature(doc_cfg))]\npub fn f() {}"
+        ));
+        assert!(!no_std("#[no_std]\nmod inner {}"));
+
+        let code = r#"
+            pub fn blink(pin: Option<u8>) {
+                pin.unwrap();
+            }
+
+            /// # Panics
+            ///
+            /// If there's no pin
+            pub fn reset(pin: Option<u8>) {
+                pin.unwrap();
+            }
+        "#;
+        let notes = |options: Options| {
+            AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+                .with_options(Options {
+                    report_documented: true,
+                    ..options
+                })
+                .process()
+                .into_iter()
+                .map(|x| x.notes)
+                .collect::<Vec<_>>()
+        };
+        assert!(notes(Options::default()).iter().all(Vec::is_empty));
+        let abort = notes(Options {
+            panic_abort: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            abort[0],
+            ["(panics abort the process under the release profile)"]
+        );
+        assert!(abort[1].is_empty());
+        let no_std = notes(Options {
+            no_std: true,
+            panic_abort: true,
+            ..Default::default()
+        });
+        assert_eq!(no_std[0], ["(panics halt or reset on this no_std target)"]);
+    }
+
+    #[test]
+    fn into_iter_impls() {
+        let code = r#"
+            pub struct Batch(Vec<Job>);
+
+            impl IntoIterator for Batch {
+                type Item = Job;
+                type IntoIter = std::vec::IntoIter<Job>;
+                fn into_iter(self) -> Self::IntoIter {
+                    if self.0.is_empty() {
+                        panic!("an empty batch can't be run");
+                    }
+                    self.0.into_iter()
+                }
+            }
+
+            impl<'a> IntoIterator for &'a Batch {
+                type Item = &'a Job;
+                type IntoIter = std::slice::Iter<'a, Job>;
+                fn into_iter(self) -> Self::IntoIter {
+                    self.0.iter()
+                }
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("batch.rs"), code);
+        assert!(walker.process().is_empty());
+
+        let options = Options {
+            check_into_iter: true,
+            ..Default::default()
+        };
+        let panik = walker.with_options(options).process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(panik[0].ident, "Batch::into_iter");
+        assert_eq!(panik[0].kind, FindingKind::ImplicitCall);
+        assert_eq!(panik[0].notes, ["(called implicitly by for loop)"]);
+    }
+
+    #[test]
+    fn prefilter() {
+        let code = r#"
+            pub fn first(v: &[u8]) -> u8 { *v.first().unwrap() }
+
+            pub fn stop(code: i32) {
+                telemetry::fatal(code)
+            }
+        "#;
+        // Items on a single line are searched too
+        let walker = AstWalker::new_with_source(PathBuf::from("lib.rs"), code);
+        assert!(walker.options.prefilter());
+        let idents = |walker: &AstWalker| {
+            walker
+                .process()
+                .iter()
+                .map(|x| x.ident().to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(idents(&walker), ["first"]);
+
+        // The text search can't tell `fatal` panics, so declaring it turns the prefilter off
+        let options = Options {
+            panicking_fns: vec!["telemetry::fatal".to_string()],
+            ..Default::default()
+        };
+        let walker = walker.with_options(options);
+        assert!(!walker.options.prefilter());
+        assert_eq!(idents(&walker), ["first", "stop"]);
+
+        let options = Options {
+            no_prefilter: true,
+            ..Default::default()
+        };
+        assert!(!options.prefilter());
+
+        // Checking the public surface turns it off as well
+        let options = Options {
+            surface: Some(Arc::new(Surface::default())),
+            ..Default::default()
+        };
+        assert!(!options.prefilter());
+    }
+
+    #[test]
+    fn operator_impls() {
+        let code = r#"
+            pub struct Money(u64);
+
+            impl Add for Money {
+                type Output = Money;
+                fn add(self, other: Money) -> Money {
+                    Money(self.0.checked_add(other.0).expect("overflow"))
+                }
+            }
+
+            impl<'a> std::ops::Div<&'a Money> for Money {
+                type Output = u64;
+                fn div(self, other: &'a Money) -> u64 {
+                    if other.0 == 0 {
+                        panic!("divide by zero");
+                    }
+                    self.0 / other.0
+                }
+            }
+
+            /// # Panics
+            ///
+            /// Panics if the result is negative
+            impl Sub for Money {
+                type Output = Money;
+                fn sub(self, other: Money) -> Money {
+                    Money(self.0.checked_sub(other.0).unwrap())
+                }
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("money.rs"), code);
+        assert!(walker.process().is_empty());
+
+        let options = Options {
+            check_operators: true,
+            ..Default::default()
+        };
+        let panik = walker.with_options(options).process();
+        assert_eq!(panik.len(), 2);
+        assert_eq!(panik[0].ident, "Money::add");
+        assert_eq!(panik[0].kind, FindingKind::ImplicitCall);
+        assert_eq!(panik[0].tags, ["OPERATOR OVERLOAD"]);
+        assert!(panik[0].notes[0].contains("the `+` operator"));
+        assert_eq!(panik[1].ident, "Money::div");
+        assert!(panik[1].notes[0].contains("the `/` operator"));
+    }
+
+    #[test]
+    fn clone_impls() {
+        let code = r#"
+            pub struct Handle(Option<u32>);
+
+            /// # Panics
+            ///
+            /// Panics if the handle was closed, which doesn't excuse it
+            impl Clone for Handle {
+                fn clone(&self) -> Self {
+                    Handle(Some(self.0.unwrap()))
+                }
+            }
+        "#;
+        let options = Options {
+            check_clone: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("clone.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(panik[0].ident, "Handle::clone");
+        assert_eq!(panik[0].kind, FindingKind::ContractViolation);
+        assert_eq!(panik[0].tags, &["CLONE CONTRACT VIOLATION"]);
+    }
+
+    #[test]
+    fn foreign_non_exhaustive() {
+        let code = r#"
+            use dep::Kind;
+
+            pub enum Local {
+                A,
+                B,
+                C,
+            }
+
+            pub fn local(x: Local) -> u8 {
+                match x {
+                    Local::A => 1,
+                    Local::B => 2,
+                    _ => panic!("unknown local"),
+                }
+            }
+
+            pub fn imported(x: Kind) -> u8 {
+                match x {
+                    Kind::A => 1,
+                    _ => panic!("unknown kind"),
+                }
+            }
+
+            pub fn qualified(x: other::Level) -> u8 {
+                match x {
+                    other::Level::Low | other::Level::High => 1,
+                    _ => panic!("unknown level"),
+                }
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        let found = panik
+            .iter()
+            .map(|x| (x.ident(), x.kind()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("local", FindingKind::UndocumentedPanic),
+                ("imported", FindingKind::ForeignNonExhaustive),
+                ("qualified", FindingKind::ForeignNonExhaustive)
+            ]
+        );
+        assert_eq!(panik[0].tags, ["wildcard-match-arm"]);
+        assert_eq!(panik[1].tags, ["foreign-non-exhaustive"]);
+        assert!(panik[1].notes[0].contains("`dep::Kind`"));
+        assert!(panik[2].notes[0].contains("`other::Level`"));
+        assert_eq!(panik[1].kind().level(), "error");
+    }
+
+    #[test]
+    fn default_impls() {
+        let code = r#"
+            pub struct Config {
+                path: PathBuf,
+            }
+
+            /// # Panics
+            ///
+            /// Panics if there's no home directory
+            impl Default for Config {
+                fn default() -> Self {
+                    Config {
+                        path: dirs::home_dir().expect("no home directory"),
+                    }
+                }
+            }
+
+            impl Default for Limits {
+                fn default() -> Self {
+                    Limits { max: 10 }
+                }
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("config.rs"), code);
+        assert!(walker.process().is_empty());
+
+        let options = Options {
+            check_default: true,
+            ..Default::default()
+        };
+        let panik = walker.with_options(options).process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(panik[0].ident, "Config::default");
+        assert_eq!(panik[0].kind, FindingKind::ContractViolation);
+        assert_eq!(panik[0].tags, &["DEFAULT CONTRACT"]);
+    }
+
+    #[test]
+    fn try_from_impls() {
+        let code = r#"
+            pub struct Port(u16);
+
+            /// # Panics
+            ///
+            /// Panics if the port is zero
+            impl TryFrom<u32> for Port {
+                type Error = TryFromIntError;
+
+                fn try_from(x: u32) -> Result<Self, Self::Error> {
+                    if x == 0 {
+                        panic!("port can't be zero");
+                    }
+                    Ok(Port(u16::try_from(x)?))
+                }
+            }
+
+            impl TryFrom<&str> for Port {
+                type Error = ParseIntError;
+
+                fn try_from(s: &str) -> Result<Self, Self::Error> {
+                    s.parse().map(Port)
+                }
+            }
+        "#;
+        let walk = |check_try_from| {
+            AstWalker::new_with_source(PathBuf::from("port.rs"), code)
+                .with_options(Options {
+                    check_try_from,
+                    ..Default::default()
+                })
+                .process()
+        };
+        assert!(walk(false).is_empty());
+        let panik = walk(true);
+        assert_eq!(panik.len(), 1);
+        assert_eq!(
+            compact_ident(&panik[0].ident),
+            "<Port as TryFrom<u32>>::try_from"
+        );
+        assert_eq!(panik[0].kind, FindingKind::ContractViolation);
+        assert_eq!(panik[0].tags, &["TRY_FROM CONTRACT"]);
+        assert!(panik[0].notes[0].contains("return an error"));
+    }
+
+    #[test]
+    fn fallible_fns() {
+        let code = r#"
+            pub enum Color {
+                Red,
+            }
+
+            /// # Panics
+            ///
+            /// Panics on anything but red
+            impl FromStr for Color {
+                type Err = ();
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    Ok(match s {
+                        "red" => Color::Red,
+                        _ => panic!("bad color"),
+                    })
+                }
+            }
+
+            impl TryFrom<u8> for Color {
+                type Error = ();
+
+                fn try_from(x: u8) -> Result<Self, Self::Error> {
+                    Ok([Color::Red][x as usize].expect("red"))
+                }
+            }
+
+            /// Reads the config
+            ///
+            /// # Panics
+            ///
+            /// Panics if the file isn't UTF-8
+            pub fn documented(path: &Path) -> io::Result<String> {
+                Ok(String::from_utf8(fs::read(path)?).unwrap())
+            }
+
+            pub fn undocumented(raw: &str) -> Result<u16, ParseIntError> {
+                Ok(raw.parse::<u16>()?.checked_add(1).unwrap())
+            }
+
+            pub fn infallible(raw: &str) -> u16 {
+                raw.parse().unwrap()
+            }
+        "#;
+        let walk = |check_fallible_fns| {
+            AstWalker::new_with_source(PathBuf::from("color.rs"), code)
+                .with_options(Options {
+                    check_fallible_fns,
+                    ..Default::default()
+                })
+                .process()
+        };
+        let kinds = |panik: Vec<PanicLocation>| {
+            panik
+                .iter()
+                .map(|x| (compact_ident(&x.ident), x.kind))
+                .collect::<Vec<_>>()
+        };
+        let fallible = |x: &str| (x.to_string(), FindingKind::PanicInFallibleFn);
+        let undocumented = |x: &str| (x.to_string(), FindingKind::UndocumentedPanic);
+        assert_eq!(
+            kinds(walk(false)),
+            [
+                (
+                    "<Color as TryFrom<u8>>::try_from".to_string(),
+                    FindingKind::ImplicitCall
+                ),
+                undocumented("undocumented"),
+                undocumented("infallible"),
+            ]
+        );
+        // Documenting the panics doesn't help, a fallible function should return an error
+        let panik = walk(true);
+        assert!(panik[0]
+            .notes()
+            .iter()
+            .any(|x| x.contains("return an `Err`")));
+        assert_eq!(
+            kinds(panik),
+            [
+                fallible("Color::from_str"),
+                fallible("<Color as TryFrom<u8>>::try_from"),
+                fallible("documented"),
+                fallible("undocumented"),
+                undocumented("infallible"),
+            ]
+        );
+    }
+
+    #[test]
+    fn type_conversions() {
+        let code = r#"
+            pub struct Port(u16);
+
+            /// # Panics
+            ///
+            /// Panics if the port isn't a number
+            impl From<&str> for Port {
+                fn from(s: &str) -> Self {
+                    Port(s.parse().unwrap())
+                }
+            }
+
+            impl TryFrom<u32> for Port {
+                type Error = TryFromIntError;
+
+                fn try_from(x: u32) -> Result<Self, Self::Error> {
+                    Ok(Port(u16::try_from(x).unwrap()))
+                }
+            }
+        "#;
+        let walk = |check_type_conversions| {
+            AstWalker::new_with_source(PathBuf::from("port.rs"), code)
+                .with_options(Options {
+                    check_type_conversions,
+                    ..Default::default()
+                })
+                .process()
+        };
+        // Documenting the panic is enough without the flag
+        let panik = walk(false);
+        assert_eq!(panik.len(), 1);
+        assert_eq!(
+            compact_ident(&panik[0].ident),
+            "<Port as TryFrom<u32>>::try_from"
+        );
+
+        let panik = walk(true);
+        assert_eq!(panik.len(), 2);
+        assert_eq!(compact_ident(&panik[0].ident), "Port::from");
+        assert_eq!(panik[0].kind, FindingKind::ContractViolation);
+        assert_eq!(panik[0].tags, &["FROM IMPL"]);
+        assert!(panik[0].notes[0].contains("implement `TryFrom`"));
+        assert_eq!(panik[1].kind, FindingKind::ImplicitCall);
+    }
+
+    #[test]
+    fn io_write_impls() {
+        let code = r#"
+            use std::fmt;
+            use std::io::{self, Write};
+
+            pub struct Sink(Option<Vec<u8>>);
+
+            impl Write for Sink {
+                fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                    self.0.as_mut().expect("closed").extend_from_slice(buf);
+                    Ok(buf.len())
+                }
+
+                fn flush(&mut self) -> io::Result<()> {
+                    Ok(())
+                }
+            }
+
+            impl std::io::Write for Pipe {
+                fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                    Ok(buf.len())
+                }
+
+                fn flush(&mut self) -> io::Result<()> {
+                    todo!()
+                }
+            }
+
+            impl fmt::Write for Text {
+                fn write_str(&mut self, s: &str) -> fmt::Result {
+                    self.0.as_mut().unwrap().push_str(s);
+                    Ok(())
+                }
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("sink.rs"), code);
+        assert!(walker.process().is_empty());
+
+        let options = Options {
+            check_io_write: true,
+            ..Default::default()
+        };
+        let panik = walker.with_options(options).process();
+        let found = panik
+            .iter()
+            .map(|x| (x.ident(), x.kind()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            found,
+            [
+                ("Sink::write", FindingKind::ContractViolation),
+                ("Pipe::flush", FindingKind::ContractViolation)
+            ]
+        );
+        assert_eq!(panik[0].tags, ["IO CONTRACT"]);
+        assert!(panik[0].notes[0].contains("`Err(io::Error::new(..))`"));
+    }
+
+    #[test]
+    fn io_read_impls() {
+        let code = r#"
+            use std::io::{self, Read};
+
+            pub struct Source(Vec<u8>);
+
+            impl Read for Source {
+                fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                    let n = buf.len().min(self.0.len());
+                    buf[..n].copy_from_slice(&self.0[..n]);
+                    self.0.drain(..n);
+                    Ok(n)
+                }
+
+                fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+                    let data = self.0.split_first().map(|_| self.0.clone()).unwrap();
+                    buf.extend_from_slice(&data);
+                    Ok(data.len())
+                }
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("source.rs"), code);
+        assert!(walker.process().is_empty());
+
+        let options = Options {
+            check_io_read: true,
+            ..Default::default()
+        };
+        let panik = walker.with_options(options).process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(panik[0].ident, "Source::read_to_end");
+        assert_eq!(panik[0].kind, FindingKind::ContractViolation);
+        assert_eq!(panik[0].tags, ["IO CONTRACT"]);
+    }
+
+    #[test]
+    fn hash_impls() {
+        let code = r#"
+            impl Hash for Key {
+                fn hash<H: Hasher>(&self, state: &mut H) {
+                    self.id.lock().unwrap().hash(state);
+                }
+            }
+
+            impl Hash for Fine {
+                fn hash<H: Hasher>(&self, state: &mut H) {
+                    if let Ok(id) = self.id.lock() {
+                        id.hash(state);
+                    }
+                    self.name.unwrap_or_default().hash(state);
+                }
+            }
+        "#;
+        let options = Options {
+            check_hash: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("hash.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(panik[0].ident, "Key::hash");
+        assert_eq!(panik[0].tags, &["HASH CONTRACT"]);
+    }
+
+    #[test]
+    fn renamed_panic_macros() {
+        let code = r#"
+            use std::panic as die;
+
+            pub fn foo() {
+                die!("oh no");
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("die.rs"), code).process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(panik[0].ident, "foo");
+    }
+
+    #[test]
+    fn ord_impls() {
+        let code = r#"
+            impl PartialOrd for Score {
+                fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                    self.0.partial_cmp(&other.0)
+                }
+            }
+
+            impl Ord for Score {
+                fn cmp(&self, other: &Self) -> Ordering {
+                    self.partial_cmp(other).expect("scores aren't NaN")
+                }
+            }
+
+            impl std::cmp::PartialOrd for Total {
+                fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                    Some(self.0.partial_cmp(&other.0).unwrap())
+                }
+            }
+        "#;
+        let options = Options {
+            check_ord: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("ord.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik.len(), 2);
+        assert_eq!(panik[0].ident, "Score::cmp");
+        assert_eq!(panik[0].tags, &["ORDERING CONTRACT"]);
+        assert_eq!(panik[1].ident, "Total::partial_cmp");
+    }
+
+    #[test]
+    fn serde_impls() {
+        let code = r#"
+            impl Serialize for Port {
+                fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+                    s.serialize_u16(self.0.expect("port is set"))
+                }
+            }
+
+            impl<'de> serde::Deserialize<'de> for Port {
+                fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+                    let port = u16::deserialize(d)?;
+                    if port == 0 {
+                        panic!("port can't be zero");
+                    }
+                    Ok(Port(Some(port)))
+                }
+            }
+        "#;
+        let options = Options {
+            check_serde: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("serde.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik.len(), 2);
+        assert_eq!(panik[0].ident, "Port::serialize");
+        assert_eq!(panik[1].ident, "Port::deserialize");
+        assert!(panik.iter().all(|x| x.tags == ["SERDE CONTRACT"]));
+    }
+
+    #[test]
+    fn error_display_impls() {
+        let code = r#"
+            impl fmt::Display for ParseError {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    match self.kind {
+                        Kind::Empty => write!(f, "empty input"),
+                        Kind::Internal => unreachable!(),
+                    }
+                }
+            }
+
+            impl std::error::Error for ParseError {}
+
+            impl fmt::Display for Version {
+                fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                    write!(f, "{}", self.0.expect("version is set"))
+                }
+            }
+
+            impl serde::de::Error for Version {
+                fn custom<T: fmt::Display>(msg: T) -> Self {
+                    panic!("{}", msg)
+                }
+            }
+        "#;
+        let options = Options {
+            check_display_error: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("error.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(panik[0].ident, "ParseError::fmt");
+        assert_eq!(panik[0].kind, FindingKind::ContractViolation);
+        assert_eq!(panik[0].tags, &["ERROR DISPLAY"]);
+        assert_eq!(panik[0].panic_kinds(), &[PanicKind::Unreachable]);
+
+        // Without the flag these are ordinary trait impls, whose methods aren't public
+        let panik = AstWalker::new_with_source(PathBuf::from("error.rs"), code).process();
+        assert!(panik.is_empty());
+    }
+
+    #[test]
+    fn associated_functions() {
+        let code = r#"
+            pub struct Version(u32, u32);
+
+            impl Version {
+                pub fn new(major: u32) -> Self {
+                    Self(major, MINORS.get(&major).copied().unwrap())
+                }
+
+                pub fn parse_str(s: &str) -> Result<Version, Error> {
+                    let (major, minor) = s.split_once('.').expect("has a dot");
+                    Ok(Version(major.parse()?, minor.parse()?))
+                }
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        let idents = panik.iter().map(|x| x.ident()).collect::<Vec<_>>();
+        assert_eq!(idents, ["Version::new", "Version::parse_str"]);
+    }
+
+    #[test]
+    fn private_calls() {
+        let code = r#"
+            pub struct Config {
+                port: u16,
+            }
+
+            impl Config {
+                pub fn parse(s: &str) -> Self {
+                    Self::port_of(s)
+                }
+
+                pub fn from_env() -> Self {
+                    let s = env_value();
+                    Config::parse_port(&s)
+                }
+
+                pub fn reload(&mut self) {
+                    self.refresh();
+                }
+
+                pub fn name(&self) -> &str {
+                    self.describe()
+                }
+
+                fn port_of(s: &str) -> Self {
+                    Config { port: s.parse().unwrap() }
+                }
+
+                fn parse_port(s: &str) -> Self {
+                    Self::port_of(s)
+                }
+
+                fn refresh(&mut self) {
+                    *self = Self::port_of("80");
+                }
+
+                fn describe(&self) -> &str {
+                    "config"
+                }
+            }
+
+            fn env_value() -> String {
+                std::env::var("CONFIG").expect("CONFIG is set")
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("lib.rs"), code);
+        assert!(walker.process().is_empty());
+
+        let options = Options {
+            follow_private_calls: true,
+            ..Default::default()
+        };
+        let panik = walker.with_options(options).process();
+        let idents = panik.iter().map(|x| x.ident()).collect::<Vec<_>>();
+        assert_eq!(
+            idents,
+            ["Config::parse", "Config::from_env", "Config::reload"]
+        );
+        assert_eq!(panik[1].panic_sites(), 2);
+        assert!(panik
+            .iter()
+            .all(|x| x.panic_kinds() == [PanicKind::HelperCall]));
+    }
+
+    #[test]
+    fn private_calls_in_modules() {
+        let code = r#"
+            fn load() -> u16 {
+                80
+            }
+
+            pub fn top() -> u16 {
+                load()
+            }
+
+            pub mod inner {
+                fn load() -> u16 {
+                    std::env::var("PORT").unwrap().parse().unwrap()
+                }
+
+                pub fn read() -> u16 {
+                    load()
+                }
+
+                pub fn read_self() -> u16 {
+                    self::load()
+                }
+
+                pub mod deeper {
+                    pub fn read_super() -> u16 {
+                        super::load()
+                    }
+
+                    pub fn read_crate() -> u16 {
+                        crate::load()
+                    }
+                }
+            }
+        "#;
+        let options = Options {
+            follow_private_calls: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+            .with_options(options)
+            .process();
+        let idents = panik.iter().map(|x| x.ident()).collect::<Vec<_>>();
+        assert_eq!(
+            idents,
+            [
+                "inner::read",
+                "inner::read_self",
+                "inner::deeper::read_super"
+            ]
+        );
+    }
+
+    #[test]
+    fn associated_constructors() {
+        let code = r#"
+            pub struct Config {
+                port: u16,
+            }
+
+            impl Config {
+                pub fn new(port: &str) -> Self {
+                    Self { port: port.parse().unwrap() }
+                }
+
+                pub fn parse_str(s: &str) -> Result<Config, ParseIntError> {
+                    Ok(Self::new(s.split(':').nth(1).unwrap()))
+                }
+
+                pub fn port(&self) -> u16 {
+                    self.port.checked_add(0).unwrap()
+                }
+            }
+        "#;
+        let walk = |options| {
+            AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+                .with_options(options)
+                .process()
+        };
+        let panik = walk(Options {
+            check_struct_new: true,
+            ..Default::default()
+        });
+        assert_eq!(panik.len(), 3);
+        assert_eq!(panik[0].tags(), ["CONSTRUCTOR"]);
+        assert_eq!(panik[0].notes(), ["(constructs `Config`)"]);
+        assert_eq!(panik[1].tags(), ["CONSTRUCTOR"]);
+        assert!(panik[2].tags().is_empty());
+        assert!(walk(Options::default())
+            .iter()
+            .all(|x| x.tags().is_empty() && x.notes().is_empty()));
+
+        // Associated functions returning a `Result` get the fallible function check like free
+        // functions do
+        let kinds = walk(Options {
+            check_fallible_fns: true,
+            ..Default::default()
+        })
+        .iter()
+        .map(|x| (x.ident().to_string(), x.kind))
+        .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                ("Config::new".to_string(), FindingKind::UndocumentedPanic),
+                (
+                    "Config::parse_str".to_string(),
+                    FindingKind::PanicInFallibleFn
+                ),
+                ("Config::port".to_string(), FindingKind::UndocumentedPanic),
+            ]
+        );
+    }
+
+    #[test]
+    fn conversions() {
+        let code = r#"
+            pub struct RawConfig {
+                port: String,
+            }
+
+            impl From<RawConfig> for Config {
+                fn from(raw: RawConfig) -> Self {
+                    Config { port: raw.port.parse().unwrap() }
+                }
+            }
+
+            impl From<u16> for Port {
+                fn from(port: u16) -> Self {
+                    Port(port)
+                }
+            }
+
+            pub fn direct(raw: RawConfig) -> Config {
+                Config::from(raw)
+            }
+
+            pub fn annotated(port: String) -> Config {
+                let raw: RawConfig = RawConfig { port };
+                let config: Config = raw.into();
+                config
+            }
+
+            pub fn unresolved(raw: RawConfig) {
+                apply(raw.into());
+            }
+
+            pub fn port(port: u16) -> Port {
+                Port::from(port)
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("lib.rs"), code);
+        let panik = walker.process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(
+            compact_ident(panik[0].ident()),
+            "<Config as From<RawConfig>>::from"
+        );
+        assert_eq!(panik[0].kind(), FindingKind::ImplicitCall);
+
+        let options = Options {
+            follow_private_calls: true,
+            ..Default::default()
+        };
+        let panik = walker.with_options(options).process();
+        let idents = panik
+            .iter()
+            .map(|x| compact_ident(x.ident()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            idents,
+            ["<Config as From<RawConfig>>::from", "direct", "annotated"]
+        );
+        assert_eq!(panik[2].panic_kinds(), [PanicKind::HelperCall]);
+    }
+
+    #[test]
+    fn editions() {
+        for name in EDITIONS {
+            assert_eq!(name.parse::<Edition>().unwrap().to_string(), *name);
+        }
+        assert_eq!(Edition::default(), Edition::E2021);
+        assert!(Edition::E2015 < Edition::E2018);
+        assert!("2020".parse::<Edition>().is_err());
+    }
+
+    #[test]
+    fn crate_root_docs() {
+        let code = r#"
+            //! A tiny config parser.
+            //!
+            //! # Panics
+            //!
+            //! Every function panics if the input isn't valid UTF-8.
+
+            pub fn parse(input: &[u8]) -> Config {
+                Config::new(std::str::from_utf8(input).unwrap())
+            }
+
+            pub mod raw {
+                pub fn parse(input: &[u8]) -> &str {
+                    std::str::from_utf8(input).unwrap()
+                }
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("src/lib.rs"), code);
+        assert!(walker.docs_mention_panics());
+        let idents = |options: Options| {
+            walker
+                .clone()
+                .with_options(options)
+                .process()
+                .iter()
+                .map(|x| (x.ident().to_string(), x.kind))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            idents(Options::default()),
+            [
+                ("parse".to_string(), FindingKind::UndocumentedPanic),
+                ("raw::parse".to_string(), FindingKind::UndocumentedPanic)
+            ]
+        );
+        // Only items directly in the root inherit its docs
+        let inherit = Options {
+            inherit_module_docs: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            idents(inherit.clone()),
+            [("raw::parse".to_string(), FindingKind::UndocumentedPanic)]
+        );
+        assert_eq!(
+            idents(Options {
+                report_documented: true,
+                ..inherit
+            }),
+            [
+                ("parse".to_string(), FindingKind::DocumentedPanic),
+                ("raw::parse".to_string(), FindingKind::UndocumentedPanic)
+            ]
+        );
+
+        let undocumented = "//! A tiny config parser.\npub fn parse() {}".to_string();
+        assert!(
+            !AstWalker::new_with_source(PathBuf::from("src/lib.rs"), undocumented)
+                .docs_mention_panics()
+        );
+    }
+
+    #[test]
+    fn free_constructors() {
+        let code = r#"
+            pub fn new_config(path: &str) -> io::Result<Box<Config>> {
+                let x = std::fs::read_to_string(path).unwrap();
+                Ok(Box::new(parse(x)))
+            }
+
+            pub fn new() -> Parser {
+                Parser::with_capacity(16).unwrap()
+            }
+
+            pub fn newest() -> Version {
+                VERSIONS.last().unwrap()
+            }
+        "#;
+        let options = Options {
+            check_struct_new: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik.len(), 3);
+        assert_eq!(panik[0].notes, &["(free fn constructing `Config`)"]);
+        assert_eq!(panik[1].notes, &["(free fn constructing `Parser`)"]);
+        assert!(panik[2].notes.is_empty());
+
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        assert!(panik.iter().all(|x| x.notes.is_empty()));
+    }
+
+    #[test]
+    fn panic_details() {
+        let code = r#"
+            /// Gets a value
+            ///
+            /// # Panics
+            ///
+            /// # Examples
+            pub fn empty(x: Option<i32>) -> i32 {
+                x.unwrap()
+            }
+
+            /// # Panics
+            /// Panics.
+            pub fn two_kinds(x: Option<i32>) -> i32 {
+                if x == Some(0) {
+                    panic!("zero");
+                }
+                x.unwrap()
+            }
+
+            /// # Panics
+            ///
+            /// Panics if `x` is zero.
+            ///
+            /// Panics if `x` is `None`.
+            pub fn detailed(x: Option<i32>) -> i32 {
+                if x == Some(0) {
+                    panic!("zero");
+                }
+                x.unwrap()
+            }
+
+            /// Panics if there's no value
+            pub fn no_section(x: Option<i32>) -> i32 {
+                x.unwrap()
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("details.rs"), code);
+        assert!(walker.process().is_empty());
+
+        let options = Options {
+            require_panic_details: true,
+            min_panic_doc_lines: 1,
+            panic_doc_lines_per_kind: 1,
+            ..Default::default()
+        };
+        let panik = walker.with_options(options).process();
+        let idents = panik.iter().map(|x| x.ident.as_str()).collect::<Vec<_>>();
+        assert_eq!(idents, &["empty", "two_kinds"]);
+        assert!(panik.iter().all(|x| x.kind == FindingKind::ThinPanicDocs));
+        assert_eq!(
+            panik[1].notes,
+            &["(the `# Panics` section has 1 lines of explanation, expected at least 2)"]
+        );
+    }
+
+    #[test]
+    fn blanket_impls() {
+        let code = r#"
+            impl<T: Display> Describe for T {
+                fn describe(&self) -> String {
+                    let s = self.to_string();
+                    assert!(!s.is_empty());
+                    s.lines().next().unwrap().to_string()
+                }
+            }
+
+            impl<T> Shout for T where T: AsRef<str> {
+                /// # Panics
+                ///
+                /// Panics if the string is empty
+                fn shout(&self) -> String {
+                    self.as_ref().chars().next().unwrap().to_string()
+                }
+            }
+
+            impl Describe for Foo {
+                fn describe(&self) -> String {
+                    todo!()
+                }
+            }
+
+            impl<T> Wrapper<T> {
+                pub fn get(&self) -> &T {
+                    self.0.as_ref().unwrap()
+                }
+            }
+        "#;
+        let options = Options {
+            check_blanket_impls: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("blanket.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik.len(), 2);
+        assert_eq!(panik[0].ident, "Describe::describe");
+        assert_eq!(panik[0].tags, &["BLANKET IMPL"]);
+        assert_eq!(panik[1].ident, "Wrapper < T >::get");
+        assert!(panik[1].tags.is_empty());
+    }
+
+    #[test]
+    fn send_wrappers() {
+        let code = r#"
+            pub struct Local {
+                inner: Rc<u32>,
+                owner: ThreadId,
+            }
+
+            unsafe impl Send for Local {}
+
+            impl Local {
+                pub fn get(&self) -> u32 {
+                    if thread::current().id() != self.owner {
+                        panic!("Local accessed from a different thread");
+                    }
+                    *self.inner
+                }
+
+                pub fn set(&mut self, x: u32) {
+                    if x == 0 {
+                        panic!("zero isn't allowed");
+                    }
+                }
+            }
+
+            pub struct Plain;
+
+            impl Plain {
+                pub fn get(&self) {
+                    panic!("not on this thread");
+                }
+            }
+        "#;
+
+        let options = Options {
+            check_send_wrappers: true,
+            ..Default::default()
+        };
+        let ast_walker =
+            AstWalker::new_with_source(PathBuf::from("send.rs"), code).with_options(options);
+        let panik = ast_walker.process();
+        assert_eq!(panik.len(), 3);
+        assert_eq!(panik[0].ident, "Local::get");
+        assert_eq!(panik[0].tags, &["THREADING"]);
+        assert_eq!(panik[1].ident, "Local::set");
+        assert!(panik[1].tags.is_empty());
+        assert_eq!(panik[2].ident, "Plain::get");
+        assert!(panik[2].tags.is_empty());
+    }
+
+    #[test]
+    fn callback_propagation() {
+        let code = r#"
+            pub fn with_impl(f: impl FnOnce()) {
+                f()
+            }
+
+            pub fn with_generic<F>(f: F) where F: Fn(i32) -> i32 {
+                f(2);
+            }
+
+            pub fn with_bound<F: FnMut()>(f: &mut F) {
+                f()
+            }
+
+            pub fn with_boxed(f: Option<Box<dyn Fn()>>) {
+            }
+
+            pub fn with_pointer(f: fn()) {
+            }
+
+            /// # Panics
+            ///
+            /// If `f` panics the panic will propagate to the caller
+            pub fn documented(f: impl Fn()) {
+                f()
+            }
+
+            pub fn no_callback(x: i32) -> i32 {
+                x
+            }
+
+            pub struct Foo;
+
+            impl Foo {
+                pub fn method<T: FnOnce()>(&self, t: T) {
+                    t()
+                }
+            }
+        "#;
+
+        let ast_walker = AstWalker::new_with_source(PathBuf::from("callbacks.rs"), code);
+        assert!(ast_walker.process().is_empty());
+
+        let options = Options {
+            check_callback_propagation: true,
+            ..Default::default()
+        };
+        let ast_walker =
+            AstWalker::new_with_source(PathBuf::from("callbacks.rs"), code).with_options(options);
+        let idents = ast_walker
+            .process()
+            .iter()
+            .inspect(|x| assert_eq!(x.kind, FindingKind::CallbackPropagation))
+            .map(|x| x.ident.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            idents,
+            &[
+                "with_impl",
+                "with_generic",
+                "with_bound",
+                "with_boxed",
+                "with_pointer",
+                "Foo::method"
+            ]
+        );
+    }
+
+    #[test]
+    fn delegation_tags() {
+        let code = r#"
+            pub struct Wrapper(Inner);
+
+            impl Wrapper {
+                pub fn for_each(&self, f: impl Fn(u8)) {
+                    self.0.for_each(f)
+                }
+
+                pub fn first(&self, f: impl Fn(u8)) {
+                    self.0.for_each(f);
+                    self.0.first().unwrap();
+                }
+            }
+
+            pub fn get(x: Option<u8>) -> u8 {
+                x.unwrap()
+            }
+        "#;
+        let options = Options {
+            check_callback_propagation: true,
+            ..Default::default()
+        };
+        let ast_walker =
+            AstWalker::new_with_source(PathBuf::from("delegation.rs"), code).with_options(options);
+        let violations = ast_walker.process();
+        assert_eq!(violations.len(), 3);
+        assert_eq!(violations[0].tags(), &[DELEGATION.to_string()]);
+        assert_eq!(violations[0].panic_sites(), 0);
+        assert!(violations[1].tags().is_empty());
+        assert_eq!(violations[1].panic_sites(), 1);
+        assert!(violations[2].tags().is_empty());
+    }
+
+    #[test]
+    fn process_exits() {
+        let code = r#"
+            pub fn fail(code: i32) -> ! {
+                eprintln!("giving up");
+                std::process::exit(code)
+            }
+
+            pub fn check(x: Option<u8>) -> u8 {
+                if x == Some(0) {
+                    std::process::abort();
+                }
+                x.unwrap()
+            }
+        "#;
+        let ast_walker = AstWalker::new_with_source(PathBuf::from("exit.rs"), code);
+        let violations = ast_walker.process();
+        assert_eq!(violations.len(), 2);
+        assert!(violations
+            .iter()
+            .all(|x| x.tags() == [PROCESS_EXIT.to_string()]));
+
+        let options = Options {
+            ignore_process_exit: true,
+            ..Default::default()
+        };
+        let violations = ast_walker.with_options(options).process();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].ident(), "check");
+        assert!(violations[0].tags().is_empty());
+    }
+
+    #[test]
+    fn foreign_functions() {
+        let code = r#"
+            /// # Panics
+            ///
+            /// If `x` is null
+            #[no_mangle]
+            pub extern "C" fn documented(x: *const u8) -> u8 {
+                unsafe { x.as_ref() }.copied().unwrap()
+            }
+
+            extern fn callback(x: Option<u8>) {
+                x.unwrap();
+            }
+
+            #[no_mangle]
+            pub extern "C" fn caught(x: Option<u8>) -> u8 {
+                std::panic::catch_unwind(|| x.unwrap()).unwrap_or(0)
+            }
+
+            pub extern "Rust" fn rust(x: Option<u8>) -> u8 {
+                x.unwrap()
+            }
+        "#;
+        let ast_walker = AstWalker::new_with_source(PathBuf::from("ffi.rs"), code);
+        let idents = ast_walker
+            .process()
+            .iter()
+            .map(|x| x.ident().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(idents, ["caught", "rust"]);
+
+        let options = Options {
+            check_ffi: true,
+            ..Default::default()
+        };
+        let violations = ast_walker.with_options(options).process();
+        let idents = violations.iter().map(|x| x.ident()).collect::<Vec<_>>();
+        assert_eq!(idents, ["documented", "callback", "rust"]);
+        for violation in &violations[..2] {
+            assert_eq!(violation.kind(), FindingKind::ForeignUnwind);
+            assert_eq!(violation.tags(), &[FFI_UB.to_string()]);
+            assert!(violation.suggestion().is_none());
+        }
+        assert_eq!(violations[2].kind(), FindingKind::UndocumentedPanic);
+    }
+
+    #[test]
+    fn panics_near_unsafe() {
+        let code = r#"
+            pub fn name(thing: &Thing) -> &CStr {
+                let p = unsafe { ffi::thing_name(thing.raw) };
+                assert_not_null(p).expect("thing has no name")
+            }
+
+            pub fn len(thing: &Thing) -> usize {
+                thing.len.unwrap()
+            }
+        "#;
+        let ast_walker = AstWalker::new_with_source(PathBuf::from("wrapper.rs"), code);
+        let violations = ast_walker.process();
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].tags(), &[NEAR_UNSAFE.to_string()]);
+        let stub = &violations[0].suggestion().unwrap().suggested_replacement;
+        assert!(stub.contains("invariant the `unsafe` code relies on"));
+        assert!(violations[1].tags().is_empty());
+        let stub = &violations[1].suggestion().unwrap().suggested_replacement;
+        assert!(!stub.contains("unsafe"));
+    }
+
+    #[test]
+    fn option_lookup_hints() {
+        let code = r#"
+            pub fn first_word(text: &str, words: &HashMap<String, u32>) -> u32 {
+                let word = text.split(' ').next().unwrap();
+                *words.get(word).expect("known word")
+            }
+        "#;
+        let walker = AstWalker::new_with_source(PathBuf::from("words.rs"), code);
+        let panik = walker.process();
+        assert_eq!(panik.len(), 1);
+        assert!(panik[0].tags().is_empty());
+        assert!(panik[0].notes.is_empty());
+
+        let options = Options {
+            check_option_lookups: true,
+            ..Default::default()
+        };
+        let panik = walker.with_options(options).process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(panik[0].tags(), &["OPTION LOOKUP"]);
+        assert_eq!(
+            panik[0].notes,
+            [
+                Lookup::Get.hint().to_string(),
+                Lookup::Next.hint().to_string()
+            ]
+        );
+
+        let options = Options {
+            check_option_lookups: true,
+            ignore_lookups: vec![Lookup::Next],
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("words.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik.len(), 1);
+        assert_eq!(panik[0].notes, [Lookup::Get.hint().to_string()]);
+
+        let options = Options {
+            ignore_lookups: vec![Lookup::Next, Lookup::Get],
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("words.rs"), code)
+            .with_options(options)
+            .process();
+        assert!(panik.is_empty());
+    }
+
+    #[test]
+    fn mutex_poison() {
+        let code = r#"
+            pub fn bump(counter: &Mutex<u32>) {
+                let mut count = counter.lock().unwrap();
+                *count += 1;
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lock.rs"), code).process();
+        assert_eq!(panik.len(), 1);
+        assert!(panik[0].tags().is_empty());
+
+        let options = Options {
+            check_lock_poison: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lock.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik[0].tags(), &[MUTEX_POISON.to_string()]);
+        assert!(panik[0].notes[0].contains("handle the `PoisonError` explicitly"));
+
+        // Leaving them out doesn't need them tagged
+        let options = Options {
+            ignore_mutex_poison: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lock.rs"), code)
+            .with_options(options)
+            .process();
+        assert!(panik.is_empty());
+    }
+
+    #[test]
+    fn alternative_modules() {
+        let code = r#"
+            #[cfg(unix)]
+            pub mod imp {
+                pub fn run() {
+                    unix::exec().unwrap();
+                }
+            }
+
+            #[cfg(all(windows, not(target_vendor = "uwp")))]
+            pub mod imp {
+                pub fn run() {
+                    windows::exec().unwrap();
+                }
+            }
+
+            pub mod shim {
+                pub fn run() {
+                    todo!()
+                }
+            }
+
+            pub mod shim {
+                pub fn run() {
+                    unimplemented!()
+                }
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        let idents = panik.iter().map(|x| x.ident()).collect::<Vec<_>>();
+        assert_eq!(
+            idents,
+            [
+                "imp[cfg(unix)]::run",
+                "imp[cfg(all(windows,not(target_vendor=\"uwp\")))]::run",
+                "shim[1]::run",
+                "shim[2]::run"
+            ]
+        );
+
+        // The idents stay distinct through a saved report
+        let findings = panik
+            .iter()
+            .map(|x| Finding::new(x, None))
+            .collect::<Vec<_>>();
+        let loaded: Vec<Finding> =
+            serde_json::from_str(&serde_json::to_string(&findings).unwrap()).unwrap();
+        assert_eq!(loaded, findings);
+    }
+
+    #[test]
+    fn const_fns() {
+        let code = r#"
+            pub const fn with_panic(x: i32) -> i32 {
+                if x < 0 {
+                    panic!("negative")
+                }
+                x
+            }
+
+            impl Celsius {
+                pub const fn new(degrees: f64) -> Self {
+                    assert!(degrees >= -273.15);
+                    match Self::checked(degrees) {
+                        Some(x) => x,
+                        None => panic!("below absolute zero"),
+                    }
+                }
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        let idents = panik.iter().map(|x| x.ident()).collect::<Vec<_>>();
+        assert_eq!(idents, ["with_panic", "Celsius::new"]);
+        assert!(panik.iter().all(|x| x.notes.is_empty()));
+
+        let options = Options {
+            check_const_fn: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik.len(), 2);
+        for location in &panik {
+            assert_eq!(
+                location.notes,
+                &["(const fn, panics at runtime when called outside a const context)"]
+            );
+        }
+    }
+
+    #[test]
+    fn unsafe_block_notes() {
+        let code = r#"
+            pub fn inside(x: &[u8]) -> u8 {
+                unsafe { *x.as_ptr().add(x.len().checked_sub(1).unwrap()) }
+            }
+
+            pub fn after(x: *const u8) -> u8 {
+                let value = unsafe { x.as_ref() };
+                *value.unwrap()
+            }
+
+            /// # Safety
+            ///
+            /// `x` must be valid
+            pub unsafe fn unsafe_fn(x: *const u8) -> u8 {
+                unsafe { *x.as_ref().unwrap() }
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        assert_eq!(panik.len(), 3);
+        assert!(panik
+            .iter()
+            .all(|x| !x.notes.iter().any(|x| x.contains("inside unsafe"))));
+
+        let options = Options {
+            check_unsafe_blocks: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+            .with_options(options)
+            .process();
+        let noted = panik
+            .iter()
+            .filter(|x| {
+                x.notes.contains(
+                    &"(panic inside unsafe block — callers cannot catch this with safe code)"
+                        .to_string(),
+                )
+            })
+            .map(|x| x.ident())
+            .collect::<Vec<_>>();
+        assert_eq!(noted, ["inside"]);
+    }
+
+    #[test]
+    fn format_writes() {
+        let code = r#"
+            pub fn header(out: &mut String, title: &str) {
+                writeln!(out, "title: {}", title).unwrap();
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("fmt.rs"), code).process();
+        assert_eq!(panik.len(), 1);
+        assert!(panik[0].tags().is_empty());
+
+        let options = Options {
+            check_format_writes: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("fmt.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik[0].tags(), &["WRITE UNWRAP"]);
+        assert!(panik[0].notes[0].contains("with `?`"));
+    }
+
+    #[test]
+    fn channel_sends() {
+        let code = r#"
+            pub fn finish(done: &Sender<()>) {
+                done.send(()).unwrap();
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        assert_eq!(panik.len(), 1);
+        assert!(panik[0].tags().is_empty());
+
+        let options = Options {
+            check_channel_sends: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik[0].tags(), &[CHANNEL_SEND.to_string()]);
+        assert!(panik[0].notes[0].contains("if let Err(_) = sender.send(value)"));
+    }
+
+    #[test]
+    fn env_vars() {
+        let code = r#"
+            pub fn database_url() -> String {
+                std::env::var("DATABASE_URL").expect("DATABASE_URL is set")
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        assert_eq!(panik.len(), 1);
+        assert!(panik[0].tags().is_empty());
+
+        let options = Options {
+            check_env_vars: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik[0].tags(), &[ENV_VAR.to_string()]);
+        assert!(panik[0].notes[0].contains(".unwrap_or_else(|_| default)"));
+    }
+
+    #[test]
+    fn file_io() {
+        let code = r#"
+            pub fn load(path: &Path) -> Vec<u8> {
+                std::fs::read(path).unwrap()
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        assert_eq!(panik.len(), 1);
+        assert!(panik[0].tags().is_empty());
+
+        let options = Options {
+            check_file_io: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik[0].tags(), &[FILE_IO.to_string()]);
+        assert!(panik[0].notes[0].contains("with `?`"));
+    }
+
+    #[test]
+    fn sort_keys() {
+        let code = r#"
+            pub fn by_port(hosts: &mut [String]) {
+                hosts.sort_by_key(|x| x.rsplit(':').next().unwrap().parse::<u16>().unwrap());
+            }
+        "#;
+        let walk = |check_sort_keys| {
+            AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+                .with_options(Options {
+                    check_sort_keys,
+                    ..Default::default()
+                })
+                .process()
+        };
+        let panik = walk(false);
+        assert_eq!(panik.len(), 1);
+        assert!(panik[0].tags().is_empty());
+        let panik = walk(true);
+        assert_eq!(panik[0].tags(), &[SORT_CLOSURE_PANIC.to_string()]);
+        assert!(panik[0].notes[0].contains("validating the elements before sorting"));
+    }
+
+    #[test]
+    fn iterator_collect() {
+        let code = r#"
+            use std::num::ParseIntError;
+
+            pub fn ports(raw: &[&str]) -> Vec<u16> {
+                raw.iter().map(|x| x.parse().unwrap()).collect()
+            }
+
+            pub fn try_ports(raw: Option<&[&str]>) -> Result<Vec<u16>, ParseIntError> {
+                let raw = raw.expect("ports are configured");
+                raw.iter().map(|x| x.parse()).collect::<Result<_, _>>()
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        assert_eq!(panik.len(), 2);
+        assert!(panik[0].tags().is_empty());
+
+        let options = Options {
+            check_iterator_collect: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik[0].tags(), &[COLLECT_PANIC.to_string()]);
+        assert!(panik[0].notes[0].contains("and collect with `.collect::<Result<Vec<_>, _>>()?`"));
+        assert!(panik[1].tags().is_empty());
+    }
+
+    #[test]
+    fn utf8_decode() {
+        let code = r#"
+            pub fn name(bytes: Vec<u8>) -> String {
+                String::from_utf8(bytes).unwrap()
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        assert_eq!(panik[0].tags(), &[UTF8_DECODE.to_string()]);
+        assert!(panik[0].notes[0].contains("`String::from_utf8(bytes)?`"));
+        assert!(panik[0].notes[0].contains("`String::from_utf8_lossy(&bytes)`"));
+    }
+
+    #[test]
+    fn const_generic_defaults() {
+        let code = r#"
+            const fn block_size() -> usize {
+                checked(4096)
+            }
+
+            const fn checked(n: usize) -> usize {
+                if n % 8 != 0 {
+                    panic!("not a multiple of 8");
+                }
+                n
+            }
+
+            const fn lanes() -> usize {
+                4
+            }
+
+            pub struct Buffer<const N: usize = { block_size() }> {
+                data: [u8; N],
+            }
+
+            /// A vector of lanes
+            ///
+            /// # Panics
+            ///
+            /// The default fails to compile if the block size isn't a multiple of 8
+            pub struct Documented<const N: usize = { block_size() }>;
+
+            pub enum Simd<const L: usize = { lanes() }, const W: usize = 8> {
+                Lanes([u8; L]),
+            }
+
+            struct Private<const N: usize = { block_size() }>;
+        "#;
+        let walk = |check_const_generic_defaults| {
+            AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+                .with_options(Options {
+                    check_const_generic_defaults,
+                    ..Default::default()
+                })
+                .process()
+        };
+        assert!(walk(false).is_empty());
+        let panik = walk(true);
+        assert_eq!(
+            panik.iter().map(|x| x.ident()).collect::<Vec<_>>(),
+            &["Buffer"]
+        );
+        assert_eq!(panik[0].panic_line(), 17);
+        assert_eq!(panik[0].tags(), &[CONST_GENERIC_DEFAULT.to_string()]);
+        assert!(panik[0].notes[0].contains("const parameter `N`"));
+    }
+
+    #[test]
+    fn cfg_features() {
+        let code = r#"
+            pub fn parse(s: &str) -> u32 {
+                #[cfg(not(feature = "safe-mode"))]
+                {
+                    return s.parse().unwrap();
+                }
+                #[cfg(feature = "safe-mode")]
+                s.parse().unwrap_or_default()
+            }
+
+            #[cfg(unix)]
+            pub fn uid(s: &str) -> u32 {
+                #[cfg(target_os = "linux")]
+                let uid = s.parse().expect("numeric uid");
+                #[cfg(not(target_os = "linux"))]
+                let uid = 0;
+                uid
+            }
+
+            pub fn always(s: &str) -> u32 {
+                s.parse().unwrap()
+            }
+        "#;
+        let walk = |check_cfg_features| {
+            AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+                .with_options(Options {
+                    check_cfg_features,
+                    ..Default::default()
+                })
+                .process()
+        };
+        assert!(walk(false).iter().all(|x| x.notes.is_empty()));
+        let panik = walk(true);
+        assert_eq!(panik.len(), 3);
+        assert_eq!(panik[0].tags(), &[CFG_GATED.to_string()]);
+        assert_eq!(panik[0].notes, &["(cfg(not(feature = \"safe-mode\")))"]);
+        assert_eq!(panik[1].notes, &["(cfg(all(unix, target_os = \"linux\")))"]);
+        assert!(panik[2].tags().is_empty());
+        assert!(panik[2].notes.is_empty());
+    }
+
+    #[test]
+    fn dynamic_dispatch() {
+        let code = r#"
+            pub trait Handler {
+                fn handle(&self, data: &[u8]) {
+                    if data.is_empty() { panic!("no data") }
+                }
+            }
+
+            pub trait Quiet {
+                fn handle(&self) {}
+            }
+
+            pub fn run(handler: &dyn Handler, data: &[u8]) {
+                handler.handle(data);
+            }
+
+            pub fn run_quiet(quiet: Box<dyn Quiet>) {
+                quiet.handle();
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        assert_eq!(
+            panik.iter().map(|x| x.ident()).collect::<Vec<_>>(),
+            &["Handler::handle"]
+        );
+
+        let options = Options {
+            check_dynamic_dispatch: true,
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik.len(), 2);
+        assert_eq!(panik[1].ident(), "run");
+        assert!(panik[1].tags().contains(&DYNAMIC_DISPATCH.to_string()));
+        assert_eq!(panik[1].panic_line(), 13);
+        assert!(panik[1].notes[0].starts_with("(may panic"));
+    }
+
+    #[test]
+    fn pedantic() {
+        let code = r#"
+            pub fn config(path: &str) -> String {
+                std::fs::read_to_string(path).unwrap_or_else(die)
+            }
+
+            pub fn scale(x: u8) -> u8 {
+                if x < 4 {
+                    return x * 2;
+                }
+                unimplemented!()
+            }
+
+            pub fn later() -> u8 {
+                todo!()
+            }
+        "#;
+        let walk = |pedantic| {
+            AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+                .with_options(Options {
+                    pedantic,
+                    ..Default::default()
+                })
+                .process()
+        };
+        let panik = walk(false);
+        assert_eq!(
+            panik.iter().map(|x| x.ident()).collect::<Vec<_>>(),
+            &["scale", "later"]
+        );
+        assert!(panik.iter().all(|x| x.tags().is_empty()));
+
+        let panik = walk(true);
+        let tags = panik
+            .iter()
+            .map(|x| (x.ident(), x.tags().to_vec()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tags,
+            [
+                ("config", vec![OPAQUE_FALLBACK.to_string()]),
+                ("scale", vec![UNFINISHED_BRANCH.to_string()]),
+                ("later", vec![]),
+            ]
+        );
+        assert_eq!(panik[0].panic_kinds, [PanicKind::OpaqueFallback]);
+        assert!(panik[1].notes[0].contains("unfinished branch"));
+    }
+
+    #[test]
+    fn std_preconditions() {
+        let code = r#"
+            pub struct Buffer(Vec<u8>);
+
+            impl Buffer {
+                pub fn split_at(&self, mid: usize) -> (&[u8], &[u8]) {
+                    self.0.split_at(mid.min(self.0.len()))
+                }
+            }
+
+            /// Splits off the header
+            pub fn header(data: Vec<u8>) -> Vec<u8> {
+                let (header, _) = data.split_at(4);
+                header.to_vec()
+            }
+
+            pub fn header_of(buffer: &Buffer) -> usize {
+                let (header, _) = buffer.split_at(4);
+                header.len()
+            }
+
+            pub fn block(n: usize) -> std::num::NonZeroUsize {
+                std::num::NonZeroUsize::new(n).unwrap()
+            }
+        "#;
+        let walk = |options| {
+            AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+                .with_options(options)
+                .process()
+        };
+        let panik = walk(Options::default());
+        assert_eq!(
+            panik.iter().map(|x| x.ident()).collect::<Vec<_>>(),
+            &["block"]
+        );
+        assert!(panik[0].notes.is_empty());
+
+        let panik = walk(Options {
+            check_std_panics: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            panik.iter().map(|x| x.ident()).collect::<Vec<_>>(),
+            &["Buffer::split_at", "header", "header_of", "block"]
+        );
+        let header = &panik[1];
+        assert_eq!(header.panic_line(), 12);
+        assert!(header.tags().contains(&"STD PRECONDITION".to_string()));
+        // The receiver hint is there for judging the custom type's method
+        let note = Precondition::named("split_at").unwrap().note();
+        assert!(note.contains("panics if mid > len"));
+        assert!(note.contains("slices, `Vec`, `str` and `String`"));
+        assert_eq!(header.notes, panik[2].notes);
+        assert_eq!(panik[2].notes, [note]);
+        let finding = serde_json::to_value(Finding::new(header, None)).unwrap();
+        assert_eq!(
+            finding["fix_hint"],
+            "`split_at` panics if mid > len, check it before the call or document it"
+        );
+        assert_eq!(finding["notes"][0], header.notes[0]);
+        assert_eq!(
+            header.suggestion().unwrap().suggested_replacement,
+            "            ///\n\
+             \x20           /// # Panics\n\
+             \x20           ///\n\
+             \x20           /// Describe the conditions under which this panics.\n\
+             \x20           ///\n\
+             \x20           /// It calls:\n\
+             \x20           ///\n\
+             \x20           /// - `split_at`, which panics if mid > len\n"
+        );
+        // Unwrapping a `NonZero*::new` is already a panic, it gains the precondition
+        assert_eq!(panik[3].fix_hint(), Some("consider using ? or match"));
+        assert!(panik[3].notes[0].contains("panics if the value is 0"));
+
+        // The crate's own `split_at` can be left out
+        let panik = walk(Options {
+            check_std_panics: true,
+            ignore_std_panics: vec!["split_at".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(
+            panik.iter().map(|x| x.ident()).collect::<Vec<_>>(),
+            &["block"]
+        );
+    }
+
+    #[test]
+    fn doc_section_order() {
+        let code = r#"
+            /// Gets the first byte
+            ///
+            /// # Examples
+            /// # Panics
+            /// Panics if `v` is empty
+            pub fn first(v: &[u8]) -> u8 { v[0] }
+
+            /// Gets the last byte
+            ///
+            /// # Panics
+            /// Panics if `v` is empty
+            /// # Examples
+            pub fn last(v: &[u8]) -> u8 { v[v.len() - 1] }
+
+            pub struct Buffer;
+
+            impl Buffer {
+                /// # Safety
+                /// Doesn't panic
+                pub fn len(&self) -> usize { 0 }
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code).process();
+        assert!(panik.is_empty());
+
+        let options = Options {
+            doc_section_order: vec![
+                "Panics".to_string(),
+                "Safety".to_string(),
+                "Examples".to_string(),
+            ],
+            ..Default::default()
+        };
+        let panik = AstWalker::new_with_source(PathBuf::from("lib.rs"), code)
+            .with_options(options)
+            .process();
+        assert_eq!(panik.len(), 2);
+        assert!(panik
+            .iter()
+            .all(|x| x.kind == FindingKind::DocSectionOrder && x.suggestion.is_none()));
+        assert_eq!(panik[0].ident, "first");
+        assert_eq!(
+            panik[0].notes,
+            vec![
+                "(`# Panics` should come before `# Examples`, the expected order is Panics, \
+                  Safety, Examples)"
+                    .to_string()
+            ]
+        );
+        assert_eq!(panik[1].ident, "Buffer::len");
+        assert_eq!(
+            panik[1].notes,
+            vec!["(`# Safety` should come after the summary)".to_string()]
+        );
+    }
+
+    #[test]
+    fn triage_attributes() {
+        let code = r#"
+            #[non_exhaustive]
+            pub struct Config {
+                port: Option<u16>,
+            }
+
+            impl Config {
+                #[must_use]
+                #[inline]
+                pub fn port(&self) -> u16 {
+                    self.port.unwrap()
+                }
+            }
+
+            #[deprecated]
+            #[doc(hidden)]
+            pub fn old_port(config: &Config) -> u16 {
+                config.port.unwrap()
+            }
+        "#;
+        let panik = AstWalker::new_with_source(PathBuf::from("config.rs"), code).process();
+        assert_eq!(panik.len(), 2);
+        assert_eq!(
+            panik[0].attributes(),
+            &["must_use", "inline", "non_exhaustive"]
+        );
+        assert_eq!(panik[1].attributes(), &["deprecated"]);
+    }
+
+    #[test]
+    fn check_main() {
+        let code = r#"
+fn main() {
+    let path = std::env::args().nth(1).expect("usage: tool <path>");
+    run(&path).unwrap();
+}
+
+mod cli {
+    fn main() {
+        todo!()
+    }
+}
+"#;
+        let ast_walker = AstWalker::new_with_source(PathBuf::from("src/main.rs"), code);
+        assert!(ast_walker.process().is_empty());
+
+        let options = Options {
+            check_main: true,
+            ..Default::default()
+        };
+        let violations = ast_walker.with_options(options).process();
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].ident(), "main");
+        assert_eq!(violations[0].kind(), FindingKind::EntryPoint);
+        assert!(violations[0].notes()[0].contains("anyhow::Result<()>"));
+
+        let fix = violations[0].suggestion().unwrap();
+        let mut fixed = code.to_string();
+        fixed.replace_range(fix.byte_start..fix.byte_end, &fix.suggested_replacement);
+        assert!(fixed.contains("\nfn main() -> anyhow::Result<()> {\n"));
+    }
 }
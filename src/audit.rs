@@ -0,0 +1,176 @@
+//! Audits a crate from the JSON output of rustdoc (`cargo +nightly rustdoc -- -Z unstable-options
+//! --output-format json`) for when only the docs are available. There are no function bodies, so
+//! this takes an inventory of documented panics and flags items whose names suggest they can
+//! panic but have no "Panics" section. It can't find undocumented panics.
+use crate::ast_walker::FindingKind;
+use crate::doc_sections::section_body;
+use crate::report::{FileReport, Finding, Report};
+use regex::Regex;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+
+/// Names which suggest a function panics rather than returning an error
+pub const DEFAULT_RISK_PATTERNS: &[&str] = &["^unwrap", "^expect", "^index(_mut)?$", "^assert"];
+
+/// Explains what an audit from the docs can and can't find, added to the report
+pub const AUDIT_NOTE: &str = "Audited from rustdoc JSON which has no function bodies, \
+    undocumented panics can't be detected. Findings are documented panics and items whose names \
+    suggest they can panic without a \"Panics\" section.";
+
+/// Loads the rustdoc JSON at `path` and audits it
+pub fn audit_file(path: &Path, risk_patterns: &[Regex]) -> io::Result<Report> {
+    let reader = BufReader::new(File::open(path)?);
+    let value: Value = serde_json::from_reader(reader)?;
+    audit(&value, risk_patterns)
+}
+
+/// Audits the public items of the documented crate
+pub fn audit(rustdoc: &Value, risk_patterns: &[Regex]) -> io::Result<Report> {
+    let index = rustdoc
+        .get("index")
+        .and_then(Value::as_object)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "rustdoc JSON doesn't have an index",
+            )
+        })?;
+    let mut files: BTreeMap<PathBuf, Vec<Finding>> = BTreeMap::new();
+    for (id, item) in index {
+        let local = item.get("crate_id").and_then(Value::as_u64) == Some(0);
+        let public = item.get("visibility").and_then(Value::as_str) == Some("public");
+        let is_fn = item
+            .get("inner")
+            .map(|x| x.get("function").is_some())
+            .unwrap_or(false);
+        if !(local && public && is_fn) {
+            continue;
+        }
+        let name = item.get("name").and_then(Value::as_str).unwrap_or_default();
+        let docs = item.get("docs").and_then(Value::as_str).unwrap_or_default();
+        let (kind, note) = if section_body(docs, "panics").is_some() {
+            (
+                FindingKind::DocumentedPanic,
+                "(documents that it can panic)",
+            )
+        } else if risk_patterns.iter().any(|x| x.is_match(name)) {
+            (
+                FindingKind::UndocumentedRisk,
+                "(the name suggests it can panic but there's no \"Panics\" section)",
+            )
+        } else {
+            continue;
+        };
+        let span = match item.get("span") {
+            Some(span) if !span.is_null() => span,
+            _ => continue,
+        };
+        let filename = span
+            .get("filename")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let line = |key: &str| {
+            span.get(key)
+                .and_then(|x| x.get(0))
+                .and_then(Value::as_u64)
+                .unwrap_or_default() as usize
+        };
+        let ident = rustdoc
+            .pointer(&format!(
+                "/paths/{}/path",
+                id.replace('~', "~0").replace('/', "~1")
+            ))
+            .and_then(Value::as_array)
+            .map(|x| {
+                x.iter()
+                    .skip(1)
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("::")
+            })
+            .unwrap_or_else(|| name.to_string());
+        files
+            .entry(PathBuf::from(filename))
+            .or_default()
+            .push(Finding {
+                ident,
+                kind,
+                start_line: line("begin"),
+                end_line: line("end"),
+                panic_line: line("begin"),
+                context: vec![],
+                underline: None,
+                attributes: vec![],
+                tags: vec![],
+                notes: vec![note.to_string()],
+                snippet: None,
+                suggestion: None,
+                group_id: None,
+                fix_hint: None,
+                level: None,
+            });
+    }
+    let files = files
+        .into_iter()
+        .map(|(path, mut findings)| {
+            findings.sort_by_key(|x| x.start_line);
+            FileReport {
+                path,
+                symlink_target: None,
+                findings,
+            }
+        })
+        .collect();
+    let mut report = Report::new(files);
+    report.crate_docs_mention_panics = rustdoc
+        .get("root")
+        .and_then(Value::as_str)
+        .and_then(|x| index.get(x))
+        .and_then(|x| x.get("docs"))
+        .and_then(Value::as_str)
+        .is_some_and(|x| x.to_lowercase().contains("panic"));
+    report.notes.push(AUDIT_NOTE.to_string());
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audit_fixture() {
+        let rustdoc = serde_json::from_str(include_str!("../tests/fixtures/rustdoc.json")).unwrap();
+        let patterns = DEFAULT_RISK_PATTERNS
+            .iter()
+            .map(|x| Regex::new(x).unwrap())
+            .collect::<Vec<_>>();
+        let report = audit(&rustdoc, &patterns).unwrap();
+        assert_eq!(report.notes, &[AUDIT_NOTE]);
+        let findings = report
+            .files
+            .iter()
+            .flat_map(|x| x.findings.iter().map(move |f| (x.path.clone(), f)))
+            .map(|(path, f)| (path.display().to_string(), f.ident.clone(), f.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            findings,
+            &[
+                (
+                    "src/lib.rs".to_string(),
+                    "parse".to_string(),
+                    FindingKind::DocumentedPanic
+                ),
+                (
+                    "src/value.rs".to_string(),
+                    "unwrap_value".to_string(),
+                    FindingKind::UndocumentedRisk
+                ),
+            ]
+        );
+        assert_eq!(report.files[0].findings[0].start_line, 12);
+        assert!(report.crate_docs_mention_panics);
+    }
+}
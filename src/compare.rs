@@ -0,0 +1,226 @@
+//! Compares which items in the public API of two versions of a crate can panic, for release notes.
+//! This is about behaviour so a documented panic counts the same as an undocumented one.
+use crate::ast_walker::{AstWalker, Options, PanicKind};
+use crate::report::compact_ident;
+use crate::surface::Surface;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A public item which can panic
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PanickingItem {
+    /// The shortest public path to the item
+    pub ident: String,
+    /// The file the item is in, relative to the crate root
+    pub path: PathBuf,
+    pub kinds: Vec<PanicKind>,
+}
+
+/// The differences in which public items can panic between two versions of a crate
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Comparison {
+    /// Items which can panic in the new version but couldn't in the old one
+    pub added: Vec<PanickingItem>,
+    /// Items which could panic in the old version but can't in the new one
+    pub removed: Vec<PanickingItem>,
+    /// Items which can panic in both versions but in different ways, as the old and new item
+    pub changed: Vec<(PanickingItem, PanickingItem)>,
+}
+
+impl Comparison {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Finds the public items of the library crate at `root` which can panic, whether or not they
+/// document it
+pub fn panicking_items(root: &Path, options: &Options) -> io::Result<Vec<PanickingItem>> {
+    let crate_root = root.join("src").join("lib.rs");
+    let surface = Surface::new(&crate_root).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("Unable to load crate root {}: {}", crate_root.display(), e),
+        )
+    })?;
+    let mut files = surface.files().map(Path::to_path_buf).collect::<Vec<_>>();
+    files.sort();
+    let options = Options {
+        surface: Some(Arc::new(surface)),
+        report_documented: true,
+        ..options.clone()
+    };
+    // An item can be reported more than once, for example a trait method in each impl
+    let mut items = BTreeMap::<String, PanickingItem>::new();
+    for file in files {
+        let path = file.strip_prefix(root).unwrap_or(&file).to_path_buf();
+        let walker = AstWalker::new(file.clone())?.with_options(options.clone());
+        walker
+            .process_with(|location| {
+                if location.panic_sites() == 0 {
+                    return;
+                }
+                let ident = compact_ident(location.ident());
+                let item = items.entry(ident.clone()).or_insert_with(|| PanickingItem {
+                    ident,
+                    path: path.clone(),
+                    kinds: vec![],
+                });
+                for kind in location.panic_kinds() {
+                    if !item.kinds.contains(kind) {
+                        item.kinds.push(*kind);
+                    }
+                }
+            })
+            .map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unable to parse {}: {}", file.display(), e),
+                )
+            })?;
+    }
+    Ok(items.into_values().collect())
+}
+
+/// Matches the items of two versions by their public path
+pub fn compare(old: &[PanickingItem], new: &[PanickingItem]) -> Comparison {
+    let old_items = old
+        .iter()
+        .map(|x| (x.ident.as_str(), x))
+        .collect::<BTreeMap<_, _>>();
+    let new_items = new
+        .iter()
+        .map(|x| (x.ident.as_str(), x))
+        .collect::<BTreeMap<_, _>>();
+    let mut comparison = Comparison::default();
+    for (ident, item) in &new_items {
+        match old_items.get(ident) {
+            None => comparison.added.push((*item).clone()),
+            Some(old) if !same_kinds(&old.kinds, &item.kinds) => {
+                comparison.changed.push(((*old).clone(), (*item).clone()))
+            }
+            Some(_) => {}
+        }
+    }
+    for (ident, item) in &old_items {
+        if !new_items.contains_key(ident) {
+            comparison.removed.push((*item).clone());
+        }
+    }
+    comparison
+}
+
+fn same_kinds(a: &[PanicKind], b: &[PanicKind]) -> bool {
+    a.len() == b.len() && a.iter().all(|x| b.contains(x))
+}
+
+fn kind_list(kinds: &[PanicKind]) -> String {
+    kinds
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn render_text(comparison: &Comparison, writer: &mut impl Write) -> io::Result<()> {
+    if comparison.is_empty() {
+        return writeln!(writer, "No changes to which public items can panic");
+    }
+    if !comparison.added.is_empty() {
+        writeln!(writer, "Newly panicking:")?;
+        for item in &comparison.added {
+            writeln!(writer, "\t{} ({})", item.ident, kind_list(&item.kinds))?;
+        }
+    }
+    if !comparison.removed.is_empty() {
+        writeln!(writer, "No longer panicking:")?;
+        for item in &comparison.removed {
+            writeln!(writer, "\t{}", item.ident)?;
+        }
+    }
+    if !comparison.changed.is_empty() {
+        writeln!(writer, "Panicking differently:")?;
+        for (old, new) in &comparison.changed {
+            writeln!(
+                writer,
+                "\t{} ({} -> {})",
+                new.ident,
+                kind_list(&old.kinds),
+                kind_list(&new.kinds)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Renders the comparison as a section which can be pasted into a changelog
+pub fn render_markdown(comparison: &Comparison, writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "### Panics")?;
+    if comparison.is_empty() {
+        return writeln!(writer, "\nNo changes to which public items can panic.");
+    }
+    let code_list = |kinds: &[PanicKind]| {
+        kinds
+            .iter()
+            .map(|x| format!("`{}`", x))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    writeln!(writer)?;
+    for item in &comparison.added {
+        writeln!(
+            writer,
+            "- `{}` can now panic ({})",
+            item.ident,
+            code_list(&item.kinds)
+        )?;
+    }
+    for item in &comparison.removed {
+        writeln!(writer, "- `{}` no longer panics", item.ident)?;
+    }
+    for (old, new) in &comparison.changed {
+        writeln!(
+            writer,
+            "- `{}` panics differently ({} before, {} now)",
+            new.ident,
+            code_list(&old.kinds),
+            code_list(&new.kinds)
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compare_snapshots() {
+        let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/compare");
+        let old = panicking_items(&fixtures.join("old"), &Options::default()).unwrap();
+        let new = panicking_items(&fixtures.join("new"), &Options::default()).unwrap();
+        let comparison = compare(&old, &new);
+
+        let idents =
+            |items: &[PanickingItem]| items.iter().map(|x| x.ident.clone()).collect::<Vec<_>>();
+        assert_eq!(idents(&comparison.added), ["quiet"]);
+        assert_eq!(idents(&comparison.removed), ["fixed"]);
+        assert_eq!(comparison.changed.len(), 1);
+        let (before, after) = &comparison.changed[0];
+        assert_eq!(after.ident, "documented");
+        assert_eq!(before.kinds, [PanicKind::Expect]);
+        assert_eq!(after.kinds, [PanicKind::Panic, PanicKind::Expect]);
+
+        let mut markdown = vec![];
+        render_markdown(&comparison, &mut markdown).unwrap();
+        assert_eq!(
+            String::from_utf8(markdown).unwrap(),
+            "### Panics\n\n\
+             - `quiet` can now panic (`todo`)\n\
+             - `fixed` no longer panics\n\
+             - `documented` panics differently (`expect` before, `panic`, `expect` now)\n"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+//! Gets the source of a crate published on crates.io so it can be analysed like a local project.
+//! The `.crate` archive is taken from the local cargo cache when it's there, otherwise it's
+//! downloaded which needs the `remote` feature.
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tar::Archive;
+
+/// A published crate written as `<name>@<version>`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrateSpec {
+    pub name: String,
+    pub version: String,
+}
+
+impl FromStr for CrateSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('@') {
+            Some((name, version)) if !name.is_empty() && !version.is_empty() => Ok(Self {
+                name: name.to_string(),
+                version: version.to_string(),
+            }),
+            _ => Err(format!("expected <name>@<version>, got `{}`", s)),
+        }
+    }
+}
+
+impl fmt::Display for CrateSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.name, self.version)
+    }
+}
+
+impl CrateSpec {
+    fn file_name(&self) -> String {
+        format!("{}-{}.crate", self.name, self.version)
+    }
+}
+
+/// A crate unpacked into a temporary directory, which is removed when this is dropped
+pub struct UnpackedCrate {
+    /// The directory containing the crate's `Cargo.toml`
+    pub root: PathBuf,
+    /// The SHA-256 of the `.crate` archive, as listed in the registry index and `Cargo.lock`
+    pub checksum: String,
+    dir: PathBuf,
+}
+
+impl Drop for UnpackedCrate {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Reads the `.crate` archive from the cargo cache, or downloads it unless `offline` is set
+pub fn fetch(spec: &CrateSpec, offline: bool) -> io::Result<Vec<u8>> {
+    if let Some(path) = cached_crate(spec) {
+        return fs::read(path);
+    }
+    if offline {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{} isn't in the cargo cache", spec),
+        ));
+    }
+    download(spec)
+}
+
+/// Unpacks a `.crate` archive into `dir`
+pub fn unpack(spec: &CrateSpec, archive: &[u8], dir: &Path) -> io::Result<UnpackedCrate> {
+    let checksum = Sha256::digest(archive)
+        .iter()
+        .map(|x| format!("{:02x}", x))
+        .collect();
+    fs::create_dir_all(dir)?;
+    let unpacked = UnpackedCrate {
+        root: dir.join(format!("{}-{}", spec.name, spec.version)),
+        checksum,
+        dir: dir.to_path_buf(),
+    };
+    Archive::new(GzDecoder::new(archive)).unpack(dir)?;
+    if !unpacked.root.join("Cargo.toml").is_file() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("The archive for {} doesn't contain its Cargo.toml", spec),
+        ));
+    }
+    Ok(unpacked)
+}
+
+/// `CARGO_HOME` if it's set, otherwise where cargo puts it by default
+pub fn cargo_home() -> Option<PathBuf> {
+    match env::var_os("CARGO_HOME") {
+        Some(home) => Some(PathBuf::from(home)),
+        None => env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .map(|x| PathBuf::from(x).join(".cargo")),
+    }
+}
+
+/// The archive in the cargo cache, there's a directory in the cache for each registry
+fn cached_crate(spec: &CrateSpec) -> Option<PathBuf> {
+    let cache = cargo_home()?.join("registry").join("cache");
+    fs::read_dir(cache)
+        .ok()?
+        .filter_map(|x| x.ok())
+        .map(|x| x.path().join(spec.file_name()))
+        .find(|x| x.is_file())
+}
+
+#[cfg(feature = "remote")]
+fn download(spec: &CrateSpec) -> io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let url = format!(
+        "https://crates.io/api/v1/crates/{}/{}/download",
+        spec.name, spec.version
+    );
+    tracing::info!("Downloading {}", url);
+    let response = ureq::get(&url).call().map_err(io::Error::other)?;
+    let mut archive = vec![];
+    response.into_reader().read_to_end(&mut archive)?;
+    Ok(archive)
+}
+
+#[cfg(not(feature = "remote"))]
+fn download(spec: &CrateSpec) -> io::Result<Vec<u8>> {
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!(
+            "{} isn't in the cargo cache and downloading it needs the `remote` feature",
+            spec
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_walker::AstWalker;
+    use crate::dir_walker::get_dir_walker;
+
+    #[test]
+    fn crate_specs() {
+        let spec = "serde_json@1.0.64".parse::<CrateSpec>().unwrap();
+        assert_eq!(spec.name, "serde_json");
+        assert_eq!(spec.version, "1.0.64");
+        assert_eq!(spec.to_string(), "serde_json@1.0.64");
+        assert!("serde_json".parse::<CrateSpec>().is_err());
+        assert!("@1.0.64".parse::<CrateSpec>().is_err());
+    }
+
+    #[test]
+    fn unpack_and_analyse() {
+        let spec = "tiny@0.1.0".parse::<CrateSpec>().unwrap();
+        let archive =
+            fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tiny-0.1.0.crate"))
+                .unwrap();
+        let dir = env::temp_dir().join(format!("doc_panic_checker_unpack-{}", std::process::id()));
+        let unpacked = unpack(&spec, &archive, &dir).unwrap();
+        assert_eq!(
+            unpacked.checksum,
+            "6cc212b981e4e1725f838c4e3734a8379e6c6843717e891c0e3f538cdf0e1842"
+        );
+
+        let mut idents = vec![];
+        for entry in get_dir_walker(unpacked.root.join("src")) {
+            for location in AstWalker::new(entry.path().to_path_buf())
+                .unwrap()
+                .process()
+            {
+                idents.push(location.ident().to_string());
+            }
+        }
+        idents.sort();
+        assert_eq!(idents, ["helper", "port"]);
+
+        drop(unpacked);
+        assert!(!dir.exists());
+    }
+}
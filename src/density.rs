@@ -0,0 +1,265 @@
+//! Counts public items and their panics per top level module, so how dense the panics are can be
+//! tracked over time. Items are counted where they're defined rather than where they're re-exported
+//! so each one is counted once.
+use crate::ast_walker::{FindingKind, PanicLocation};
+use crate::report::ModuleStats;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path};
+use syn::spanned::Spanned;
+use syn::*;
+
+/// The name given to items directly in the crate root
+pub const CRATE_ROOT: &str = "crate";
+
+/// Adds up the stats of each file's items by top level module
+#[derive(Clone, Debug, Default)]
+pub struct ModuleCounter {
+    modules: BTreeMap<String, ModuleStats>,
+}
+
+/// The first and last line of a public item
+type Lines = (usize, usize);
+
+impl ModuleCounter {
+    /// Counts the public items in a file. `path` is relative to the project root and decides the
+    /// module, `locations` are everything found in the file including documented panics. Source
+    /// which can't be parsed isn't counted.
+    pub fn add_file(&mut self, path: &Path, source: &str, locations: &[PanicLocation]) {
+        let file = match parse_file(source) {
+            Ok(file) => file,
+            Err(_) => return,
+        };
+        let file_module = file_module(path);
+        let mut items = BTreeMap::<Lines, Option<String>>::new();
+        let mut inline_modules = vec![];
+        public_items(&file.items, None, &mut items, &mut inline_modules);
+        // Items checked in other ways, like the methods of blanket impls, still count
+        for location in locations {
+            items.entry(location.lines()).or_insert_with(|| {
+                let (line, _) = location.lines();
+                inline_modules
+                    .iter()
+                    .find(|(_, (start, end))| (*start..=*end).contains(&line))
+                    .map(|(name, _)| name.clone())
+            });
+        }
+        let panicking = locations
+            .iter()
+            .filter(|x| {
+                !matches!(
+                    x.kind(),
+                    FindingKind::CallbackPropagation | FindingKind::DocSectionOrder
+                )
+            })
+            .map(|x| x.lines())
+            .collect::<BTreeSet<_>>();
+        let undocumented = locations
+            .iter()
+            .filter(|x| {
+                !matches!(
+                    x.kind(),
+                    FindingKind::CallbackPropagation
+                        | FindingKind::DocumentedPanic
+                        | FindingKind::ThinPanicDocs
+                        | FindingKind::DocSectionOrder
+                )
+            })
+            .map(|x| x.lines())
+            .collect::<BTreeSet<_>>();
+        for (lines, inline_module) in items {
+            let module = file_module
+                .clone()
+                .or(inline_module)
+                .unwrap_or_else(|| CRATE_ROOT.to_string());
+            let stats = self
+                .modules
+                .entry(module.clone())
+                .or_insert_with(|| ModuleStats {
+                    module,
+                    ..Default::default()
+                });
+            stats.items += 1;
+            stats.lines += lines.1 + 1 - lines.0;
+            if panicking.contains(&lines) {
+                stats.items_panicking += 1;
+            }
+            if undocumented.contains(&lines) {
+                stats.items_undocumented += 1;
+            }
+        }
+    }
+
+    /// The stats for each module sorted by name
+    pub fn finish(self) -> Vec<ModuleStats> {
+        self.modules.into_values().collect()
+    }
+}
+
+/// The top level module of a file, the first part of its path under `src` or the project root
+/// for files elsewhere. The crate root has none as it can contain top level modules inline.
+fn file_module(path: &Path) -> Option<String> {
+    let path = path.strip_prefix("src").unwrap_or(path);
+    let mut components = path.components().filter_map(|x| match x {
+        Component::Normal(s) => Some(s.to_string_lossy()),
+        _ => None,
+    });
+    let first = components.next()?;
+    if path == Path::new("lib.rs") || path == Path::new("main.rs") {
+        None
+    } else if components.next().is_none() {
+        Some(first.trim_end_matches(".rs").to_string())
+    } else {
+        Some(first.to_string())
+    }
+}
+
+/// The number of public items in a file, which is how many `AstWalker` checks for panics
+pub fn count_public_items(items: &[Item]) -> usize {
+    let mut result = BTreeMap::new();
+    public_items(items, None, &mut result, &mut vec![]);
+    result.len()
+}
+
+/// Finds the items checked for panics the same way `AstWalker` does: public functions, public
+/// methods of impls and default methods of public traits, in public modules. Items are keyed by
+/// their lines and given the inline top level module they're in.
+fn public_items(
+    items: &[Item],
+    inline_module: Option<&String>,
+    result: &mut BTreeMap<Lines, Option<String>>,
+    inline_modules: &mut Vec<(String, Lines)>,
+) {
+    let mut add = |span: proc_macro2::Span| {
+        let lines = (span.start().line, span.end().line);
+        result.insert(lines, inline_module.cloned());
+    };
+    for item in items {
+        match item {
+            Item::Fn(i) if is_public(&i.vis) => add(i.span()),
+            Item::Trait(i) if is_public(&i.vis) => {
+                for method in &i.items {
+                    if let TraitItem::Method(m) = method {
+                        if m.default.is_some() {
+                            add(m.span());
+                        }
+                    }
+                }
+            }
+            Item::Impl(i) => {
+                for method in &i.items {
+                    if let ImplItem::Method(m) = method {
+                        if is_public(&m.vis) {
+                            add(m.span());
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for item in items {
+        if let Item::Mod(ItemMod {
+            vis: Visibility::Public(_),
+            ident,
+            content: Some((_, content)),
+            ..
+        }) = item
+        {
+            let module = inline_module.cloned().unwrap_or_else(|| {
+                let span = item.span();
+                let name = ident.to_string();
+                inline_modules.push((name.clone(), (span.start().line, span.end().line)));
+                name
+            });
+            public_items(content, Some(&module), result, inline_modules);
+        }
+    }
+}
+
+fn is_public(visibility: &Visibility) -> bool {
+    matches!(visibility, Visibility::Public(_))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_walker::{AstWalker, Options};
+    use std::path::PathBuf;
+
+    fn add(counter: &mut ModuleCounter, path: &str, source: &str) {
+        let options = Options {
+            report_documented: true,
+            ..Default::default()
+        };
+        let locations = AstWalker::new_with_source(PathBuf::from(path), source)
+            .with_options(options)
+            .process();
+        counter.add_file(Path::new(path), source, &locations);
+    }
+
+    #[test]
+    fn two_modules() {
+        let root = r#"
+pub mod small {
+    pub fn quiet() {}
+}
+
+pub fn top() {}
+"#;
+        let big = r#"
+/// # Panics
+///
+/// If it's empty
+pub fn documented(x: Option<u8>) -> u8 {
+    x.unwrap()
+}
+
+pub fn undocumented(x: Option<u8>) -> u8 {
+    x.expect("present")
+}
+
+fn private(x: Option<u8>) -> u8 {
+    x.unwrap()
+}
+
+pub struct Parser;
+
+impl Parser {
+    pub fn parse(&self) {
+        todo!()
+    }
+}
+"#;
+        let mut counter = ModuleCounter::default();
+        add(&mut counter, "src/lib.rs", root);
+        add(&mut counter, "src/big.rs", big);
+        add(&mut counter, "src/big/extra.rs", "pub fn more() {}\n");
+        assert_eq!(
+            counter.finish(),
+            [
+                ModuleStats {
+                    module: "big".to_string(),
+                    items: 4,
+                    items_panicking: 3,
+                    items_undocumented: 2,
+                    // Doc comments count as part of the item
+                    lines: 6 + 3 + 3 + 1,
+                },
+                ModuleStats {
+                    module: "crate".to_string(),
+                    items: 1,
+                    items_panicking: 0,
+                    items_undocumented: 0,
+                    lines: 1,
+                },
+                ModuleStats {
+                    module: "small".to_string(),
+                    items: 1,
+                    items_panicking: 0,
+                    items_undocumented: 0,
+                    lines: 1,
+                },
+            ]
+        );
+    }
+}
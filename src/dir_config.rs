@@ -0,0 +1,378 @@
+//! Overrides for a subtree of the project from `.doc-panic.toml` files, so a directory like
+//! `src/experimental` can relax the checks without changing them for the rest of the crate.
+//!
+//! A file is checked with the config files in each directory from the project root down to its
+//! own directory merged in that order, so the deepest one wins. Lists replace the list from a
+//! shallower file rather than extending it, and `levels` is merged for each finding kind.
+//!
+//! The settings sit between the profile and the command line: a config file changes what the
+//! profile set, and flags given on the command line win over both.
+use crate::ast_walker::Options;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The name of a per-directory config file
+pub const FILE_NAME: &str = ".doc-panic.toml";
+
+/// The levels a finding kind can be set to in `levels`
+pub const LEVELS: &[&str] = &["error", "warning", "note"];
+
+/// The profiles `profile` can be set to
+pub const PROFILES: &[&str] = &["default", "release-gate", "embedded"];
+
+/// The settings a `.doc-panic.toml` can change, named after the command line flags. Anything
+/// which isn't set is left as it is.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DirConfig {
+    /// The profile to start from for the files under it, unless one is given with `--profile`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Report `todo!` and `unimplemented!` whatever the docs say, as the `release-gate` profile
+    /// does
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_blockers: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check_callback_propagation: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check_panics_in_send_fn: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check_panics_in_env_var: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check_panics_in_file_open: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check_panics_in_unsafe_blocks: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub check_lookup_unwraps: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_panic_details: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_process_exit: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_mutex_poison: Option<bool>,
+    /// Whether `--rustdoc-surface` follows `mod foo;` declarations into their files, read for
+    /// `src/lib.rs` only as the surface is found once for the whole crate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recurse_modules: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub panicking_fn: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_fn: Option<Vec<String>>,
+    /// The order for `--check-doc-section-order`, an empty list turns the check off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub doc_section_order: Option<Vec<String>>,
+    /// The level to report each kind of finding at, keyed by its code like `undocumented-panic`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub levels: BTreeMap<String, String>,
+}
+
+impl DirConfig {
+    /// Loads the config file in `dir`, `None` if there isn't one
+    pub fn load_dir(dir: &Path) -> io::Result<Option<Self>> {
+        let path = dir.join(FILE_NAME);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let invalid = |e: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        };
+        let config: Self = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+        if let Some(level) = config
+            .levels
+            .values()
+            .find(|x| !LEVELS.contains(&x.as_str()))
+        {
+            return Err(invalid(format!(
+                "unknown level `{}`, expected one of {}",
+                level,
+                LEVELS.join(", ")
+            )));
+        }
+        if let Some(profile) = config
+            .profile
+            .as_ref()
+            .filter(|x| !PROFILES.contains(&x.as_str()))
+        {
+            return Err(invalid(format!(
+                "unknown profile `{}`, expected one of {}",
+                profile,
+                PROFILES.join(", ")
+            )));
+        }
+        Ok(Some(config))
+    }
+
+    /// Applies `deeper`, the config of a directory below this one, on top of this
+    pub fn merge(mut self, deeper: &DirConfig) -> DirConfig {
+        let deeper = deeper.clone();
+        let mut levels = std::mem::take(&mut self.levels);
+        levels.extend(deeper.levels);
+        DirConfig {
+            profile: deeper.profile.or(self.profile),
+            release_blockers: deeper.release_blockers.or(self.release_blockers),
+            check_callback_propagation: deeper
+                .check_callback_propagation
+                .or(self.check_callback_propagation),
+            check_panics_in_send_fn: deeper
+                .check_panics_in_send_fn
+                .or(self.check_panics_in_send_fn),
+            check_panics_in_env_var: deeper
+                .check_panics_in_env_var
+                .or(self.check_panics_in_env_var),
+            check_panics_in_file_open: deeper
+                .check_panics_in_file_open
+                .or(self.check_panics_in_file_open),
+            check_panics_in_unsafe_blocks: deeper
+                .check_panics_in_unsafe_blocks
+                .or(self.check_panics_in_unsafe_blocks),
+            check_lookup_unwraps: deeper.check_lookup_unwraps.or(self.check_lookup_unwraps),
+            require_panic_details: deeper.require_panic_details.or(self.require_panic_details),
+            ignore_process_exit: deeper.ignore_process_exit.or(self.ignore_process_exit),
+            ignore_mutex_poison: deeper.ignore_mutex_poison.or(self.ignore_mutex_poison),
+            recurse_modules: deeper.recurse_modules.or(self.recurse_modules),
+            panicking_fn: deeper.panicking_fn.or(self.panicking_fn),
+            allow_fn: deeper.allow_fn.or(self.allow_fn),
+            doc_section_order: deeper.doc_section_order.or(self.doc_section_order),
+            levels,
+        }
+    }
+
+    /// Fills in the settings this sets which `options` leaves off or empty, so flags given on
+    /// the command line win. `release_blockers` has no flag and only comes from the profile, so
+    /// it's always changed. The `profile` itself is up to the caller to apply first.
+    pub fn apply(&self, options: &mut Options) {
+        if let Some(value) = self.release_blockers {
+            options.release_blockers = value;
+        }
+        let flags = [
+            (
+                self.check_callback_propagation,
+                &mut options.check_callback_propagation,
+            ),
+            (
+                self.check_panics_in_send_fn,
+                &mut options.check_channel_sends,
+            ),
+            (self.check_panics_in_env_var, &mut options.check_env_vars),
+            (self.check_panics_in_file_open, &mut options.check_file_io),
+            (
+                self.check_panics_in_unsafe_blocks,
+                &mut options.check_unsafe_blocks,
+            ),
+            (self.check_lookup_unwraps, &mut options.check_option_lookups),
+            (
+                self.require_panic_details,
+                &mut options.require_panic_details,
+            ),
+            (self.ignore_process_exit, &mut options.ignore_process_exit),
+            (self.ignore_mutex_poison, &mut options.ignore_mutex_poison),
+        ];
+        for (value, option) in flags {
+            if let Some(value) = value {
+                *option |= value;
+            }
+        }
+        let lists = [
+            (&self.panicking_fn, &mut options.panicking_fns),
+            (&self.allow_fn, &mut options.allowed_fns),
+            (&self.doc_section_order, &mut options.doc_section_order),
+        ];
+        for (value, option) in lists {
+            if let Some(value) = value.as_ref().filter(|_| option.is_empty()) {
+                option.clone_from(value);
+            }
+        }
+    }
+}
+
+impl fmt::Display for DirConfig {
+    /// The settings on one line, for logging
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let json = serde_json::to_string(self).map_err(|_| fmt::Error)?;
+        write!(f, "{}", json)
+    }
+}
+
+/// The config a file is checked with
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Effective {
+    /// The config files which apply to it, shallowest first
+    pub sources: Vec<PathBuf>,
+    /// Those files merged
+    pub config: DirConfig,
+}
+
+/// The config files found while walking a project, each directory is only read once
+#[derive(Debug, Default)]
+pub struct DirConfigs {
+    dirs: HashMap<PathBuf, Option<DirConfig>>,
+}
+
+impl DirConfigs {
+    /// The merged config for the source file at `path` from the directories between `root` and
+    /// the file. Files outside `root` don't have any. A config file which can't be loaded is an
+    /// error the first time it's needed and ignored after that.
+    pub fn for_file(&mut self, root: &Path, path: &Path) -> io::Result<Effective> {
+        let mut effective = Effective::default();
+        let relative = match path.parent().and_then(|x| x.strip_prefix(root).ok()) {
+            Some(relative) => relative,
+            None => return Ok(effective),
+        };
+        let mut dir = root.to_path_buf();
+        let components = relative.components().map(Some);
+        for component in std::iter::once(None).chain(components) {
+            if let Some(component) = component {
+                dir.push(component);
+            }
+            if !self.dirs.contains_key(&dir) {
+                self.dirs.insert(dir.clone(), None);
+                let config = DirConfig::load_dir(&dir)?;
+                self.dirs.insert(dir.clone(), config);
+            }
+            if let Some(config) = &self.dirs[&dir] {
+                effective.config = effective.config.merge(config);
+                effective.sources.push(dir.join(FILE_NAME));
+            }
+        }
+        Ok(effective)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(x: &[&str]) -> Option<Vec<String>> {
+        Some(x.iter().map(|x| x.to_string()).collect())
+    }
+
+    #[test]
+    fn merging() {
+        let root: DirConfig = toml::from_str(
+            r#"
+            release-blockers = true
+            panicking-fn = ["log::fatal"]
+
+            [levels]
+            undocumented-panic = "error"
+            thin-panic-docs = "note"
+            "#,
+        )
+        .unwrap();
+        let deeper: DirConfig = toml::from_str(
+            r#"
+            release-blockers = false
+            recurse-modules = false
+            panicking-fn = []
+
+            [levels]
+            undocumented-panic = "warning"
+            "#,
+        )
+        .unwrap();
+        let merged = root.clone().merge(&deeper);
+        assert_eq!(merged.release_blockers, Some(false));
+        assert_eq!(merged.recurse_modules, Some(false));
+        // Lists are replaced, not extended
+        assert_eq!(merged.panicking_fn, strings(&[]));
+        assert_eq!(merged.levels["undocumented-panic"], "warning");
+        assert_eq!(merged.levels["thin-panic-docs"], "note");
+        // Merging in something which sets nothing changes nothing
+        assert_eq!(root.clone().merge(&DirConfig::default()), root);
+
+        // The profile's setting is changed, flags given on the command line are kept
+        let mut options = Options {
+            release_blockers: true,
+            panicking_fns: vec!["exit".to_string()],
+            check_env_vars: true,
+            ..Default::default()
+        };
+        let config = DirConfig {
+            check_panics_in_env_var: Some(false),
+            check_lookup_unwraps: Some(true),
+            allow_fn: strings(&["log::fatal"]),
+            ..merged.clone()
+        };
+        config.apply(&mut options);
+        assert!(!options.release_blockers);
+        assert_eq!(options.panicking_fns, ["exit"]);
+        assert!(options.check_env_vars);
+        // What the command line leaves unset is filled in
+        assert!(options.check_option_lookups);
+        assert_eq!(options.allowed_fns, ["log::fatal"]);
+        assert_eq!(
+            merged.to_string(),
+            r#"{"release-blockers":false,"recurse-modules":false,"panicking-fn":[],"levels":{"thin-panic-docs":"note","undocumented-panic":"warning"}}"#
+        );
+    }
+
+    #[test]
+    fn invalid_configs() {
+        assert!(toml::from_str::<DirConfig>("check-everything = true").is_err());
+        assert!(toml::from_str::<DirConfig>("release-blockers = \"yes\"").is_err());
+
+        let dir =
+            std::env::temp_dir().join(format!("doc_panic_checker_profile-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(FILE_NAME), "profile = \"nightly\"").unwrap();
+        let error = DirConfig::load_dir(&dir).unwrap_err();
+        fs::write(dir.join(FILE_NAME), "profile = \"release-gate\"").unwrap();
+        let config = DirConfig::load_dir(&dir).unwrap().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(error.to_string().ends_with(
+            "unknown profile `nightly`, expected one of default, release-gate, embedded"
+        ));
+        assert_eq!(config.profile.as_deref(), Some("release-gate"));
+    }
+
+    #[test]
+    fn nested_directories() {
+        let root = Path::new("tests/fixtures/dir_config");
+        let mut configs = DirConfigs::default();
+        let config = |configs: &mut DirConfigs, path: &str| {
+            configs.for_file(root, &root.join(path)).unwrap()
+        };
+
+        let build = config(&mut configs, "build.rs");
+        assert_eq!(build.sources, vec![root.join(FILE_NAME)]);
+        assert_eq!(build.config.panicking_fn, strings(&["log::fatal"]));
+        assert_eq!(build.config.release_blockers, Some(true));
+
+        let lib = config(&mut configs, "src/lib.rs");
+        assert_eq!(lib.sources.len(), 2);
+        assert_eq!(lib.config.panicking_fn, strings(&["log::fatal"]));
+        assert_eq!(lib.config.check_panics_in_env_var, Some(true));
+        assert_eq!(lib.config.levels["undocumented-panic"], "error");
+
+        // A file in the directory with a config file is covered by it
+        let boundary = config(&mut configs, "src/experimental/mod.rs");
+        assert_eq!(
+            boundary.sources,
+            vec![
+                root.join(FILE_NAME),
+                root.join("src").join(FILE_NAME),
+                root.join("src/experimental").join(FILE_NAME),
+            ]
+        );
+        assert_eq!(boundary.config.release_blockers, Some(false));
+        assert_eq!(boundary.config.panicking_fn, strings(&[]));
+        assert_eq!(boundary.config.check_panics_in_env_var, Some(true));
+        assert_eq!(boundary.config.levels["undocumented-panic"], "note");
+        assert_eq!(boundary.config.levels["release-blocker"], "error");
+
+        let nested = config(&mut configs, "src/experimental/parser/mod.rs");
+        assert_eq!(nested, boundary);
+
+        let outside = configs.for_file(root, Path::new("src/lib.rs")).unwrap();
+        assert_eq!(outside, Effective::default());
+    }
+}
@@ -1,8 +1,20 @@
+use std::collections::HashMap;
 use std::env::var;
 use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
+use tracing::warn;
 use walkdir::{DirEntry, WalkDir};
 
+/// A source file found under the project root
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceFile {
+    /// The path the file was reached by from the root, which decides whether it's a test, example
+    /// or hidden and is what's shown in the report
+    pub path: PathBuf,
+    /// The real file if `path` is a symlink, reading `path` reads through the link to this
+    pub symlink_target: Option<PathBuf>,
+}
+
 /// Returns true if the file is a rust source file
 fn is_source_file(entry: &DirEntry) -> bool {
     let p = entry.path();
@@ -11,7 +23,7 @@ fn is_source_file(entry: &DirEntry) -> bool {
 
 /// Returns true if the folder is a target folder
 fn is_target_folder(entry: &Path, target: &Path) -> bool {
-    entry.starts_with(&target)
+    entry.starts_with(target)
 }
 
 /// Returns true if the file or folder is hidden
@@ -63,6 +75,23 @@ fn is_coverable_file_path(
     ignorable_paths && is_part_of_project(e, root.as_ref())
 }
 
+/// Returns true if the file is the root of a binary or example target auto-discovered by cargo,
+/// either `<dir>/<name>.rs` or `<dir>/<name>/main.rs` where `<dir>` is `src/bin` or `examples`
+pub fn is_binary_root(path: &Path, root: &Path) -> bool {
+    let bin = root.join("src").join("bin");
+    let examples = root.join("examples");
+    match path
+        .strip_prefix(&bin)
+        .or_else(|_| path.strip_prefix(&examples))
+    {
+        Ok(relative) => {
+            let components = relative.components().count();
+            components == 1 || (components == 2 && relative.ends_with("main.rs"))
+        }
+        Err(_) => false,
+    }
+}
+
 pub fn get_dir_walker(root: PathBuf) -> impl Iterator<Item = DirEntry> {
     let target = root.join("target");
 
@@ -70,7 +99,50 @@ pub fn get_dir_walker(root: PathBuf) -> impl Iterator<Item = DirEntry> {
     walker
         .filter_entry(move |e| is_coverable_file_path(e.path(), root.clone(), &target))
         .filter_map(|e| e.ok())
-        .filter(|e| is_source_file(e))
+        .filter(is_source_file)
+}
+
+/// The source files under the root. Symlinks are kept at the path they were reached by, even if
+/// they point outside the project, and a file reachable by several paths is only listed once.
+/// The path kept is a real file over a link, then one outside `tests` and `examples` so the file
+/// is still analysed, then the first in order. Dangling links are skipped with a warning. Only
+/// the paths are collected, none of the files are read.
+pub fn source_files(root: PathBuf) -> Vec<SourceFile> {
+    let tests = root.join("tests");
+    let examples = root.join("examples");
+    let rank = |file: &SourceFile| {
+        (
+            file.symlink_target.is_some(),
+            file.path.starts_with(&tests) || file.path.starts_with(&examples),
+        )
+    };
+    let mut entries = get_dir_walker(root.clone()).collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.path().cmp(b.path()));
+    let mut files: Vec<SourceFile> = vec![];
+    let mut seen = HashMap::new();
+    for entry in entries {
+        let path = entry.path().to_path_buf();
+        let canonical = match path.canonicalize() {
+            Ok(canonical) => canonical,
+            Err(e) => {
+                warn!("Skipping {} which can't be resolved: {}", path.display(), e);
+                continue;
+            }
+        };
+        let file = SourceFile {
+            path,
+            symlink_target: entry.path_is_symlink().then(|| canonical.clone()),
+        };
+        match seen.get(&canonical) {
+            Some(&i) if rank(&file) < rank(&files[i]) => files[i] = file,
+            Some(_) => {}
+            None => {
+                seen.insert(canonical, files.len());
+                files.push(file);
+            }
+        }
+    }
+    files
 }
 
 #[cfg(test)]
@@ -121,17 +193,129 @@ mod tests {
         let hidden_file = Path::new(".cargo/src/hello.rs");
         let visible_file = Path::new("src/hello.rs");
 
-        assert!(is_hidden(&hidden_root.join(hidden_file), &hidden_root));
-        assert!(is_hidden(&visible_root.join(hidden_file), &visible_root));
+        assert!(is_hidden(&hidden_root.join(hidden_file), hidden_root));
+        assert!(is_hidden(&visible_root.join(hidden_file), visible_root));
+
+        assert!(!is_hidden(&hidden_root.join(visible_file), hidden_root));
+        assert!(!is_hidden(&visible_root.join(visible_file), visible_root));
+    }
+
+    #[test]
+    fn binary_roots() {
+        let root = Path::new("/foo");
+        assert!(is_binary_root(Path::new("/foo/src/bin/cli.rs"), root));
+        assert!(is_binary_root(Path::new("/foo/src/bin/cli/main.rs"), root));
+        assert!(!is_binary_root(Path::new("/foo/src/bin/cli/args.rs"), root));
+        assert!(!is_binary_root(Path::new("/foo/src/lib.rs"), root));
+        assert!(!is_binary_root(Path::new("/foo/src/binary.rs"), root));
+        assert!(is_binary_root(Path::new("/foo/examples/demo.rs"), root));
+        assert!(is_binary_root(
+            Path::new("/foo/examples/demo/main.rs"),
+            root
+        ));
+        assert!(!is_binary_root(
+            Path::new("/foo/examples/demo/util.rs"),
+            root
+        ));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn symlinked_files() {
+        use std::fs;
+        use std::os::unix::fs::symlink;
+
+        let dir =
+            std::env::temp_dir().join(format!("doc_panic_checker_symlinks-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let root = dir.join("project");
+        let shared = dir.join("shared");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("tests")).unwrap();
+        fs::create_dir_all(&shared).unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::write(shared.join("version.rs"), "").unwrap();
+        fs::write(shared.join("build_info.rs"), "").unwrap();
+        symlink("../../shared/version.rs", root.join("src/version.rs")).unwrap();
+        symlink(shared.join("build_info.rs"), root.join("src/build_info.rs")).unwrap();
+        symlink("../../shared/version.rs", root.join("tests/version.rs")).unwrap();
+        fs::create_dir_all(root.join("examples")).unwrap();
+        symlink(
+            "../../shared/build_info.rs",
+            root.join("examples/build_info.rs"),
+        )
+        .unwrap();
+        symlink("lib.rs", root.join("src/also_lib.rs")).unwrap();
+        symlink("missing.rs", root.join("src/dangling.rs")).unwrap();
 
-        assert!(!is_hidden(&hidden_root.join(visible_file), &hidden_root));
-        assert!(!is_hidden(&visible_root.join(visible_file), &visible_root));
+        let files = source_files(root.clone());
+        let shared = shared.canonicalize().unwrap();
+        assert_eq!(
+            files,
+            vec![
+                SourceFile {
+                    path: root.join("src/build_info.rs"),
+                    symlink_target: Some(shared.join("build_info.rs")),
+                },
+                SourceFile {
+                    path: root.join("src/lib.rs"),
+                    symlink_target: None,
+                },
+                SourceFile {
+                    path: root.join("src/version.rs"),
+                    symlink_target: Some(shared.join("version.rs")),
+                },
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
     fn walk_own_project() {
         let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        let project_files = &["src/main.rs", "src/dir_walker.rs", "src/ast_walker.rs"];
+        let project_files = &[
+            "build.rs",
+            "src/main.rs",
+            "src/lib.rs",
+            "src/dir_walker.rs",
+            "src/ast_walker.rs",
+            "src/audit.rs",
+            "src/compare.rs",
+            "src/crates_io.rs",
+            "src/density.rs",
+            "src/dir_config.rs",
+            "src/doc_sections.rs",
+            "src/github.rs",
+            "src/imports.rs",
+            "src/panic_visitor.rs",
+            "src/ratchet.rs",
+            "src/report.rs",
+            "src/snippets.rs",
+            "src/status.rs",
+            "src/surface.rs",
+            "src/workspace.rs",
+            "src/render/mod.rs",
+            "src/render/fold.rs",
+            "src/render/html.rs",
+            "src/render/junit.rs",
+            "src/render/markdown.rs",
+            "src/render/sarif.rs",
+            "src/render/short.rs",
+            "src/render/text.rs",
+            "examples/embed.rs",
+            "benches/large_report.rs",
+            "tests/fixtures/compare/old/src/lib.rs",
+            "tests/fixtures/compare/new/src/lib.rs",
+            "tests/fixtures/surface/src/lib.rs",
+            "tests/fixtures/surface/src/prelude.rs",
+            "tests/fixtures/surface/src/deep/mod.rs",
+            "tests/fixtures/surface/src/deep/nested.rs",
+            "tests/fixtures/no_std/src/lib.rs",
+            "tests/fixtures/panic_abort/src/lib.rs",
+            "tests/fixtures/workspace/crates/alpha/src/lib.rs",
+            "tests/fixtures/workspace/crates/beta/src/lib.rs",
+            "tests/fixtures/expanded/lib.rs",
+        ];
         let project_files = project_files
             .iter()
             .map(|p| manifest_dir.join(p))
@@ -0,0 +1,221 @@
+//! Picks apart doc comments by their markdown headings, e.g. to find the `# Panics` section.
+
+/// The lines of the section with the given heading, without the `///` prefix. The section runs
+/// until the next heading of the same or a higher level. Returns `None` if there's no such
+/// section, headings are compared case insensitively.
+pub fn section_body(comment: &str, heading: &str) -> Option<Vec<String>> {
+    let mut lines = comment.lines().map(strip_doc_prefix);
+    let mut in_code = false;
+    let level = loop {
+        let line = lines.next()?;
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        match heading_of(line) {
+            Some((level, text)) if !in_code && text.eq_ignore_ascii_case(heading) => break level,
+            _ => {}
+        }
+    };
+    let mut body = vec![];
+    for line in lines {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        match heading_of(line) {
+            Some((l, _)) if !in_code && l <= level => break,
+            _ => body.push(line.to_string()),
+        }
+    }
+    Some(body)
+}
+
+/// Counts the lines of a section body which are written explanation, skipping blank lines, code
+/// blocks and subheadings
+pub fn prose_lines(body: &[String]) -> usize {
+    let mut in_code = false;
+    let mut count = 0;
+    for line in body {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+        } else if !in_code && !trimmed.is_empty() && heading_of(trimmed).is_none() {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// A section which is out of place in a doc comment
+#[derive(Debug, PartialEq, Eq)]
+pub struct Misplaced<'a> {
+    /// The section as it's named in the order
+    pub section: &'a str,
+    /// The section it should come before, or `None` if it should come after the summary
+    pub before: Option<&'a str>,
+}
+
+/// The first section of the comment which isn't where `order` puts it, sections which aren't in
+/// `order` can go anywhere. Any section in `order` has to come after the summary, and before the
+/// sections later in `order` than it.
+pub fn misplaced_section<'a>(comment: &str, order: &'a [String]) -> Option<Misplaced<'a>> {
+    let mut summary = false;
+    let mut seen: Vec<usize> = vec![];
+    let mut in_code = false;
+    for line in comment.lines().map(strip_doc_prefix) {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+        }
+        let heading = match heading_of(line) {
+            Some((1, text)) if !in_code => text,
+            Some(_) if !in_code => continue,
+            _ => {
+                summary |= !line.trim().is_empty();
+                continue;
+            }
+        };
+        let index = match order.iter().position(|x| x.eq_ignore_ascii_case(heading)) {
+            Some(index) => index,
+            None => continue,
+        };
+        let before = if summary {
+            match seen.iter().find(|x| **x > index) {
+                Some(later) => Some(&order[*later]),
+                None => {
+                    seen.push(index);
+                    continue;
+                }
+            }
+        } else {
+            None
+        };
+        return Some(Misplaced {
+            section: &order[index],
+            before: before.map(String::as_str),
+        });
+    }
+    None
+}
+
+fn strip_doc_prefix(line: &str) -> &str {
+    let line = line.trim_start();
+    let line = line.strip_prefix("///").unwrap_or(line);
+    line.strip_prefix(' ').unwrap_or(line)
+}
+
+/// The level and text of a markdown heading
+fn heading_of(line: &str) -> Option<(usize, &str)> {
+    let line = line.trim_start();
+    let level = line.chars().take_while(|x| *x == '#').count();
+    if level > 0 && line[level..].starts_with(' ') {
+        Some((level, line[level..].trim()))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prose(comment: &str) -> Option<usize> {
+        section_body(comment, "Panics").map(|x| prose_lines(&x))
+    }
+
+    #[test]
+    fn empty_section() {
+        let comment = "/// Gets a value\n///\n/// # Panics\n///\n/// # Examples\n/// foo";
+        assert_eq!(prose(comment), Some(0));
+        assert_eq!(prose("/// # Panics"), Some(0));
+        assert_eq!(prose("/// Gets a value, panics if it's missing"), None);
+    }
+
+    #[test]
+    fn one_liner() {
+        let comment = "/// # panics\n/// Panics.\n/// # Details\n/// More";
+        assert_eq!(prose(comment), Some(1));
+        assert_eq!(section_body(comment, "Panics").unwrap(), &["Panics."]);
+        // Subsections are part of the section
+        let comment = "/// # panics\n/// Panics.\n/// ## Details\n/// More";
+        assert_eq!(prose(comment), Some(2));
+    }
+
+    fn misplaced(comment: &str) -> Option<Misplaced<'static>> {
+        static ORDER: std::sync::OnceLock<Vec<String>> = std::sync::OnceLock::new();
+        let order = ORDER.get_or_init(|| {
+            ["Errors", "Panics", "Safety", "Examples"]
+                .iter()
+                .map(|x| x.to_string())
+                .collect()
+        });
+        misplaced_section(comment, order)
+    }
+
+    #[test]
+    fn section_order() {
+        let comment = "/// Gets a value\n///\n/// # Errors\n/// # Panics\n/// # Examples";
+        assert_eq!(misplaced(comment), None);
+        // Only the relative order of the sections which are there matters
+        assert_eq!(
+            misplaced("/// Gets a value\n/// # Panics\n/// # Examples"),
+            None
+        );
+        assert_eq!(
+            misplaced("/// Gets a value\n/// # Details\n/// # Panics"),
+            None
+        );
+        assert_eq!(misplaced("/// Gets a value"), None);
+
+        let swapped = "/// Gets a value\n///\n/// # examples\n/// ```\n/// # Panics\n/// ```\n/// \
+                       # panics\n/// Panics if it's missing";
+        assert_eq!(
+            misplaced(swapped),
+            Some(Misplaced {
+                section: "Panics",
+                before: Some("Examples"),
+            })
+        );
+        assert_eq!(
+            misplaced("/// # Panics\n/// Panics if it's missing"),
+            Some(Misplaced {
+                section: "Panics",
+                before: None,
+            })
+        );
+    }
+
+    #[test]
+    fn duplicate_sections() {
+        let comment = "/// Gets a value\n/// # Panics\n/// # Panics\n/// # Examples";
+        assert_eq!(misplaced(comment), None);
+        let comment = "/// Gets a value\n/// # Panics\n/// # Examples\n/// # Panics";
+        assert_eq!(
+            misplaced(comment),
+            Some(Misplaced {
+                section: "Panics",
+                before: Some("Examples"),
+            })
+        );
+    }
+
+    #[test]
+    fn multi_paragraph() {
+        let comment = r#"/// Looks up a key
+///
+/// # Panics
+///
+/// Panics if the key isn't in the map,
+/// which is checked on insertion.
+///
+/// ```should_panic
+/// # Panics
+/// map.get("missing");
+/// ```
+///
+/// Also panics if the map is poisoned.
+///
+/// # Errors
+///
+/// Returns an error on Tuesdays"#;
+        assert_eq!(prose(comment), Some(3));
+    }
+}
@@ -0,0 +1,309 @@
+//! Annotates a pull request with the findings through a GitHub check run. Workflow commands like
+//! `::warning` only show the first 10 annotations of a step, the checks API takes any number in
+//! batches of 50.
+//!
+//! Sending the requests needs the `github` feature, building them doesn't so they can be printed
+//! for a dry run.
+use crate::render::finding_message;
+use crate::report::{compact_ident, Report};
+use serde_json::{json, Value};
+use std::io;
+use std::path::Path;
+
+/// The most annotations the checks API accepts in one request
+pub const ANNOTATIONS_PER_REQUEST: usize = 50;
+/// The longest summary the checks API accepts
+const MAX_SUMMARY: usize = 65535;
+const CHECK_NAME: &str = "doc_panic_checker";
+
+/// Where to post the check run, from the environment of a GitHub Actions job
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PrContext {
+    pub token: String,
+    /// `owner/repo`
+    pub repository: String,
+    /// The commit the check run is attached to
+    pub head_sha: String,
+    pub pr_number: u64,
+    pub api_url: String,
+}
+
+impl PrContext {
+    /// Reads the context with `var`, `None` unless this is a pull request build with a token.
+    /// The pull request is taken from `PR_NUMBER` or a `GITHUB_REF` like `refs/pull/7/merge`.
+    /// The check run goes on `PR_HEAD_SHA` if it's set, as `GITHUB_SHA` is the merge commit for
+    /// a pull request.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Option<Self> {
+        let pr_number = var("PR_NUMBER")
+            .or_else(|| {
+                var("GITHUB_REF")?
+                    .strip_prefix("refs/pull/")?
+                    .split('/')
+                    .next()
+                    .map(String::from)
+            })?
+            .parse()
+            .ok()?;
+        Some(Self {
+            token: var("GITHUB_TOKEN")?,
+            repository: var("GITHUB_REPOSITORY")?,
+            head_sha: var("PR_HEAD_SHA").or_else(|| var("GITHUB_SHA"))?,
+            pr_number,
+            api_url: var("GITHUB_API_URL").unwrap_or_else(|| "https://api.github.com".to_string()),
+        })
+    }
+}
+
+/// Sends a request to the GitHub API and returns the response body
+pub trait Transport {
+    fn send(&mut self, method: &str, url: &str, body: &Value) -> io::Result<Value>;
+}
+
+/// The bodies of the requests making the check run, the first creates it and the others add the
+/// rest of the annotations to it. `prefix` is the crate's directory in the repository, as
+/// annotation paths are relative to the repository root. `summary` is markdown.
+pub fn check_run_payloads(
+    report: &Report,
+    prefix: &Path,
+    summary: &str,
+    failed: bool,
+) -> Vec<Value> {
+    let annotations = report
+        .files
+        .iter()
+        .flat_map(|file| {
+            let path = prefix.join(&file.path).to_string_lossy().replace('\\', "/");
+            file.findings.iter().map(move |finding| {
+                json!({
+                    "path": path,
+                    "start_line": finding.start_line,
+                    "end_line": finding.end_line,
+                    "annotation_level": match finding.level() {
+                        "error" => "failure",
+                        "warning" => "warning",
+                        _ => "notice",
+                    },
+                    "title": format!("{}: {}", finding.kind.code(), compact_ident(&finding.ident)),
+                    "message": finding_message(finding),
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    let summary_counts = report.summary();
+    let output = |annotations: &[Value]| {
+        json!({
+            "title": format!("{} findings", summary_counts.findings),
+            "summary": truncate(summary),
+            "annotations": annotations,
+        })
+    };
+    let mut batches = annotations.chunks(ANNOTATIONS_PER_REQUEST);
+    let mut payloads = vec![json!({
+        "name": CHECK_NAME,
+        "status": "completed",
+        "conclusion": if failed { "failure" } else { "success" },
+        "output": output(batches.next().unwrap_or_default()),
+    })];
+    payloads.extend(batches.map(|x| json!({ "output": output(x) })));
+    payloads
+}
+
+/// Cuts the summary down to what the API accepts, on a line boundary
+fn truncate(summary: &str) -> String {
+    const NOTE: &str = "\n\n_The summary was cut short, see the full report._\n";
+    if summary.len() <= MAX_SUMMARY {
+        return summary.to_string();
+    }
+    let mut end = MAX_SUMMARY - NOTE.len();
+    while !summary.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = summary[..end].rfind('\n').unwrap_or(end);
+    format!("{}{}", &summary[..end], NOTE)
+}
+
+/// Creates the check run on the pull request's head commit with the first payload and adds the
+/// rest to it, returning the check run's id
+pub fn annotate(
+    context: &PrContext,
+    payloads: Vec<Value>,
+    transport: &mut impl Transport,
+) -> io::Result<u64> {
+    let url = format!(
+        "{}/repos/{}/check-runs",
+        context.api_url.trim_end_matches('/'),
+        context.repository
+    );
+    let mut payloads = payloads.into_iter();
+    let mut create = payloads
+        .next()
+        .unwrap_or_else(|| json!({ "name": CHECK_NAME }));
+    create["head_sha"] = json!(context.head_sha);
+    let response = transport.send("POST", &url, &create)?;
+    let id = response["id"].as_u64().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the check run response doesn't have an id",
+        )
+    })?;
+    for payload in payloads {
+        transport.send("PATCH", &format!("{}/{}", url, id), &payload)?;
+    }
+    Ok(id)
+}
+
+/// Sends requests with `ureq`, authenticated with the job's token
+#[cfg(feature = "github")]
+pub struct UreqTransport {
+    pub token: String,
+}
+
+#[cfg(feature = "github")]
+impl Transport for UreqTransport {
+    fn send(&mut self, method: &str, url: &str, body: &Value) -> io::Result<Value> {
+        let response = ureq::request(method, url)
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .set("Accept", "application/vnd.github+json")
+            .set("User-Agent", CHECK_NAME)
+            .send_string(&body.to_string())
+            .map_err(io::Error::other)?;
+        serde_json::from_str(&response.into_string()?).map_err(io::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_walker::FindingKind;
+    use crate::report::{FileReport, Finding};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    #[derive(Default)]
+    struct Recorder {
+        requests: Vec<(String, String, Value)>,
+    }
+
+    impl Transport for Recorder {
+        fn send(&mut self, method: &str, url: &str, body: &Value) -> io::Result<Value> {
+            self.requests
+                .push((method.to_string(), url.to_string(), body.clone()));
+            Ok(json!({ "id": 42 }))
+        }
+    }
+
+    fn report(findings: usize) -> Report {
+        let finding = |i: usize| Finding {
+            ident: format!("Map < K > :: get{}", i),
+            kind: FindingKind::UndocumentedPanic,
+            start_line: i + 1,
+            end_line: i + 3,
+            panic_line: i + 2,
+            level: (i == 0).then(|| "error".to_string()),
+            ..Default::default()
+        };
+        Report::new(vec![FileReport {
+            path: PathBuf::from("src/map.rs"),
+            symlink_target: None,
+            findings: (0..findings).map(finding).collect(),
+        }])
+    }
+
+    fn context() -> PrContext {
+        let env = vec![
+            ("GITHUB_TOKEN", "secret"),
+            ("GITHUB_REPOSITORY", "owner/repo"),
+            ("GITHUB_REF", "refs/pull/7/merge"),
+            ("GITHUB_SHA", "merge"),
+            ("PR_HEAD_SHA", "head"),
+        ]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+        PrContext::from_env(|x| env.get(x).map(|x| x.to_string())).unwrap()
+    }
+
+    #[test]
+    fn environment() {
+        let context = context();
+        assert_eq!(context.pr_number, 7);
+        assert_eq!(context.head_sha, "head");
+        assert_eq!(context.api_url, "https://api.github.com");
+        // A push build isn't annotated
+        let push = |x: &str| match x {
+            "GITHUB_REF" => Some("refs/heads/main".to_string()),
+            _ => Some("x".to_string()),
+        };
+        assert_eq!(PrContext::from_env(push), None);
+    }
+
+    #[test]
+    fn payload_shape() {
+        let payloads = check_run_payloads(&report(2), Path::new("crates/map"), "# Summary", true);
+        assert_eq!(payloads.len(), 1);
+        assert_eq!(payloads[0]["conclusion"], "failure");
+        assert_eq!(payloads[0]["output"]["title"], "2 findings");
+        assert_eq!(payloads[0]["output"]["summary"], "# Summary");
+        assert_eq!(
+            payloads[0]["output"]["annotations"][0],
+            json!({
+                "path": "crates/map/src/map.rs",
+                "start_line": 1,
+                "end_line": 3,
+                "annotation_level": "failure",
+                "title": "undocumented-panic: Map<K>::get0",
+                "message": "`Map<K>::get0` can panic but doesn't document it",
+            })
+        );
+        assert_eq!(
+            payloads[0]["output"]["annotations"][1]["annotation_level"],
+            "warning"
+        );
+    }
+
+    #[test]
+    fn batching() {
+        let payloads = check_run_payloads(&report(120), Path::new(""), "", false);
+        let sizes = payloads
+            .iter()
+            .map(|x| x["output"]["annotations"].as_array().unwrap().len())
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, &[50, 50, 20]);
+
+        let mut recorder = Recorder::default();
+        let id = annotate(&context(), payloads, &mut recorder).unwrap();
+        assert_eq!(id, 42);
+        let requests = recorder
+            .requests
+            .iter()
+            .map(|(method, url, _)| (method.as_str(), url.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            requests,
+            &[
+                ("POST", "https://api.github.com/repos/owner/repo/check-runs"),
+                (
+                    "PATCH",
+                    "https://api.github.com/repos/owner/repo/check-runs/42"
+                ),
+                (
+                    "PATCH",
+                    "https://api.github.com/repos/owner/repo/check-runs/42"
+                ),
+            ]
+        );
+        let create = &recorder.requests[0].2;
+        assert_eq!(create["head_sha"], "head");
+        assert_eq!(create["conclusion"], "success");
+        assert!(recorder.requests[1].2.get("head_sha").is_none());
+    }
+
+    #[test]
+    fn long_summaries() {
+        let summary = "- a finding\n".repeat(10000);
+        let truncated = truncate(&summary);
+        assert!(truncated.len() <= MAX_SUMMARY);
+        assert!(truncated.starts_with("- a finding\n"));
+        assert!(truncated.ends_with("see the full report._\n"));
+        assert_eq!(truncate("short"), "short");
+    }
+}
@@ -0,0 +1,201 @@
+//! Flattens `use` declarations so names used in a file can be traced back to what they were
+//! imported as, e.g. `die!` after `use std::panic as die;`.
+//!
+//! Glob imports can't be resolved without knowing what the glob target contains, so they're
+//! ignored by the `ImportMap`.
+use std::collections::HashMap;
+use syn::*;
+
+/// A single name brought into scope by a `use` declaration
+#[derive(Debug)]
+pub struct UseEntry {
+    pub public: bool,
+    pub path: Vec<String>,
+    /// Name the item is brought in as, unused for globs
+    pub name: String,
+    pub glob: bool,
+}
+
+/// The names imported in a file and the paths they refer to. Scoping isn't tracked, an import in
+/// an inline module applies to the whole file.
+#[derive(Debug, Default)]
+pub struct ImportMap {
+    imports: HashMap<String, Vec<String>>,
+}
+
+impl ImportMap {
+    pub fn new(items: &[Item]) -> Self {
+        let mut map = Self::default();
+        map.add_items(items);
+        map
+    }
+
+    fn add_items(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Use(u) => {
+                    let mut entries = vec![];
+                    flatten_use(&u.tree, vec![], false, &mut entries);
+                    for entry in entries {
+                        // `as _` only brings trait methods into scope so there's no name to map
+                        if !entry.glob && entry.name != "_" {
+                            self.imports.insert(entry.name, entry.path);
+                        }
+                    }
+                }
+                Item::Mod(m) => {
+                    if let Some((_, items)) = &m.content {
+                        self.add_items(items);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The full path of a path used in the file, replacing its first segment if that was
+    /// imported. Paths which weren't imported are returned unchanged.
+    pub fn resolve(&self, path: &Path) -> Vec<String> {
+        let mut segments = path.segments.iter().map(|x| x.ident.to_string());
+        let mut result = vec![];
+        if let Some(first) = segments.next() {
+            match self.imports.get(&first) {
+                Some(imported) if path.leading_colon.is_none() => {
+                    result.extend(imported.iter().cloned())
+                }
+                _ => result.push(first),
+            }
+        }
+        result.extend(segments);
+        result
+    }
+
+    /// Names which were imported under a different name for any of the given items
+    pub fn renames_of(&self, names: &[&str]) -> Vec<String> {
+        self.imports
+            .iter()
+            .filter(|(name, path)| {
+                path.last()
+                    .map(|x| x != *name && names.contains(&x.as_str()))
+                    .unwrap_or(false)
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// The name of the item a path refers to once imports are resolved
+    pub fn resolve_name(&self, path: &Path) -> String {
+        self.resolve(path).pop().unwrap_or_default()
+    }
+}
+
+/// Adds an entry for every name brought into scope by a use tree, `prefix` is the path leading
+/// to the tree
+pub fn flatten_use(
+    tree: &UseTree,
+    mut prefix: Vec<String>,
+    public: bool,
+    result: &mut Vec<UseEntry>,
+) {
+    match tree {
+        UseTree::Path(p) => {
+            prefix.push(p.ident.to_string());
+            flatten_use(&p.tree, prefix, public, result);
+        }
+        UseTree::Name(n) => {
+            let name = n.ident.to_string();
+            // `use foo::{self}` brings in `foo`
+            let name = if name == "self" {
+                prefix.last().cloned().unwrap_or(name)
+            } else {
+                prefix.push(name.clone());
+                name
+            };
+            result.push(UseEntry {
+                public,
+                path: prefix,
+                name,
+                glob: false,
+            });
+        }
+        UseTree::Rename(r) => {
+            if r.ident != "self" {
+                prefix.push(r.ident.to_string());
+            }
+            result.push(UseEntry {
+                public,
+                path: prefix,
+                name: r.rename.to_string(),
+                glob: false,
+            });
+        }
+        UseTree::Glob(_) => result.push(UseEntry {
+            public,
+            path: prefix,
+            name: String::new(),
+            glob: true,
+        }),
+        UseTree::Group(g) => {
+            for tree in g.items.iter() {
+                flatten_use(tree, prefix.clone(), public, result);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(map: &ImportMap, path: &str) -> String {
+        map.resolve(&parse_str(path).unwrap()).join("::")
+    }
+
+    #[test]
+    fn flatten_nested_uses() {
+        let item: ItemUse = parse_str("pub use a::{b, c::{d as e, self}, f::*};").unwrap();
+        let mut entries = vec![];
+        flatten_use(&item.tree, vec![], true, &mut entries);
+        let entries = entries
+            .iter()
+            .map(|x| (x.path.join("::"), x.name.as_str(), x.glob))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            entries,
+            &[
+                ("a::b".to_string(), "b", false),
+                ("a::c::d".to_string(), "e", false),
+                ("a::c".to_string(), "c", false),
+                ("a::f".to_string(), "", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn renames_in_nested_groups() {
+        let file = parse_file(
+            r#"
+            use std::{panic as oops, process::{self as proc, exit as quit}};
+            use anyhow::Context as _;
+            use crate::macros::{fatal as die, *};
+
+            mod inner {
+                use core::panic::{self as p};
+            }
+            "#,
+        )
+        .unwrap();
+        let map = ImportMap::new(&file.items);
+        assert_eq!(resolve(&map, "oops"), "std::panic");
+        assert_eq!(resolve(&map, "proc::abort"), "std::process::abort");
+        assert_eq!(resolve(&map, "quit"), "std::process::exit");
+        assert_eq!(resolve(&map, "die"), "crate::macros::fatal");
+        assert_eq!(resolve(&map, "p::panic_any"), "core::panic::panic_any");
+        assert_eq!(resolve(&map, "Context"), "Context");
+        assert_eq!(resolve(&map, "::oops"), "oops");
+        assert_eq!(map.resolve_name(&parse_str("oops").unwrap()), "panic");
+        let mut renames = map.renames_of(&["panic", "exit"]);
+        renames.sort();
+        assert_eq!(renames, &["oops", "p", "quit"]);
+    }
+}
@@ -0,0 +1,45 @@
+//! Finds public functions and methods which can panic without documenting it. The command line
+//! tool walks a whole project, `findings_for_source` checks a single file for tools which want to
+//! report the findings themselves.
+use crate::ast_walker::{AstWalker, Options};
+use crate::report::Finding;
+use std::path::Path;
+
+pub mod ast_walker;
+#[cfg(feature = "cli")]
+pub mod audit;
+#[cfg(feature = "cli")]
+pub mod compare;
+#[cfg(feature = "cli")]
+pub mod crates_io;
+pub mod density;
+#[cfg(feature = "cli")]
+pub mod dir_config;
+pub mod dir_walker;
+mod doc_sections;
+#[cfg(feature = "cli")]
+pub mod github;
+mod imports;
+mod panic_visitor;
+#[cfg(feature = "cli")]
+pub mod ratchet;
+#[cfg(feature = "cli")]
+pub mod render;
+pub mod report;
+pub mod snippets;
+#[cfg(feature = "cli")]
+pub mod status;
+pub mod surface;
+#[cfg(feature = "cli")]
+pub mod workspace;
+
+/// Checks the source code of a single file. Nothing is read from disk, `path` is only used to
+/// find the file's module when `Options::surface` is set. Source which can't be parsed has no
+/// findings.
+pub fn findings_for_source(source: &str, path: &Path, opts: &Options) -> Vec<Finding> {
+    let mut findings = vec![];
+    let _ = AstWalker::new_with_source(path.to_path_buf(), source)
+        .with_options(opts.clone())
+        .process_with(|x| findings.push(Finding::new(&x, None)));
+    findings
+}
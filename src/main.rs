@@ -1,15 +1,46 @@
-use crate::ast_walker::AstWalker;
-use crate::dir_walker::get_dir_walker;
+use cargo_toml::Manifest;
+use doc_panic_checker::ast_walker::{
+    AstWalker, Edition, FindingKind, Lookup, Options, PanicLocation, DEFERRED_ITERATOR, DELEGATION,
+    EDITIONS, LOOKUPS, STD_PANICS, TRIAGE_ATTRIBUTES,
+};
+use doc_panic_checker::audit::{audit_file, DEFAULT_RISK_PATTERNS};
+use doc_panic_checker::compare::{
+    compare, panicking_items, render_markdown as render_comparison_markdown,
+    render_text as render_comparison,
+};
+use doc_panic_checker::crates_io::{cargo_home, fetch, unpack, CrateSpec};
+use doc_panic_checker::density::ModuleCounter;
+use doc_panic_checker::dir_config::{DirConfigs, Effective, PROFILES};
+use doc_panic_checker::dir_walker::{is_binary_root, source_files, SourceFile};
+use doc_panic_checker::github;
+use doc_panic_checker::ratchet::Ratchet;
+use doc_panic_checker::render::{
+    group_repeated, render, render_html_dir, render_skipped, strip_report_generics, write_atomic,
+    Limit, OutputFormat,
+};
+use doc_panic_checker::report::{
+    compact_ident, CompactReport, Discovery, FileReport, Finding, Report, SkipReason, Summary,
+};
+use doc_panic_checker::status::Status;
+use doc_panic_checker::surface::Surface;
+use doc_panic_checker::workspace::{self, Index, PackageSummary};
 use glob::Pattern;
+use regex::Regex;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::Arc;
 use structopt::{clap::arg_enum, StructOpt};
-use tracing::{info, warn};
+use tracing::{error, info, warn};
 use tracing_subscriber::{filter::LevelFilter, EnvFilter};
 
-mod ast_walker;
-mod dir_walker;
-
 arg_enum! {
 #[derive(Copy, Debug, Clone, Eq, PartialEq)]
 pub enum Color {
@@ -19,57 +50,1468 @@ pub enum Color {
 }
 }
 
+/// A named bundle of settings, `.doc-panic.toml` files and then flags given on the command line
+/// are applied on top of it
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Profile {
+    Default,
+    /// For checking a crate before releasing it, unfinished items fail the run
+    ReleaseGate,
+    /// For `no_std` crates, where a panic halts or resets the device, undocumented panics fail
+    /// the run
+    Embedded,
+}
+
+impl FromStr for Profile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(Profile::Default),
+            "release-gate" => Ok(Profile::ReleaseGate),
+            "embedded" => Ok(Profile::Embedded),
+            _ => Err(format!(
+                "expected one of {}, got `{}`",
+                PROFILES.join(", "),
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Profile::Default => write!(f, "default"),
+            Profile::ReleaseGate => write!(f, "release-gate"),
+            Profile::Embedded => write!(f, "embedded"),
+        }
+    }
+}
+
+impl Profile {
+    /// What the profile changes from the defaults, for the report and `--verbose`
+    fn description(&self) -> Option<&'static str> {
+        match self {
+            Profile::Default => None,
+            Profile::ReleaseGate => Some(
+                "`todo!` and `unimplemented!` in public items fail the run whatever their docs \
+                 say",
+            ),
+            Profile::Embedded => {
+                Some("any finding which isn't a note fails the run, as a panic resets the device")
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug, StructOpt)]
 pub struct Config {
+    /// The `Cargo.toml` of the project to analyse. Give it more than once to analyse several
+    /// projects side by side in one report, with each one's files under its label
     #[structopt(long = "manifest-path")]
-    manifest_path: Option<PathBuf>,
+    manifest_path: Vec<PathBuf>,
+    /// The labels of the projects given with `--manifest-path`, in the same order. By default a
+    /// project is labelled with the name of its directory
+    #[structopt(long = "label")]
+    labels: Vec<String>,
     #[structopt(long = "color", default_value = "auto")]
     color: Color,
     #[structopt(long = "exclude-files")]
     excluded_files: Vec<Pattern>,
+    /// Don't report items whose path matches any of these regexes, e.g. `^ffi::`
+    #[structopt(long = "allow-regex")]
+    allow_regex: Vec<Regex>,
+    /// Output format for the results. `short` is one `path:line:column: code: level: ident` line
+    /// per finding, which vim reads with `errorformat=%f:%l:%c: %*[^:]: %t%*[a-z]: %m`
+    #[structopt(long = "format", default_value = "text")]
+    format: OutputFormat,
+    /// Also write an HTML page for each file with findings to this directory, along with an
+    /// `index.html` summarising them
+    #[structopt(long = "html-report-dir")]
+    html_report_dir: Option<PathBuf>,
+    /// Write a small JSON file saying whether the run passed, had findings or failed with an
+    /// error, along with the counts per level. It's written however the run ends.
+    #[structopt(long = "status-file")]
+    status_file: Option<PathBuf>,
+    /// Also write a report in `--format` for each workspace member to this directory, along with
+    /// an `index.json` of their summaries and the totals
+    #[structopt(long = "output-dir")]
+    output_dir: Option<PathBuf>,
+    /// Analyse this file of `cargo expand` output instead of the project's sources, to find
+    /// panics hidden in macro expansions. Impls generated by derives are skipped and line numbers
+    /// are in the expanded file
+    #[structopt(long = "expanded-source", conflicts_with = "run-expand")]
+    expanded_source: Option<PathBuf>,
+    /// Run `cargo expand` on the project and analyse its output like `--expanded-source`, this
+    /// needs cargo-expand to be installed
+    #[structopt(long = "run-expand")]
+    run_expand: bool,
+    /// Embed the source code of each reported item in the report
+    #[structopt(long = "embed-snippets")]
+    embed_snippets: bool,
+    /// Add counts of the public items, the ones which can panic and their lines for each top
+    /// level module to the JSON report, for tracking how dense the panics are
+    #[structopt(long = "module-stats")]
+    module_stats: bool,
+    /// Flag public functions taking closures or function pointers which don't document that
+    /// panics in them propagate to the caller
+    #[structopt(long = "check-callback-propagation")]
+    check_callback_propagation: bool,
+    /// Don't accept a trait's own documentation as documenting panics in its default methods
+    #[structopt(long = "ignore-trait-docs")]
+    ignore_trait_docs: bool,
+    /// Check `fn main` in the binaries under `src/bin`, by default only the other public
+    /// functions in them are checked
+    #[structopt(long = "check-binaries")]
+    check_binaries: bool,
+    /// Also check the examples, including their `fn main`
+    #[structopt(long = "include-examples")]
+    include_examples: bool,
+    /// Check the top level `fn main` of every file including `src/main.rs`, suggesting it returns
+    /// a `Result` instead of panicking
+    #[structopt(long = "check-panics-in-main", alias = "check-main")]
+    check_main: bool,
+    /// Report panics in the `fn main` of binaries and examples at the same level as other
+    /// findings, by default they're only a note as unwrapping in `main` is usually deliberate
+    #[structopt(long = "strict-main")]
+    strict_main: bool,
+    /// Only check items reachable from outside the crate via `src/lib.rs`, reporting them by
+    /// their shortest public path like `cargo doc` would
+    #[structopt(long = "rustdoc-surface")]
+    rustdoc_surface: bool,
+    /// With `--rustdoc-surface` only check `src/lib.rs` and its inline modules, without following
+    /// `mod foo;` declarations into other files. Without `--rustdoc-surface` every source file is
+    /// checked, so there are no modules to follow. Also set by `recurse-modules = false` in a
+    /// `.doc-panic.toml`
+    #[structopt(long = "no-recurse-modules", requires = "rustdoc-surface")]
+    no_recurse_modules: bool,
+    /// Also check the crates under `[build-dependencies]` which are local path dependencies, as
+    /// they run during `cargo build`
+    #[structopt(long = "check-panic-in-build-dependencies")]
+    check_build_dependencies: bool,
+    /// Tag panics about being on the wrong thread in types which manually implement `Send`
+    #[structopt(long = "check-panics-in-send-wrappers")]
+    check_send_wrappers: bool,
+    /// Check `Deref` and `DerefMut` impls, panics in them need documenting on the impl block as
+    /// they're called implicitly by auto-deref
+    #[structopt(long = "check-panics-in-deref-targets")]
+    check_deref: bool,
+    /// Flag `Clone` impls which can panic, the `Clone` contract says they shouldn't
+    #[structopt(long = "check-panics-in-clone")]
+    check_clone: bool,
+    /// Flag `Default` impls which can panic, they're called implicitly by struct update syntax
+    /// and `unwrap_or_default` so should always return a safe value
+    #[structopt(long = "check-panics-in-default-impls")]
+    check_default: bool,
+    /// Flag `From` impls which can panic even if they're documented, they're called implicitly
+    /// by `.into()` and `?` and should be infallible, a `TryFrom` impl returning an error is
+    /// suggested instead
+    #[structopt(long = "check-panics-in-type-conversions")]
+    check_type_conversions: bool,
+    /// Flag `TryFrom` impls which can panic even if they're documented, they should return an
+    /// `Err` instead
+    #[structopt(long = "check-panics-in-try-from")]
+    check_try_from: bool,
+    /// Report panics in functions returning a `Result`, and in `FromStr` and `TryFrom` impls, as
+    /// `panic-in-fallible-fn` even if they're documented, they should return an `Err` instead.
+    /// The level can be changed in the `levels` of a `.doc-panic.toml`
+    #[structopt(long = "check-panics-in-fallible-fns")]
+    check_fallible_fns: bool,
+    /// Flag `io::Write` impls whose `write`, `flush` or `write_all` can panic, they should return
+    /// an error instead
+    #[structopt(long = "check-panics-in-impl-write")]
+    check_io_write: bool,
+    /// Flag `io::Read` impls whose `read` or `read_to_end` can panic, they should return an
+    /// error instead
+    #[structopt(long = "check-panics-in-read-impls")]
+    check_io_read: bool,
+    /// Flag `Index` and `IndexMut` impls which can panic unless the impl documents which keys or
+    /// indices are valid
+    #[structopt(long = "check-panics-in-index-impls")]
+    check_index: bool,
+    /// Never report `Index` and `IndexMut` impls, even with `--check-panics-in-index-impls` or
+    /// `--check-panics-in-std-trait-blanket-impls`
+    #[structopt(long = "ignore-index-impls")]
+    ignore_index_impls: bool,
+    /// Flag `IntoIterator` impls whose `into_iter` can panic, which `for` loops call implicitly
+    #[structopt(long = "check-panics-in-into-iter")]
+    check_into_iter: bool,
+    /// Flag `Add`, `Sub`, `Mul`, `Div` and `Rem` impls which can panic unless the impl documents
+    /// it, `a + b` doesn't look like it can panic
+    #[structopt(long = "check-panics-in-binary-op-overloads")]
+    check_operators: bool,
+    /// Tag unwrapping the result of `write!` and `writeln!`, which can usually return the error
+    /// with `?` instead
+    #[structopt(long = "check-panics-in-format-strings")]
+    check_format_writes: bool,
+    /// Tag unwrapping the result of `send` on a channel, which panics once the receiver is dropped
+    #[structopt(long = "check-panics-in-send-fn")]
+    check_channel_sends: bool,
+    /// Tag unwrapping `Mutex::lock`, `RwLock::read` or `RwLock::write`, which only panics if
+    /// another thread panicked while holding the lock
+    #[structopt(long = "check-panics-in-lock-poison")]
+    check_lock_poison: bool,
+    /// Tag unwrapping the result of `std::env::var`, which panics when the variable isn't set
+    #[structopt(long = "check-panics-in-env-var")]
+    check_env_vars: bool,
+    /// Tag unwrapping the result of `File::open`, `fs::read` or `fs::read_to_string`, which panics
+    /// when the file is missing or can't be read
+    #[structopt(long = "check-panics-in-file-open")]
+    check_file_io: bool,
+    /// Tag unwrapping inside the `map` closure of an iterator chain collected into a plain
+    /// collection, like `.map(|x| x.parse().unwrap()).collect::<Vec<_>>()`, which panics on the
+    /// first error where collecting into a `Result` would return it
+    #[structopt(long = "check-panics-in-iterator-collect")]
+    check_iterator_collect: bool,
+    /// Tag panics inside the closure given to `sort_by`, `sort_by_key`, `sort_unstable_by` or
+    /// `sort_unstable_by_key`, like `v.sort_by_key(|x| x.parse::<i32>().unwrap())`
+    #[structopt(long = "check-panics-in-sort-keys")]
+    check_sort_keys: bool,
+    /// Tag unwrapping the `Option` from `get`, `first`, `last`, `next` or `nth`, with a hint for
+    /// the non-panicking alternative
+    #[structopt(long = "check-lookup-unwraps")]
+    check_option_lookups: bool,
+    /// Report items whose doc sections, like `# Panics`, don't come after the summary in the
+    /// order given by `--doc-section-order`
+    #[structopt(long = "check-doc-section-order")]
+    check_doc_section_order: bool,
+    /// The order doc sections should come in for `--check-doc-section-order`, sections which
+    /// aren't listed can go anywhere
+    #[structopt(
+        long = "doc-section-order",
+        default_value = "Errors,Panics,Safety,Examples",
+        use_delimiter = true
+    )]
+    doc_section_order: Vec<String>,
+    /// Note when a `const fn` can panic, a panic only happens when it's called at runtime as in a
+    /// const context it fails to compile instead
+    #[structopt(long = "check-panics-in-const-fn-stable")]
+    check_const_fn: bool,
+    /// Note when a safe function panics inside one of its `unsafe { }` blocks
+    #[structopt(long = "check-panics-in-unsafe-blocks")]
+    check_unsafe_blocks: bool,
+    /// Count calling a method on a `dyn Trait` parameter or local as a panic when the trait has
+    /// a default method in the same file which can panic
+    #[structopt(
+        long = "check-panics-in-trait-object-methods",
+        alias = "check-dynamic-dispatch"
+    )]
+    check_dynamic_dispatch: bool,
+    /// Count calls to std methods which panic unless their arguments meet a precondition, like
+    /// `split_at(mid)` or `copy_from_slice`, as panics. They're matched by name so the finding
+    /// says which types the precondition applies to
+    #[structopt(long = "check-std-panics")]
+    check_std_panics: bool,
+    /// With `--check-std-panics`, don't count calls to this method, e.g. because a type in the
+    /// crate has its own method of the same name
+    #[structopt(long = "ignore-std-panic", possible_values = STD_PANICS)]
+    ignore_std_panics: Vec<String>,
+    /// Flag public structs and enums whose const generic defaults, like
+    /// `struct Buf<const N: usize = { size() }>`, call a `const fn` in the same file which can
+    /// panic, failing to compile for users relying on the default
+    #[structopt(long = "check-panics-in-const-generic-default")]
+    check_const_generic_defaults: bool,
+    /// Note the cfg predicate enabling panics behind a `#[cfg]` on the function or the statements
+    /// around them, like `#[cfg(not(feature = "safe-mode"))] { .. }`, as they only happen in
+    /// some builds
+    #[structopt(long = "check-panics-in-cfg-feature")]
+    check_cfg_features: bool,
+    /// Strict checks for codebases aiming for no undocumented panics at all. Passing a function
+    /// rather than a closure to `unwrap_or_else`, `ok_or_else`, `or_else` or `map_or_else`
+    /// counts as a panic as its body isn't checked, and `todo!` or `unimplemented!` next to other
+    /// logic is tagged as an unfinished branch
+    #[structopt(long = "pedantic")]
+    pedantic: bool,
+    /// Flag `Hash` impls which can panic, they're called implicitly by hash maps and sets
+    #[structopt(long = "check-panics-in-hash")]
+    check_hash: bool,
+    /// Flag `PartialOrd` and `Ord` impls which can panic, they're called implicitly when sorting
+    /// and comparing
+    #[structopt(long = "check-panics-in-partial-ord")]
+    check_ord: bool,
+    /// Flag serde `Serialize` and `Deserialize` impls which can panic, they should return an
+    /// error instead
+    #[structopt(long = "check-panics-in-serde", alias = "check-serde-impls")]
+    check_serde: bool,
+    /// Flag `Display` impls of error types which can panic, including `unreachable!`, as error
+    /// messages are formatted while logging and reporting failures
+    #[structopt(long = "check-panics-in-impl-display-error")]
+    check_display_error: bool,
+    /// Name the type built by free constructor functions like `fn new_foo() -> Foo` in their
+    /// findings, which otherwise don't say what they construct, and tag associated functions
+    /// returning `Self` as constructors
+    #[structopt(long = "check-panics-in-struct-new")]
+    check_struct_new: bool,
+    /// Count a call to a private function or method in the same file which can panic, like
+    /// `helper()`, `Self::helper()` or `self.helper()`, as a panic in the public item calling it
+    #[structopt(long = "follow-private-calls")]
+    follow_private_calls: bool,
+    /// Accept a module's own docs, like the `//!` comments at the top of `src/lib.rs`, as
+    /// documenting panics in the items directly inside it
+    #[structopt(long = "inherit-module-docs")]
+    inherit_module_docs: bool,
+    /// Parse every file and check every item, rather than skipping those whose source has
+    /// nothing which looks like a panic. Slower, but finds panics the text search misses
+    #[structopt(long = "no-prefilter")]
+    no_prefilter: bool,
+    /// The Rust edition the source is written in
+    #[structopt(long = "edition", default_value = "2021", possible_values = EDITIONS)]
+    edition: Edition,
+    /// Check blanket impls like `impl<T: Bound> Trait for T`, which apply to types in other crates
+    /// without their authors necessarily knowing
+    #[structopt(long = "check-panics-in-std-trait-blanket-impls")]
+    check_blanket_impls: bool,
+    /// Report items whose `# Panics` section has too few lines of explanation
+    #[structopt(long = "require-panic-details")]
+    require_panic_details: bool,
+    /// With `--require-panic-details`, the fewest lines a `# Panics` section should have
+    #[structopt(long = "min-panic-doc-lines", default_value = "1")]
+    min_panic_doc_lines: usize,
+    /// With `--require-panic-details`, the lines a `# Panics` section should have for each kind
+    /// of panic in the item, e.g. an `unwrap` and a `panic!`
+    #[structopt(long = "panic-doc-lines-per-kind", default_value = "1")]
+    panic_doc_lines_per_kind: usize,
+    /// Don't count `process::exit` and `process::abort` as panics, by default they're reported
+    /// with a `PROCESS EXIT` tag
+    #[structopt(long = "ignore-process-exit")]
+    ignore_process_exit: bool,
+    /// Don't count unwrapping `Mutex::lock`, `RwLock::read` or `RwLock::write` as panics
+    #[structopt(long = "ignore-mutex-poison")]
+    ignore_mutex_poison: bool,
+    /// Don't count unwrapping the `Option` from one of these lookups as a panic
+    #[structopt(long = "ignore-lookup-unwrap", possible_values = LOOKUPS)]
+    ignore_lookups: Vec<Lookup>,
+    /// Count calls to these functions as panics, like `our_telemetry::fatal`. Paths are matched
+    /// by their last segments after resolving imports, method calls never match.
+    #[structopt(long = "panicking-fn")]
+    panicking_fns: Vec<String>,
+    /// Never count calls to these functions as panics, even ones given to `--panicking-fn` or
+    /// built in like `std::process::exit`
+    #[structopt(long = "allow-fn")]
+    allowed_fns: Vec<String>,
+    /// Check `extern "C"` functions whatever their visibility, a panic unwinding out of one is
+    /// undefined behaviour so it's reported even if it's documented
+    #[structopt(long = "check-panics-in-foreign-functions")]
+    check_ffi: bool,
+    /// Only report items with at least this many expressions in them which can panic
+    #[structopt(long = "min-panic-sites", default_value = "1")]
+    min_panic_sites: usize,
+    /// Don't report functions whose body only forwards their parameters to another call, like
+    /// `self.inner.len()`
+    #[structopt(long = "skip-delegations")]
+    skip_delegations: bool,
+    /// Fold findings in a file which panic on the same line of code into one entry in text and
+    /// markdown output when there are more than this many of them
+    #[structopt(long = "fold-threshold", default_value = "5")]
+    fold_threshold: usize,
+    /// Show at most this many findings per file in text and markdown output, the totals and exit
+    /// code still count every finding
+    #[structopt(long = "max-findings-per-file", default_value = "20")]
+    max_findings_per_file: usize,
+    /// Show at most this many findings in text and markdown output, after the per file limit
+    #[structopt(long = "max-findings", default_value = "200")]
+    max_findings: usize,
+    /// Show every finding in text and markdown output
+    #[structopt(long = "no-limit")]
+    no_limit: bool,
+    /// Show generic arguments in item paths as `<...>`, like `BTreeMap<...>::insert`, in all but
+    /// the JSON and SARIF output
+    #[structopt(long = "strip-generics", alias = "strip-private-generics")]
+    strip_generics: bool,
+    /// Only show findings for items with any of these attributes
+    #[structopt(long = "filter-attr", possible_values = TRIAGE_ATTRIBUTES)]
+    filter_attrs: Vec<String>,
+    /// Don't show findings for items with any of these attributes
+    #[structopt(long = "exclude-attr", possible_values = TRIAGE_ATTRIBUTES)]
+    exclude_attrs: Vec<String>,
+    /// Fail if any file has more findings than allowed by this TOML file of counts per file
+    #[structopt(long = "ratchet")]
+    ratchet: Option<PathBuf>,
+    /// Lower the counts in the `--ratchet` file to the current ones, creating it if needed
+    #[structopt(long = "update-ratchet", requires = "ratchet")]
+    update_ratchet: bool,
+    /// The bundle of settings to start from, `release-gate` fails the run if any public item
+    /// has a `todo!` or `unimplemented!` whatever its docs say, `embedded` fails it on any
+    /// finding which isn't a note. Without it files use the `profile` of their `.doc-panic.toml`,
+    /// or `default`
+    #[structopt(long = "profile", possible_values = PROFILES)]
+    profile: Option<Profile>,
+    /// Fail if there are any findings which aren't notes or any problems running the analysis,
+    /// like source files which can't be read or parsed
+    #[structopt(long = "strict")]
+    strict: bool,
+    /// Fail with exit code 3 if fewer source files than this are analysed, which usually means
+    /// the wrong manifest path or exclusions which are too broad
+    #[structopt(long = "min-files", default_value = "1")]
+    min_files: usize,
+    /// Don't fail when no source files, or fewer than `--min-files`, are analysed
+    #[structopt(long = "allow-empty")]
+    allow_empty: bool,
+    /// After the findings, print how many files and items weren't checked for each reason, like
+    /// parse errors or `--allow-regex`
+    #[structopt(long = "print-unchecked-summary")]
+    print_unchecked_summary: bool,
+    /// Post the findings to the pull request as a GitHub check run with annotations, using
+    /// `GITHUB_TOKEN`, `GITHUB_REPOSITORY` and `GITHUB_REF` or `PR_NUMBER`. Needs the `github`
+    /// feature, failing to post is a warning.
+    #[structopt(long = "annotate-pr")]
+    annotate_pr: bool,
+    /// Print the requests `--annotate-pr` would send to stderr instead of sending them
+    #[structopt(long = "annotate-pr-dry-run")]
+    annotate_pr_dry_run: bool,
+    /// Only log errors and don't print the findings. The summary line saying the run succeeded
+    /// is still printed.
+    #[structopt(short = "q", long = "quiet", conflicts_with = "verbose")]
+    quiet: bool,
+    /// Show more information about the analysis
+    #[structopt(short = "v", long = "verbose")]
+    verbose: bool,
+    #[structopt(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Clone, Debug, StructOpt)]
+pub enum Command {
+    /// Re-render a report previously produced with `--format json`
+    Render {
+        /// Path to the JSON report
+        report: PathBuf,
+        /// Output format for the results
+        #[structopt(long = "format", default_value = "text")]
+        format: OutputFormat,
+    },
+    /// Audit a crate from its rustdoc JSON when the source isn't available. This lists the
+    /// documented panics and the items whose names suggest they can panic without a "Panics"
+    /// section, undocumented panics can't be found without the function bodies
+    AuditDocs {
+        /// Path to the JSON output of `cargo +nightly rustdoc -- -Z unstable-options
+        /// --output-format json`
+        rustdoc_json: PathBuf,
+        /// Regexes for function names which suggest it can panic, replaces the defaults
+        #[structopt(long = "risk-pattern")]
+        risk_patterns: Vec<Regex>,
+        /// Output format for the results
+        #[structopt(long = "format", default_value = "text")]
+        format: OutputFormat,
+    },
+    /// Analyse the library of a crate published on crates.io, taken from the cargo cache if it's
+    /// there and downloaded otherwise
+    Crate {
+        /// The crate to analyse as `<name>@<version>`
+        spec: CrateSpec,
+        /// Fail rather than download the crate if it isn't in the cargo cache
+        #[structopt(long = "offline")]
+        offline: bool,
+        /// Output format for the results
+        #[structopt(long = "format", default_value = "text")]
+        format: OutputFormat,
+    },
+    /// Compare which public items of two versions of a library can panic, documented or not, for
+    /// release notes
+    Compare {
+        /// The directory containing the old version's `Cargo.toml`
+        old_root: PathBuf,
+        /// The directory containing the new version's `Cargo.toml`
+        new_root: PathBuf,
+        /// Output format for the results, only `text` and `markdown` are supported
+        #[structopt(long = "format", default_value = "text")]
+        format: OutputFormat,
+    },
+    /// Print details of the build and environment to attach to bug reports, also printed by
+    /// `--version --verbose`
+    Info {
+        /// Output format, only `text` and `json` are supported
+        #[structopt(long = "format", default_value = "text")]
+        format: OutputFormat,
+    },
+}
+
+/// What `info` prints, the keys are listed in the README
+#[derive(Debug, Serialize)]
+struct Diagnostics {
+    version: &'static str,
+    git_hash: &'static str,
+    syn_version: &'static str,
+    proc_macro2_version: &'static str,
+    /// Whether spans have line numbers, which every finding needs
+    span_locations: bool,
+    target: &'static str,
+    cargo_home: Option<PathBuf>,
+    /// The first `--manifest-path`, or the nearest `Cargo.toml` above the current directory. The
+    /// other projects of a merged run are described by running `info` for each of them.
+    manifest_path: Option<PathBuf>,
+    workspace_members: Vec<String>,
+    profile: String,
+    /// The options the analysis would run with, after applying the profile and flags
+    options: String,
+}
+
+impl Diagnostics {
+    fn new(config: &Config, current_dir: &Path) -> Self {
+        let manifest_path = match config.manifest_path.first() {
+            Some(path) => Some(path.canonicalize().unwrap_or_else(|_| path.clone())),
+            None => current_dir
+                .ancestors()
+                .map(|x| x.join("Cargo.toml"))
+                .find(|x| x.is_file()),
+        };
+        let workspace_members = manifest_path
+            .as_ref()
+            .and_then(|x| workspace::members(x).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|x| x.name)
+            .collect();
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: env!("DOC_PANIC_CHECKER_GIT_HASH"),
+            syn_version: env!("DOC_PANIC_CHECKER_SYN_VERSION"),
+            proc_macro2_version: env!("DOC_PANIC_CHECKER_PROC_MACRO2_VERSION"),
+            span_locations: span_locations(),
+            target: env!("DOC_PANIC_CHECKER_TARGET"),
+            cargo_home: cargo_home(),
+            manifest_path,
+            workspace_members,
+            profile: config.profile().to_string(),
+            options: format!("{:?}", config.options()),
+        }
+    }
+
+    fn render(&self, format: OutputFormat, writer: &mut impl Write) -> io::Result<()> {
+        let path = |x: &Option<PathBuf>| {
+            x.as_ref()
+                .map(|x| x.display().to_string())
+                .unwrap_or_else(|| "none".to_string())
+        };
+        match format {
+            OutputFormat::Json => {
+                serde_json::to_writer_pretty(&mut *writer, self)?;
+                writeln!(writer)
+            }
+            OutputFormat::Text => {
+                writeln!(
+                    writer,
+                    "{} {} ({})",
+                    env!("CARGO_PKG_NAME"),
+                    self.version,
+                    self.git_hash
+                )?;
+                writeln!(writer, "syn: {}", self.syn_version)?;
+                writeln!(writer, "proc-macro2: {}", self.proc_macro2_version)?;
+                writeln!(writer, "span locations: {}", self.span_locations)?;
+                writeln!(writer, "target: {}", self.target)?;
+                writeln!(writer, "cargo home: {}", path(&self.cargo_home))?;
+                writeln!(writer, "manifest path: {}", path(&self.manifest_path))?;
+                writeln!(
+                    writer,
+                    "workspace members: {}",
+                    self.workspace_members.join(", ")
+                )?;
+                writeln!(writer, "profile: {}", self.profile)?;
+                writeln!(writer, "options: {}", self.options)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "info only supports the text and json formats",
+            )),
+        }
+    }
+}
+
+/// Returns true if proc-macro2 gives spans line numbers, if not every finding would be on line 0
+fn span_locations() -> bool {
+    "first\nsecond"
+        .parse::<proc_macro2::TokenStream>()
+        .ok()
+        .and_then(|x| x.into_iter().nth(1))
+        .is_some_and(|x| x.span().start().line == 2)
+}
+
+/// Files which aren't part of a crate's library target, left out when analysing a published crate
+const NON_LIBRARY_FILES: &[&str] = &["src/main.rs", "src/bin/**", "benches/**", "build.rs"];
+
+/// The exit code when too few source files were analysed, see `--min-files`
+const NOTHING_ANALYSED: i32 = 3;
+
+/// Why a run stopped other than being unable to do the analysis
+#[derive(Debug)]
+enum Stop {
+    /// The findings failed the run, through the profile, `--strict` or the ratchet
+    Findings(String),
+    /// Too few source files were analysed, see `--min-files`
+    NothingAnalysed(String),
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Findings(message) | Self::NothingAnalysed(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Stop {}
+
+impl Config {
+    /// Returns true if the user has allowed the item to panic without documenting it
+    fn is_allowed(&self, ident: &str) -> bool {
+        let ident = compact_ident(ident);
+        self.allow_regex.iter().any(|x| x.is_match(&ident))
+    }
+
+    /// Why a finding is left out of the report to cut down the noise, if it is
+    fn suppression(&self, location: &PanicLocation) -> Option<(SkipReason, String)> {
+        if self.skip_delegations && location.tags().iter().any(|x| x == DELEGATION) {
+            Some((SkipReason::Delegation, "delegation".to_string()))
+        } else if !matches!(
+            location.kind(),
+            FindingKind::CallbackPropagation | FindingKind::DocSectionOrder
+        ) && !location.tags().iter().any(|x| x == DEFERRED_ITERATOR)
+            && location.panic_sites() < self.min_panic_sites
+        {
+            Some((
+                SkipReason::MinPanicSites,
+                format!("{} panic sites", location.panic_sites()),
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// The report as it's shown in the format, which is only different if generics are stripped
+    /// or findings are filtered by their attributes. The ratchet always sees every finding.
+    fn displayed<'a>(&self, report: &'a Report, format: OutputFormat) -> Cow<'a, Report> {
+        let strip = self.strip_generics && format.is_human_readable();
+        let filter = !(self.filter_attrs.is_empty() && self.exclude_attrs.is_empty());
+        if !(strip || filter) {
+            return Cow::Borrowed(report);
+        }
+        let mut report = report.clone();
+        if strip {
+            strip_report_generics(&mut report);
+        }
+        if filter {
+            self.filter_attributes(&mut report);
+        }
+        Cow::Owned(report)
+    }
+
+    /// Returns true if the finding's attributes pass `--filter-attr` and `--exclude-attr`
+    fn shows_attributes(&self, finding: &Finding) -> bool {
+        let has_any = |attrs: &[String]| finding.attributes.iter().any(|x| attrs.contains(x));
+        (self.filter_attrs.is_empty() || has_any(&self.filter_attrs))
+            && !has_any(&self.exclude_attrs)
+    }
+
+    /// Leaves out findings by the attributes on their item, noting the filters in the report
+    fn filter_attributes(&self, report: &mut Report) {
+        let mut total = 0;
+        let mut shown = 0;
+        for file in report.files.iter_mut() {
+            total += file.findings.len();
+            file.findings.retain(|x| self.shows_attributes(x));
+            shown += file.findings.len();
+        }
+        let attr_list = |attrs: &[String]| {
+            attrs
+                .iter()
+                .map(|x| format!("`#[{}]`", x))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        };
+        let mut filters = vec![];
+        if !self.filter_attrs.is_empty() {
+            filters.push(format!("only items with {}", attr_list(&self.filter_attrs)));
+        }
+        if !self.exclude_attrs.is_empty() {
+            filters.push(format!(
+                "excluding items with {}",
+                attr_list(&self.exclude_attrs)
+            ));
+        }
+        report.notes.push(format!(
+            "Showing {} of {} findings, {}",
+            shown,
+            total,
+            filters.join(" and ")
+        ));
+    }
+
+    /// With `--strict` fails if there are findings which aren't notes or problems running the
+    /// analysis, otherwise returns the line saying the run succeeded
+    fn outcome(&self, summary: Summary) -> Result<String, String> {
+        if self.profile() == Profile::ReleaseGate && summary.release_blockers > 0 {
+            Err(format!(
+                "Failing because of the release-gate profile, {} items have a `todo!` or \
+                 `unimplemented!`",
+                summary.release_blockers
+            ))
+        } else if self.profile() == Profile::Embedded && summary.failures > 0 {
+            Err(format!(
+                "Failing because of the embedded profile, there are {} findings",
+                summary.failures
+            ))
+        } else if self.strict && (summary.failures > 0 || summary.warnings > 0) {
+            Err(format!(
+                "Failing because of --strict, there are {} findings and {} warnings",
+                summary.failures, summary.warnings
+            ))
+        } else {
+            Ok(summary.to_string())
+        }
+    }
+
+    /// The directory of each `--manifest-path` and the label its files are prefixed with. A
+    /// single project isn't labelled unless it's given a `--label`.
+    fn roots(&self) -> Result<Vec<(String, PathBuf)>, String> {
+        if self
+            .manifest_path
+            .iter()
+            .any(|x| x.file_name() != Some(OsStr::new("Cargo.toml")))
+        {
+            return Err("The manifest-path must be a path to a Cargo.toml file".to_string());
+        }
+        if self.labels.len() > self.manifest_path.len() {
+            return Err(format!(
+                "{} labels were given for {} manifest paths",
+                self.labels.len(),
+                self.manifest_path.len()
+            ));
+        }
+        let root = |manifest: &Path| {
+            manifest
+                .canonicalize()
+                .ok()
+                .map(|x| x.parent().map(|x| x.to_path_buf()).unwrap_or_default())
+        };
+        if self.manifest_path.len() < 2 && self.labels.is_empty() {
+            let root = self.manifest_path.first().and_then(|x| root(x));
+            return Ok(vec![(String::new(), root.unwrap_or_default())]);
+        }
+        let mut roots: Vec<(String, PathBuf)> = vec![];
+        for (i, manifest) in self.manifest_path.iter().enumerate() {
+            let root =
+                root(manifest).ok_or_else(|| format!("Unable to find {}", manifest.display()))?;
+            let label = match self.labels.get(i) {
+                Some(label) => label.clone(),
+                None => root
+                    .file_name()
+                    .map(|x| x.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            };
+            if roots.iter().any(|(x, _)| *x == label) {
+                return Err(format!(
+                    "More than one project is labelled `{}`, tell them apart with --label",
+                    label
+                ));
+            }
+            roots.push((label, root));
+        }
+        Ok(roots)
+    }
+
+    /// Fails if fewer files than `--min-files` were analysed, listing what happened to the files
+    /// which were found, unless `--allow-empty` is given
+    fn check_analysed(&self, report: &Report) -> Result<(), String> {
+        if self.allow_empty || report.files_checked >= self.min_files {
+            Ok(())
+        } else {
+            Err(format!(
+                "Only {} source files were analysed but --min-files is {}, check the manifest \
+                 path and exclusions or pass --allow-empty ({})",
+                report.files_checked, self.min_files, report.discovery
+            ))
+        }
+    }
+
+    fn render(&self, report: &Report, format: OutputFormat) -> io::Result<()> {
+        // Stdout is line buffered, which is slow for large reports written a line at a time
+        let mut stdout = BufWriter::new(io::stdout().lock());
+        render(
+            &self.displayed(report, format),
+            format,
+            self.verbose,
+            self.limit().as_ref(),
+            &mut stdout,
+        )?;
+        stdout.flush()
+    }
+
+    /// The cap on the findings printed, pointing at the report being rendered or the HTML report
+    /// or output directory if one is written
+    fn limit(&self) -> Option<Limit> {
+        if self.no_limit {
+            return None;
+        }
+        let full_report = match &self.command {
+            Some(Command::Render { report, .. }) => Some(report.clone()),
+            _ => self
+                .html_report_dir
+                .as_ref()
+                .map(|x| x.join("index.html"))
+                .or_else(|| self.output_dir.clone()),
+        };
+        Some(Limit {
+            per_file: self.max_findings_per_file,
+            total: self.max_findings,
+            full_report,
+        })
+    }
+
+    /// The profile given with `--profile`, `default` if there isn't one
+    fn profile(&self) -> Profile {
+        self.profile.unwrap_or(Profile::Default)
+    }
+
+    /// The options from the command line, what's found out about the crate like whether it's
+    /// `no_std` is filled in by `get_analysis`
+    fn options(&self) -> Options {
+        Options {
+            check_callback_propagation: self.check_callback_propagation,
+            ignore_trait_docs: self.ignore_trait_docs,
+            binary_root: false,
+            check_binaries: self.check_binaries,
+            strict_main: self.strict_main,
+            surface: None,
+            check_send_wrappers: self.check_send_wrappers,
+            check_deref: self.check_deref,
+            check_clone: self.check_clone,
+            check_default: self.check_default,
+            check_type_conversions: self.check_type_conversions,
+            check_try_from: self.check_try_from,
+            check_fallible_fns: self.check_fallible_fns,
+            check_io_write: self.check_io_write,
+            check_io_read: self.check_io_read,
+            no_std: false,
+            panic_abort: false,
+            check_index: self.check_index,
+            ignore_index_impls: self.ignore_index_impls,
+            check_into_iter: self.check_into_iter,
+            check_operators: self.check_operators,
+            check_format_writes: self.check_format_writes,
+            check_channel_sends: self.check_channel_sends,
+            check_lock_poison: self.check_lock_poison,
+            check_env_vars: self.check_env_vars,
+            check_file_io: self.check_file_io,
+            check_iterator_collect: self.check_iterator_collect,
+            check_sort_keys: self.check_sort_keys,
+            check_option_lookups: self.check_option_lookups,
+            doc_section_order: if self.check_doc_section_order {
+                self.doc_section_order.clone()
+            } else {
+                vec![]
+            },
+            check_const_fn: self.check_const_fn,
+            check_unsafe_blocks: self.check_unsafe_blocks,
+            check_dynamic_dispatch: self.check_dynamic_dispatch,
+            check_std_panics: self.check_std_panics,
+            ignore_std_panics: self.ignore_std_panics.clone(),
+            check_const_generic_defaults: self.check_const_generic_defaults,
+            check_cfg_features: self.check_cfg_features,
+            pedantic: self.pedantic,
+            expanded_source: false,
+            check_hash: self.check_hash,
+            check_ord: self.check_ord,
+            check_serde: self.check_serde,
+            check_display_error: self.check_display_error,
+            check_blanket_impls: self.check_blanket_impls,
+            require_panic_details: self.require_panic_details,
+            min_panic_doc_lines: self.min_panic_doc_lines,
+            panic_doc_lines_per_kind: self.panic_doc_lines_per_kind,
+            ignore_process_exit: self.ignore_process_exit,
+            ignore_mutex_poison: self.ignore_mutex_poison,
+            ignore_lookups: self.ignore_lookups.clone(),
+            panicking_fns: self.panicking_fns.clone(),
+            allowed_fns: self.allowed_fns.clone(),
+            check_ffi: self.check_ffi,
+            check_main: self.check_main,
+            report_documented: false,
+            check_struct_new: self.check_struct_new,
+            follow_private_calls: self.follow_private_calls,
+            edition: self.edition,
+            inherit_module_docs: self.inherit_module_docs,
+            no_prefilter: self.no_prefilter,
+            release_blockers: self.profile() == Profile::ReleaseGate,
+        }
+    }
 }
 
-pub fn get_analysis(root: PathBuf, excluded_files: &[Pattern]) {
+/// What happened while analysing the files, besides their findings
+#[derive(Default)]
+struct Progress {
+    /// Findings left out to cut down the noise, see `Config::suppression`
+    suppressed: Vec<String>,
+    /// Problems like files which couldn't be read or parsed
+    warnings: Vec<String>,
+    files_checked: usize,
+    items_checked: usize,
+    /// Counts for `--module-stats`
+    modules: ModuleCounter,
+    discovery: Discovery,
+    skipped: BTreeMap<SkipReason, usize>,
+    /// The `.doc-panic.toml` files read so far
+    dir_configs: DirConfigs,
+    per_file: PerFile,
+}
+
+/// The files checked and the problems with each file, relative to the project root, so a
+/// workspace's report can be split between its members
+#[derive(Default)]
+struct PerFile {
+    /// Each file checked with the number of items in it
+    checked: Vec<(PathBuf, usize)>,
+    warnings: Vec<(PathBuf, String)>,
+}
+
+#[cfg(debug_assertions)]
+thread_local! {
+    /// Source files held in memory by this thread and the most held at once, debug builds only
+    static HELD_SOURCES: std::cell::Cell<(usize, usize)> = const { std::cell::Cell::new((0, 0)) };
+}
+
+/// Counts a source file as held in memory until it's dropped. Files are analysed one at a time
+/// so no more than one should ever be held, the tests check this
+#[cfg(debug_assertions)]
+struct HeldSource;
+
+#[cfg(debug_assertions)]
+impl HeldSource {
+    fn new() -> Self {
+        HELD_SOURCES.with(|x| {
+            let (held, peak) = x.get();
+            x.set((held + 1, peak.max(held + 1)));
+        });
+        Self
+    }
+
+    /// The most source files held at once since the last call
+    #[cfg(test)]
+    fn take_peak() -> usize {
+        HELD_SOURCES.with(|x| {
+            let (held, peak) = x.get();
+            x.set((held, held));
+            peak
+        })
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Drop for HeldSource {
+    fn drop(&mut self) {
+        HELD_SOURCES.with(|x| {
+            let (held, peak) = x.get();
+            x.set((held - 1, peak));
+        });
+    }
+}
+
+impl Progress {
+    fn warn(&mut self, warning: String) {
+        warn!("{}", warning);
+        self.warnings.push(warning);
+    }
+
+    /// Warns about a problem with the file at `relative`
+    fn warn_file(&mut self, relative: &Path, warning: String) {
+        self.per_file
+            .warnings
+            .push((relative.to_path_buf(), warning.clone()));
+        self.warn(warning);
+    }
+
+    fn skip(&mut self, reason: SkipReason) {
+        *self.skipped.entry(reason).or_default() += 1;
+    }
+}
+
+pub fn get_analysis(root: PathBuf, config: &Config) -> io::Result<Report> {
+    analyse_project(root, config).map(|(report, _)| report)
+}
+
+/// Analyses the project like `get_analysis`, also returning what was checked in each file
+fn analyse_project(root: PathBuf, config: &Config) -> io::Result<(Report, PerFile)> {
     info!("Analysing project in {}", root.display());
-    for e in get_dir_walker(root.clone()) {
-        let relative = e.path().strip_prefix(&root).unwrap();
-        if !excluded_files.iter().any(|x| x.matches_path(&relative)) {
-            analyse_package(e.path(), &root);
-        }
-    }
-}
-
-/// Analyses a package of the target crate.
-fn analyse_package(path: &Path, root: &Path) {
-    if let Some(_file) = path.to_str() {
-        let skip_cause_test = path.starts_with(root.join("tests"));
-        let skip_cause_example = path.starts_with(root.join("examples"));
-        if !(skip_cause_test || skip_cause_example) {
-            if let Ok(walker) = AstWalker::new(path.to_path_buf()) {
-                let bad_panics = walker.process();
-                if !bad_panics.is_empty() {
-                    warn!(
-                        "Potentially undocumented panics in {}",
-                        path.strip_prefix(root).unwrap().display()
-                    );
+    let profile = config.profile().description();
+    if let (Some(description), true) = (profile, config.verbose) {
+        info!("Using the {} profile: {}", config.profile(), description);
+    }
+    let mut progress = Progress::default();
+    let mut options = config.options();
+    for path in options
+        .panicking_fns
+        .iter()
+        .filter(|x| options.allowed_fns.contains(x))
+    {
+        warn!(
+            "{} is given to both --panicking-fn and --allow-fn, calls to it aren't counted",
+            path
+        );
+    }
+    let crate_root = ["lib.rs", "main.rs"]
+        .iter()
+        .map(|x| root.join("src").join(x))
+        .find(|x| x.exists())
+        .and_then(|x| AstWalker::new(x).ok());
+    options.no_std = crate_root.as_ref().is_some_and(AstWalker::is_no_std);
+    options.panic_abort = match release_panic_strategy(&root.join("Cargo.toml")) {
+        Ok(strategy) => strategy.as_deref() == Some("abort"),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+        Err(e) => {
+            progress.warn(format!("Unable to read the release profile: {}", e));
+            false
+        }
+    };
+    if config.rustdoc_surface {
+        let crate_root = root.join("src").join("lib.rs");
+        let recurse_modules = match progress.dir_configs.for_file(&root, &crate_root) {
+            Ok(effective) => effective.config.recurse_modules.unwrap_or(true),
+            Err(e) => {
+                progress.warn(format!("Unable to load config: {}", e));
+                true
+            }
+        };
+        let surface = if config.no_recurse_modules || !recurse_modules {
+            Surface::without_module_files(&crate_root)
+        } else {
+            Surface::new(&crate_root)
+        }
+        .map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Unable to load crate root {}: {}", crate_root.display(), e),
+            )
+        })?;
+        if config.verbose {
+            let unreachable = surface.unreachable();
+            if !unreachable.is_empty() {
+                info!("Public items which can't be reached from outside the crate:");
+                for item in &unreachable {
+                    info!("\t{}", item);
+                }
+            }
+        }
+        options.surface = Some(Arc::new(surface));
+    }
+    if config.verbose {
+        if options.prefilter() {
+            info!("Skipping files and items with nothing which looks like a panic");
+        } else {
+            info!("Parsing every file, the prefilter is off");
+        }
+    }
+    // Each file is analysed and dropped before the next is read, so only a single source file is
+    // held in memory at a time
+    let sources = match &options.surface {
+        Some(surface) => {
+            let mut paths = surface.files().map(Path::to_path_buf).collect::<Vec<_>>();
+            paths.sort();
+            paths
+                .into_iter()
+                .map(|path| SourceFile {
+                    path,
+                    symlink_target: None,
+                })
+                .collect()
+        }
+        None => source_files(root.clone()),
+    };
+    // Findings are held compactly while the rest of the crate is analysed
+    let mut files = CompactReport::from(Report::new(vec![]));
+    for source in sources {
+        progress.discovery.files_walked += 1;
+        let relative = source.path.strip_prefix(&root).unwrap_or(&source.path);
+        if config
+            .excluded_files
+            .iter()
+            .any(|x| x.matches_path(relative))
+        {
+            progress.discovery.excluded_by_glob += 1;
+        } else {
+            files.extend(
+                analyse_package(&source.path, &root, config, &options, &mut progress).map(
+                    |mut x| {
+                        x.symlink_target = source.symlink_target.clone();
+                        x
+                    },
+                ),
+            );
+        }
+    }
+    // Build dependencies are other crates so they aren't counted
+    let modules = std::mem::take(&mut progress.modules);
+    let per_file = std::mem::take(&mut progress.per_file);
+    if config.check_build_dependencies {
+        // The build dependencies are only reachable by the build script so the surface doesn't
+        // apply to them
+        let options = Options {
+            surface: None,
+            ..options.clone()
+        };
+        let project = root.canonicalize().unwrap_or_else(|_| root.clone());
+        for dependency in build_dependency_paths(&root.join("Cargo.toml"))? {
+            let dependency_root = match root.join(&dependency).canonicalize() {
+                Ok(path) => path,
+                Err(e) => {
+                    progress.warn(format!(
+                        "Unable to find build dependency {}: {}",
+                        dependency.display(),
+                        e
+                    ));
+                    continue;
                 }
-                for panik in &bad_panics {
-                    println!("\t{}", panik);
+            };
+            if !config.rustdoc_surface && dependency_root.starts_with(&project) {
+                // Already walked as part of the project
+                continue;
+            }
+            info!(
+                "Analysing build dependency in {}",
+                dependency_root.display()
+            );
+            for source in source_files(dependency_root.clone()) {
+                progress.discovery.files_walked += 1;
+                let path = &source.path;
+                let relative = dependency.join(path.strip_prefix(&dependency_root).unwrap_or(path));
+                if config
+                    .excluded_files
+                    .iter()
+                    .any(|x| x.matches_path(&relative))
+                {
+                    progress.discovery.excluded_by_glob += 1;
+                } else {
+                    files.extend(
+                        analyse_package(path, &dependency_root, config, &options, &mut progress)
+                            .map(|mut x| {
+                                x.path = relative;
+                                x.symlink_target = source.symlink_target.clone();
+                                x
+                            }),
+                    );
                 }
             }
         }
     }
+    let mut report = files.into_report();
+    if let Some(description) = profile {
+        report.notes.push(format!(
+            "Checked with the {} profile, {}",
+            config.profile(),
+            description
+        ));
+    }
+    report.crate_docs_mention_panics = crate_root.is_some_and(|x| x.docs_mention_panics());
+    report.no_std = options.no_std;
+    report.panic_abort = options.panic_abort;
+    report.files_checked = progress.files_checked;
+    report.items_checked = progress.items_checked;
+    report.prefiltered = options.prefilter();
+    report.modules = modules.finish();
+    report.discovery = progress.discovery;
+    let filtered = report
+        .files
+        .iter()
+        .flat_map(|x| x.findings.iter())
+        .filter(|x| !config.shows_attributes(x))
+        .count();
+    if filtered > 0 {
+        progress.skipped.insert(SkipReason::Attribute, filtered);
+    }
+    report.skipped = progress.skipped;
+    report.warnings = progress.warnings;
+    let suppressed = progress.suppressed;
+    if !suppressed.is_empty() {
+        report.notes.push(format!(
+            "{} findings were left out by --skip-delegations or --min-panic-sites, run with \
+             --verbose to list them",
+            suppressed.len()
+        ));
+        if config.verbose {
+            info!("Findings left out of the report:");
+            for item in &suppressed {
+                info!("\t{}", item);
+            }
+        }
+    }
+    report.shrink_to_fit();
+    Ok((report, per_file))
+}
+
+/// Analyses a file of `cargo expand` output, with the impls generated by derives skipped. The
+/// file stands in for the whole crate so it's reported relative to its own directory.
+pub fn get_expanded_analysis(path: &Path, config: &Config) -> io::Result<Report> {
+    info!("Analysing expanded source in {}", path.display());
+    let mut progress = Progress::default();
+    let options = Options {
+        expanded_source: true,
+        ..config.options()
+    };
+    let root = path.parent().unwrap_or_else(|| Path::new(""));
+    progress.discovery.files_walked += 1;
+    let file = analyse_package(path, root, config, &options, &mut progress);
+    let residue = AstWalker::new(path.to_path_buf())
+        .map(|x| x.derive_residue())
+        .unwrap_or_default();
+    if residue > 0 {
+        progress.skipped.insert(SkipReason::DeriveResidue, residue);
+    }
+    let mut report = Report::new(file.into_iter().collect());
+    report.notes.push(
+        "Analysed `cargo expand` output, line numbers are in the expanded file rather than the \
+         crate's sources"
+            .to_string(),
+    );
+    report.expanded_source = true;
+    report.files_checked = progress.files_checked;
+    report.items_checked = progress.items_checked;
+    report.prefiltered = options.prefilter();
+    report.discovery = progress.discovery;
+    report.skipped = progress.skipped;
+    report.warnings = progress.warnings;
+    report.shrink_to_fit();
+    Ok(report)
+}
+
+/// The output of `cargo expand` saved to a temporary file, which is removed when this is dropped
+struct ExpandedFile {
+    path: PathBuf,
+}
+
+impl Drop for ExpandedFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Runs `cargo expand` on the project and saves its output to a temporary file
+fn run_cargo_expand(root: &Path) -> io::Result<ExpandedFile> {
+    info!("Running cargo expand");
+    let mut command =
+        process::Command::new(std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into()));
+    command.arg("expand");
+    if !root.as_os_str().is_empty() {
+        command.current_dir(root);
+    }
+    let output = command.output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = if stderr.contains("no such command") {
+            "cargo-expand isn't installed, install it with `cargo install cargo-expand` or pass \
+             its output to --expanded-source"
+                .to_string()
+        } else {
+            format!("cargo expand failed: {}", stderr.trim())
+        };
+        return Err(io::Error::other(message));
+    }
+    let path =
+        std::env::temp_dir().join(format!("doc_panic_checker-expanded-{}.rs", process::id()));
+    let expanded = ExpandedFile { path };
+    fs::write(&expanded.path, output.stdout)?;
+    Ok(expanded)
+}
+
+/// Analyses each of the labelled projects on its own and merges their reports, or returns the
+/// report of a lone unlabelled project as is
+pub fn get_analyses(roots: &[(String, PathBuf)], config: &Config) -> io::Result<Report> {
+    if let [(label, root)] = roots {
+        if label.is_empty() {
+            return get_analysis(root.clone(), config);
+        }
+    }
+    let mut reports = vec![];
+    for (label, root) in roots {
+        reports.push((label.clone(), get_analysis(root.clone(), config)?));
+    }
+    Ok(Report::merge(reports))
+}
+
+/// Returns the `panic` setting of the release profile in a `Cargo.toml`, if it has one
+fn release_panic_strategy(manifest: &Path) -> io::Result<Option<String>> {
+    let manifest = Manifest::from_slice(&fs::read(manifest)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(manifest.profile.release.and_then(|x| x.panic))
+}
+
+/// Returns the paths of the local build dependencies in a `Cargo.toml`, relative to the directory
+/// it's in. Target specific build dependencies are included.
+fn build_dependency_paths(manifest: &Path) -> io::Result<Vec<PathBuf>> {
+    let manifest = Manifest::from_slice(&fs::read(manifest)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut paths = manifest
+        .build_dependencies
+        .values()
+        .chain(
+            manifest
+                .target
+                .values()
+                .flat_map(|x| x.build_dependencies.values()),
+        )
+        .filter_map(|x| x.detail()?.path.as_ref())
+        .map(PathBuf::from)
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// Analyses a package of the target crate. The source is dropped before returning, anything
+/// needed from it later like snippets is copied into the `FileReport`.
+fn analyse_package(
+    path: &Path,
+    root: &Path,
+    config: &Config,
+    options: &Options,
+    progress: &mut Progress,
+) -> Option<FileReport> {
+    path.to_str()?;
+    let skip_cause_test = path.starts_with(root.join("tests"));
+    let is_example = path.starts_with(root.join("examples"));
+    let skip_cause_example = is_example && !config.include_examples;
+    if skip_cause_test {
+        progress.discovery.tests += 1;
+        return None;
+    }
+    if skip_cause_example {
+        progress.discovery.examples += 1;
+        return None;
+    }
+    let mut options = Options {
+        binary_root: is_binary_root(path, root),
+        check_binaries: options.check_binaries || is_example,
+        // The stats count items which can panic whether or not they document it
+        report_documented: options.report_documented || config.module_stats,
+        ..options.clone()
+    };
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    let effective = match progress.dir_configs.for_file(root, path) {
+        Ok(effective) => effective,
+        Err(e) => {
+            progress.warn_file(relative, format!("Unable to load config: {}", e));
+            Effective::default()
+        }
+    };
+    let dir_profile = effective
+        .config
+        .profile
+        .as_ref()
+        .and_then(|x| x.parse::<Profile>().ok());
+    if let (None, Some(profile)) = (config.profile, dir_profile) {
+        options.release_blockers = profile == Profile::ReleaseGate;
+    }
+    effective.config.apply(&mut options);
+    if config.verbose && !effective.sources.is_empty() {
+        let sources = effective
+            .sources
+            .iter()
+            .map(|x| x.strip_prefix(root).unwrap_or(x).display().to_string())
+            .collect::<Vec<_>>();
+        info!(
+            "{} uses {} from {}",
+            relative.display(),
+            effective.config,
+            sources.join(", ")
+        );
+    }
+    let walker = match AstWalker::new(path.to_path_buf()) {
+        Ok(walker) => walker.with_options(options),
+        Err(e) => {
+            progress.discovery.unreadable += 1;
+            progress.skip(SkipReason::Unreadable);
+            progress.warn_file(
+                relative,
+                format!("Unable to read {}: {}", relative.display(), e),
+            );
+            return None;
+        }
+    };
+    #[cfg(debug_assertions)]
+    let _held = HeldSource::new();
+    progress.files_checked += 1;
+    let source = if config.embed_snippets {
+        Some(walker.source_code())
+    } else {
+        None
+    };
+    let mut findings = vec![];
+    let mut locations = vec![];
+    let suppressed = &mut progress.suppressed;
+    let skipped = &mut progress.skipped;
+    let parsed = walker.process_with(|x| {
+        if config.module_stats {
+            if x.kind() == FindingKind::DocumentedPanic {
+                locations.push(x);
+                return;
+            }
+            locations.push(x.clone());
+        }
+        if config.is_allowed(x.ident()) {
+            *skipped.entry(SkipReason::AllowRegex).or_default() += 1;
+            return;
+        }
+        match config.suppression(&x) {
+            Some((reason, detail)) => {
+                *skipped.entry(reason).or_default() += 1;
+                suppressed.push(format!(
+                    "{} in {} ({})",
+                    compact_ident(x.ident()),
+                    relative.display(),
+                    detail
+                ))
+            }
+            None => {
+                let mut finding = Finding::new(&x, source);
+                finding.level = effective.config.levels.get(x.kind().code()).cloned();
+                findings.push(finding)
+            }
+        }
+    });
+    let items = match parsed {
+        Ok(items) => items,
+        Err(e) => {
+            progress.skip(SkipReason::ParseError);
+            progress.warn_file(
+                relative,
+                format!("Unable to parse {}: {}", relative.display(), e),
+            );
+            0
+        }
+    };
+    progress.items_checked += items;
+    progress
+        .per_file
+        .checked
+        .push((relative.to_path_buf(), items));
+    if config.module_stats {
+        progress
+            .modules
+            .add_file(relative, walker.source_code(), &locations);
+    }
+    if findings.is_empty() {
+        None
+    } else {
+        Some(FileReport {
+            path: path.strip_prefix(root).unwrap().to_path_buf(),
+            symlink_target: None,
+            findings,
+        })
+    }
 }
 
-pub fn setup_logging(color: Color) {
+pub fn setup_logging(color: Color, quiet: bool) {
+    let level = if quiet {
+        LevelFilter::ERROR
+    } else {
+        LevelFilter::INFO
+    };
     let base_exceptions = |env: EnvFilter| {
-        env.add_directive("doc_panic_checker=info".parse().unwrap())
-            .add_directive(LevelFilter::INFO.into())
+        env.add_directive(format!("doc_panic_checker={}", level).parse().unwrap())
+            .add_directive(level.into())
     };
     let filter = match std::env::var_os("RUST_LOG").map(|s| s.into_string()) {
         Some(Ok(env)) => {
             let mut filter = base_exceptions(EnvFilter::new(""));
-            for s in env.split(',').into_iter() {
+            for s in env.split(',') {
                 match s.parse() {
                     Ok(d) => filter = filter.add_directive(d),
                     Err(err) => println!("WARN ignoring log directive: `{}`: {}", s, err),
@@ -85,31 +1527,1133 @@ pub fn setup_logging(color: Color) {
         .with_max_level(tracing::Level::ERROR)
         .with_env_filter(filter)
         .with_ansi(with_colour)
+        .with_writer(io::stderr)
         .with_target(false)
         .without_time()
         .init();
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // `--version --verbose` prints the same as `info`, clap would print the version and exit
+    let args = std::env::args_os().collect::<Vec<_>>();
+    let is_arg = |names: &[&str]| args.iter().skip(1).any(|x| names.iter().any(|n| x == *n));
+    if is_arg(&["--version", "-V"]) && is_arg(&["--verbose", "-v"]) {
+        let config = Config::from_iter(args.iter().filter(|x| *x != "--version" && *x != "-V"));
+        let diagnostics = Diagnostics::new(&config, &std::env::current_dir()?);
+        diagnostics.render(OutputFormat::Text, &mut io::stdout().lock())?;
+        return Ok(());
+    }
+
     let config = Config::from_args();
-    setup_logging(config.color);
+    setup_logging(config.color, config.quiet);
 
-    if config
-        .manifest_path
-        .as_ref()
-        .map(|x| x.file_name() != Some(OsStr::new("Cargo.toml")))
-        .unwrap_or(false)
+    let mut report = None;
+    let result = run(&config, &mut report);
+    if let Some(path) = &config.status_file {
+        if let Err(e) = status(&result, report.as_ref()).write(path) {
+            error!("Unable to write the status file {}: {}", path.display(), e);
+            if result.is_ok() {
+                return Err(e.into());
+            }
+        }
+    }
+    match result.map_err(|e| e.downcast::<Stop>()) {
+        Ok(()) => Ok(()),
+        // Shown like any other error, as a string
+        Err(Ok(stop)) => match *stop {
+            Stop::Findings(message) => Err(message.into()),
+            // Not the usual failure code, so CI can tell a misconfigured run apart from one with
+            // findings
+            Stop::NothingAnalysed(message) => {
+                error!("{}", message);
+                process::exit(NOTHING_ANALYSED);
+            }
+        },
+        Err(Err(e)) => Err(e),
+    }
+}
+
+/// How a run ended, for `--status-file`
+fn status(result: &Result<(), Box<dyn std::error::Error>>, report: Option<&Report>) -> Status {
+    match result {
+        Ok(()) => Status::finished(report, None),
+        Err(e) => match e.downcast_ref::<Stop>() {
+            Some(Stop::Findings(message)) => Status::finished(report, Some(message.clone())),
+            _ => Status::error(report, e.to_string()),
+        },
+    }
+}
+
+/// Runs the command, keeping the report in `analysed` as soon as there is one so how the run
+/// ended can be reported even if it fails afterwards
+fn run(config: &Config, analysed: &mut Option<Report>) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(Command::Info { format }) = &config.command {
+        let diagnostics = Diagnostics::new(config, &std::env::current_dir()?);
+        diagnostics.render(*format, &mut io::stdout().lock())?;
+        return Ok(());
+    }
+
+    if let Some(Command::Render { report, format }) = &config.command {
+        let mut report = Report::load(report)?;
+        group_repeated(&mut report, config.fold_threshold);
+        config.render(analysed.insert(report), *format)?;
+        return Ok(());
+    }
+    if let Some(Command::AuditDocs {
+        rustdoc_json,
+        risk_patterns,
+        format,
+    }) = &config.command
+    {
+        let risk_patterns = if risk_patterns.is_empty() {
+            DEFAULT_RISK_PATTERNS
+                .iter()
+                .map(|x| Regex::new(x))
+                .collect::<Result<Vec<_>, _>>()?
+        } else {
+            risk_patterns.clone()
+        };
+        let report = audit_file(rustdoc_json, &risk_patterns)?;
+        config.render(analysed.insert(report), *format)?;
+        return Ok(());
+    }
+    if let Some(Command::Crate {
+        spec,
+        offline,
+        format,
+    }) = &config.command
+    {
+        let archive = fetch(spec, *offline)?;
+        let dir =
+            std::env::temp_dir().join(format!("doc_panic_checker-{}-{}", spec, std::process::id()));
+        let unpacked = unpack(spec, &archive, &dir)?;
+        let mut config = config.clone();
+        for pattern in NON_LIBRARY_FILES {
+            config.excluded_files.push(Pattern::new(pattern)?);
+        }
+        let mut report = get_analysis(unpacked.root.clone(), &config)?;
+        report.notes.insert(
+            0,
+            format!(
+                "Analysed {} from crates.io, sha256 {}",
+                spec, unpacked.checksum
+            ),
+        );
+        group_repeated(&mut report, config.fold_threshold);
+        let report = &*analysed.insert(report);
+        if !config.quiet {
+            config.render(report, *format)?;
+        }
+        if config.print_unchecked_summary {
+            render_skipped(report, &mut io::stderr().lock())?;
+        }
+        config
+            .check_analysed(report)
+            .map_err(Stop::NothingAnalysed)?;
+        eprintln!(
+            "{}",
+            config.outcome(report.summary()).map_err(Stop::Findings)?
+        );
+        return Ok(());
+    }
+    if let Some(Command::Compare {
+        old_root,
+        new_root,
+        format,
+    }) = &config.command
     {
-        Err("The manifest-path must be a path to a Cargo.toml file")?;
+        let options = config.options();
+        let old = panicking_items(old_root, &options)?;
+        let new = panicking_items(new_root, &options)?;
+        let comparison = compare(&old, &new);
+        let mut stdout = io::stdout().lock();
+        match format {
+            OutputFormat::Text => render_comparison(&comparison, &mut stdout)?,
+            OutputFormat::Markdown => render_comparison_markdown(&comparison, &mut stdout)?,
+            _ => Err("compare only supports the text and markdown formats")?,
+        }
+        return Ok(());
     }
 
-    let root = config
-        .manifest_path
-        .map(|x| x.canonicalize().ok())
-        .flatten()
-        .map(|x| x.parent().map(|x| x.to_path_buf()).unwrap_or_default())
-        .unwrap_or_default();
+    let roots = config.roots()?;
+    let extension = match config.output_dir {
+        Some(_) if roots.len() > 1 || !roots[0].0.is_empty() => {
+            Err("--output-dir only supports a single unlabelled --manifest-path")?
+        }
+        Some(_) => Some(workspace::extension(config.format)?),
+        None => None,
+    };
+
+    if (config.expanded_source.is_some() || config.run_expand) && config.output_dir.is_some() {
+        Err("--output-dir can't be used with expanded source")?;
+    }
+    let expanded_file = match &config.expanded_source {
+        Some(_) => None,
+        None if config.run_expand && roots.len() > 1 => {
+            Err("--run-expand only supports a single --manifest-path")?
+        }
+        None if config.run_expand => Some(run_cargo_expand(&roots[0].1)?),
+        None => None,
+    };
+    let expanded = config
+        .expanded_source
+        .as_deref()
+        .or(expanded_file.as_ref().map(|x| x.path.as_path()));
+    let mut per_file = PerFile::default();
+    let mut report = match expanded {
+        Some(path) => get_expanded_analysis(path, config)?,
+        None if extension.is_some() => {
+            let (report, files) = analyse_project(roots[0].1.clone(), config)?;
+            per_file = files;
+            report
+        }
+        None => get_analyses(&roots, config)?,
+    };
+    group_repeated(&mut report, config.fold_threshold);
+    let report = &*analysed.insert(report);
+    if let Some(dir) = &config.html_report_dir {
+        render_html_dir(&config.displayed(report, OutputFormat::Html), dir)?;
+    }
+    if let (Some(dir), Some(extension)) = (&config.output_dir, extension) {
+        write_package_reports(&roots[0].1, report, &per_file, dir, extension, config)?;
+    }
+    if !config.quiet {
+        config.render(report, config.format)?;
+    }
+    if let Some(path) = &config.ratchet {
+        check_ratchet(path, report, config.update_ratchet)?;
+    }
+    if config.print_unchecked_summary {
+        render_skipped(report, &mut io::stderr().lock())?;
+    }
+    if config.annotate_pr || config.annotate_pr_dry_run {
+        annotate_pr(&roots, report, config)?;
+    }
+    config
+        .check_analysed(report)
+        .map_err(Stop::NothingAnalysed)?;
+    // On stderr so it can't end up in a JSON or SARIF report redirected from stdout
+    eprintln!(
+        "{}",
+        config.outcome(report.summary()).map_err(Stop::Findings)?
+    );
+    Ok(())
+}
+
+/// Splits the report of the workspace at `root` between its members, writing each one's report
+/// and an index of them to `dir`. A file belongs to the member with the deepest directory it's
+/// in, and is reported relative to that directory. Problems running the analysis which aren't
+/// about a file are only in the workspace's report.
+fn write_package_reports(
+    root: &Path,
+    whole: &Report,
+    per_file: &PerFile,
+    dir: &Path,
+    extension: &str,
+    config: &Config,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let members = workspace::members(&root.join("Cargo.toml"))?;
+    if members.is_empty() {
+        Err("--output-dir needs the manifest of a workspace with members")?;
+    }
+    let relative = members
+        .iter()
+        .map(|x| x.root.strip_prefix(root).unwrap_or(&x.root).to_path_buf())
+        .collect::<Vec<_>>();
+    // The index of the member `path` belongs to
+    let owner = |path: &Path| {
+        (0..relative.len())
+            .filter(|i| path.starts_with(&relative[*i]))
+            .max_by_key(|i| relative[*i].components().count())
+    };
+    fs::create_dir_all(dir)?;
+    let mut packages = vec![];
+    for (i, member) in members.into_iter().enumerate() {
+        let in_member = |path: &Path| {
+            (owner(path) == Some(i)).then(|| {
+                path.strip_prefix(&relative[i])
+                    .unwrap_or(path)
+                    .to_path_buf()
+            })
+        };
+        let files = whole
+            .files
+            .iter()
+            .filter_map(|x| {
+                Some(FileReport {
+                    path: in_member(&x.path)?,
+                    ..x.clone()
+                })
+            })
+            .collect();
+        let mut report = Report::new(files);
+        for (path, items) in &per_file.checked {
+            if in_member(path).is_some() {
+                report.files_checked += 1;
+                report.items_checked += items;
+            }
+        }
+        report.warnings = per_file
+            .warnings
+            .iter()
+            .filter(|(path, _)| in_member(path).is_some())
+            .map(|(_, warning)| warning.clone())
+            .collect();
+        report.prefiltered = whole.prefiltered;
+        let name = workspace::report_name(&member.name, extension);
+        write_atomic(&dir.join(&name), |writer| {
+            render(
+                &config.displayed(&report, config.format),
+                config.format,
+                config.verbose,
+                None,
+                writer,
+            )
+        })?;
+        packages.push(PackageSummary {
+            name: member.name,
+            path: member
+                .root
+                .strip_prefix(root)
+                .unwrap_or(&member.root)
+                .to_path_buf(),
+            report: PathBuf::from(name),
+            summary: report.summary(),
+        });
+    }
+    let index = Index::new(packages);
+    write_atomic(&dir.join("index.json"), |writer| {
+        serde_json::to_writer_pretty(&mut *writer, &index)?;
+        writeln!(writer)
+    })?;
+    Ok(())
+}
+
+/// Posts the findings to the pull request as a check run, problems with the API are logged
+/// rather than failing the run
+fn annotate_pr(roots: &[(String, PathBuf)], report: &Report, config: &Config) -> io::Result<()> {
+    let mut summary = vec![];
+    render(
+        &config.displayed(report, OutputFormat::Markdown),
+        OutputFormat::Markdown,
+        false,
+        None,
+        &mut summary,
+    )?;
+    // Annotation paths are relative to the repository, which the crates may be inside of
+    let workspace = std::env::var_os("GITHUB_WORKSPACE");
+    let in_repository = |root: &Path| {
+        workspace
+            .as_ref()
+            .and_then(|x| root.strip_prefix(x).ok().map(Path::to_path_buf))
+            .unwrap_or_default()
+    };
+    let mut report = Cow::Borrowed(report);
+    let prefix = match roots {
+        [(label, root)] if label.is_empty() => in_repository(root),
+        _ => {
+            for file in &mut report.to_mut().files {
+                let labelled = roots
+                    .iter()
+                    .find_map(|(label, root)| Some((file.path.strip_prefix(label).ok()?, root)));
+                if let Some((path, root)) = labelled {
+                    file.path = in_repository(root).join(path);
+                }
+            }
+            PathBuf::new()
+        }
+    };
+    let failed = config.outcome(report.summary()).is_err();
+    let payloads =
+        github::check_run_payloads(&report, &prefix, &String::from_utf8_lossy(&summary), failed);
+    if config.annotate_pr_dry_run {
+        let mut stderr = io::stderr().lock();
+        for payload in &payloads {
+            serde_json::to_writer_pretty(&mut stderr, payload)?;
+            writeln!(stderr)?;
+        }
+        return Ok(());
+    }
+    let context = match github::PrContext::from_env(|x| std::env::var(x).ok()) {
+        Some(context) => context,
+        None => {
+            warn!(
+                "Not annotating the pull request, --annotate-pr needs GITHUB_TOKEN, \
+                 GITHUB_REPOSITORY, GITHUB_SHA and a pull request in GITHUB_REF or PR_NUMBER"
+            );
+            return Ok(());
+        }
+    };
+    post_annotations(&context, payloads);
+    Ok(())
+}
+
+#[cfg(feature = "github")]
+fn post_annotations(context: &github::PrContext, payloads: Vec<serde_json::Value>) {
+    let mut transport = github::UreqTransport {
+        token: context.token.clone(),
+    };
+    match github::annotate(context, payloads, &mut transport) {
+        Ok(id) => info!(
+            "Annotated pull request #{} with check run {}",
+            context.pr_number, id
+        ),
+        Err(e) => warn!("Unable to annotate the pull request: {}", e),
+    }
+}
 
-    get_analysis(root, &config.excluded_files);
+#[cfg(not(feature = "github"))]
+fn post_annotations(_: &github::PrContext, _: Vec<serde_json::Value>) {
+    warn!("Not annotating the pull request, --annotate-pr needs the `github` feature");
+}
+
+/// Fails if any file has more findings than the ratchet allows, otherwise lowers the counts in it
+/// if asked to
+fn check_ratchet(
+    path: &Path,
+    report: &Report,
+    update: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let current = Ratchet::from_report(report);
+    let ratchet = match Ratchet::load(path) {
+        Ok(ratchet) => ratchet,
+        // A new ratchet starts from the current counts
+        Err(e) if update && e.kind() == io::ErrorKind::NotFound => current.clone(),
+        Err(e) => Err(format!("Unable to load ratchet {}: {}", path.display(), e))?,
+    };
+    let regressions = ratchet.regressions(&current);
+    if !regressions.is_empty() {
+        for regression in &regressions {
+            error!(
+                "{} has {} findings but the ratchet allows {}",
+                regression.path.display(),
+                regression.found,
+                regression.allowed
+            );
+        }
+        Err(Stop::Findings(format!(
+            "{} files have more findings than allowed by {}",
+            regressions.len(),
+            path.display()
+        )))?;
+    }
+    if update {
+        let tightened = ratchet.tighten(&current);
+        if tightened != ratchet || !path.exists() {
+            info!("Updating ratchet {}", path.display());
+            tightened.save(path)?;
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use doc_panic_checker::ast_walker::PanicKind;
+    use doc_panic_checker::status::Outcome;
+
+    #[test]
+    fn allow_regexes() {
+        let config =
+            Config::from_iter_safe(&["doc_panic_checker", "--allow-regex", "^ffi::", "::raw_"])
+                .unwrap();
+        assert!(config.is_allowed("ffi::open"));
+        assert!(config.is_allowed("Buffer < u8 > :: raw_push"));
+        assert!(!config.is_allowed("api::ffi::open"));
+        assert!(!config.is_allowed("raw_open"));
+
+        assert!(Config::from_iter_safe(&["doc_panic_checker", "--allow-regex", "(ffi"]).is_err());
+    }
+
+    #[test]
+    fn flag_aliases() {
+        for flag in ["--check-panics-in-serde", "--check-serde-impls"] {
+            let config = Config::from_iter_safe(&["doc_panic_checker", flag]).unwrap();
+            assert!(config.options().check_serde);
+        }
+    }
+
+    #[test]
+    fn local_build_dependencies() {
+        let dir = std::env::temp_dir().join(format!(
+            "doc_panic_checker_build_deps-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let manifest = dir.join("Cargo.toml");
+        fs::write(
+            &manifest,
+            r#"
+[package]
+name = "builds"
+version = "0.1.0"
+
+[build-dependencies]
+cc = "1.0"
+codegen = { path = "../codegen" }
+vendored = { version = "0.2", path = "vendor/vendored" }
+
+[target.'cfg(windows)'.build-dependencies]
+winres = { path = "../winres" }
+"#,
+        )
+        .unwrap();
+        let paths = build_dependency_paths(&manifest).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from("../codegen"),
+                PathBuf::from("../winres"),
+                PathBuf::from("vendor/vendored"),
+            ]
+        );
+    }
+
+    #[test]
+    fn info_outside_a_crate() {
+        let config =
+            Config::from_iter_safe(&["doc_panic_checker", "info", "--format", "json"]).unwrap();
+        let dir =
+            std::env::temp_dir().join(format!("doc_panic_checker_info-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let diagnostics = Diagnostics::new(&config, &dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let mut output = vec![];
+        diagnostics.render(OutputFormat::Json, &mut output).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+        let mut keys = json.as_object().unwrap().keys().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "cargo_home",
+                "git_hash",
+                "manifest_path",
+                "options",
+                "proc_macro2_version",
+                "profile",
+                "span_locations",
+                "syn_version",
+                "target",
+                "version",
+                "workspace_members",
+            ]
+        );
+        assert!(json["manifest_path"].is_null());
+        assert_eq!(json["workspace_members"], serde_json::json!([]));
+        assert_eq!(json["span_locations"], true);
+        assert!(diagnostics
+            .render(OutputFormat::Markdown, &mut vec![])
+            .is_err());
+    }
+
+    #[test]
+    fn package_reports() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/workspace");
+        let config = Config::from_iter_safe(&["doc_panic_checker", "--format", "json"]).unwrap();
+        let dir =
+            std::env::temp_dir().join(format!("doc_panic_checker_packages-{}", std::process::id()));
+        // An old report is replaced rather than appended to
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("alpha.json"), "stale").unwrap();
+        // The workspace is analysed once and its report split between the members
+        let (whole, per_file) = analyse_project(root.clone(), &config).unwrap();
+        write_package_reports(&root, &whole, &per_file, &dir, "json", &config).unwrap();
+
+        let mut names = fs::read_dir(&dir)
+            .unwrap()
+            .map(|x| x.unwrap().file_name().to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["alpha.json", "beta.json", "index.json"]);
+
+        let index: Index =
+            serde_json::from_slice(&fs::read(dir.join("index.json")).unwrap()).unwrap();
+        let packages = index
+            .packages
+            .iter()
+            .map(|x| (x.name.as_str(), x.summary.findings, x.summary.files_checked))
+            .collect::<Vec<_>>();
+        assert_eq!(packages, [("alpha", 1, 1), ("beta", 2, 1)]);
+        assert_eq!(index.packages[1].path, Path::new("crates/beta"));
+        for package in &index.packages {
+            let report = Report::load(&dir.join(&package.report)).unwrap();
+            assert_eq!(report.summary(), package.summary);
+            assert!(report
+                .files
+                .iter()
+                .all(|x| x.path == Path::new("src/lib.rs")));
+        }
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(index.totals.findings, 3);
+        assert_eq!(index.totals.failures, 3);
+        assert_eq!(index.totals.files_checked, 2);
+        let whole = whole.summary();
+        assert_eq!(whole.findings, index.totals.findings);
+        assert_eq!(whole.items_checked, index.totals.items_checked);
+        assert_eq!(whole.files_checked, index.totals.files_checked);
+    }
+
+    #[test]
+    fn multiple_roots() {
+        let crates =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/workspace/crates");
+        let alpha = crates.join("alpha/Cargo.toml");
+        let beta = crates.join("beta/Cargo.toml");
+        let config = |args: &[&Path]| {
+            let mut argv = vec!["doc_panic_checker".as_ref()];
+            argv.extend(args.iter().map(|x| x.as_os_str()));
+            Config::from_iter_safe(argv).unwrap()
+        };
+        let manifest = Path::new("--manifest-path");
+        let config = config(&[
+            manifest,
+            &alpha,
+            manifest,
+            &beta,
+            Path::new("--label"),
+            Path::new("a"),
+        ]);
+        let roots = config.roots().unwrap();
+        assert_eq!(
+            roots,
+            [
+                (
+                    "a".to_string(),
+                    crates.join("alpha").canonicalize().unwrap()
+                ),
+                (
+                    "beta".to_string(),
+                    crates.join("beta").canonicalize().unwrap()
+                ),
+            ]
+        );
+
+        let labels = |args: &[&str]| {
+            let mut argv = vec!["doc_panic_checker"];
+            argv.extend_from_slice(args);
+            Config::from_iter_safe(argv).unwrap().roots()
+        };
+        let report = get_analyses(&roots, &config).unwrap();
+        let findings = report
+            .files
+            .iter()
+            .flat_map(|x| {
+                x.findings
+                    .iter()
+                    .map(move |y| (x.path.clone(), y.ident.clone()))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            findings,
+            [
+                (PathBuf::from("a/src/lib.rs"), "first".to_string()),
+                (PathBuf::from("beta/src/lib.rs"), "parse".to_string()),
+                (PathBuf::from("beta/src/lib.rs"), "halve".to_string()),
+            ]
+        );
+        assert_eq!(report.files_checked, 2);
+        assert_eq!(report.summary().failures, 3);
+
+        // One project is reported as before, unless it's given a label
+        let beta = beta.to_str().unwrap();
+        let single = labels(&["--manifest-path", beta]).unwrap();
+        let single = get_analyses(&single, &config).unwrap();
+        assert_eq!(single.files[0].path, PathBuf::from("src/lib.rs"));
+        let labelled = labels(&["--manifest-path", beta, "--label", "b"]).unwrap();
+        assert_eq!(labelled[0].0, "b");
+        let labelled = get_analyses(&labelled, &config).unwrap();
+        assert_eq!(labelled.files[0].path, PathBuf::from("b/src/lib.rs"));
+
+        let alpha = alpha.to_str().unwrap();
+        let copy = crates.join("alpha/../alpha/Cargo.toml");
+        assert!(labels(&[
+            "--manifest-path",
+            alpha,
+            "--manifest-path",
+            copy.to_str().unwrap()
+        ])
+        .unwrap_err()
+        .contains("labelled `alpha`"));
+        assert!(labels(&["--manifest-path", alpha, "--label", "a", "--label", "b"]).is_err());
+        assert!(labels(&["--manifest-path", "alpha/lib.rs"]).is_err());
+    }
+
+    #[test]
+    fn status_files() {
+        let fixture =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/expanded/lib.rs");
+        let dir = std::env::temp_dir().join(format!(
+            "doc_panic_checker_status_files-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let run_with = |args: &[&OsStr]| {
+            let status_file = dir.join("status.json");
+            let _ = fs::remove_file(&status_file);
+            let mut all = vec![
+                "doc_panic_checker".as_ref(),
+                "--quiet".as_ref(),
+                "--expanded-source".as_ref(),
+                fixture.as_os_str(),
+                "--status-file".as_ref(),
+                status_file.as_os_str(),
+            ];
+            all.extend(args);
+            let config = Config::from_iter_safe(all).unwrap();
+            let mut report = None;
+            let result = run(&config, &mut report);
+            status(&result, report.as_ref())
+                .write(&status_file)
+                .unwrap();
+            let status = fs::read_to_string(&status_file).unwrap();
+            serde_json::from_str::<serde_json::Value>(&status).unwrap()
+        };
+
+        let found = run_with(&[]);
+        assert_eq!(found["status"], "findings");
+        assert_eq!(found["findings"]["warning"], 3);
+        assert_eq!(found["files_checked"], 1);
+
+        // The HTML report can't be written over a file, the findings are still counted
+        let blocker = dir.join("blocker");
+        fs::write(&blocker, "").unwrap();
+        let failed = run_with(&["--html-report-dir".as_ref(), blocker.as_os_str()]);
+        assert_eq!(failed["status"], "error");
+        assert_eq!(failed["findings"]["warning"], 3);
+        assert!(failed["message"].as_str().is_some());
+
+        // Failing before the analysis leaves nothing to count
+        let early = run_with(&["--output-dir".as_ref(), dir.as_os_str()]);
+        assert_eq!(early["status"], "error");
+        assert_eq!(early["files_checked"], 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn expanded_file_removed() {
+        let path = std::env::temp_dir().join(format!(
+            "doc_panic_checker_expanded-{}.rs",
+            std::process::id()
+        ));
+        let expanded = ExpandedFile { path: path.clone() };
+        fs::copy("tests/fixtures/expanded/lib.rs", &expanded.path).unwrap();
+        let config = Config::from_iter_safe(["doc_panic_checker"]).unwrap();
+        let report = get_expanded_analysis(&expanded.path, &config).unwrap();
+        assert!(path.exists());
+        drop(expanded);
+        assert!(!path.exists());
+        assert!(report.files_checked > 0);
+    }
+
+    #[test]
+    fn expanded_source() {
+        let fixture =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/expanded/lib.rs");
+        let config = Config::from_iter_safe([
+            "doc_panic_checker".as_ref(),
+            "--check-panics-in-partial-ord".as_ref(),
+            "--check-panics-in-impl-display-error".as_ref(),
+            "--expanded-source".as_ref(),
+            fixture.as_os_str(),
+        ])
+        .unwrap();
+        let report = get_expanded_analysis(&fixture, &config).unwrap();
+        assert!(report.expanded_source);
+        assert!(report.notes[0].contains("expanded file"));
+        assert_eq!(report.files.len(), 1);
+        assert_eq!(report.files[0].path, PathBuf::from("lib.rs"));
+        let findings = report.files[0]
+            .findings
+            .iter()
+            .map(|x| (compact_ident(&x.ident), x.panic_line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            findings,
+            [
+                ("config::Config::port".to_string(), 88),
+                ("config::Config::check".to_string(), 92),
+                ("reload".to_string(), 98),
+            ]
+        );
+        // The expanded `todo!()` is still recognised as one
+        assert_eq!(
+            report.files[0].findings[2].fix_hint.as_deref(),
+            PanicKind::Todo.fix_hint()
+        );
+        assert_eq!(
+            report.skipped.get(&SkipReason::DeriveResidue).copied(),
+            Some(5)
+        );
+        // Otherwise the derived `Display` is reported for its unreachable arm
+        let walker = AstWalker::new(fixture)
+            .unwrap()
+            .with_options(config.options());
+        assert!(walker
+            .process()
+            .iter()
+            .any(|x| x.ident() == "config::Error::fmt"));
+    }
+
+    #[test]
+    fn recurse_modules_config() {
+        let dir =
+            std::env::temp_dir().join(format!("doc_panic_checker_recurse-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "pub mod parser;\n").unwrap();
+        fs::write(
+            dir.join("src/parser.rs"),
+            "pub fn parse(s: &str) -> u32 {\n    s.parse().unwrap()\n}\n",
+        )
+        .unwrap();
+        let files_checked = |args: &[&str]| {
+            let args = ["doc_panic_checker", "--rustdoc-surface"]
+                .iter()
+                .chain(args);
+            let config = Config::from_iter_safe(args).unwrap();
+            get_analysis(dir.clone(), &config).unwrap().files_checked
+        };
+        let recursing = files_checked(&[]);
+        let flag = files_checked(&["--no-recurse-modules"]);
+        fs::write(dir.join(".doc-panic.toml"), "recurse-modules = false\n").unwrap();
+        let configured = files_checked(&[]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(recursing, 2);
+        assert_eq!(flag, 1);
+        assert_eq!(configured, 1);
+    }
+
+    #[test]
+    fn nothing_analysed() {
+        let config = |args: &[&str]| {
+            Config::from_iter_safe(["doc_panic_checker"].iter().chain(args)).unwrap()
+        };
+        let dir =
+            std::env::temp_dir().join(format!("doc_panic_checker_empty-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let report = get_analysis(dir.clone(), &config(&[])).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(report.files_checked, 0);
+        assert_eq!(report.discovery, Discovery::default());
+        let error = config(&[]).check_analysed(&report).unwrap_err();
+        assert!(error.contains("0 files walked"), "{}", error);
+        assert!(config(&["--allow-empty"]).check_analysed(&report).is_ok());
+
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let excluded = config(&["--exclude-files", "**"]);
+        let report = get_analysis(root.clone(), &excluded).unwrap();
+        assert_eq!(report.files_checked, 0);
+        assert!(report.discovery.files_walked > 0);
+        assert_eq!(
+            report.discovery.excluded_by_glob,
+            report.discovery.files_walked
+        );
+        let error = excluded.check_analysed(&report).unwrap_err();
+        assert!(
+            error.contains(&format!(
+                "{} excluded by --exclude-files",
+                report.discovery.files_walked
+            )),
+            "{}",
+            error
+        );
+
+        let report = get_analysis(root, &config(&["--quiet"])).unwrap();
+        assert!(report.files_checked > 0);
+        assert_eq!(report.discovery.unreadable, 0);
+        assert!(config(&[]).check_analysed(&report).is_ok());
+        assert!(config(&["--min-files", "100"])
+            .check_analysed(&report)
+            .is_err());
+        assert!(config(&["--min-files", "100", "--allow-empty"])
+            .check_analysed(&report)
+            .is_ok());
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn sources_held_one_at_a_time() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let config = Config::from_iter_safe(&["doc_panic_checker", "--quiet"]).unwrap();
+        HeldSource::take_peak();
+        let report = get_analysis(root, &config).unwrap();
+        assert!(report.files_checked > 1);
+        assert_eq!(HeldSource::take_peak(), 1);
+    }
+
+    #[test]
+    fn panic_strategy_fixtures() {
+        let fixtures = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+        let config = Config::from_iter_safe(&["doc_panic_checker"]).unwrap();
+        let notes = |report: &Report| report.files[0].findings[0].notes.clone();
+
+        let report = get_analysis(fixtures.join("no_std"), &config).unwrap();
+        assert!(report.no_std);
+        assert!(!report.panic_abort);
+        assert_eq!(
+            notes(&report),
+            ["(panics halt or reset on this no_std target)"]
+        );
+
+        let report = get_analysis(fixtures.join("panic_abort"), &config).unwrap();
+        assert!(!report.no_std);
+        assert!(report.panic_abort);
+        assert_eq!(
+            notes(&report),
+            ["(panics abort the process under the release profile)"]
+        );
+        assert!(config.outcome(report.summary()).is_ok());
+
+        let embedded =
+            Config::from_iter_safe(&["doc_panic_checker", "--profile", "embedded"]).unwrap();
+        assert_eq!(
+            embedded.outcome(report.summary()),
+            Err("Failing because of the embedded profile, there are 1 findings".to_string())
+        );
+    }
+
+    #[test]
+    fn unchecked_items() {
+        let dir = std::env::temp_dir().join(format!(
+            "doc_panic_checker_unchecked-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(
+            dir.join("src/lib.rs"),
+            r#"
+pub fn ffi_open(x: Option<u8>) -> u8 {
+    x.unwrap()
+}
+
+#[deprecated]
+pub fn old(x: Option<u8>) -> u8 {
+    x.unwrap()
+}
+
+pub fn new(x: Option<u8>) -> u8 {
+    x.unwrap()
+}
+"#,
+        )
+        .unwrap();
+        fs::write(dir.join("src/broken.rs"), "pub fn broken( { x.unwrap() }").unwrap();
+        let config = Config::from_iter_safe(&[
+            "doc_panic_checker",
+            "--allow-regex",
+            "^ffi_",
+            "--exclude-attr",
+            "deprecated",
+        ])
+        .unwrap();
+        let report = get_analysis(dir.clone(), &config);
+        fs::remove_dir_all(&dir).unwrap();
+        let report = report.unwrap();
+        assert_eq!(
+            report.skipped.into_iter().collect::<Vec<_>>(),
+            [
+                (SkipReason::ParseError, 1),
+                (SkipReason::AllowRegex, 1),
+                (SkipReason::Attribute, 1),
+            ]
+        );
+    }
+
+    fn finding(ident: &str, kind: FindingKind, attributes: &[&str]) -> Finding {
+        Finding {
+            ident: ident.to_string(),
+            kind,
+            start_line: 1,
+            end_line: 3,
+            panic_line: 2,
+            attributes: attributes.iter().map(|x| x.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn attribute_filters() {
+        let finding =
+            |ident, attributes| finding(ident, FindingKind::UndocumentedPanic, attributes);
+        let report = Report::new(vec![FileReport {
+            path: PathBuf::from("src/lib.rs"),
+            symlink_target: None,
+            findings: vec![
+                finding("Config::new", &["must_use"]),
+                finding("Config::old", &["must_use", "deprecated"]),
+                finding("Config::len", &["inline"]),
+                finding("Config::set", &[]),
+            ],
+        }]);
+        let config = Config::from_iter_safe(&[
+            "doc_panic_checker",
+            "--filter-attr",
+            "must_use",
+            "inline",
+            "--exclude-attr",
+            "deprecated",
+        ])
+        .unwrap();
+        let shown = config.displayed(&report, OutputFormat::Json);
+        let idents = shown.files[0]
+            .findings
+            .iter()
+            .map(|x| x.ident.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(idents, ["Config::new", "Config::len"]);
+        assert_eq!(
+            shown.notes,
+            [
+                "Showing 2 of 4 findings, only items with `#[must_use]` or `#[inline]` and \
+              excluding items with `#[deprecated]`"
+            ]
+        );
+        assert_eq!(report.files[0].findings.len(), 4);
+
+        assert!(Config::from_iter_safe(&["doc_panic_checker", "--filter-attr", "derive"]).is_err());
+    }
+
+    #[test]
+    fn strict_and_quiet() {
+        let quiet = Config::from_iter_safe(&["doc_panic_checker", "--quiet"]).unwrap();
+        let strict = Config::from_iter_safe(&["doc_panic_checker", "--quiet", "--strict"]).unwrap();
+        let mut report = Report::new(vec![]);
+        report.files_checked = 12;
+        report.items_checked = 312;
+        let ok = |findings| {
+            Ok(format!(
+                "doc_panic_checker: OK ({} findings, 312 items checked)",
+                findings
+            ))
+        };
+        assert_eq!(quiet.outcome(report.summary()), ok(0));
+        assert_eq!(strict.outcome(report.summary()), ok(0));
+
+        // Problems running the analysis only fail the run in strict mode
+        report
+            .warnings
+            .push("Unable to parse src/broken.rs: expected `;`".to_string());
+        assert_eq!(quiet.outcome(report.summary()), ok(0));
+        assert!(strict.outcome(report.summary()).is_err());
+        report.warnings.clear();
+
+        // Notes don't fail the run but count as findings
+        report.files.push(FileReport {
+            path: PathBuf::from("src/main.rs"),
+            symlink_target: None,
+            findings: vec![finding("main", FindingKind::EntryPoint, &[])],
+        });
+        assert_eq!(strict.outcome(report.summary()), ok(1));
+
+        report.files[0]
+            .findings
+            .push(finding("run", FindingKind::UndocumentedPanic, &[]));
+        // The line follows the status file, saying there are findings without failing the run
+        assert_eq!(
+            quiet.outcome(report.summary()),
+            Ok("doc_panic_checker: FINDINGS (2 findings, 312 items checked)".to_string())
+        );
+        assert_eq!(
+            Status::finished(Some(&report), None).status,
+            Outcome::Findings
+        );
+        assert_eq!(
+            strict.outcome(report.summary()),
+            Err("Failing because of --strict, there are 1 findings and 0 warnings".to_string())
+        );
+
+        assert!(Config::from_iter_safe(&["doc_panic_checker", "--quiet", "--verbose"]).is_err());
+    }
+
+    #[test]
+    fn release_gate_profile() {
+        let source = r#"
+            /// Parses the config
+            ///
+            /// # Panics
+            ///
+            /// Not implemented yet
+            pub fn parse(s: &str) -> Config {
+                todo!()
+            }
+        "#;
+        let outcome = |args: &[&str]| {
+            let config = Config::from_iter_safe(args).unwrap();
+            let findings = doc_panic_checker::findings_for_source(
+                source,
+                Path::new("src/lib.rs"),
+                &config.options(),
+            );
+            let report = Report::new(vec![FileReport {
+                path: PathBuf::from("src/lib.rs"),
+                symlink_target: None,
+                findings,
+            }]);
+            config.outcome(report.summary())
+        };
+        assert!(outcome(&["doc_panic_checker"]).is_ok());
+        assert!(outcome(&["doc_panic_checker", "--strict"]).is_ok());
+        assert_eq!(
+            outcome(&["doc_panic_checker", "--profile", "release-gate"]),
+            Err(
+                "Failing because of the release-gate profile, 1 items have a `todo!` or \
+                 `unimplemented!`"
+                    .to_string()
+            )
+        );
+        assert!(Config::from_iter_safe(&["doc_panic_checker", "--profile", "nightly"]).is_err());
+    }
+
+    #[test]
+    fn dir_config_layering() {
+        let source = r#"
+            /// Parses the config
+            ///
+            /// # Panics
+            ///
+            /// Not implemented yet
+            pub fn parse(s: &str) -> Config {
+                todo!()
+            }
+
+            /// Logs the error
+            pub fn report(e: &str) {
+                fatal(e)
+            }
+        "#;
+        let dir =
+            std::env::temp_dir().join(format!("doc_panic_checker_layering-{}", std::process::id()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(
+            dir.join(".doc-panic.toml"),
+            "profile = \"release-gate\"\npanicking-fn = []\n",
+        )
+        .unwrap();
+        fs::write(dir.join("src/lib.rs"), source).unwrap();
+        let idents = |args: &[&str]| {
+            let config = Config::from_iter_safe(args).unwrap();
+            let mut progress = Progress::default();
+            let path = dir.join("src/lib.rs");
+            analyse_package(&path, &dir, &config, &config.options(), &mut progress)
+                .map(|x| x.findings.into_iter().map(|x| x.ident).collect::<Vec<_>>())
+                .unwrap_or_default()
+        };
+        // The config file's profile and settings are used where the command line doesn't say
+        let defaults = idents(&["doc_panic_checker"]);
+        // and the command line wins where it does
+        let flags = idents(&[
+            "doc_panic_checker",
+            "--profile",
+            "default",
+            "--panicking-fn",
+            "fatal",
+        ]);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(defaults, ["parse"]);
+        assert_eq!(flags, ["report"]);
+    }
+}
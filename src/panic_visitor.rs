@@ -0,0 +1,2680 @@
+//! Finds the expressions within a function body which can panic.
+use crate::imports::ImportMap;
+use proc_macro2::{Delimiter, LineColumn, Span, TokenStream, TokenTree};
+use quote::ToTokens;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::*;
+
+/// Tag for panics inside closures which are handed back to the caller to run later
+pub const DEFERRED_CLOSURE: &str = "deferred-closure";
+/// Tag for unwrapping a lock whose guard is still in scope at a later `.await`
+pub const HELD_ACROSS_AWAIT: &str = "held-across-await";
+/// Tag for panics in an `unsafe` block or in the statement after one, often checking what the
+/// unsafe code returned
+pub const NEAR_UNSAFE: &str = "near-unsafe";
+/// Tag for panics inside an `unsafe` block itself, a subset of `NEAR_UNSAFE`
+pub const IN_UNSAFE_BLOCK: &str = "in-unsafe-block";
+/// Tag for panics inside the closures of an iterator adapter chain which is returned, they only
+/// run while the caller iterates
+pub const DEFERRED_ITERATOR: &str = "deferred-iterator";
+/// Tag for unwrapping the result of locking a `Mutex` or `RwLock`, which only fails if another
+/// thread panicked while holding the lock
+pub const MUTEX_POISON: &str = "MUTEX POISON";
+/// Tag for unwrapping inside the `map` closure of an iterator chain collected into a plain
+/// collection, which panics on the first error where collecting into a `Result` would return it
+pub const COLLECT_PANIC: &str = "COLLECT PANIC";
+/// Tag for panics inside the closure given to `sort_by`, `sort_by_key` or their unstable
+/// versions, which runs for every comparison while sorting
+pub const SORT_CLOSURE_PANIC: &str = "SORT CLOSURE PANIC";
+/// Tag for unwrapping the result of `write!` or `writeln!`, which only fails if the writer does
+pub const WRITE_UNWRAP: &str = "WRITE UNWRAP";
+/// Tag for unwrapping the result of sending on a channel, which fails once the receiver is dropped
+pub const CHANNEL_SEND: &str = "CHANNEL SEND";
+/// Tag for unwrapping the result of reading an environment variable, which fails when it isn't set
+pub const ENV_VAR: &str = "ENV VAR";
+/// Tag for unwrapping the result of opening or reading a file, which fails when it's missing or
+/// can't be read
+pub const FILE_IO: &str = "FILE IO";
+/// Tag for unwrapping the result of decoding bytes as text with `String::from_utf8` or
+/// `str::from_utf8`, which fails when they aren't valid UTF-8
+pub const UTF8_DECODE: &str = "UTF8 DECODE";
+/// Tag for passing a function rather than a closure as the fallback of `unwrap_or_else` or
+/// similar, whose body isn't checked
+pub const OPAQUE_FALLBACK: &str = "opaque-fallback";
+/// Tag for a `_ => panic!()` arm matching on a variable or field, usually an enum which could be
+/// matched exhaustively instead
+pub const WILDCARD_MATCH_ARM: &str = "wildcard-match-arm";
+/// Tag for a wildcard arm panic where the enum looks like it's from another crate, so the arm is
+/// probably forced by `#[non_exhaustive]` and fires when the crate adds a variant
+pub const FOREIGN_NON_EXHAUSTIVE: &str = "foreign-non-exhaustive";
+/// Tag for unwrapping the `Option` from a lookup like `get` or `first`, which has a non-panicking
+/// alternative
+pub const OPTION_LOOKUP: &str = "OPTION LOOKUP";
+/// Tag for calling a method on a trait object whose trait has a default method which can panic,
+/// whether it does depends on the implementor
+pub const DYNAMIC_DISPATCH: &str = "DYNAMIC DISPATCH";
+/// Tag for calling a std method which panics unless its arguments meet a precondition, like
+/// `split_at` with an index past the end
+pub const STD_PRECONDITION: &str = "STD PRECONDITION";
+
+/// The names of the lookups `Lookup` can be parsed from
+pub const LOOKUPS: &[&str] = &["get", "first", "last", "next", "nth"];
+
+/// Slice methods which sort with a closure comparing elements or extracting their keys
+const SORT_METHODS: &[&str] = &[
+    "sort_by",
+    "sort_by_key",
+    "sort_unstable_by",
+    "sort_unstable_by_key",
+];
+
+/// Iterator adapters which don't run anything until the iterator is consumed
+const LAZY_ADAPTERS: &[&str] = &[
+    "map",
+    "filter",
+    "filter_map",
+    "flat_map",
+    "flatten",
+    "inspect",
+    "map_while",
+    "scan",
+    "skip_while",
+    "take_while",
+    "step_by",
+    "skip",
+    "take",
+    "chain",
+    "zip",
+    "rev",
+    "enumerate",
+    "peekable",
+    "cloned",
+    "copied",
+    "fuse",
+];
+
+/// Methods which create an iterator, a chain of lazy adapters has to start with one of these or a
+/// range to be an iterator rather than say an `Option`
+const ITERATOR_SOURCES: &[&str] = &[
+    "iter",
+    "iter_mut",
+    "into_iter",
+    "chars",
+    "char_indices",
+    "bytes",
+    "lines",
+    "split",
+    "split_whitespace",
+    "keys",
+    "values",
+    "values_mut",
+    "drain",
+    "windows",
+    "chunks",
+];
+
+/// Methods taking a fallback which is only called on the error path, a panic in it is as good as
+/// an `unwrap`
+const FALLBACKS: &[&str] = &["unwrap_or_else", "ok_or_else", "or_else", "map_or_else"];
+
+/// The names of the entries in `PRECONDITIONS`
+pub const STD_PANICS: &[&str] = &[
+    "split_at",
+    "split_at_mut",
+    "copy_from_slice",
+    "clone_from_slice",
+    "chunks",
+    "chunks_exact",
+    "windows",
+    "swap",
+    "split_off",
+    "step_by",
+    "NonZero::new",
+];
+
+/// A std method which panics when its arguments don't meet a precondition. They're matched by
+/// name and number of arguments as the receiver's type isn't known, so each entry says which
+/// receivers it's meant for and a method of the same name on another type can be told apart.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Precondition {
+    /// The method's name, `NonZero::new` is unwrapping the result of a `NonZero*::new` call
+    pub name: &'static str,
+    args: usize,
+    /// When it panics
+    pub condition: &'static str,
+    /// The types it panics on
+    pub receivers: &'static str,
+}
+
+/// The std methods with preconditions which panic, in the same order as `STD_PANICS`
+pub const PRECONDITIONS: &[Precondition] = &[
+    Precondition {
+        name: "split_at",
+        args: 1,
+        condition: "panics if mid > len",
+        receivers: "slices, `Vec`, `str` and `String`",
+    },
+    Precondition {
+        name: "split_at_mut",
+        args: 1,
+        condition: "panics if mid > len",
+        receivers: "slices, `Vec`, `str` and `String`",
+    },
+    Precondition {
+        name: "copy_from_slice",
+        args: 1,
+        condition: "panics if the two slices have different lengths",
+        receivers: "slices and `Vec`",
+    },
+    Precondition {
+        name: "clone_from_slice",
+        args: 1,
+        condition: "panics if the two slices have different lengths",
+        receivers: "slices and `Vec`",
+    },
+    Precondition {
+        name: "chunks",
+        args: 1,
+        condition: "panics if the chunk size is 0",
+        receivers: "slices and `Vec`",
+    },
+    Precondition {
+        name: "chunks_exact",
+        args: 1,
+        condition: "panics if the chunk size is 0",
+        receivers: "slices and `Vec`",
+    },
+    Precondition {
+        name: "windows",
+        args: 1,
+        condition: "panics if the window size is 0",
+        receivers: "slices and `Vec`",
+    },
+    Precondition {
+        name: "swap",
+        args: 2,
+        condition: "panics if either index >= len",
+        receivers: "slices, `Vec` and `VecDeque`",
+    },
+    Precondition {
+        name: "split_off",
+        args: 1,
+        condition: "panics if at > len",
+        receivers: "`Vec`, `VecDeque` and `String`",
+    },
+    Precondition {
+        name: "step_by",
+        args: 1,
+        condition: "panics if the step is 0",
+        receivers: "iterators and ranges",
+    },
+    Precondition {
+        name: "NonZero::new",
+        args: 1,
+        condition: "panics if the value is 0",
+        receivers: "the `NonZero` integer types",
+    },
+];
+
+impl Precondition {
+    /// The entry with this name in `PRECONDITIONS`
+    pub fn named(name: &str) -> Option<&'static Self> {
+        PRECONDITIONS.iter().find(|x| x.name == name)
+    }
+
+    /// The note for a finding calling the method
+    pub fn note(&self) -> String {
+        if self.name == "NonZero::new" {
+            format!(
+                "(unwrapping `NonZero*::new` {}, the precondition applies to {})",
+                self.condition, self.receivers
+            )
+        } else {
+            format!(
+                "(`{}` {}, the precondition applies to {} and a method of the same name on \
+                 another type may not panic)",
+                self.name, self.condition, self.receivers
+            )
+        }
+    }
+}
+
+/// A method returning an `Option` which is commonly unwrapped straight away
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, Ord, PartialOrd)]
+pub enum Lookup {
+    /// `get(key)` on a map or slice
+    Get,
+    First,
+    Last,
+    /// `next()` on an iterator
+    Next,
+    /// `nth(n)` on an iterator
+    Nth,
+}
+
+impl Lookup {
+    /// The lookup a method call is, checking the number of arguments so `first()` matches but a
+    /// `get()` without a key doesn't
+    fn from_call(call: &ExprMethodCall) -> Option<Self> {
+        match (call.method.to_string().as_str(), call.args.len()) {
+            ("get", 1) => Some(Lookup::Get),
+            ("first", 0) => Some(Lookup::First),
+            ("last", 0) => Some(Lookup::Last),
+            ("next", 0) => Some(Lookup::Next),
+            ("nth", 1) => Some(Lookup::Nth),
+            _ => None,
+        }
+    }
+
+    /// What to do instead of unwrapping the lookup
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Lookup::Get => {
+                "(`get(..)` is unwrapped, document when the key is present or handle a missing \
+                 key with `entry`, `if let` or `?`)"
+            }
+            Lookup::First | Lookup::Last => {
+                "(`first()` or `last()` is unwrapped, document that the collection can't be \
+                 empty or handle it with `if let` or `?`)"
+            }
+            Lookup::Next => {
+                "(`next()` is unwrapped, document why the iterator has another item or handle \
+                 the end with `if let` or `?`)"
+            }
+            Lookup::Nth => {
+                "(`nth(..)` is unwrapped, document how many items there are or handle too few \
+                 with `if let` or `?`)"
+            }
+        }
+    }
+}
+
+impl FromStr for Lookup {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "get" => Ok(Lookup::Get),
+            "first" => Ok(Lookup::First),
+            "last" => Ok(Lookup::Last),
+            "next" => Ok(Lookup::Next),
+            "nth" => Ok(Lookup::Nth),
+            _ => Err(format!(
+                "expected one of {}, got `{}`",
+                LOOKUPS.join(", "),
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Lookup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Lookup::Get => "get",
+            Lookup::First => "first",
+            Lookup::Last => "last",
+            Lookup::Next => "next",
+            Lookup::Nth => "nth",
+        };
+        f.write_str(name)
+    }
+}
+
+/// What sort of expression can panic
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PanicKind {
+    Panic,
+    Unwrap,
+    Expect,
+    Todo,
+    Unimplemented,
+    /// `process::exit` or `process::abort`, which end the program without unwinding
+    ProcessExit,
+    /// `unreachable!`, only counted where even a broken invariant shouldn't panic
+    Unreachable,
+    /// A call to a private function in the same file which can panic
+    HelperCall,
+    /// A call to a function the user declared as panicking, like a crate's own fatal error
+    /// helper
+    ConfiguredFn,
+    /// A call to a std method which panics if its arguments don't meet a precondition, like
+    /// `split_at` past the end
+    PreconditionPanic,
+    /// A function passed as the fallback of `unwrap_or_else` or similar, which isn't looked into
+    /// so may panic
+    OpaqueFallback,
+}
+
+impl fmt::Display for PanicKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PanicKind::Panic => "panic",
+            PanicKind::Unwrap => "unwrap",
+            PanicKind::Expect => "expect",
+            PanicKind::Todo => "todo",
+            PanicKind::Unimplemented => "unimplemented",
+            PanicKind::ProcessExit => "process exit",
+            PanicKind::Unreachable => "unreachable",
+            PanicKind::HelperCall => "helper call",
+            PanicKind::ConfiguredFn => "configured panic fn",
+            PanicKind::PreconditionPanic => "precondition panic",
+            PanicKind::OpaqueFallback => "opaque fallback",
+        };
+        f.write_str(name)
+    }
+}
+
+impl PanicKind {
+    fn from_macro(name: &str) -> Option<Self> {
+        match name {
+            "panic" => Some(PanicKind::Panic),
+            "todo" => Some(PanicKind::Todo),
+            "unimplemented" => Some(PanicKind::Unimplemented),
+            "unreachable" => Some(PanicKind::Unreachable),
+            _ => None,
+        }
+    }
+
+    /// The kind of a call to a free function given its full path
+    fn from_function(path: &[String]) -> Option<Self> {
+        match path {
+            [.., name] if name == "panic_any" => Some(PanicKind::Panic),
+            // What the panic macros expand to, as seen in `cargo expand` output
+            [krate, module, name]
+                if (krate == "core" || krate == "std")
+                    && (module == "panicking" || (module == "rt" && name == "begin_panic")) =>
+            {
+                Some(PanicKind::Panic)
+            }
+            [.., module, name] if module == "process" && (name == "exit" || name == "abort") => {
+                Some(PanicKind::ProcessExit)
+            }
+            _ => None,
+        }
+    }
+
+    /// The macro an expanded panic came from, `todo!()` expands to a panic with the message
+    /// "not yet implemented" and so on
+    fn from_expanded_message(message: &str) -> Option<Self> {
+        match message {
+            "not yet implemented" => Some(PanicKind::Todo),
+            "not implemented" => Some(PanicKind::Unimplemented),
+            _ if message.starts_with("internal error: entered unreachable code") => {
+                Some(PanicKind::Unreachable)
+            }
+            _ => None,
+        }
+    }
+
+    /// What to do instead of panicking this way, for kinds with a usual fix
+    pub fn fix_hint(self) -> Option<&'static str> {
+        match self {
+            PanicKind::Unwrap | PanicKind::Expect => Some("consider using ? or match"),
+            PanicKind::Panic => {
+                Some("add '# Panics\n\nPanics when <condition>.' to the doc comment")
+            }
+            PanicKind::Todo | PanicKind::Unimplemented => {
+                Some("implement the function or replace with Result::Err")
+            }
+            PanicKind::PreconditionPanic => {
+                Some("check the precondition before the call or document it")
+            }
+            _ => None,
+        }
+    }
+
+    fn from_method(name: &str) -> Option<Self> {
+        match name {
+            "unwrap" => Some(PanicKind::Unwrap),
+            "expect" => Some(PanicKind::Expect),
+            _ => None,
+        }
+    }
+}
+
+/// A single expression which can panic
+#[derive(Clone, Debug)]
+pub struct PanicSite {
+    pub span: Span,
+    pub kind: PanicKind,
+    /// The message given to `panic!` or `expect` if it's a string literal
+    pub message: Option<String>,
+    pub tags: Vec<&'static str>,
+    /// For a panic in a wildcard match arm, the path of the enum being matched if the other arms
+    /// name its variants
+    pub matched_enum: Option<Vec<String>>,
+    /// For an `unwrap` or `expect`, the lookup which returned the `Option` being unwrapped
+    pub lookup: Option<Lookup>,
+    /// For a call to a function declared as panicking, the path it was declared with
+    pub configured_fn: Option<String>,
+    /// For a call to a std method with a precondition, or unwrapping a `NonZero*::new`, the
+    /// precondition
+    pub precondition: Option<&'static Precondition>,
+    /// The predicates of the `#[cfg]`s on the statements around it, outermost first, like
+    /// `not(feature = "safe-mode")`
+    pub cfg: Vec<String>,
+}
+
+/// A `From` or `TryFrom` impl in the same file, which `T::from(x)` and `.into()` calls are
+/// resolved to when the types involved can be worked out
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Conversion {
+    /// The trait's type argument
+    pub source: String,
+    pub target: String,
+    /// `TryFrom` rather than `From`
+    pub fallible: bool,
+}
+
+/// Private functions in the same file which can panic, calls to them count as panics
+#[derive(Clone, Copy, Default)]
+pub struct Helpers<'a> {
+    /// `name` for free functions and `Type::name` for methods and associated functions, after
+    /// the path of the inline module they're in like `config::load`
+    pub panicking: Option<&'a HashSet<String>>,
+    /// The conversions in the file and whether each can panic
+    pub conversions: Option<&'a HashMap<Conversion, bool>>,
+    /// The type whose impl the function body is in, which `Self::` and `self.` calls refer to
+    pub self_type: Option<&'a str>,
+    /// The inline module the function body is in, like `config::env`, which calls to private
+    /// functions are resolved from
+    pub module: Option<&'a str>,
+    /// Paths of functions outside the file which count as panics, like `our_telemetry::fatal`
+    pub panicking_fns: &'a [String],
+    /// Paths of functions which never count as panics, even if they're in `panicking_fns` or
+    /// are built in like `std::process::exit`
+    pub allowed_fns: &'a [String],
+    /// The std methods with preconditions which count as panics
+    pub preconditions: &'a [&'static Precondition],
+    /// Count passing a function as the fallback of `unwrap_or_else` or similar as a panic, as
+    /// unlike a closure its body isn't checked
+    pub opaque_fallbacks: bool,
+}
+
+impl Helpers<'_> {
+    /// The declared path a call to `path` matches, if any. Either path can be the longer one so
+    /// `fatal` declared as `our_telemetry::fatal` matches after `use our_telemetry::fatal` and
+    /// `telemetry::fatal` matches it as written, but a single segment only matches itself.
+    fn declared<'b>(declared: &'b [String], path: &[String]) -> Option<&'b String> {
+        declared.iter().find(|x| {
+            let declared = x.split("::").collect::<Vec<_>>();
+            let shorter = declared.len().min(path.len());
+            (shorter > 1 || declared.len() == path.len())
+                && declared[declared.len() - shorter..]
+                    .iter()
+                    .zip(&path[path.len() - shorter..])
+                    .all(|(a, b)| a == b)
+        })
+    }
+
+    /// Returns true if calling the function at `path` can panic
+    fn path_panics(&self, path: &[String]) -> bool {
+        match self.helper_key(path) {
+            Some(key) => self.panicking.is_some_and(|x| x.contains(&key)),
+            None => false,
+        }
+    }
+
+    /// The key in `panicking` of the function a call to `path` refers to, with the path resolved
+    /// relative to `module` the way rustc does so `load()` in `config` is `config::load`
+    fn helper_key(&self, path: &[String]) -> Option<String> {
+        let mut key = self
+            .module
+            .map_or(vec![], |x| x.split("::").collect::<Vec<_>>());
+        match path {
+            [] => return None,
+            [.., ty, name] if ty == "Self" => {
+                key.push(self.self_type?);
+                key.push(name);
+            }
+            _ => {
+                for (i, segment) in path.iter().enumerate() {
+                    match segment.as_str() {
+                        "crate" if i == 0 => key.clear(),
+                        "self" if i == 0 => {}
+                        "super" => {
+                            key.pop()?;
+                        }
+                        _ => key.push(segment),
+                    }
+                }
+            }
+        }
+        Some(key.join("::"))
+    }
+
+    /// Returns true if converting into `target` can panic. Without a `source` the conversion is
+    /// only resolved if it's the file's only one into `target`.
+    fn conversion_panics(&self, source: Option<&str>, target: &str, fallible: bool) -> bool {
+        let conversions = match self.conversions {
+            Some(conversions) => conversions,
+            None => return false,
+        };
+        let mut candidates = conversions.iter().filter(|(x, _)| {
+            x.target == target && x.fallible == fallible && source.is_none_or(|s| x.source == s)
+        });
+        match (candidates.next(), candidates.next()) {
+            (Some((_, panics)), None) => *panics,
+            _ => false,
+        }
+    }
+
+    /// The precondition of a method call if it's one of `preconditions`
+    fn precondition(&self, call: &ExprMethodCall) -> Option<&'static Precondition> {
+        let name = call.method.to_string();
+        self.preconditions
+            .iter()
+            .find(|x| x.name == name && x.args == call.args.len())
+            .copied()
+    }
+
+    /// Returns true if calling the method on `self` can panic
+    fn method_panics(&self, receiver: &Expr, method: &Ident) -> bool {
+        let is_self = matches!(receiver, Expr::Path(p) if p.path.is_ident("self"));
+        is_self && self.path_panics(&["Self".to_string(), method.to_string()])
+    }
+}
+
+struct PanicVisitor<'a> {
+    sites: Vec<PanicSite>,
+    /// How many closures we're inside which will be run after the function returns
+    deferred_depth: usize,
+    /// How many closures of a returned iterator chain we're inside
+    iterator_depth: usize,
+    /// The start and end of the closures in a returned iterator chain
+    lazy_closures: HashSet<(LineColumn, LineColumn)>,
+    /// How many `map` closures of a collected iterator chain we're inside
+    collect_depth: usize,
+    /// The start and end of the `map` closures in a collected iterator chain
+    collected_closures: HashSet<(LineColumn, LineColumn)>,
+    /// How many closures given to a sort method we're inside
+    sort_depth: usize,
+    /// How many `unsafe` blocks we're inside
+    unsafe_depth: usize,
+    /// How many `unsafe` blocks have been visited, to tell if a statement contained one
+    unsafe_blocks: usize,
+    /// Used to see through renamed imports, so `die!` after `use std::panic as die` is a panic
+    imports: &'a ImportMap,
+    helpers: Helpers<'a>,
+    /// Local variables bound to the result of a lookup, like `let first = items.first();`
+    lookups: HashMap<String, Lookup>,
+    /// The types of local variables whose `let` gives one, like `let raw: RawConfig = ..;`
+    bindings: HashMap<String, String>,
+}
+
+/// Finds everything in a function body which can panic, including calls to the panicking
+/// `helpers`
+pub fn find_panics<'a>(
+    block: &Block,
+    imports: &'a ImportMap,
+    helpers: Helpers<'a>,
+) -> Vec<PanicSite> {
+    let mut visitor = PanicVisitor {
+        sites: vec![],
+        deferred_depth: 0,
+        iterator_depth: 0,
+        lazy_closures: HashSet::new(),
+        collect_depth: 0,
+        collected_closures: HashSet::new(),
+        sort_depth: 0,
+        unsafe_depth: 0,
+        unsafe_blocks: 0,
+        imports,
+        helpers,
+        lookups: HashMap::new(),
+        bindings: HashMap::new(),
+    };
+    visitor.visit_stmts(&block.stmts, true);
+    visitor.sites
+}
+
+/// Returns true if the function body is a single call forwarding every parameter, in any order,
+/// like `self.inner.len()` or `Self::with_capacity(capacity)`. Calling something which panics
+/// itself, like `self.inner.expect(msg)`, isn't a delegation.
+pub fn is_delegation(sig: &Signature, block: &Block) -> bool {
+    let expr = match block.stmts.as_slice() {
+        [Stmt::Expr(expr)] | [Stmt::Semi(expr, _)] => expr,
+        _ => return false,
+    };
+    let mut forwarded = vec![];
+    let args = match expr {
+        Expr::MethodCall(call) if PanicKind::from_method(&call.method.to_string()).is_none() => {
+            forwarded.push(forwarded_param(&call.receiver));
+            &call.args
+        }
+        Expr::Call(call) => match &*call.func {
+            Expr::Path(p) => {
+                let path = p
+                    .path
+                    .segments
+                    .iter()
+                    .map(|x| x.ident.to_string())
+                    .collect::<Vec<_>>();
+                if PanicKind::from_function(&path).is_some() {
+                    return false;
+                }
+                &call.args
+            }
+            _ => return false,
+        },
+        _ => return false,
+    };
+    forwarded.extend(args.iter().map(forwarded_param));
+    let params = sig
+        .inputs
+        .iter()
+        .map(|arg| match arg {
+            FnArg::Receiver(_) => Some("self".to_string()),
+            FnArg::Typed(t) => match &*t.pat {
+                Pat::Ident(p) => Some(p.ident.to_string()),
+                _ => None,
+            },
+        })
+        .collect::<Option<Vec<_>>>();
+    match (forwarded.into_iter().collect::<Option<Vec<_>>>(), params) {
+        (Some(mut forwarded), Some(mut params)) => {
+            forwarded.sort();
+            forwarded.dedup();
+            params.sort();
+            params.dedup();
+            forwarded == params
+        }
+        _ => false,
+    }
+}
+
+/// The parameter an argument passes on, seeing through references and field accesses so
+/// `&mut self.inner` forwards `self`
+fn forwarded_param(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Path(p) => p.path.get_ident().map(|x| x.to_string()),
+        Expr::Reference(r) => forwarded_param(&r.expr),
+        Expr::Field(f) => forwarded_param(&f.base),
+        Expr::Paren(p) => forwarded_param(&p.expr),
+        _ => None,
+    }
+}
+
+/// The predicates of the `#[cfg(..)]` attributes, formatted like they're written so
+/// `#[cfg(not(feature = "safe-mode"))]` gives `not(feature = "safe-mode")`
+pub fn cfg_predicates(attrs: &[Attribute]) -> Vec<String> {
+    fn format(meta: &NestedMeta) -> String {
+        match meta {
+            NestedMeta::Lit(lit) => lit.to_token_stream().to_string(),
+            NestedMeta::Meta(Meta::Path(path)) => path.to_token_stream().to_string(),
+            NestedMeta::Meta(Meta::NameValue(x)) => {
+                format!("{} = {}", x.path.to_token_stream(), x.lit.to_token_stream())
+            }
+            NestedMeta::Meta(Meta::List(x)) => format!(
+                "{}({})",
+                x.path.to_token_stream(),
+                x.nested.iter().map(format).collect::<Vec<_>>().join(", ")
+            ),
+        }
+    }
+    attrs
+        .iter()
+        .filter(|x| x.path.is_ident("cfg"))
+        .filter_map(|x| match x.parse_meta().ok()? {
+            Meta::List(list) if list.nested.len() == 1 => list.nested.first().map(format),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The attributes on a statement, for the expressions which usually start one
+fn stmt_attrs(stmt: &Stmt) -> &[Attribute] {
+    let expr = match stmt {
+        Stmt::Local(local) => return &local.attrs,
+        Stmt::Item(Item::Macro(mac)) => return &mac.attrs,
+        Stmt::Item(_) => return &[],
+        Stmt::Expr(e) | Stmt::Semi(e, _) => e,
+    };
+    match expr {
+        Expr::Assign(x) => &x.attrs,
+        Expr::AssignOp(x) => &x.attrs,
+        Expr::Block(x) => &x.attrs,
+        Expr::Call(x) => &x.attrs,
+        Expr::ForLoop(x) => &x.attrs,
+        Expr::If(x) => &x.attrs,
+        Expr::Loop(x) => &x.attrs,
+        Expr::Macro(x) => &x.attrs,
+        Expr::Match(x) => &x.attrs,
+        Expr::MethodCall(x) => &x.attrs,
+        Expr::Return(x) => &x.attrs,
+        Expr::Unsafe(x) => &x.attrs,
+        Expr::While(x) => &x.attrs,
+        _ => &[],
+    }
+}
+
+/// If the statement binds a lock guard from unwrapping `lock`, `read` or `write`, returns the
+/// name it's bound to
+fn guard_binding(stmt: &Stmt) -> Option<&Ident> {
+    let local = match stmt {
+        Stmt::Local(local) => local,
+        _ => return None,
+    };
+    let ident = match &local.pat {
+        Pat::Ident(p) => &p.ident,
+        _ => return None,
+    };
+    match local.init.as_ref().map(|(_, e)| &**e) {
+        Some(Expr::MethodCall(unwrap))
+            if PanicKind::from_method(&unwrap.method.to_string()).is_some() =>
+        {
+            if is_lock(&unwrap.receiver) {
+                Some(ident)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// If the statement is a `let` binding a single name, returns it and the lookup it's bound to if
+/// it's the result of one
+fn lookup_binding(stmt: &Stmt) -> Option<(String, Option<Lookup>)> {
+    let local = match stmt {
+        Stmt::Local(local) => local,
+        _ => return None,
+    };
+    let ident = match &local.pat {
+        Pat::Ident(p) => p.ident.to_string(),
+        _ => return None,
+    };
+    let lookup = match local.init.as_ref().map(|(_, e)| &**e) {
+        Some(Expr::MethodCall(call)) => Lookup::from_call(call),
+        _ => None,
+    };
+    Some((ident, lookup))
+}
+
+/// If the statement is a `let` binding a single name, returns it and its type if it's given
+fn typed_binding(stmt: &Stmt) -> Option<(String, Option<String>)> {
+    let pat = match stmt {
+        Stmt::Local(local) => &local.pat,
+        _ => return None,
+    };
+    match pat {
+        Pat::Ident(p) => Some((p.ident.to_string(), None)),
+        Pat::Type(p) => match &*p.pat {
+            Pat::Ident(ident) => Some((ident.ident.to_string(), type_name(&p.ty))),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// For a `let` with a type converting a value with `into()` or `try_into()?`, returns the
+/// conversion's span, the value, the type converted to and whether it's a `try_into`
+fn converting_let(stmt: &Stmt) -> Option<(Span, &Expr, String, bool)> {
+    let (ty, init) = match stmt {
+        Stmt::Local(Local {
+            pat: Pat::Type(p),
+            init: Some((_, init)),
+            ..
+        }) => (&p.ty, &**init),
+        _ => return None,
+    };
+    let init = match init {
+        Expr::Try(t) => &*t.expr,
+        init => init,
+    };
+    match init {
+        Expr::MethodCall(call)
+            if call.args.is_empty() && (call.method == "into" || call.method == "try_into") =>
+        {
+            Some((
+                call.method.span(),
+                &call.receiver,
+                type_name(ty)?,
+                call.method == "try_into",
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// The name of a type without any path or generics, e.g. `Foo` for `crate::Foo<T>`
+pub(crate) fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(p) => p.path.segments.last().map(|x| x.ident.to_string()),
+        _ => None,
+    }
+}
+
+/// The lookup whose result an `unwrap` or `expect` is called on, either directly like
+/// `map.get(&k).unwrap()` or through a variable bound to it
+fn unwrapped_lookup(receiver: &Expr, lookups: &HashMap<String, Lookup>) -> Option<Lookup> {
+    match receiver {
+        Expr::MethodCall(call) => Lookup::from_call(call),
+        Expr::Paren(p) => unwrapped_lookup(&p.expr, lookups),
+        Expr::Path(p) => p
+            .path
+            .get_ident()
+            .and_then(|x| lookups.get(&x.to_string()).copied()),
+        _ => None,
+    }
+}
+
+/// Returns true for `lock()`, `read()` or `write()` without arguments, which is locking a `Mutex`
+/// or `RwLock` rather than IO
+fn is_lock(expr: &Expr) -> bool {
+    match expr {
+        Expr::MethodCall(call) => {
+            call.args.is_empty()
+                && (call.method == "lock" || call.method == "read" || call.method == "write")
+        }
+        _ => false,
+    }
+}
+
+/// Returns true for `send(value)` on something other than a literal, which is sending on a channel
+fn is_send(expr: &Expr) -> bool {
+    match expr {
+        Expr::MethodCall(call) => {
+            call.method == "send" && call.args.len() == 1 && !matches!(*call.receiver, Expr::Lit(_))
+        }
+        _ => false,
+    }
+}
+
+/// Returns true for a call to a function whose path ends with one of `paths`, like `env::var` for
+/// `std::env::var` or for `var` after importing it
+fn is_call_to(expr: &Expr, imports: &ImportMap, paths: &[[&str; 2]]) -> bool {
+    match expr {
+        Expr::Call(call) => match &*call.func {
+            Expr::Path(p) => {
+                let path = imports.resolve(&p.path);
+                paths
+                    .iter()
+                    .any(|x| path.ends_with(&[x[0].into(), x[1].into()]))
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Returns true for a call like `NonZeroUsize::new(x)` or `NonZero::new(x)`
+fn is_non_zero_new(expr: &Expr, imports: &ImportMap) -> bool {
+    match expr {
+        Expr::Call(call) => match &*call.func {
+            Expr::Path(p) => match imports.resolve(&p.path).as_slice() {
+                [.., ty, name] => ty.starts_with("NonZero") && name == "new",
+                _ => false,
+            },
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Returns true for a `write!` or `writeln!` call
+fn is_write(expr: &Expr, imports: &ImportMap) -> bool {
+    match expr {
+        Expr::Macro(m) => {
+            let name = imports.resolve_name(&m.mac.path);
+            name == "write" || name == "writeln"
+        }
+        _ => false,
+    }
+}
+
+/// Returns true if any of the statements awaits before `guard` is passed to `drop`. Shadowing
+/// doesn't drop the guard so it's still held, moving it elsewhere isn't tracked.
+fn held_across_await(guard: &Ident, stmts: &[Stmt]) -> bool {
+    for stmt in stmts {
+        if is_drop_of(stmt, guard) {
+            return false;
+        }
+        let mut finder = AwaitFinder::default();
+        finder.visit_stmt(stmt);
+        if finder.found {
+            return true;
+        }
+    }
+    false
+}
+
+fn is_drop_of(stmt: &Stmt, guard: &Ident) -> bool {
+    let expr = match stmt {
+        Stmt::Semi(e, _) | Stmt::Expr(e) => e,
+        _ => return false,
+    };
+    match expr {
+        Expr::Call(call) if call.args.len() == 1 => {
+            let is_drop = matches!(&*call.func, Expr::Path(p) if p.path.is_ident("drop"));
+            let is_guard = matches!(&call.args[0], Expr::Path(p) if p.path.is_ident(guard));
+            is_drop && is_guard
+        }
+        _ => false,
+    }
+}
+
+/// Looks for an `.await` which runs as part of the current async body
+#[derive(Default)]
+struct AwaitFinder {
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for AwaitFinder {
+    fn visit_expr_await(&mut self, _: &'ast ExprAwait) {
+        self.found = true;
+    }
+
+    fn visit_expr_async(&mut self, _: &'ast ExprAsync) {}
+
+    fn visit_expr_closure(&mut self, _: &'ast ExprClosure) {}
+}
+
+/// Looks for an `unwrap` or `expect` call
+#[derive(Default)]
+struct UnwrapFinder {
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for UnwrapFinder {
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        self.found |= PanicKind::from_method(&call.method.to_string()).is_some();
+        visit::visit_expr_method_call(self, call);
+    }
+}
+
+/// The closures given to the lazy adapters of an iterator chain, like the `map` in
+/// `items.iter().map(|x| ..)`, which only run once the iterator is consumed. Empty if the chain
+/// is consumed, like with `collect()`, or isn't an iterator.
+fn lazy_closures(mut expr: &Expr) -> Vec<&ExprClosure> {
+    let mut closures = vec![];
+    while let Expr::MethodCall(call) = expr {
+        let method = call.method.to_string();
+        if ITERATOR_SOURCES.contains(&&*method) {
+            return closures;
+        }
+        if !LAZY_ADAPTERS.contains(&&*method) {
+            return vec![];
+        }
+        closures.extend(call.args.iter().filter_map(|x| match x {
+            Expr::Closure(closure) => Some(closure),
+            _ => None,
+        }));
+        expr = &call.receiver;
+    }
+    match expr {
+        Expr::Range(_) => closures,
+        Expr::Paren(p) if matches!(&*p.expr, Expr::Range(_)) => closures,
+        _ => vec![],
+    }
+}
+
+fn span_key(span: Span) -> (LineColumn, LineColumn) {
+    (span.start(), span.end())
+}
+
+/// The closures given to `map` in the chain an iterator is collected from, like the one in
+/// `items.iter().map(|x| x.parse().unwrap()).collect()`. A chain collected into a `Result` or an
+/// `Option` stops at the first error rather than panicking, so only its closures which unwrap
+/// anyway are included.
+fn collected_closures(call: &ExprMethodCall) -> Vec<&ExprClosure> {
+    if call.method != "collect" {
+        return vec![];
+    }
+    let mut closures = vec![];
+    let mut expr = &*call.receiver;
+    while let Expr::MethodCall(call) = expr {
+        if call.method == "map" {
+            closures.extend(call.args.iter().filter_map(|x| match x {
+                Expr::Closure(closure) => Some(closure),
+                _ => None,
+            }));
+        }
+        expr = &call.receiver;
+    }
+    if collects_fallibly(call) {
+        closures.retain(|x| {
+            let mut finder = UnwrapFinder::default();
+            finder.visit_expr_closure(x);
+            finder.found
+        });
+    }
+    closures
+}
+
+/// Returns true for `collect::<Result<..>>()` or `collect::<Option<..>>()`
+fn collects_fallibly(call: &ExprMethodCall) -> bool {
+    let args = call.turbofish.iter().flat_map(|x| x.args.iter());
+    args.filter_map(|x| match x {
+        GenericMethodArgument::Type(Type::Path(p)) => p.path.segments.last(),
+        _ => None,
+    })
+    .any(|x| x.ident == "Result" || x.ident == "Option")
+}
+
+/// Returns true if the expression is a closure literal
+fn is_closure(expr: &Expr) -> bool {
+    match expr {
+        Expr::Closure(_) => true,
+        Expr::Paren(p) => is_closure(&p.expr),
+        Expr::Group(g) => is_closure(&g.expr),
+        _ => false,
+    }
+}
+
+/// The value of the last `let` binding the returned variable, for a body ending like
+/// `let ids = ..; ids`
+fn returned_binding<'a>(returned: &Expr, stmts: &'a [Stmt]) -> Option<&'a Expr> {
+    let name = match returned {
+        Expr::Path(p) => p.path.get_ident()?,
+        _ => return None,
+    };
+    stmts.iter().rev().find_map(|x| match x {
+        Stmt::Local(Local {
+            pat: Pat::Ident(binding),
+            init: Some((_, init)),
+            ..
+        }) if binding.ident == *name => Some(&**init),
+        _ => None,
+    })
+}
+
+/// The value of a string literal expression
+fn string_literal(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(s), ..
+        }) => Some(s.value()),
+        _ => None,
+    }
+}
+
+/// Returns true if the body is only a `todo!()` or `unimplemented!()`, standing in for a function
+/// which hasn't been written yet rather than an unfinished branch of one which has
+pub fn is_placeholder(block: &Block, imports: &ImportMap) -> bool {
+    let mac = match block.stmts.as_slice() {
+        [Stmt::Expr(Expr::Macro(mac))] | [Stmt::Semi(Expr::Macro(mac), _)] => &mac.mac,
+        [Stmt::Item(Item::Macro(mac))] => &mac.mac,
+        _ => return false,
+    };
+    matches!(
+        PanicKind::from_macro(&imports.resolve_name(&mac.path)),
+        Some(PanicKind::Todo) | Some(PanicKind::Unimplemented)
+    )
+}
+
+/// Returns true if the arm is `_ => panic!()` or `_ => unreachable!()` in a match on a variable
+/// or field with other arms, where the other variants could be listed instead
+fn is_wildcard_panic_arm(m: &ExprMatch, arm: &Arm, imports: &ImportMap) -> bool {
+    if !matches!(&*m.expr, Expr::Path(_) | Expr::Field(_))
+        || m.arms.len() < 2
+        || arm.guard.is_some()
+        || !matches!(arm.pat, Pat::Wild(_))
+    {
+        return false;
+    }
+    let mac = match &*arm.body {
+        Expr::Macro(mac) => &mac.mac,
+        Expr::Block(block) => match block.block.stmts.as_slice() {
+            [Stmt::Expr(Expr::Macro(mac))] | [Stmt::Semi(Expr::Macro(mac), _)] => &mac.mac,
+            [Stmt::Item(Item::Macro(mac))] => &mac.mac,
+            _ => return false,
+        },
+        _ => return false,
+    };
+    matches!(
+        PanicKind::from_macro(&imports.resolve_name(&mac.path)),
+        Some(PanicKind::Panic) | Some(PanicKind::Unreachable)
+    )
+}
+
+/// The path of the enum a match is on, from the first arm naming a variant like `Kind::A` or
+/// `dep::Kind::B { .. }`
+fn matched_enum(m: &ExprMatch, imports: &ImportMap) -> Option<Vec<String>> {
+    fn variant_path(pat: &Pat) -> Option<&Path> {
+        match pat {
+            Pat::Path(p) => Some(&p.path),
+            Pat::TupleStruct(p) => Some(&p.path),
+            Pat::Struct(p) => Some(&p.path),
+            Pat::Or(p) => p.cases.iter().find_map(variant_path),
+            _ => None,
+        }
+    }
+    let path = m.arms.iter().find_map(|x| variant_path(&x.pat))?;
+    let mut resolved = imports.resolve(path);
+    resolved.pop();
+    if resolved.is_empty() {
+        None
+    } else {
+        Some(resolved)
+    }
+}
+
+/// Returns true if the expression is a call to a smart pointer constructor which would store a
+/// closure to be called later
+fn is_pointer_new(expr: &Expr) -> bool {
+    if let Expr::Path(p) = expr {
+        let segments = p.path.segments.iter().collect::<Vec<_>>();
+        if let [.., ty, method] = segments.as_slice() {
+            return method.ident == "new"
+                && (ty.ident == "Box" || ty.ident == "Arc" || ty.ident == "Rc");
+        }
+    }
+    false
+}
+
+impl PanicVisitor<'_> {
+    fn push(&mut self, span: Span, kind: PanicKind, message: Option<String>) {
+        let mut tags = vec![];
+        if self.deferred_depth > 0 {
+            tags.push(DEFERRED_CLOSURE);
+        }
+        if self.iterator_depth > 0 {
+            tags.push(DEFERRED_ITERATOR);
+        }
+        if self.collect_depth > 0 && matches!(kind, PanicKind::Unwrap | PanicKind::Expect) {
+            tags.push(COLLECT_PANIC);
+        }
+        if self.sort_depth > 0 {
+            tags.push(SORT_CLOSURE_PANIC);
+        }
+        if self.unsafe_depth > 0 {
+            tags.push(NEAR_UNSAFE);
+            tags.push(IN_UNSAFE_BLOCK);
+        }
+        self.sites.push(PanicSite {
+            span,
+            kind,
+            message,
+            tags,
+            matched_enum: None,
+            lookup: None,
+            configured_fn: None,
+            precondition: None,
+            cfg: vec![],
+        });
+    }
+
+    /// How passing a function as the fallback of `unwrap_or_else` or similar can panic, if it's
+    /// counted. Constructors like `Vec::new` or `Error::Io` and allowed functions aren't counted.
+    fn fallback_kind(&self, call: &ExprMethodCall) -> Option<PanicKind> {
+        if !self.helpers.opaque_fallbacks || !FALLBACKS.contains(&&*call.method.to_string()) {
+            return None;
+        }
+        let path = match call.args.first()? {
+            Expr::Closure(_) => return None,
+            Expr::Path(p) if p.qself.is_none() => self.imports.resolve(&p.path),
+            _ => return Some(PanicKind::OpaqueFallback),
+        };
+        let name = path.last()?;
+        if name == "new"
+            || name == "default"
+            || name.starts_with(char::is_uppercase)
+            || Helpers::declared(self.helpers.allowed_fns, &path).is_some()
+        {
+            None
+        } else if self.helpers.path_panics(&path) {
+            Some(PanicKind::HelperCall)
+        } else {
+            Some(PanicKind::OpaqueFallback)
+        }
+    }
+
+    /// Visits a block's statements, a closure at the end of the function body is returned to the
+    /// caller so it's deferred
+    fn visit_stmts(&mut self, stmts: &[Stmt], function_body: bool) {
+        // Bindings go out of scope at the end of the block
+        let outer_lookups = self.lookups.clone();
+        let outer_bindings = self.bindings.clone();
+        let last = stmts.len().saturating_sub(1);
+        if function_body {
+            if let Some(Stmt::Expr(returned)) = stmts.last() {
+                self.defer_iterator(returned);
+                // Best effort for a chain bound to a variable which is then returned, shadowing
+                // and reassignment aren't followed
+                if let Some(init) = returned_binding(returned, &stmts[..last]) {
+                    self.defer_iterator(init);
+                }
+            }
+        }
+        let mut after_unsafe = false;
+        for (i, stmt) in stmts.iter().enumerate() {
+            let first_site = self.sites.len();
+            let unsafe_blocks = self.unsafe_blocks;
+            if let Some((span, value, target, fallible)) = converting_let(stmt) {
+                if self
+                    .helpers
+                    .conversion_panics(self.binding_type(value), &target, fallible)
+                {
+                    self.push(span, PanicKind::HelperCall, None);
+                }
+            }
+            match stmt {
+                Stmt::Expr(e) if function_body && i == last && is_closure(e) => {
+                    self.visit_deferred(e)
+                }
+                _ => self.visit_stmt(stmt),
+            }
+            let has_unsafe = self.unsafe_blocks > unsafe_blocks;
+            if has_unsafe || after_unsafe {
+                for site in &mut self.sites[first_site..] {
+                    if !site.tags.contains(&NEAR_UNSAFE) {
+                        site.tags.push(NEAR_UNSAFE);
+                    }
+                }
+            }
+            after_unsafe = has_unsafe;
+            let cfg = cfg_predicates(stmt_attrs(stmt));
+            if !cfg.is_empty() {
+                // The statements inside were visited first so the outer predicates go in front
+                for site in &mut self.sites[first_site..] {
+                    site.cfg.splice(0..0, cfg.iter().cloned());
+                }
+            }
+            if let Some(guard) = guard_binding(stmt) {
+                // The unwrap is pushed before the expressions inside it so it's the first site
+                if held_across_await(guard, &stmts[i + 1..]) && first_site < self.sites.len() {
+                    self.sites[first_site].tags.push(HELD_ACROSS_AWAIT);
+                }
+            }
+            if let Some((ident, lookup)) = lookup_binding(stmt) {
+                match lookup {
+                    Some(lookup) => self.lookups.insert(ident, lookup),
+                    None => self.lookups.remove(&ident),
+                };
+            }
+            if let Some((ident, ty)) = typed_binding(stmt) {
+                match ty {
+                    Some(ty) => self.bindings.insert(ident, ty),
+                    None => self.bindings.remove(&ident),
+                };
+            }
+        }
+        self.lookups = outer_lookups;
+        self.bindings = outer_bindings;
+    }
+
+    /// The type of a local variable, if its `let` gave one
+    fn binding_type(&self, expr: &Expr) -> Option<&str> {
+        match expr {
+            Expr::Path(p) => p
+                .path
+                .get_ident()
+                .and_then(|x| self.bindings.get(&x.to_string()))
+                .map(String::as_str),
+            _ => None,
+        }
+    }
+
+    /// For a call like `Config::from(raw)` or `Self::try_from(raw)`, the type converted to and
+    /// whether it's a `try_from`
+    fn conversion_call(&self, path: &[String]) -> Option<(String, bool)> {
+        let (ty, method) = match path {
+            [.., ty, method] => (ty, method),
+            _ => return None,
+        };
+        let fallible = match method.as_str() {
+            "from" => false,
+            "try_from" => true,
+            _ => return None,
+        };
+        let ty = match ty.as_str() {
+            "Self" => self.helpers.self_type?.to_string(),
+            _ => ty.clone(),
+        };
+        Some((ty, fallible))
+    }
+
+    /// Marks the closures of a returned iterator chain, so their panics are tagged when they're
+    /// visited
+    fn defer_iterator(&mut self, expr: &Expr) {
+        self.lazy_closures
+            .extend(lazy_closures(expr).into_iter().map(|x| span_key(x.span())));
+    }
+
+    fn visit_deferred(&mut self, expr: &Expr) {
+        self.deferred_depth += 1;
+        self.visit_expr(expr);
+        self.deferred_depth -= 1;
+    }
+
+    /// Fallback for macro bodies which aren't a list of expressions, looks for `.unwrap(` and
+    /// `panic!` style token sequences
+    fn scan_tokens(&mut self, tokens: TokenStream) {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                TokenTree::Group(g) => self.scan_tokens(g.stream()),
+                TokenTree::Ident(ident) => {
+                    let name = self.imports.resolve_name(&Path::from(ident.clone()));
+                    let prev = i.checked_sub(1).and_then(|x| tokens.get(x));
+                    let next = tokens.get(i + 1);
+                    let is_method = matches!(prev, Some(TokenTree::Punct(p)) if p.as_char() == '.')
+                        && matches!(next, Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Parenthesis);
+                    let is_macro = matches!(next, Some(TokenTree::Punct(p)) if p.as_char() == '!');
+                    if is_method {
+                        if let Some(kind) = PanicKind::from_method(&name) {
+                            self.push(ident.span(), kind, None);
+                        }
+                    } else if is_macro {
+                        if let Some(kind) = PanicKind::from_macro(&name) {
+                            self.push(ident.span(), kind, None);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for PanicVisitor<'_> {
+    fn visit_block(&mut self, block: &'ast Block) {
+        self.visit_stmts(&block.stmts, false);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        let name = self.imports.resolve_name(&mac.path);
+        if let Some(kind) = PanicKind::from_macro(&name) {
+            let message = mac
+                .parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated)
+                .ok()
+                .and_then(|args| args.first().and_then(string_literal));
+            self.push(mac.span(), kind, message);
+        } else {
+            match mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+                Ok(exprs) => exprs.iter().for_each(|e| self.visit_expr(e)),
+                Err(_) => self.scan_tokens(mac.tokens.clone()),
+            }
+        }
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        self.collected_closures.extend(
+            collected_closures(call)
+                .into_iter()
+                .map(|x| span_key(x.span())),
+        );
+        if let Some(kind) = PanicKind::from_method(&call.method.to_string()) {
+            let message = call.args.first().and_then(string_literal);
+            self.push(call.method.span(), kind, message);
+            match self.sites.last_mut() {
+                Some(site) if is_lock(&call.receiver) => site.tags.push(MUTEX_POISON),
+                Some(site) if is_write(&call.receiver, self.imports) => {
+                    site.tags.push(WRITE_UNWRAP)
+                }
+                Some(site) if is_send(&call.receiver) => site.tags.push(CHANNEL_SEND),
+                Some(site) if is_call_to(&call.receiver, self.imports, &[["env", "var"]]) => {
+                    site.tags.push(ENV_VAR)
+                }
+                Some(site)
+                    if is_call_to(
+                        &call.receiver,
+                        self.imports,
+                        &[["File", "open"], ["fs", "read"], ["fs", "read_to_string"]],
+                    ) =>
+                {
+                    site.tags.push(FILE_IO)
+                }
+                Some(site)
+                    if is_call_to(
+                        &call.receiver,
+                        self.imports,
+                        &[
+                            ["String", "from_utf8"],
+                            ["String", "from_utf16"],
+                            ["str", "from_utf8"],
+                            ["str", "from_utf8_mut"],
+                        ],
+                    ) =>
+                {
+                    site.tags.push(UTF8_DECODE)
+                }
+                Some(site) => {
+                    site.lookup = unwrapped_lookup(&call.receiver, &self.lookups);
+                    if site.lookup.is_some() {
+                        site.tags.push(OPTION_LOOKUP);
+                    }
+                }
+                None => {}
+            }
+            let non_zero = self
+                .helpers
+                .preconditions
+                .iter()
+                .find(|x| x.name == "NonZero::new")
+                .filter(|_| is_non_zero_new(&call.receiver, self.imports));
+            if let (Some(site), Some(precondition)) = (self.sites.last_mut(), non_zero) {
+                site.tags.push(STD_PRECONDITION);
+                site.precondition = Some(precondition);
+            }
+        } else if self.helpers.method_panics(&call.receiver, &call.method) {
+            self.push(call.method.span(), PanicKind::HelperCall, None);
+        } else if let Some(precondition) = self.helpers.precondition(call) {
+            self.push(call.method.span(), PanicKind::PreconditionPanic, None);
+            if let Some(site) = self.sites.last_mut() {
+                site.tags.push(STD_PRECONDITION);
+                site.precondition = Some(precondition);
+            }
+        } else if let Some(kind) = self.fallback_kind(call) {
+            self.push(call.method.span(), kind, None);
+            if let Some(site) = self.sites.last_mut() {
+                site.tags.push(OPAQUE_FALLBACK);
+            }
+        }
+        if SORT_METHODS.contains(&&*call.method.to_string()) {
+            self.visit_expr(&call.receiver);
+            self.sort_depth += 1;
+            call.args.iter().for_each(|x| self.visit_expr(x));
+            self.sort_depth -= 1;
+        } else {
+            visit::visit_expr_method_call(self, call);
+        }
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+        if let Expr::Path(p) = &*call.func {
+            let path = self.imports.resolve(&p.path);
+            // The user's lists take precedence over the built in functions, and allowing a
+            // function takes precedence over declaring it as panicking
+            let configured = Helpers::declared(self.helpers.panicking_fns, &path);
+            if Helpers::declared(self.helpers.allowed_fns, &path).is_some() {
+            } else if let Some(declared) = configured {
+                self.push(call.span(), PanicKind::ConfiguredFn, None);
+                if let Some(site) = self.sites.last_mut() {
+                    site.configured_fn = Some(declared.clone());
+                }
+            } else if let Some(kind) = PanicKind::from_function(&path) {
+                let message = call.args.first().and_then(string_literal);
+                let kind = match &message {
+                    Some(message) if kind == PanicKind::Panic => {
+                        PanicKind::from_expanded_message(message).unwrap_or(kind)
+                    }
+                    _ => kind,
+                };
+                self.push(call.span(), kind, message);
+            } else if p.qself.is_none() && self.helpers.path_panics(&path) {
+                self.push(call.span(), PanicKind::HelperCall, None);
+            } else if let Some((target, fallible)) = self.conversion_call(&path) {
+                let source = call.args.first().and_then(|x| self.binding_type(x));
+                if call.args.len() == 1 && self.helpers.conversion_panics(source, &target, fallible)
+                {
+                    self.push(call.span(), PanicKind::HelperCall, None);
+                }
+            }
+        }
+        if is_pointer_new(&call.func) && call.args.len() == 1 && is_closure(&call.args[0]) {
+            self.visit_expr(&call.func);
+            self.visit_deferred(&call.args[0]);
+        } else {
+            visit::visit_expr_call(self, call);
+        }
+    }
+
+    fn visit_expr_unsafe(&mut self, expr: &'ast ExprUnsafe) {
+        self.unsafe_blocks += 1;
+        self.unsafe_depth += 1;
+        visit::visit_expr_unsafe(self, expr);
+        self.unsafe_depth -= 1;
+    }
+
+    fn visit_expr_struct(&mut self, expr: &'ast ExprStruct) {
+        self.visit_path(&expr.path);
+        for field in expr.fields.iter() {
+            if is_closure(&field.expr) {
+                self.visit_deferred(&field.expr);
+            } else {
+                self.visit_expr(&field.expr);
+            }
+        }
+        if let Some(rest) = &expr.rest {
+            self.visit_expr(rest);
+        }
+    }
+
+    fn visit_expr_match(&mut self, m: &'ast ExprMatch) {
+        self.visit_expr(&m.expr);
+        for arm in &m.arms {
+            let first_site = self.sites.len();
+            self.visit_arm(arm);
+            if is_wildcard_panic_arm(m, arm, self.imports) {
+                let matched = matched_enum(m, self.imports);
+                for site in &mut self.sites[first_site..] {
+                    site.tags.push(WILDCARD_MATCH_ARM);
+                    site.matched_enum = matched.clone();
+                }
+            }
+        }
+    }
+
+    fn visit_expr_return(&mut self, ret: &'ast ExprReturn) {
+        match &ret.expr {
+            Some(e) if is_closure(e) => self.visit_deferred(e),
+            Some(e) => {
+                self.defer_iterator(e);
+                self.visit_expr(e)
+            }
+            None => {}
+        }
+    }
+
+    fn visit_expr_closure(&mut self, closure: &'ast ExprClosure) {
+        let lazy = self.lazy_closures.contains(&span_key(closure.span()));
+        let collected = self.collected_closures.contains(&span_key(closure.span()));
+        if lazy {
+            self.iterator_depth += 1;
+        }
+        if collected {
+            self.collect_depth += 1;
+        }
+        visit::visit_expr_closure(self, closure);
+        if lazy {
+            self.iterator_depth -= 1;
+        }
+        if collected {
+            self.collect_depth -= 1;
+        }
+    }
+}
+
+/// Finds method calls on trait objects whose trait is one of `traits`, like `handler.handle()`
+/// where `handler` is a `Box<dyn Handler>` parameter or local. Any method counts as the
+/// implementor could override it with one which panics.
+pub fn dyn_dispatch_calls(
+    sig: &Signature,
+    block: &Block,
+    traits: &HashSet<String>,
+) -> Vec<PanicSite> {
+    let objects = sig
+        .inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(t) => match &*t.pat {
+                Pat::Ident(p) => Some((p.ident.to_string(), object_traits(&t.ty))),
+                _ => None,
+            },
+            FnArg::Receiver(_) => None,
+        })
+        .collect();
+    let mut visitor = DynDispatchVisitor {
+        objects,
+        traits,
+        sites: vec![],
+    };
+    visitor.visit_block(block);
+    visitor.sites
+}
+
+struct DynDispatchVisitor<'a> {
+    /// The traits of each variable which is a trait object, empty for other variables
+    objects: HashMap<String, Vec<String>>,
+    traits: &'a HashSet<String>,
+    sites: Vec<PanicSite>,
+}
+
+impl<'ast> Visit<'ast> for DynDispatchVisitor<'_> {
+    fn visit_block(&mut self, block: &'ast Block) {
+        // Bindings go out of scope at the end of the block
+        let outer = self.objects.clone();
+        visit::visit_block(self, block);
+        self.objects = outer;
+    }
+
+    fn visit_local(&mut self, local: &'ast Local) {
+        let binding = match &local.pat {
+            Pat::Type(p) => match &*p.pat {
+                Pat::Ident(ident) => Some((ident.ident.to_string(), object_traits(&p.ty))),
+                _ => None,
+            },
+            Pat::Ident(p) => Some((p.ident.to_string(), vec![])),
+            _ => None,
+        };
+        visit::visit_local(self, local);
+        if let Some((name, traits)) = binding {
+            self.objects.insert(name, traits);
+        }
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        let traits = match &*call.receiver {
+            Expr::Path(p) => p
+                .path
+                .get_ident()
+                .and_then(|x| self.objects.get(&x.to_string())),
+            _ => None,
+        };
+        if traits.is_some_and(|x| x.iter().any(|x| self.traits.contains(x))) {
+            self.sites.push(PanicSite {
+                span: call.method.span(),
+                kind: PanicKind::HelperCall,
+                message: None,
+                tags: vec![DYNAMIC_DISPATCH],
+                matched_enum: None,
+                lookup: None,
+                configured_fn: None,
+                precondition: None,
+                cfg: vec![],
+            });
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+}
+
+/// The traits of a trait object type, seeing through references and `Box`, `Rc` or `Arc`
+fn object_traits(ty: &Type) -> Vec<String> {
+    match ty {
+        Type::TraitObject(t) => t
+            .bounds
+            .iter()
+            .filter_map(|x| match x {
+                TypeParamBound::Trait(t) => t.path.segments.last().map(|x| x.ident.to_string()),
+                TypeParamBound::Lifetime(_) => None,
+            })
+            .collect(),
+        Type::Reference(r) => object_traits(&r.elem),
+        Type::Paren(p) => object_traits(&p.elem),
+        Type::Path(p) => match p.path.segments.last() {
+            Some(segment) if ["Box", "Rc", "Arc"].iter().any(|x| segment.ident == x) => {
+                match &segment.arguments {
+                    PathArguments::AngleBracketed(args) => args
+                        .args
+                        .iter()
+                        .filter_map(|x| match x {
+                            GenericArgument::Type(t) => Some(object_traits(t)),
+                            _ => None,
+                        })
+                        .flatten()
+                        .collect(),
+                    _ => vec![],
+                }
+            }
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sites(code: &str) -> Vec<PanicSite> {
+        let func: ItemFn = parse_str(code).unwrap();
+        find_panics(&func.block, &ImportMap::default(), Helpers::default())
+    }
+
+    fn delegates(code: &str) -> bool {
+        let func: ItemFn = parse_str(code).unwrap();
+        is_delegation(&func.sig, &func.block)
+    }
+
+    fn is_deferred(site: &PanicSite) -> bool {
+        site.tags.contains(&DEFERRED_CLOSURE)
+    }
+
+    #[test]
+    fn finds_panic_kinds() {
+        let found = sites(
+            r#"
+            fn foo(x: Option<i32>) {
+                // panic in a comment and a string "unwrap" don't count
+                let y = x.unwrap();
+                let z = x.expect("a value").unwrap_or_default();
+                println!("{}", x.unwrap());
+                todo!();
+            }"#,
+        );
+        let kinds = found.iter().map(|x| x.kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            &[
+                PanicKind::Unwrap,
+                PanicKind::Expect,
+                PanicKind::Unwrap,
+                PanicKind::Todo
+            ]
+        );
+        assert!(!found.iter().any(is_deferred));
+        assert_eq!(found[1].message.as_deref(), Some("a value"));
+        assert_eq!(found[0].message, None);
+    }
+
+    #[test]
+    fn renamed_imports() {
+        let file = parse_file(
+            r#"
+            use std::panic as die;
+            use std::panic::{panic_any as bail};
+            use log::warn as panic;
+
+            fn foo() {
+                die!("oh no");
+                bail(42);
+                panic!("just a warning");
+                vec![die!()];
+            }"#,
+        )
+        .unwrap();
+        let imports = ImportMap::new(&file.items);
+        let func = match &file.items[3] {
+            Item::Fn(f) => f,
+            _ => unreachable!(),
+        };
+        let found = find_panics(&func.block, &imports, Helpers::default());
+        assert_eq!(found.len(), 3);
+        assert!(found.iter().all(|x| x.kind == PanicKind::Panic));
+        assert_eq!(found[0].message.as_deref(), Some("oh no"));
+    }
+
+    #[test]
+    fn process_exits() {
+        let file = parse_file(
+            r#"
+            use std::process::{self, abort as die};
+
+            fn foo(code: i32) {
+                std::process::exit(code);
+                process::exit(1);
+                die();
+                exit(2);
+                cache.exit();
+            }"#,
+        )
+        .unwrap();
+        let imports = ImportMap::new(&file.items);
+        let func = match &file.items[1] {
+            Item::Fn(f) => f,
+            _ => unreachable!(),
+        };
+        let found = find_panics(&func.block, &imports, Helpers::default());
+        assert_eq!(found.len(), 3);
+        assert!(found.iter().all(|x| x.kind == PanicKind::ProcessExit));
+    }
+
+    #[test]
+    fn conversions() {
+        let func: ItemFn = parse_str(
+            r#"
+            fn load(&self, text: String, raw: RawConfig) {
+                let a: RawConfig = parse(text);
+                let b: u16 = 8080;
+                Config::from(a);
+                Config::from(b);
+                Config::from(raw);
+                let c: Config = a.into();
+                let d: Config = b.into();
+                let e = a.into();
+                let f: Config = raw.into();
+            }"#,
+        )
+        .unwrap();
+        let conversion = |source: &str| Conversion {
+            source: source.to_string(),
+            target: "Config".to_string(),
+            fallible: false,
+        };
+        let conversions = vec![(conversion("RawConfig"), true), (conversion("u16"), false)]
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        let helpers = Helpers {
+            conversions: Some(&conversions),
+            ..Default::default()
+        };
+        let found = find_panics(&func.block, &ImportMap::default(), helpers);
+        let lines = found
+            .iter()
+            .map(|x| (x.kind, x.span.start().line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [(PanicKind::HelperCall, 5), (PanicKind::HelperCall, 8)]
+        );
+    }
+
+    #[test]
+    fn configured_panic_fns() {
+        let file = parse_file(
+            r#"
+            use our_telemetry as telemetry;
+            use our_telemetry::fatal as die;
+
+            fn foo(code: i32) {
+                our_telemetry::fatal("qualified");
+                telemetry::fatal("renamed crate");
+                die("renamed function");
+                fatal("not imported");
+                logger.fatal("a method");
+                other::fatal("another crate");
+                std::process::abort();
+                std::process::exit(code);
+            }"#,
+        )
+        .unwrap();
+        let imports = ImportMap::new(&file.items);
+        let func = match &file.items[2] {
+            Item::Fn(f) => f,
+            _ => unreachable!(),
+        };
+        let declared = |panicking: &[&str], allowed: &[&str]| {
+            let panicking_fns = panicking.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+            let allowed_fns = allowed.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+            let helpers = Helpers {
+                panicking_fns: &panicking_fns,
+                allowed_fns: &allowed_fns,
+                ..Default::default()
+            };
+            find_panics(&func.block, &imports, helpers)
+                .into_iter()
+                .map(|x| (x.kind, x.configured_fn))
+                .collect::<Vec<_>>()
+        };
+        let configured = (
+            PanicKind::ConfiguredFn,
+            Some("our_telemetry::fatal".to_string()),
+        );
+        assert_eq!(
+            declared(&["our_telemetry::fatal"], &[]),
+            [
+                configured.clone(),
+                configured.clone(),
+                configured.clone(),
+                (PanicKind::ProcessExit, None),
+                (PanicKind::ProcessExit, None),
+            ]
+        );
+
+        // Declaring a built in function takes precedence over the built in kind, allowing one
+        // takes precedence over both
+        let abort = (
+            PanicKind::ConfiguredFn,
+            Some("std::process::abort".to_string()),
+        );
+        assert_eq!(
+            declared(&["std::process::abort"], &[]),
+            [abort, (PanicKind::ProcessExit, None)]
+        );
+        assert_eq!(
+            declared(
+                &["our_telemetry::fatal", "std::process::abort"],
+                &["our_telemetry::fatal", "process::exit"]
+            ),
+            [(
+                PanicKind::ConfiguredFn,
+                Some("std::process::abort".to_string())
+            )]
+        );
+
+        // A single segment only matches an unqualified call
+        assert_eq!(
+            declared(&["fatal"], &[]),
+            [
+                (PanicKind::ConfiguredFn, Some("fatal".to_string())),
+                (PanicKind::ProcessExit, None),
+                (PanicKind::ProcessExit, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn guards_held_across_await() {
+        let held = |code: &str| {
+            sites(code)
+                .iter()
+                .map(|x| x.tags.contains(&HELD_ACROSS_AWAIT))
+                .collect::<Vec<_>>()
+        };
+        let dropped = r#"
+            async fn update(&self) {
+                let mut guard = self.state.lock().unwrap();
+                guard.count += 1;
+                drop(guard);
+                self.notify().await;
+            }"#;
+        assert_eq!(held(dropped), &[false]);
+
+        let across = r#"
+            async fn update(&self) {
+                let mut guard = self.state.lock().unwrap();
+                if guard.count > 0 {
+                    let cache = self.cache.read().expect("poisoned");
+                    self.notify().await;
+                }
+            }"#;
+        assert_eq!(held(across), &[true, true]);
+
+        let shadowed = r#"
+            async fn update(&self) {
+                let count = self.state.lock().unwrap();
+                let count = count.len();
+                self.notify(count).await;
+            }"#;
+        assert_eq!(held(shadowed), &[true]);
+
+        let temporary = r#"
+            async fn update(&self) {
+                let count = self.state.lock().unwrap().len();
+                let later = async { self.notify().await };
+                self.notify(count).await;
+            }"#;
+        assert_eq!(held(temporary), &[false]);
+    }
+
+    #[test]
+    fn closure_in_struct_field() {
+        let found = sites(
+            r#"
+            fn default() -> Hooks {
+                Hooks {
+                    on_error: Box::new(|e| panic!("{}", e)),
+                    on_exit: |code| std::process::exit(code.unwrap()),
+                    name: env!("NAME").parse().unwrap(),
+                }
+            }"#,
+        );
+        assert_eq!(found.len(), 4);
+        assert!(is_deferred(&found[0]));
+        assert_eq!(found[1].kind, PanicKind::ProcessExit);
+        assert!(is_deferred(&found[1]));
+        assert!(is_deferred(&found[2]));
+        assert!(!is_deferred(&found[3]));
+    }
+
+    #[test]
+    fn returned_boxed_closure() {
+        let found = sites(
+            r#"
+            fn handler() -> Box<dyn Fn(Option<i32>) -> i32> {
+                Box::new(move |x| x.expect("need a value"))
+            }"#,
+        );
+        assert_eq!(found.len(), 1);
+        assert!(is_deferred(&found[0]));
+    }
+
+    #[test]
+    fn returned_impl_fn() {
+        let found = sites(
+            r#"
+            fn handler(early: bool) -> impl Fn() {
+                if early {
+                    return || todo!();
+                }
+                || unimplemented!()
+            }"#,
+        );
+        assert_eq!(found.len(), 2);
+        assert!(found.iter().all(is_deferred));
+    }
+
+    #[test]
+    fn closures_run_immediately_not_deferred() {
+        let found = sites(
+            r#"
+            fn sum(x: &[&str]) -> i32 {
+                x.iter().map(|x| x.parse::<i32>().unwrap()).sum()
+            }"#,
+        );
+        assert_eq!(found.len(), 1);
+        assert!(!is_deferred(&found[0]));
+    }
+
+    #[test]
+    fn delegations() {
+        assert!(delegates("fn len(&self) -> usize { self.inner.len() }"));
+        assert!(delegates(
+            "fn insert(&mut self, k: K, v: V) { self.map.insert(k, v); }"
+        ));
+        assert!(delegates(
+            "fn new(capacity: usize) -> Self { Self::with_capacity(capacity) }"
+        ));
+        assert!(delegates(
+            "fn read(&self, buf: &mut [u8], at: u64) -> usize { read_at(&self.file, at, buf) }"
+        ));
+        assert!(delegates(
+            "fn swap(a: u8, b: u8) -> u8 { inner::swap(b, a) }"
+        ));
+
+        // Not every parameter is passed on
+        assert!(!delegates(
+            "fn get(&self, i: usize, j: usize) -> u8 { self.inner.get(i) }"
+        ));
+        // Arguments which aren't parameters
+        assert!(!delegates("fn get(&self) -> u8 { self.inner.get(0) }"));
+        assert!(!delegates(
+            "fn get(&self, i: usize) -> u8 { self.inner.get(i + 1) }"
+        ));
+        // More than a single call
+        assert!(!delegates(
+            "fn get(&self, i: usize) -> u8 { self.check(); self.inner.get(i) }"
+        ));
+        assert!(!delegates(
+            "fn get(&self, i: usize) -> u8 { self.inner.get(i).copied() }"
+        ));
+        // The call is the panic
+        assert!(!delegates(
+            "fn get(&self, msg: &str) -> u8 { self.inner.expect(msg) }"
+        ));
+        assert!(!delegates("fn get(self) -> u8 { self.inner.unwrap() }"));
+        assert!(!delegates(
+            "fn quit(code: i32) -> ! { std::process::exit(code) }"
+        ));
+    }
+
+    #[test]
+    fn panics_near_unsafe() {
+        let near = |code: &str| {
+            sites(code)
+                .iter()
+                .map(|x| x.tags.contains(&NEAR_UNSAFE))
+                .collect::<Vec<_>>()
+        };
+        let inside = r#"
+            fn open(path: &CStr) -> File {
+                unsafe {
+                    let fd = libc::open(path.as_ptr(), 0);
+                    File::from_raw_fd(fd.try_into().unwrap())
+                }
+            }"#;
+        assert_eq!(near(inside), &[true]);
+        let after = r#"
+            fn name(x: &Thing) -> &str {
+                let p = unsafe { ffi::thing_name(x.raw).as_ref() };
+                let p = p.expect("thing has no name");
+                let name = unsafe { CStr::from_ptr(p) }.to_str().unwrap();
+                let len = name.len();
+                Some(len).expect("unrelated");
+                name
+            }"#;
+        assert_eq!(near(after), &[true, true, false]);
+        let unrelated = r#"
+            fn parse(x: &str) -> u32 {
+                let y = x.trim();
+                y.parse().unwrap()
+            }"#;
+        assert_eq!(near(unrelated), &[false]);
+    }
+
+    #[test]
+    fn wildcard_match_arms() {
+        let wildcard = |code: &str| {
+            sites(code)
+                .iter()
+                .map(|x| x.tags.contains(&WILDCARD_MATCH_ARM))
+                .collect::<Vec<_>>()
+        };
+        let only_panic = r#"
+            fn code(&self) -> u8 {
+                match self.kind {
+                    Kind::A => 1,
+                    Kind::B => 2,
+                    _ => panic!("unsupported kind"),
+                }
+            }"#;
+        assert_eq!(wildcard(only_panic), &[true]);
+        assert_eq!(
+            sites(only_panic)[0].matched_enum,
+            Some(vec!["Kind".to_string()])
+        );
+        let extra_statements = r#"
+            fn code(kind: Kind) -> u8 {
+                match kind {
+                    Kind::A => 1,
+                    _ => {
+                        log::error!("unsupported kind");
+                        panic!("unsupported kind")
+                    }
+                }
+            }"#;
+        assert_eq!(wildcard(extra_statements), &[false]);
+        let not_wildcard = r#"
+            fn code(kind: Kind) -> u8 {
+                match kind {
+                    Kind::A => 1,
+                    Kind::B => panic!("B has no code"),
+                    _ => 0,
+                }
+            }"#;
+        assert_eq!(wildcard(not_wildcard), &[false]);
+        let computed = r#"
+            fn code(kind: Kind) -> u8 {
+                match kind.normalise() {
+                    Kind::A => 1,
+                    _ => panic!("unsupported kind"),
+                }
+            }"#;
+        assert_eq!(wildcard(computed), &[false]);
+    }
+
+    #[test]
+    fn lock_poisoning() {
+        let found = sites(
+            r#"
+            fn update(&self, buf: &mut [u8]) {
+                let mut state = self.state.lock().unwrap();
+                let config = self.config.read().expect("config lock");
+                *self.cache.write().unwrap() = config.clone();
+                self.file.read(buf).unwrap();
+            }"#,
+        );
+        let poisoned = found
+            .iter()
+            .map(|x| x.tags.contains(&MUTEX_POISON))
+            .collect::<Vec<_>>();
+        assert_eq!(poisoned, &[true, true, true, false]);
+    }
+
+    #[test]
+    fn write_unwraps() {
+        let found = sites(
+            r#"
+            fn render(&self, out: &mut impl Write) {
+                write!(out, "{}", self.name).unwrap();
+                writeln!(out).expect("stdout is open");
+                out.write_all(b"\n").unwrap();
+                format!("{}", self.name).parse::<u8>().unwrap();
+            }"#,
+        );
+        let writes = found
+            .iter()
+            .map(|x| x.tags.contains(&WRITE_UNWRAP))
+            .collect::<Vec<_>>();
+        assert_eq!(writes, &[true, true, false, false]);
+    }
+
+    #[test]
+    fn channel_sends() {
+        let found = sites(
+            r#"
+            fn notify(&self, tx: &Sender<u32>, value: u32) {
+                tx.send(value).unwrap();
+                self.events.send(Event::Done).expect("listener is alive");
+                "literal".send(value).unwrap();
+                tx.send_all(value).unwrap();
+            }"#,
+        );
+        let sends = found
+            .iter()
+            .map(|x| x.tags.contains(&CHANNEL_SEND))
+            .collect::<Vec<_>>();
+        assert_eq!(sends, &[true, true, false, false]);
+    }
+
+    #[test]
+    fn env_vars() {
+        let file: File = parse_str(
+            r#"
+            use std::env::var;
+
+            fn settings() {
+                std::env::var("HOME").unwrap();
+                env::var("PORT").expect("PORT is set");
+                var("USER").unwrap();
+                vars.var("HOME").unwrap();
+                other::var("HOME").unwrap();
+            }"#,
+        )
+        .unwrap();
+        let func = match &file.items[1] {
+            Item::Fn(f) => f,
+            _ => unreachable!(),
+        };
+        let found = find_panics(
+            &func.block,
+            &ImportMap::new(&file.items),
+            Helpers::default(),
+        );
+        let env_vars = found
+            .iter()
+            .map(|x| x.tags.contains(&ENV_VAR))
+            .collect::<Vec<_>>();
+        assert_eq!(env_vars, &[true, true, true, false, false]);
+    }
+
+    #[test]
+    fn file_io() {
+        let file: File = parse_str(
+            r#"
+            use std::fs::File;
+
+            fn load(path: &Path) {
+                File::open(path).unwrap();
+                std::fs::File::open("config.toml").expect("config exists");
+                fs::read(path).unwrap();
+                fs::read_to_string(path).unwrap();
+                File::create(path).unwrap();
+                cache.read(path).unwrap();
+            }"#,
+        )
+        .unwrap();
+        let func = match &file.items[1] {
+            Item::Fn(f) => f,
+            _ => unreachable!(),
+        };
+        let found = find_panics(
+            &func.block,
+            &ImportMap::new(&file.items),
+            Helpers::default(),
+        );
+        let file_io = found
+            .iter()
+            .map(|x| x.tags.contains(&FILE_IO))
+            .collect::<Vec<_>>();
+        assert_eq!(file_io, &[true, true, true, true, false, false]);
+    }
+
+    #[test]
+    fn utf8_decodes() {
+        let file: File = parse_str(
+            r#"
+            use std::str::from_utf8;
+
+            fn decode(bytes: Vec<u8>, raw: &[u8]) {
+                String::from_utf8(bytes).unwrap();
+                std::str::from_utf8(raw).expect("valid text");
+                from_utf8(raw).unwrap();
+                std::string::String::from_utf16(&wide).unwrap();
+                String::from_utf8_lossy(raw).into_owned().parse::<u8>().unwrap();
+                codec.from_utf8(raw).unwrap();
+            }"#,
+        )
+        .unwrap();
+        let func = match &file.items[1] {
+            Item::Fn(f) => f,
+            _ => unreachable!(),
+        };
+        let found = find_panics(
+            &func.block,
+            &ImportMap::new(&file.items),
+            Helpers::default(),
+        );
+        let decodes = found
+            .iter()
+            .map(|x| x.tags.contains(&UTF8_DECODE))
+            .collect::<Vec<_>>();
+        assert_eq!(decodes, &[true, true, true, true, false, false]);
+    }
+
+    #[test]
+    fn collected_unwraps() {
+        let func: ItemFn = parse_str(
+            r#"
+            fn ports(raw: &[&str]) {
+                let ports = raw.iter().map(|x| x.parse::<u16>().unwrap()).collect::<Vec<_>>();
+                let names: Vec<String> = raw
+                    .iter()
+                    .filter(|x| !x.is_empty())
+                    .map(|x| x.strip_prefix('-').expect("flag").to_string())
+                    .collect();
+                let checked = raw.iter().map(|x| x.parse::<u16>()).collect::<Result<Vec<_>, _>>();
+                let optional = raw.iter().map(|x| x.get(0..1)).collect::<Option<Vec<_>>>();
+                let nested = raw
+                    .iter()
+                    .map(|x| x.split(':').nth(1).unwrap().parse::<u16>())
+                    .collect::<Result<Vec<_>, _>>();
+                raw.iter().for_each(|x| { x.parse::<u16>().unwrap(); });
+                let mapped = raw.first().map(|x| x.parse::<u16>().unwrap());
+            }"#,
+        )
+        .unwrap();
+        let found = find_panics(&func.block, &ImportMap::default(), Helpers::default());
+        let collected = found
+            .iter()
+            .map(|x| x.tags.contains(&COLLECT_PANIC))
+            .collect::<Vec<_>>();
+        assert_eq!(collected, &[true, true, true, false, false]);
+    }
+
+    #[test]
+    fn sort_closures() {
+        let func: ItemFn = parse_str(
+            r#"
+            fn order(names: &mut [String], ids: &mut Vec<&str>) {
+                ids.sort_by_key(|x| x.parse::<i32>().unwrap());
+                names.sort_unstable_by(|a, b| a.partial_cmp(b).expect("comparable"));
+                names.sort_by(|a, b| a.cmp(b));
+                names.first().unwrap();
+                ids.iter().map(|x| x.len()).max().unwrap();
+            }"#,
+        )
+        .unwrap();
+        let found = find_panics(&func.block, &ImportMap::default(), Helpers::default());
+        let sorting = found
+            .iter()
+            .map(|x| x.tags.contains(&SORT_CLOSURE_PANIC))
+            .collect::<Vec<_>>();
+        assert_eq!(sorting, &[true, true, false, false]);
+    }
+
+    #[test]
+    fn option_lookups() {
+        let found = sites(
+            r#"
+            fn lookups(&self, map: &HashMap<u32, String>, text: &str, i: usize) {
+                map.get(&1).unwrap();
+                self.items.first().expect("an item");
+                (self.items.last()).unwrap();
+                text.split(',').next().unwrap();
+                text.chars().nth(i).unwrap();
+            }"#,
+        );
+        let lookups = found.iter().map(|x| x.lookup).collect::<Vec<_>>();
+        assert_eq!(
+            lookups,
+            &[
+                Some(Lookup::Get),
+                Some(Lookup::First),
+                Some(Lookup::Last),
+                Some(Lookup::Next),
+                Some(Lookup::Nth),
+            ]
+        );
+        assert!(found.iter().all(|x| x.tags.contains(&OPTION_LOOKUP)));
+    }
+
+    #[test]
+    fn option_lookups_through_let() {
+        let found = sites(
+            r#"
+            fn lookups(&self, map: &HashMap<u32, String>) {
+                let value = map.get(&1);
+                let first = self.items.first();
+                value.unwrap();
+                {
+                    let value = self.parse();
+                    value.unwrap();
+                }
+                value.expect("present");
+                first.unwrap();
+                let first = self.items.len();
+                first.unwrap();
+            }"#,
+        );
+        let lookups = found.iter().map(|x| x.lookup).collect::<Vec<_>>();
+        assert_eq!(
+            lookups,
+            &[
+                Some(Lookup::Get),
+                None,
+                Some(Lookup::Get),
+                Some(Lookup::First),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn not_option_lookups() {
+        let found = sites(
+            r#"
+            fn lookups(&self, cell: &OnceCell<u8>, text: &str) {
+                cell.get().unwrap();
+                text.parse::<u8>().unwrap();
+                self.first.unwrap();
+                self.items.get(0..2, 3).unwrap();
+                self.first().map(|x| x + 1).unwrap();
+            }"#,
+        );
+        assert_eq!(found.len(), 5);
+        assert!(found.iter().all(|x| x.lookup.is_none()));
+        assert!(found.iter().all(|x| !x.tags.contains(&OPTION_LOOKUP)));
+    }
+
+    #[test]
+    fn dyn_dispatch() {
+        let func: ItemFn = parse_str(
+            r#"
+            fn run(handler: Box<dyn Handler + Send>, other: &dyn Other, data: &[u8]) {
+                handler.handle(data);
+                other.handle(data);
+                let local: &mut dyn Handler = get();
+                local.reset();
+                {
+                    let local = Plain::new();
+                    local.reset();
+                }
+                local.reset();
+                let shared: Arc<dyn Handler> = Arc::new(Impl);
+                shared.handle(data);
+            }"#,
+        )
+        .unwrap();
+        let traits = vec!["Handler".to_string()].into_iter().collect();
+        let found = dyn_dispatch_calls(&func.sig, &func.block, &traits);
+        let lines = found
+            .iter()
+            .map(|x| x.span.start().line)
+            .collect::<Vec<_>>();
+        // `other` isn't a `Handler` and the inner `local` shadows the trait object until the end
+        // of its block
+        assert_eq!(lines, &[3, 6, 11, 13]);
+        assert!(found.iter().all(|x| x.tags == [DYNAMIC_DISPATCH]));
+    }
+
+    #[test]
+    fn returned_iterator_chains() {
+        let deferred = |code: &str| {
+            sites(code)
+                .iter()
+                .map(|x| x.tags.contains(&DEFERRED_ITERATOR))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            deferred(
+                "fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+                    self.raw.iter().filter(|s| !s.is_empty()).map(|s| s.parse().unwrap())
+                }"
+            ),
+            [true]
+        );
+        assert_eq!(
+            deferred(
+                "fn ids(n: u32) -> impl Iterator<Item = u32> {
+                    if n == 0 {
+                        return (0..10).map(|x| 10 / x.checked_sub(1).unwrap());
+                    }
+                    (0..n).step_by(2).map(|x| x.checked_mul(3).expect(\"small\"))
+                }"
+            ),
+            [true, true]
+        );
+        // Consumed inside the function, or not an iterator at all
+        assert_eq!(
+            deferred(
+                "fn total(&self) -> u32 {
+                    self.raw.iter().map(|s| s.parse::<u32>().unwrap()).sum()
+                }"
+            ),
+            [false]
+        );
+        assert_eq!(
+            deferred("fn id(raw: Option<&str>) -> Option<u32> { raw.map(|s| s.parse().unwrap()) }"),
+            [false]
+        );
+        // A chain stored in a variable and then returned is followed, but only that binding
+        assert_eq!(
+            deferred(
+                "fn ids(&self) -> impl Iterator<Item = u32> + '_ {
+                    let first = self.raw[0].parse::<u32>().unwrap();
+                    let ids = self.raw.iter().map(move |s| first + s.parse::<u32>().unwrap());
+                    ids
+                }"
+            ),
+            [false, true]
+        );
+    }
+
+    #[test]
+    fn expanded_macros() {
+        let kinds = sites(
+            "fn f(x: Option<u8>) {
+                ::core::panicking::panic(\"not yet implemented\");
+                ::core::panicking::panic(\"internal error: entered unreachable code\");
+                ::core::panicking::panic_fmt(format_args!(\"bad {0}\", x));
+                ::std::rt::begin_panic(\"old style\");
+                self::panicking::panic(\"a module of our own\");
+            }",
+        )
+        .iter()
+        .map(|x| x.kind)
+        .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [
+                PanicKind::Todo,
+                PanicKind::Unreachable,
+                PanicKind::Panic,
+                PanicKind::Panic
+            ]
+        );
+    }
+
+    #[test]
+    fn cfg_gated_statements() {
+        let cfgs = |code: &str| sites(code).into_iter().map(|x| x.cfg).collect::<Vec<_>>();
+        assert_eq!(
+            cfgs(
+                "fn parse(s: &str) -> u32 {
+                    #[cfg(not(feature = \"safe-mode\"))]
+                    {
+                        #[cfg(any(unix, windows))]
+                        let n = s.parse().unwrap();
+                        n.checked_sub(1).expect(\"positive\");
+                    }
+                    #[cfg(debug_assertions)]
+                    panic!(\"unreachable\");
+                    s.len().checked_sub(1).unwrap()
+                }"
+            ),
+            [
+                vec![
+                    "not(feature = \"safe-mode\")".to_string(),
+                    "any(unix, windows)".to_string()
+                ],
+                vec!["not(feature = \"safe-mode\")".to_string()],
+                vec!["debug_assertions".to_string()],
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn opaque_fallbacks() {
+        let file: File = parse_str(
+            r#"
+            use crate::errors::fatal;
+
+            fn load(path: &Path) {
+                read(path).unwrap_or_else(die);
+                read(path).ok_or_else(fatal);
+                read(path).map_or_else(handlers[0], |x| x);
+                read(path).unwrap_or_else(|e| panic!("{}", e));
+                read(path).unwrap_or_else(Vec::new);
+                read(path).ok_or_else(Error::Missing);
+                read(path).unwrap_or_else(Default::default);
+                read(path).unwrap_or_else(exit_quietly);
+                read(path).unwrap_or(fallback);
+            }"#,
+        )
+        .unwrap();
+        let func = match &file.items[1] {
+            Item::Fn(f) => f,
+            _ => unreachable!(),
+        };
+        let allowed = vec!["exit_quietly".to_string()];
+        let helpers = Helpers {
+            opaque_fallbacks: true,
+            allowed_fns: &allowed,
+            ..Default::default()
+        };
+        let found = find_panics(&func.block, &ImportMap::new(&file.items), helpers);
+        let lines = found
+            .iter()
+            .map(|x| (x.span.start().line, x.kind))
+            .collect::<Vec<_>>();
+        // The closure's panic is found either way, constructors and allowed functions aren't
+        // counted
+        assert_eq!(
+            lines,
+            &[
+                (5, PanicKind::OpaqueFallback),
+                (6, PanicKind::OpaqueFallback),
+                (7, PanicKind::OpaqueFallback),
+                (8, PanicKind::Panic),
+            ]
+        );
+        assert!(found[..3].iter().all(|x| x.tags == [OPAQUE_FALLBACK]));
+        let found = find_panics(
+            &func.block,
+            &ImportMap::new(&file.items),
+            Helpers::default(),
+        );
+        assert_eq!(found.len(), 1);
+    }
+
+    #[test]
+    fn placeholders() {
+        let placeholder = |code: &str| {
+            let func: ItemFn = parse_str(code).unwrap();
+            is_placeholder(&func.block, &ImportMap::default())
+        };
+        assert!(placeholder("fn later() -> u8 { todo!() }"));
+        assert!(placeholder("fn later() { unimplemented!(\"soon\"); }"));
+        assert!(!placeholder("fn later() { panic!() }"));
+        assert!(!placeholder(
+            "fn later(x: u8) -> u8 { if x > 1 { return x; } unimplemented!() }"
+        ));
+    }
+
+    #[test]
+    fn std_preconditions() {
+        assert_eq!(
+            PRECONDITIONS.iter().map(|x| x.name).collect::<Vec<_>>(),
+            STD_PANICS
+        );
+        let func: ItemFn = parse_str(
+            r#"
+            fn parse(data: &mut [u8], out: &mut [u8], n: u32) {
+                let (head, tail) = data.split_at(4);
+                out.copy_from_slice(head);
+                for _ in tail.chunks_exact(2) {}
+                data.swap(0);
+                let _ = NonZeroU32::new(n).unwrap();
+                let _ = NonZeroU32::new(n).unwrap_or(NonZeroU32::MIN);
+            }"#,
+        )
+        .unwrap();
+        let preconditions = PRECONDITIONS.iter().collect::<Vec<_>>();
+        let helpers = Helpers {
+            preconditions: &preconditions,
+            ..Default::default()
+        };
+        let found = find_panics(&func.block, &ImportMap::default(), helpers);
+        let names = found
+            .iter()
+            .map(|x| (x.kind, x.precondition.map(|x| x.name)))
+            .collect::<Vec<_>>();
+        // `swap` needs two indices, a one argument `swap` is something else
+        assert_eq!(
+            names,
+            &[
+                (PanicKind::PreconditionPanic, Some("split_at")),
+                (PanicKind::PreconditionPanic, Some("copy_from_slice")),
+                (PanicKind::PreconditionPanic, Some("chunks_exact")),
+                (PanicKind::Unwrap, Some("NonZero::new")),
+            ]
+        );
+        assert!(found.iter().all(|x| x.tags == [STD_PRECONDITION]));
+        // Without the table only the unwrap counts
+        let found = find_panics(&func.block, &ImportMap::default(), Helpers::default());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].precondition, None);
+    }
+}
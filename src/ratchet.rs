@@ -0,0 +1,157 @@
+//! A committed file of the most findings allowed in each source file. CI fails when a file has
+//! more than its count and lowers the counts as findings are fixed, so the number of undocumented
+//! panics can only go down. Only the counts are kept so renaming an item or moving it within the
+//! file doesn't break the check.
+use crate::report::Report;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "# The most findings allowed in each file, a file which isn't listed is \
+                      allowed none.\n# Lower the counts with `--update-ratchet` as findings are \
+                      fixed.\n";
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ratchet {
+    /// Keys are sorted so the file is written in the same order every time and each file is its
+    /// own line, keeping diffs and merge conflicts small
+    #[serde(default)]
+    pub files: BTreeMap<PathBuf, usize>,
+}
+
+/// A file with more findings than the ratchet allows
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Regression {
+    pub path: PathBuf,
+    pub allowed: usize,
+    pub found: usize,
+}
+
+impl Ratchet {
+    /// The number of findings in each file with any
+    pub fn from_report(report: &Report) -> Self {
+        let files = report
+            .files
+            .iter()
+            .filter(|x| !x.findings.is_empty())
+            .map(|x| (x.path.clone(), x.findings.len()))
+            .collect();
+        Self { files }
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let body =
+            toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, format!("{}\n{}", HEADER, body))
+    }
+
+    /// The files in `current` with more findings than this allows
+    pub fn regressions(&self, current: &Ratchet) -> Vec<Regression> {
+        current
+            .files
+            .iter()
+            .filter_map(|(path, &found)| {
+                let allowed = self.files.get(path).copied().unwrap_or_default();
+                if found > allowed {
+                    Some(Regression {
+                        path: path.clone(),
+                        allowed,
+                        found,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// The ratchet after tightening it to `current`, a count is never raised so a file which got
+    /// worse keeps its old count
+    pub fn tighten(&self, current: &Ratchet) -> Ratchet {
+        let files = current
+            .files
+            .iter()
+            .map(|(path, &found)| {
+                let allowed = self.files.get(path).copied().unwrap_or_default();
+                (path.clone(), found.min(allowed))
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        Ratchet { files }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ratchet(counts: &[(&str, usize)]) -> Ratchet {
+        Ratchet {
+            files: counts
+                .iter()
+                .map(|(path, count)| (PathBuf::from(path), *count))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn equal_counts_pass() {
+        let allowed = ratchet(&[("src/lib.rs", 2), ("src/main.rs", 1)]);
+        assert!(allowed.regressions(&allowed.clone()).is_empty());
+        assert!(allowed
+            .regressions(&ratchet(&[("src/lib.rs", 1)]))
+            .is_empty());
+    }
+
+    #[test]
+    fn regressions_name_the_file() {
+        let allowed = ratchet(&[("src/lib.rs", 2), ("src/main.rs", 1)]);
+        let current = ratchet(&[("src/lib.rs", 2), ("src/main.rs", 3), ("src/new.rs", 1)]);
+        assert_eq!(
+            allowed.regressions(&current),
+            vec![
+                Regression {
+                    path: PathBuf::from("src/main.rs"),
+                    allowed: 1,
+                    found: 3,
+                },
+                Regression {
+                    path: PathBuf::from("src/new.rs"),
+                    allowed: 0,
+                    found: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn improvements_are_locked_in() {
+        let path = std::env::temp_dir().join(format!(
+            "doc_panic_checker_ratchet-{}.toml",
+            std::process::id()
+        ));
+        let allowed = ratchet(&[("src/lib.rs", 3), ("src/main.rs", 1), ("src/old.rs", 2)]);
+        allowed.save(&path).unwrap();
+        let before = fs::read_to_string(&path).unwrap();
+        assert!(before.find("src/lib.rs").unwrap() < before.find("src/main.rs").unwrap());
+
+        let current = ratchet(&[("src/lib.rs", 1), ("src/main.rs", 1)]);
+        assert!(allowed.regressions(&current).is_empty());
+        Ratchet::load(&path)
+            .unwrap()
+            .tighten(&current)
+            .save(&path)
+            .unwrap();
+        let after = fs::read_to_string(&path).unwrap();
+        assert!(after.len() < before.len());
+        assert_eq!(Ratchet::load(&path).unwrap(), current);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,207 @@
+//! Folds findings which panic on the same line of code, like setters generated by a macro which
+//! all contain the same `expect`, so they don't bury everything else in the output.
+use crate::report::{FileReport, Finding, Report};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Caps how many entries the text and markdown formats show, so thousands of findings from a
+/// first run on a large crate don't bury the terminal. The other formats are read by tools and
+/// always have every finding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Limit {
+    pub per_file: usize,
+    pub total: usize,
+    /// Where the full report was written, if it was
+    pub full_report: Option<PathBuf>,
+}
+
+impl Limit {
+    /// The line saying how many findings were left out and where to find them
+    pub fn message(&self, hidden: usize) -> String {
+        match &self.full_report {
+            Some(path) => format!(
+                "… and {} more; see the full report in {}",
+                hidden,
+                path.display()
+            ),
+            None => format!(
+                "… and {} more; pass --no-limit to see them all or --format json for the full \
+                 report",
+                hidden
+            ),
+        }
+    }
+}
+
+/// The folded entries of a file which are shown
+pub struct Page<'a> {
+    pub file: &'a FileReport,
+    pub entries: Vec<Vec<&'a Finding>>,
+    /// How many of the file's findings were left out, by the per file limit or the total
+    pub cut: usize,
+}
+
+/// Gives findings in the same file with the same kind and panic line text a shared `group_id` if
+/// there are more than `threshold` of them. Any existing groups are replaced.
+pub fn group_repeated(report: &mut Report, threshold: usize) {
+    let mut next_id = 0;
+    for file in report.files.iter_mut() {
+        let mut groups: HashMap<_, Vec<usize>> = HashMap::new();
+        let mut order = vec![];
+        for (i, finding) in file.findings.iter_mut().enumerate() {
+            finding.group_id = None;
+            let line = match finding.context.get(1).map(|x| x.trim()) {
+                Some(line) if !line.is_empty() => line.to_string(),
+                _ => continue,
+            };
+            let key = (finding.kind, line);
+            if !groups.contains_key(&key) {
+                order.push(key.clone());
+            }
+            groups.entry(key).or_default().push(i);
+        }
+        for key in &order {
+            let members = &groups[key];
+            if members.len() > threshold {
+                for i in members {
+                    file.findings[*i].group_id = Some(next_id);
+                }
+                next_id += 1;
+            }
+        }
+    }
+}
+
+/// Splits findings into the entries shown to a human, each group becomes a single entry at the
+/// position of its first finding
+pub fn fold(findings: &[Finding]) -> Vec<Vec<&Finding>> {
+    let mut entries: Vec<Vec<&Finding>> = vec![];
+    let mut groups: HashMap<usize, usize> = HashMap::new();
+    for finding in findings {
+        match finding.group_id {
+            Some(id) => match groups.get(&id) {
+                Some(entry) => entries[*entry].push(finding),
+                None => {
+                    groups.insert(id, entries.len());
+                    entries.push(vec![finding]);
+                }
+            },
+            None => entries.push(vec![finding]),
+        }
+    }
+    entries
+}
+
+/// Folds the findings of each file and keeps the entries within the limit, returning them with
+/// the number of findings left out. The per file limit is applied first so one file with
+/// thousands of findings doesn't crowd out the rest, then files are shown in order until the
+/// total runs out.
+pub fn paginate<'a>(report: &'a Report, limit: Option<&Limit>) -> (Vec<Page<'a>>, usize) {
+    let count = |entries: &[Vec<&Finding>]| entries.iter().map(Vec::len).sum::<usize>();
+    let mut pages = vec![];
+    let mut left = limit.map_or(usize::MAX, |x| x.total);
+    let mut hidden = 0;
+    for file in report.files.iter().filter(|x| !x.findings.is_empty()) {
+        let mut entries = fold(&file.findings);
+        if let Some(limit) = limit {
+            entries.truncate(limit.per_file);
+        }
+        entries.truncate(left);
+        left -= entries.len();
+        let cut = file.findings.len() - count(&entries);
+        hidden += cut;
+        if !entries.is_empty() {
+            pages.push(Page { file, entries, cut });
+        }
+    }
+    (pages, hidden)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::FileReport;
+
+    fn setter(name: &str, line: &str) -> Finding {
+        Finding {
+            ident: format!("Builder::{}", name),
+            start_line: 1,
+            end_line: 3,
+            panic_line: 2,
+            context: vec!["fn foo() {".to_string(), line.to_string(), "}".to_string()],
+            ..Default::default()
+        }
+    }
+
+    fn report(repeats: usize) -> Report {
+        let mut findings = vec![setter("build", "self.inner.unwrap()")];
+        for i in 0..repeats {
+            findings.push(setter(&format!("set_{}", i), "  x.expect(\"validated\");"));
+        }
+        Report::new(vec![FileReport {
+            path: "src/builder.rs".into(),
+            symlink_target: None,
+            findings,
+        }])
+    }
+
+    #[test]
+    fn fold_above_threshold() {
+        let mut at_threshold = report(5);
+        group_repeated(&mut at_threshold, 5);
+        assert!(at_threshold.files[0]
+            .findings
+            .iter()
+            .all(|x| x.group_id.is_none()));
+        assert_eq!(fold(&at_threshold.files[0].findings).len(), 6);
+
+        let mut above = report(6);
+        group_repeated(&mut above, 5);
+        let findings = &above.files[0].findings;
+        assert_eq!(findings.len(), 7);
+        assert_eq!(findings[0].group_id, None);
+        assert!(findings[1..].iter().all(|x| x.group_id == Some(0)));
+        let folded = fold(findings);
+        assert_eq!(folded.len(), 2);
+        assert_eq!(folded[1].len(), 6);
+        assert_eq!(folded[1][0].ident, "Builder::set_0");
+    }
+
+    #[test]
+    fn pagination() {
+        let file = |path: &str, findings: usize| FileReport {
+            path: path.into(),
+            symlink_target: None,
+            findings: (0..findings)
+                .map(|i| setter(&format!("set_{}", i), &format!("x.expect(\"{}\");", i)))
+                .collect(),
+        };
+        let mut report = Report::new(vec![file("a.rs", 30), file("b.rs", 30), file("c.rs", 4)]);
+        // A folded group is a single entry
+        for finding in &mut report.files[1].findings[..10] {
+            finding.group_id = Some(0);
+        }
+        let limit = Limit {
+            per_file: 20,
+            total: 25,
+            full_report: None,
+        };
+        let (pages, hidden) = paginate(&report, Some(&limit));
+        let shown = pages
+            .iter()
+            .map(|x| (x.file.path.to_str().unwrap(), x.entries.len(), x.cut))
+            .collect::<Vec<_>>();
+        // The per file limit applies first, then what's left of the total. b.rs has 21 entries
+        // and only its first 5, covering 14 findings, fit.
+        assert_eq!(shown, &[("a.rs", 20, 10), ("b.rs", 5, 16)]);
+        assert_eq!(pages[1].entries[0].len(), 10);
+        assert_eq!(hidden, 10 + 16 + 4);
+        assert!(limit
+            .message(hidden)
+            .starts_with("… and 30 more; pass --no-limit"));
+
+        let (pages, hidden) = paginate(&report, None);
+        assert_eq!(pages.iter().map(|x| x.entries.len()).sum::<usize>(), 55);
+        assert_eq!(hidden, 0);
+    }
+}
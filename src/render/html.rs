@@ -0,0 +1,171 @@
+use super::write_atomic;
+use crate::report::{compact_ident, FileReport, Report};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Stylesheet shared by the pages written by `render_dir`
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }
+pre { background: #f6f8fa; padding: 0.5em; overflow-x: auto; }
+";
+
+pub fn render(report: &Report, writer: &mut impl Write) -> io::Result<()> {
+    write_header(writer, "Potentially undocumented panics", None)?;
+    for note in &report.notes {
+        writeln!(writer, "<p><em>{}</em></p>", escape(note))?;
+    }
+    if report.is_empty() {
+        writeln!(writer, "<p>No undocumented panics found.</p>")?;
+    }
+    for file in report.files.iter().filter(|x| !x.findings.is_empty()) {
+        writeln!(
+            writer,
+            "<h2>{}</h2>",
+            escape(&file.path.display().to_string())
+        )?;
+        render_findings(file, writer)?;
+    }
+    writeln!(writer, "</body>\n</html>")
+}
+
+/// Writes a page per source file with findings to `dir` along with an `index.html` summarising
+/// them, for projects where a single page would be unwieldy.
+pub fn render_dir(report: &Report, dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    write_atomic(&dir.join("style.css"), |writer| {
+        writer.write_all(STYLE.as_bytes())
+    })?;
+    let files = report
+        .files
+        .iter()
+        .filter(|x| !x.findings.is_empty())
+        .collect::<Vec<_>>();
+    for file in &files {
+        let title = file.path.display().to_string();
+        write_atomic(&dir.join(page_name(file)), |writer| {
+            write_header(writer, &title, Some("style.css"))?;
+            writeln!(writer, "<p><a href=\"index.html\">All files</a></p>")?;
+            render_findings(file, writer)?;
+            writeln!(writer, "</body>\n</html>")
+        })?;
+    }
+    write_atomic(&dir.join("index.html"), |writer| {
+        write_header(writer, "Potentially undocumented panics", Some("style.css"))?;
+        for note in &report.notes {
+            writeln!(writer, "<p><em>{}</em></p>", escape(note))?;
+        }
+        if files.is_empty() {
+            writeln!(writer, "<p>No undocumented panics found.</p>")?;
+        } else {
+            writeln!(writer, "<table>\n<tr><th>File</th><th>Findings</th></tr>")?;
+            for file in &files {
+                writeln!(
+                    writer,
+                    "<tr><td><a href=\"{}\">{}</a></td><td>{}</td></tr>",
+                    escape(&page_name(file)),
+                    escape(&file.path.display().to_string()),
+                    file.findings.len()
+                )?;
+            }
+            writeln!(writer, "</table>")?;
+        }
+        writeln!(writer, "</body>\n</html>")
+    })
+}
+
+/// Name of the page for a source file, its path with the separators flattened so files with the
+/// same name in different directories don't clash. Any `%` or `_` already in a component is
+/// escaped so `src/a_b.rs` and `src/a/b.rs` don't end up on the same page
+fn page_name(file: &FileReport) -> String {
+    let path = file
+        .path
+        .components()
+        .map(|x| {
+            x.as_os_str()
+                .to_string_lossy()
+                .replace('%', "%25")
+                .replace('_', "%5F")
+        })
+        .collect::<Vec<_>>()
+        .join("_");
+    format!("{}.html", path)
+}
+
+fn write_header(writer: &mut impl Write, title: &str, style: Option<&str>) -> io::Result<()> {
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html>\n<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>{}</title>", escape(title))?;
+    if let Some(style) = style {
+        writeln!(
+            writer,
+            "<link rel=\"stylesheet\" href=\"{}\">",
+            escape(style)
+        )?;
+    }
+    writeln!(writer, "</head>\n<body>")?;
+    writeln!(writer, "<h1>{}</h1>", escape(title))
+}
+
+fn render_findings(file: &FileReport, writer: &mut impl Write) -> io::Result<()> {
+    writeln!(writer, "<ul>")?;
+    for finding in &file.findings {
+        writeln!(
+            writer,
+            "<li><code>{}</code> lines {}-{}",
+            escape(&compact_ident(&finding.ident)),
+            finding.start_line,
+            finding.end_line
+        )?;
+        for tag in &finding.tags {
+            writeln!(writer, " <code>[{}]</code>", escape(tag))?;
+        }
+        for note in &finding.notes {
+            writeln!(writer, " {}", escape(note))?;
+        }
+        if let Some(snippet) = &finding.snippet {
+            writeln!(writer, "<pre><code>{}</code></pre>", escape(snippet))?;
+        }
+        writeln!(writer, "</li>")?;
+    }
+    writeln!(writer, "</ul>")
+}
+
+/// Escapes text so it can be placed inside an element or attribute
+pub fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(path: &str) -> String {
+        page_name(&FileReport {
+            path: path.into(),
+            symlink_target: None,
+            findings: vec![],
+        })
+    }
+
+    #[test]
+    fn page_names() {
+        assert_eq!(page("src/lib.rs"), "src_lib.rs.html");
+        assert_eq!(page("src/a/b.rs"), "src_a_b.rs.html");
+        assert_eq!(page("src/a_b.rs"), "src_a%5Fb.rs.html");
+        assert_ne!(page("src/a%5Fb.rs"), page("src/a_b.rs"));
+    }
+}
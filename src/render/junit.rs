@@ -0,0 +1,118 @@
+use crate::report::{compact_ident, FileReport, Finding, Report};
+use std::io::{self, Write};
+
+/// Renders the report as JUnit XML for CI systems which track test results. Each file is a test
+/// suite and each finding a test case, findings which are only notes pass and the rest fail.
+pub fn render(report: &Report, writer: &mut impl Write) -> io::Result<()> {
+    let summary = report.summary();
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<testsuites name="{}" tests="{}" failures="{}">"#,
+        env!("CARGO_PKG_NAME"),
+        summary.findings,
+        summary.failures
+    )?;
+    for file in report.files.iter().filter(|x| !x.findings.is_empty()) {
+        render_suite(file, &report.notes, writer)?;
+    }
+    writeln!(writer, "</testsuites>")
+}
+
+fn is_failure(finding: &Finding) -> bool {
+    finding.level() != "note"
+}
+
+fn render_suite(file: &FileReport, notes: &[String], writer: &mut impl Write) -> io::Result<()> {
+    let path = escape(&file.path.to_string_lossy().replace('\\', "/"));
+    writeln!(
+        writer,
+        r#"  <testsuite name="{}" tests="{}" failures="{}">"#,
+        path,
+        file.findings.len(),
+        file.findings.iter().filter(|x| is_failure(x)).count()
+    )?;
+    if !notes.is_empty() {
+        writeln!(writer, "    <properties>")?;
+        for note in notes {
+            writeln!(
+                writer,
+                r#"      <property name="note" value="{}"/>"#,
+                escape(note)
+            )?;
+        }
+        writeln!(writer, "    </properties>")?;
+    }
+    for finding in &file.findings {
+        let name = escape(&compact_ident(&finding.ident));
+        if !is_failure(finding) {
+            writeln!(
+                writer,
+                r#"    <testcase name="{}" classname="{}"/>"#,
+                name, path
+            )?;
+            continue;
+        }
+        let line = if finding.panic_line > 0 {
+            finding.panic_line
+        } else {
+            finding.start_line
+        };
+        writeln!(
+            writer,
+            r#"    <testcase name="{}" classname="{}">"#,
+            name, path
+        )?;
+        let mut details = finding
+            .tags
+            .iter()
+            .map(|x| format!("[{}]", x))
+            .chain(finding.notes.iter().cloned())
+            .collect::<Vec<_>>();
+        details.extend(finding.context.iter().cloned());
+        writeln!(
+            writer,
+            r#"      <failure message="{} at line {}" type="{}">{}</failure>"#,
+            finding.kind.code().replace('-', " "),
+            line,
+            finding.kind.code(),
+            escape(&details.join("\n"))
+        )?;
+        writeln!(writer, "    </testcase>")?;
+    }
+    writeln!(writer, "  </testsuite>")
+}
+
+/// Escapes text for use in XML content or attributes. Control characters other than whitespace
+/// aren't allowed in XML 1.0 at all, even escaped, so they're dropped.
+fn escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            '\n' => result.push_str("&#10;"),
+            '\t' | '\r' => result.push(c),
+            c if c.is_control() => {}
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_xml() {
+        assert_eq!(
+            escape("Buffer<u8>::push & \"it's\"\u{1b}"),
+            "Buffer&lt;u8&gt;::push &amp; &quot;it&apos;s&quot;"
+        );
+        assert_eq!(escape("a\nb"), "a&#10;b");
+    }
+}
@@ -0,0 +1,64 @@
+use super::fold::{paginate, Limit};
+use crate::report::{compact_ident, Report};
+use std::io::{self, Write};
+
+pub fn render(
+    report: &Report,
+    verbose: bool,
+    limit: Option<&Limit>,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    writeln!(writer, "# Potentially undocumented panics")?;
+    for note in &report.notes {
+        writeln!(writer, "\n> {}", note)?;
+    }
+    if report.is_empty() {
+        writeln!(writer, "\nNo undocumented panics found.")?;
+    }
+    let (pages, hidden) = paginate(report, limit);
+    for page in &pages {
+        writeln!(writer, "\n## `{}`\n", page.file.path.display())?;
+        for entry in &page.entries {
+            let finding = entry[0];
+            writeln!(
+                writer,
+                "- `{}` (lines {}-{})",
+                compact_ident(&finding.ident),
+                finding.start_line,
+                finding.end_line
+            )?;
+            if entry.len() > 1 {
+                writeln!(
+                    writer,
+                    "  and {} more panicking on the same line",
+                    entry.len() - 1
+                )?;
+                if verbose {
+                    for other in &entry[1..] {
+                        writeln!(writer, "  - `{}`", compact_ident(&other.ident))?;
+                    }
+                }
+            }
+            for tag in &finding.tags {
+                writeln!(writer, "  `[{}]`", tag)?;
+            }
+            for note in &finding.notes {
+                writeln!(writer, "  {}", note)?;
+            }
+            if let Some(snippet) = &finding.snippet {
+                writeln!(writer, "\n  ```rust")?;
+                for line in snippet.lines() {
+                    writeln!(writer, "  {}", line)?;
+                }
+                writeln!(writer, "  ```")?;
+            }
+        }
+        if page.cut > 0 {
+            writeln!(writer, "\n… and {} more in this file", page.cut)?;
+        }
+    }
+    if let Some(limit) = limit.filter(|_| hidden > 0) {
+        writeln!(writer, "\n{}", limit.message(hidden))?;
+    }
+    Ok(())
+}
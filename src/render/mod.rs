@@ -0,0 +1,280 @@
+//! Turns a `Report` into something a human or another tool can consume. Renderers only ever
+//! look at the report so they work the same for a fresh analysis or a report loaded from disk.
+use crate::report::Report;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use structopt::clap::arg_enum;
+
+mod fold;
+mod html;
+mod junit;
+mod markdown;
+mod sarif;
+mod short;
+mod text;
+
+pub use fold::{group_repeated, Limit};
+pub use html::render_dir as render_html_dir;
+pub use sarif::message as finding_message;
+pub use short::ERRORFORMAT;
+pub use text::render_skipped;
+
+arg_enum! {
+#[derive(Copy, Debug, Clone, Eq, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Markdown,
+    Sarif,
+    Html,
+    Junit,
+    Short,
+}
+}
+
+impl OutputFormat {
+    /// Formats meant to be read by people rather than other tools
+    pub fn is_human_readable(self) -> bool {
+        !matches!(
+            self,
+            OutputFormat::Json | OutputFormat::Sarif | OutputFormat::Short
+        )
+    }
+}
+
+/// Renders the report, `verbose` lists every finding in a folded group in human readable formats.
+/// The text and markdown formats only show the findings within `limit`.
+pub fn render(
+    report: &Report,
+    format: OutputFormat,
+    verbose: bool,
+    limit: Option<&Limit>,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => text::render(report, verbose, limit, writer),
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *writer, report)?;
+            writeln!(writer)
+        }
+        OutputFormat::Markdown => markdown::render(report, verbose, limit, writer),
+        OutputFormat::Sarif => sarif::render(report, writer),
+        OutputFormat::Html => html::render(report, writer),
+        OutputFormat::Junit => junit::render(report, writer),
+        OutputFormat::Short => short::render(report, writer),
+    }
+}
+
+/// Writes to a temporary file which is then renamed over `path` so a file is never seen half
+/// written
+pub fn write_atomic(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> io::Result<()>,
+) -> io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut writer = BufWriter::new(File::create(&tmp)?);
+    write(&mut writer)?;
+    writer.flush()?;
+    fs::rename(&tmp, path)
+}
+
+/// Replaces the generic arguments in an item's path with `<...>`, so
+/// `BTreeMap < String , Vec < u8 > > :: insert` becomes `BTreeMap<...>::insert`. Spaces are
+/// removed like they are when showing any other path.
+pub fn strip_generics(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut depth = 0usize;
+    let mut prev = ' ';
+    for c in s.chars() {
+        match c {
+            '<' => {
+                if depth == 0 {
+                    result.push_str("<...>");
+                }
+                depth += 1;
+            }
+            // The arrow in `Fn(u8) -> u8` doesn't close anything
+            '>' if prev != '-' => depth = depth.saturating_sub(1),
+            c if depth == 0 && !c.is_whitespace() => result.push(c),
+            _ => {}
+        }
+        if !c.is_whitespace() {
+            prev = c;
+        }
+    }
+    result
+}
+
+/// Shortens the idents of every finding with `strip_generics`
+pub fn strip_report_generics(report: &mut Report) {
+    for finding in report.files.iter_mut().flat_map(|x| x.findings.iter_mut()) {
+        finding.ident = strip_generics(&finding.ident);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_walker::FindingKind;
+    use crate::report::CompactReport;
+
+    fn fixture_report() -> Report {
+        let json = include_str!("../../tests/fixtures/report.json");
+        Report::from_json(serde_json::from_str(json).unwrap()).unwrap()
+    }
+
+    fn render_to_string(report: &Report, format: OutputFormat) -> String {
+        let mut output = vec![];
+        render(report, format, false, None, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn generics_are_stripped() {
+        assert_eq!(
+            strip_generics("BTreeMap < String , Vec < u8 > > :: insert"),
+            "BTreeMap<...>::insert"
+        );
+        assert_eq!(
+            strip_generics("Handler < Box < dyn Fn (u8) -> u8 > > :: call"),
+            "Handler<...>::call"
+        );
+        assert_eq!(
+            strip_generics("< Wrapper < T > as Trait > :: method"),
+            "<...>::method"
+        );
+        assert_eq!(strip_generics("prelude::Item::new"), "prelude::Item::new");
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let report = fixture_report();
+        let json = render_to_string(&report, OutputFormat::Json);
+        let reloaded = Report::from_json(serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(report, reloaded);
+    }
+
+    #[test]
+    fn json_to_markdown() {
+        let markdown = render_to_string(&fixture_report(), OutputFormat::Markdown);
+        assert_eq!(markdown, include_str!("../../tests/fixtures/report.md"));
+    }
+
+    #[test]
+    fn json_to_html_dir() {
+        let dir =
+            std::env::temp_dir().join(format!("doc_panic_checker_html_dir-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        render_html_dir(&fixture_report(), &dir).unwrap();
+        let mut files = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|x| x.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(
+            files,
+            &[
+                "index.html",
+                "src_buffer.rs.html",
+                "src_lib.rs.html",
+                "style.css"
+            ]
+        );
+        let index = std::fs::read_to_string(dir.join("index.html")).unwrap();
+        assert!(index.contains("<a href=\"src_buffer.rs.html\">src/buffer.rs</a></td><td>2</td>"));
+        let page = std::fs::read_to_string(dir.join("src_buffer.rs.html")).unwrap();
+        assert!(page.contains("<code>&lt;Sink as io::Write&gt;::flush</code>"));
+        assert!(!page.contains("parse_config"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn json_to_sarif() {
+        let sarif = render_to_string(&fixture_report(), OutputFormat::Sarif);
+        let sarif: serde_json::Value = serde_json::from_str(&sarif).unwrap();
+        let expected: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/fixtures/report.sarif")).unwrap();
+        assert_eq!(sarif, expected);
+    }
+
+    #[test]
+    fn finding_messages() {
+        let mut finding = fixture_report().files[0].findings[0].clone();
+        finding.notes.clear();
+        finding.kind = FindingKind::CallbackPropagation;
+        assert_eq!(
+            finding_message(&finding),
+            "`parse_config` calls a user provided callback which can panic through it, but \
+             doesn't document that the panic propagates"
+        );
+        finding.kind = FindingKind::ThinPanicDocs;
+        assert_eq!(
+            finding_message(&finding),
+            "`parse_config` has a \"Panics\" section which doesn't explain each way it can panic"
+        );
+    }
+
+    #[test]
+    fn json_to_junit() {
+        let junit = render_to_string(&fixture_report(), OutputFormat::Junit);
+        assert_eq!(junit, include_str!("../../tests/fixtures/report.junit.xml"));
+    }
+
+    #[test]
+    fn json_to_short() {
+        let short = render_to_string(&fixture_report(), OutputFormat::Short);
+        assert_eq!(short, include_str!("../../tests/fixtures/report.short"));
+    }
+
+    #[test]
+    fn json_to_html() {
+        let html = render_to_string(&fixture_report(), OutputFormat::Html);
+        assert_eq!(html, include_str!("../../tests/fixtures/report.html"));
+    }
+
+    #[test]
+    fn compacting_keeps_every_format() {
+        let report = fixture_report();
+        let compact = CompactReport::from(report.clone());
+        let json = serde_json::to_string_pretty(&compact).unwrap() + "\n";
+        assert_eq!(json, render_to_string(&report, OutputFormat::Json));
+        let expanded = compact.into_report();
+        for format in OutputFormat::variants() {
+            let format = format.parse().unwrap();
+            assert_eq!(
+                render_to_string(&expanded, format),
+                render_to_string(&report, format),
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn limits_only_apply_to_human_output() {
+        let report = fixture_report();
+        let limit = Limit {
+            per_file: 1,
+            total: 1,
+            full_report: Some("report.json".into()),
+        };
+        let limited = |format| {
+            let mut output = vec![];
+            render(&report, format, false, Some(&limit), &mut output).unwrap();
+            String::from_utf8(output).unwrap()
+        };
+        for format in [
+            OutputFormat::Json,
+            OutputFormat::Sarif,
+            OutputFormat::Junit,
+            OutputFormat::Short,
+            OutputFormat::Html,
+        ] {
+            assert_eq!(limited(format), render_to_string(&report, format));
+        }
+        let markdown = limited(OutputFormat::Markdown);
+        assert!(markdown.contains("\n… and 2 more; see the full report in report.json\n"));
+        assert!(!markdown.contains("src/buffer.rs"));
+    }
+}
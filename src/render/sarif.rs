@@ -0,0 +1,146 @@
+use crate::ast_walker::FindingKind;
+use crate::report::{compact_ident, Finding, Report};
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::path::Path;
+
+pub fn render(report: &Report, writer: &mut impl Write) -> io::Result<()> {
+    let mut kinds: Vec<FindingKind> = vec![];
+    for finding in report.files.iter().flat_map(|x| x.findings.iter()) {
+        if !kinds.contains(&finding.kind) {
+            kinds.push(finding.kind);
+        }
+    }
+    let rules = kinds
+        .iter()
+        .map(|kind| {
+            json!({
+                "id": kind.code(),
+                "shortDescription": {
+                    "text": kind.description()
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    let results = report
+        .files
+        .iter()
+        .flat_map(|file| file.findings.iter().map(move |x| result(&file.path, x)))
+        .collect::<Vec<_>>();
+
+    let mut sarif = json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "informationUri": "https://github.com/xd009642/doc_panic_checker",
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    });
+    if !report.notes.is_empty() {
+        let notifications = report
+            .notes
+            .iter()
+            .map(|x| json!({ "level": "note", "message": { "text": x } }))
+            .collect::<Vec<_>>();
+        sarif["runs"][0]["invocations"] = json!([{
+            "executionSuccessful": true,
+            "toolExecutionNotifications": notifications,
+        }]);
+    }
+    serde_json::to_writer_pretty(&mut *writer, &sarif)?;
+    writeln!(writer)
+}
+
+/// What the finding is, with its notes, as a sentence
+pub fn message(finding: &Finding) -> String {
+    let ident = compact_ident(&finding.ident);
+    let mut message = match finding.kind {
+        FindingKind::DocumentedPanic => format!("`{}` documents that it can panic", ident),
+        FindingKind::CallbackPropagation => format!(
+            "`{}` calls a user provided callback which can panic through it, but doesn't \
+             document that the panic propagates",
+            ident
+        ),
+        FindingKind::UndocumentedRisk => format!(
+            "`{}` looks like it can panic but has no \"Panics\" section",
+            ident
+        ),
+        FindingKind::ForeignUnwind => format!(
+            "`{}` can panic and unwind across an FFI boundary, which is undefined behaviour",
+            ident
+        ),
+        FindingKind::ReleaseBlocker => {
+            format!(
+                "`{}` isn't finished, it has a `todo!` or `unimplemented!`",
+                ident
+            )
+        }
+        FindingKind::ForeignNonExhaustive => format!(
+            "`{}` panics on variants of an enum from another crate which it doesn't know about",
+            ident
+        ),
+        FindingKind::ConfiguredPanicFn => format!(
+            "`{}` calls a function declared as panicking but doesn't document it",
+            ident
+        ),
+        FindingKind::DocSectionOrder => {
+            format!("`{}` has its documentation sections out of order", ident)
+        }
+        FindingKind::ThinPanicDocs => format!(
+            "`{}` has a \"Panics\" section which doesn't explain each way it can panic",
+            ident
+        ),
+        FindingKind::PanicInFallibleFn => format!(
+            "`{}` returns a `Result` but can panic instead of returning an error",
+            ident
+        ),
+        _ => format!("`{}` can panic but doesn't document it", ident),
+    };
+    for note in &finding.notes {
+        message.push(' ');
+        message.push_str(note);
+    }
+    message
+}
+
+fn result(path: &Path, finding: &Finding) -> Value {
+    let mut region = json!({
+        "startLine": finding.start_line,
+        "endLine": finding.end_line,
+    });
+    if let Some(snippet) = &finding.snippet {
+        region["snippet"] = json!({ "text": snippet });
+    }
+    let message = message(finding);
+    let mut result = json!({
+        "ruleId": finding.kind.code(),
+        "level": finding.level(),
+        "message": {
+            "text": message
+        },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": {
+                    "uri": path.to_string_lossy().replace('\\', "/"),
+                },
+                "region": region,
+            }
+        }]
+    });
+    if !finding.tags.is_empty() {
+        result["properties"]["tags"] = json!(finding.tags);
+    }
+    if !finding.attributes.is_empty() {
+        result["properties"]["attributes"] = json!(finding.attributes);
+    }
+    if let Some(group_id) = finding.group_id {
+        result["properties"]["groupId"] = json!(group_id);
+    }
+    result
+}
@@ -0,0 +1,135 @@
+use crate::report::{compact_ident, Finding, Report};
+use std::io::{self, Write};
+
+/// How to read the short format in vim, e.g. `:set errorformat=...` then `:cfile`
+pub const ERRORFORMAT: &str = "%f:%l:%c: %*[^:]: %t%*[a-z]: %m";
+
+/// Renders one line per finding for grep, awk and editors' quickfix lists:
+///
+/// ```text
+/// path:line:column: code: level: ident
+/// ```
+///
+/// The line and column are of the first panic, or the start of the item if the report doesn't
+/// have it, and the column is the 1-based display column. The code is the finding kind's code,
+/// the level is `error`, `warning` or `note` and the ident is compacted like everywhere else, so
+/// it only has spaces between words like `<T as Trait>::method`. Everything after the level is
+/// the ident. This is a stable format, changing it needs a note like a bump to `SCHEMA_VERSION`
+/// does.
+pub fn render(report: &Report, writer: &mut impl Write) -> io::Result<()> {
+    for file in &report.files {
+        let path = file.path.to_string_lossy().replace('\\', "/");
+        for finding in &file.findings {
+            let (line, column) = position(finding);
+            writeln!(
+                writer,
+                "{}:{}:{}: {}: {}: {}",
+                path,
+                line,
+                column,
+                finding.kind.code(),
+                finding.level(),
+                compact_ident(&finding.ident)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn position(finding: &Finding) -> (usize, usize) {
+    match (finding.panic_line, &finding.underline) {
+        (0, _) => (finding.start_line, 1),
+        (line, Some(underline)) => (line, underline.start + 1),
+        (line, None) => (line, 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_walker::FindingKind;
+    use crate::report::FileReport;
+    use regex::Regex;
+    use std::path::PathBuf;
+
+    #[test]
+    fn one_line_per_finding() {
+        let finding = |ident: &str, kind, panic_line, underline| Finding {
+            ident: ident.to_string(),
+            kind,
+            start_line: 10,
+            end_line: 20,
+            panic_line,
+            underline,
+            notes: vec!["(a note: with colons)".to_string()],
+            ..Default::default()
+        };
+        let report = Report::new(vec![FileReport {
+            path: PathBuf::from("src/map.rs"),
+            symlink_target: None,
+            findings: vec![
+                finding(
+                    "BTreeMap < String , Vec < u8 > > :: insert",
+                    FindingKind::UndocumentedPanic,
+                    14,
+                    Some(8..18),
+                ),
+                finding(
+                    "Handler < T > :: call",
+                    FindingKind::ContractViolation,
+                    0,
+                    None,
+                ),
+                finding(
+                    "< Handler < T > as From < u8 > > :: from",
+                    FindingKind::UndocumentedPanic,
+                    30,
+                    None,
+                ),
+            ],
+        }]);
+        let mut output = vec![];
+        render(&report, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(
+            output,
+            "src/map.rs:14:9: undocumented-panic: warning: BTreeMap<String,Vec<u8>>::insert\n\
+             src/map.rs:10:1: contract-violation: error: Handler<T>::call\n\
+             src/map.rs:30:1: undocumented-panic: warning: <Handler<T> as From<u8>>::from\n"
+        );
+
+        // The same shape `ERRORFORMAT` expects
+        let shape = Regex::new(r"^[^:]+:\d+:\d+: [a-z-]+: (error|warning|note): .+$").unwrap();
+        assert!(output.lines().all(|x| shape.is_match(x)));
+    }
+
+    #[test]
+    fn problem_matcher() {
+        let matcher: serde_json::Value =
+            serde_json::from_str(include_str!("../../.github/doc_panic_checker-matcher.json"))
+                .unwrap();
+        let pattern = &matcher["problemMatcher"][0]["pattern"][0];
+        let regex = Regex::new(pattern["regexp"].as_str().unwrap()).unwrap();
+        let group = |line: &str, field: &str| {
+            let index = pattern[field].as_u64().unwrap() as usize;
+            regex.captures(line).unwrap()[index].to_string()
+        };
+        let line = "src/map.rs:14:9: undocumented-panic: warning: BTreeMap<String,Vec<u8>>::insert";
+        assert_eq!(group(line, "file"), "src/map.rs");
+        assert_eq!(group(line, "line"), "14");
+        assert_eq!(group(line, "column"), "9");
+        assert_eq!(group(line, "code"), "undocumented-panic");
+        assert_eq!(group(line, "severity"), "warning");
+        assert_eq!(group(line, "message"), "BTreeMap<String,Vec<u8>>::insert");
+        // The ident is the rest of the line, spaces and all
+        let line = "src/other.rs:3:57: implicit-call: error: <W as From<u8>>::from";
+        assert_eq!(group(line, "code"), "implicit-call");
+        assert_eq!(group(line, "severity"), "error");
+        assert_eq!(group(line, "message"), "<W as From<u8>>::from");
+        let fixture = include_str!("../../tests/fixtures/report.short");
+        let line = fixture.lines().find(|x| x.contains(" as ")).unwrap();
+        assert_eq!(group(line, "message"), "<Sink as io::Write>::flush");
+        // GitHub only has errors and warnings, notes aren't annotated
+        assert!(!regex.is_match("src/main.rs:3:5: entry-point: note: main"));
+    }
+}
@@ -0,0 +1,460 @@
+use super::finding_message;
+use super::fold::{paginate, Limit};
+use crate::ast_walker::FindingKind;
+use crate::report::{Finding, Report, SkipReason};
+use crate::snippets::truncate;
+use miette::{
+    Diagnostic, GraphicalReportHandler, GraphicalTheme, LabeledSpan, MietteError,
+    MietteSpanContents, Severity, SourceCode, SourceSpan, SpanContents,
+};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::Path;
+use tracing::warn;
+
+/// Maximum number of characters shown for each line of source context
+const MAX_LINE_WIDTH: usize = 80;
+
+pub fn render(
+    report: &Report,
+    verbose: bool,
+    limit: Option<&Limit>,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    for note in &report.notes {
+        writeln!(writer, "{}", note)?;
+    }
+    let (pages, hidden) = paginate(report, limit);
+    for page in &pages {
+        let file = page.file;
+        warn!("Potentially undocumented panics in {}", file.path.display());
+        for entry in &page.entries {
+            let finding = entry[0];
+            writeln!(writer, "\t{}", finding)?;
+            if entry.len() > 1 {
+                writeln!(
+                    writer,
+                    "\t  and {} more panicking on the same line",
+                    entry.len() - 1
+                )?;
+                if verbose {
+                    for other in &entry[1..] {
+                        writeln!(writer, "\t    {}", other)?;
+                    }
+                }
+            }
+            render_context(&file.path, finding, GraphicalTheme::default(), writer)?;
+            if let Some(hint) = &finding.fix_hint {
+                writeln!(writer, "\t  hint: {}", hint.replace('\n', "\\n"))?;
+            }
+        }
+        if page.cut > 0 {
+            writeln!(writer, "\t… and {} more in this file", page.cut)?;
+        }
+    }
+    if let Some(limit) = limit.filter(|_| hidden > 0) {
+        writeln!(writer, "{}", limit.message(hidden))?;
+    }
+    Ok(())
+}
+
+/// Renders a table of how many files and items weren't checked for each reason, so skip rules
+/// which are too broad stand out
+pub fn render_skipped(report: &Report, writer: &mut impl Write) -> io::Result<()> {
+    if report.skipped.is_empty() {
+        return writeln!(writer, "Every item found was checked");
+    }
+    let width = report
+        .skipped
+        .keys()
+        .map(|x| x.description().len())
+        .max()
+        .unwrap_or_default();
+    writeln!(writer, "Unchecked:")?;
+    for (reason, count) in &report.skipped {
+        writeln!(
+            writer,
+            "\t{:<width$}  {:>5}",
+            reason.description(),
+            count,
+            width = width
+        )?;
+    }
+    let total = report
+        .skipped
+        .iter()
+        .filter(|(x, _)| !matches!(x, SkipReason::Unreadable | SkipReason::ParseError))
+        .map(|(_, count)| count)
+        .sum::<usize>();
+    writeln!(writer, "\t{:<width$}  {:>5}", "items", total, width = width)
+}
+
+/// The lines kept around a panic, numbered from where they are in the file. Findings only carry
+/// this context rather than the whole file so reports loaded from JSON render the same way
+#[derive(Debug)]
+struct Context {
+    name: String,
+    text: String,
+    /// Line in the file of the first line of `text`, counting from 1
+    first_line: usize,
+}
+
+impl SourceCode for Context {
+    fn read_span<'a>(
+        &'a self,
+        span: &SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+        let contents = self
+            .text
+            .read_span(span, context_lines_before, context_lines_after)?;
+        Ok(Box::new(MietteSpanContents::new_named(
+            self.name.clone(),
+            contents.data(),
+            *contents.span(),
+            contents.line() + self.first_line - 1,
+            contents.column(),
+            contents.line_count(),
+        )))
+    }
+}
+
+/// A finding shown like a compiler error, with its source, the panic underlined and what to do
+/// about it
+#[derive(Debug)]
+struct PanicDiagnostic<'a> {
+    finding: &'a Finding,
+    context: Context,
+    /// The underlined panic, or the whole panicking line if the finding has no underline
+    label: SourceSpan,
+}
+
+impl<'a> PanicDiagnostic<'a> {
+    /// Returns `None` for findings with no source to show, like audits from the docs
+    fn new(path: &Path, finding: &'a Finding) -> Option<Self> {
+        if finding.context.is_empty() {
+            return None;
+        }
+        let indent = finding
+            .context
+            .iter()
+            .filter(|x| !x.trim().is_empty())
+            .map(|x| x.len() - x.trim_start().len())
+            .min()
+            .unwrap_or_default();
+        // The context is the panicking line with one either side of it, padded for a panic on
+        // the first line of the file
+        let skipped = usize::from(finding.panic_line <= 1);
+        let mut text = String::new();
+        let mut label = None;
+        for (i, line) in finding.context.iter().enumerate().skip(skipped) {
+            let line = line.get(indent..).unwrap_or_default();
+            let shown = truncate(line, MAX_LINE_WIDTH);
+            if i == 1 {
+                // Stop the underline where the line was cut off, a panic past that point is
+                // pointed at the `...`
+                let visible = if shown == line {
+                    usize::MAX
+                } else {
+                    shown.chars().count() - 3
+                };
+                let (start, end) = match &finding.underline {
+                    Some(underline) if underline.start.saturating_sub(indent) >= visible => {
+                        (visible, visible + 3)
+                    }
+                    Some(underline) => (
+                        underline.start.saturating_sub(indent),
+                        underline.end.saturating_sub(indent).min(visible),
+                    ),
+                    None => (0, shown.chars().count()),
+                };
+                // The underline counts characters but miette wants bytes
+                let offset = |chars: usize| {
+                    text.len()
+                        + shown
+                            .char_indices()
+                            .nth(chars)
+                            .map_or(shown.len(), |(x, _)| x)
+                };
+                let (start, end) = (offset(start), offset(end.max(start)));
+                label = Some(SourceSpan::new(start.into(), end - start));
+            }
+            text.push_str(&shown);
+            text.push('\n');
+        }
+        Some(Self {
+            finding,
+            context: Context {
+                name: path.display().to_string(),
+                text,
+                first_line: finding.panic_line.max(2) - 1,
+            },
+            label: label?,
+        })
+    }
+}
+
+impl fmt::Display for PanicDiagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", finding_message(self.finding))
+    }
+}
+
+impl Error for PanicDiagnostic<'_> {}
+
+impl Diagnostic for PanicDiagnostic<'_> {
+    fn code<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        Some(Box::new(self.finding.kind.code()))
+    }
+
+    fn severity(&self) -> Option<Severity> {
+        Some(match self.finding.level() {
+            "error" => Severity::Error,
+            "note" => Severity::Advice,
+            _ => Severity::Warning,
+        })
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn fmt::Display + 'a>> {
+        help(self.finding.kind).map(|x| Box::new(x) as Box<dyn fmt::Display>)
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.context)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let label = LabeledSpan::new_with_span(Some("panics here".to_string()), self.label);
+        Some(Box::new(std::iter::once(label)))
+    }
+}
+
+/// Renders the source around a finding with miette, indented to sit under the finding
+fn render_context(
+    path: &Path,
+    finding: &Finding,
+    theme: GraphicalTheme,
+    writer: &mut impl Write,
+) -> io::Result<()> {
+    let diagnostic = match PanicDiagnostic::new(path, finding) {
+        Some(diagnostic) => diagnostic,
+        None => return Ok(()),
+    };
+    let mut rendered = String::new();
+    GraphicalReportHandler::new_themed(theme)
+        .render_report(&mut rendered, &diagnostic)
+        .map_err(io::Error::other)?;
+    for line in rendered.lines().filter(|x| !x.trim().is_empty()) {
+        writeln!(writer, "\t{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+/// What to do about a finding, shown under its source like a compiler's help message
+fn help(kind: FindingKind) -> Option<&'static str> {
+    match kind {
+        FindingKind::UndocumentedPanic | FindingKind::ImplicitCall => {
+            Some("add a \"Panics\" section to the docs saying when it panics")
+        }
+        FindingKind::ThinPanicDocs => {
+            Some("explain each way it can panic in the \"Panics\" section")
+        }
+        FindingKind::CallbackPropagation => {
+            Some("say in the \"Panics\" section that panics in the callback propagate")
+        }
+        FindingKind::ContractViolation => {
+            Some("return an error or move the panic out of the trait method")
+        }
+        FindingKind::ForeignUnwind => Some("catch the panic with `std::panic::catch_unwind`"),
+        FindingKind::ReleaseBlocker => Some("finish implementing it before releasing"),
+        FindingKind::ForeignNonExhaustive => {
+            Some("return an error or log the unknown variant instead of panicking")
+        }
+        FindingKind::ConfiguredPanicFn => {
+            Some("add a \"Panics\" section saying when it reaches the panicking function")
+        }
+        FindingKind::DocSectionOrder => {
+            Some("move the section to where the expected order puts it")
+        }
+        FindingKind::PanicInFallibleFn => Some("return an `Err` instead of panicking"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_walker::PanicKind;
+    use crate::report::FileReport;
+    use std::path::PathBuf;
+
+    #[test]
+    fn skipped_table() {
+        let mut report = Report::new(vec![]);
+        let mut output = vec![];
+        render_skipped(&report, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Every item found was checked\n"
+        );
+
+        report.skipped.insert(SkipReason::ParseError, 1);
+        report.skipped.insert(SkipReason::AllowRegex, 12);
+        report.skipped.insert(SkipReason::Attribute, 3);
+        let mut output = vec![];
+        render_skipped(&report, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Unchecked:\n\
+             \tfiles which couldn't be parsed          1\n\
+             \titems matching --allow-regex           12\n\
+             \titems filtered by their attributes      3\n\
+             \titems                                  15\n"
+        );
+    }
+
+    #[test]
+    fn fix_hints_on_one_line() {
+        let finding = Finding {
+            ident: "parse".to_string(),
+            start_line: 1,
+            end_line: 3,
+            panic_line: 2,
+            fix_hint: PanicKind::Panic.fix_hint().map(String::from),
+            ..Default::default()
+        };
+        let report = Report::new(vec![FileReport {
+            path: PathBuf::from("src/lib.rs"),
+            symlink_target: None,
+            findings: vec![finding],
+        }]);
+        let mut output = vec![];
+        render(&report, false, None, &mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\tparse 1:3\n\
+             \t  hint: add '# Panics\\n\\nPanics when <condition>.' to the doc comment\n"
+        );
+    }
+
+    #[test]
+    fn context_is_dedented_and_truncated() {
+        let long_line = format!("        let x = {}.unwrap();", "a".repeat(100));
+        let finding = Finding {
+            ident: "foo".to_string(),
+            start_line: 1,
+            end_line: 4,
+            panic_line: 3,
+            context: vec!["    fn foo() {".to_string(), long_line, "    }".to_string()],
+            ..Default::default()
+        };
+        let mut output = vec![];
+        render_context(
+            Path::new("src/lib.rs"),
+            &finding,
+            GraphicalTheme::unicode_nocolor(),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "\tundocumented-panic");
+        assert_eq!(lines[1], "\t  ⚠ `foo` can panic but doesn't document it");
+        assert_eq!(lines[2], "\t   ╭─[src/lib.rs:3:1]");
+        assert_eq!(lines[3], "\t 2 │ fn foo() {");
+        assert!(lines[4].starts_with("\t 3 │     let x = aaa"));
+        assert!(lines[4].ends_with("..."));
+        assert_eq!(lines[4].chars().count(), MAX_LINE_WIDTH + 6);
+        assert_eq!(lines[7], "\t 4 │ }");
+        assert_eq!(lines[8], "\t   ╰────");
+        assert_eq!(
+            lines[9],
+            "\t  help: add a \"Panics\" section to the docs saying when it panics"
+        );
+    }
+
+    #[test]
+    fn panic_is_underlined() {
+        let mut finding = Finding {
+            ident: "foo".to_string(),
+            start_line: 1,
+            end_line: 3,
+            panic_line: 2,
+            context: vec![
+                "    fn foo() {".to_string(),
+                "        let x = \"数\".parse::<u8>().unwrap();".to_string(),
+                "    }".to_string(),
+            ],
+            underline: Some(8..41),
+            ..Default::default()
+        };
+        let mut output = vec![];
+        render_context(
+            Path::new("src/lib.rs"),
+            &finding,
+            GraphicalTheme::unicode_nocolor(),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines[2], "\t   ╭─[src/lib.rs:2:5]");
+        assert_eq!(lines[3], "\t 1 │ fn foo() {");
+        assert_eq!(
+            lines[4],
+            "\t 2 │     let x = \"数\".parse::<u8>().unwrap();"
+        );
+        // The wide character takes up two columns
+        assert_eq!(
+            lines[5],
+            format!("\t   ·     {}┬{}", "─".repeat(17), "─".repeat(16))
+        );
+        assert_eq!(
+            lines[6],
+            format!("\t   ·     {}╰── panics here", " ".repeat(17))
+        );
+        assert_eq!(lines[7], "\t 3 │ }");
+
+        finding.context[1].push_str(&" ".repeat(100));
+        finding.context[1].push_str("// padding");
+        finding.underline = Some(8..200);
+        let mut output = vec![];
+        render_context(
+            Path::new("src/lib.rs"),
+            &finding,
+            GraphicalTheme::unicode_nocolor(),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let underline = output.lines().nth(5).unwrap();
+        assert_eq!(
+            underline
+                .trim_start_matches(&['\t', ' ', '·'][..])
+                .chars()
+                .count(),
+            // Up to the ellipsis, the wide character takes two columns so one less character fits
+            // before it but it's underlined twice as wide
+            MAX_LINE_WIDTH - 3 - 4
+        );
+
+        // A panic past where the line is cut off points at the ellipsis
+        finding.context[1] = format!("        let x = {}.unwrap();", "a".repeat(100));
+        finding.underline = Some(110..119);
+        let mut output = vec![];
+        render_context(
+            Path::new("src/lib.rs"),
+            &finding,
+            GraphicalTheme::unicode_nocolor(),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert!(lines[4].ends_with("..."));
+        let column = |line: &str, c: char| line.chars().position(|x| x == c).unwrap();
+        assert_eq!(column(lines[5], '┬'), column(lines[4], '.') + 1);
+    }
+}
@@ -0,0 +1,821 @@
+use crate::ast_walker::{FindingKind, PanicLocation};
+use crate::snippets::{extract, LineColRange};
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// Version of the JSON report layout. Bump this whenever a field is added, removed or changes
+/// meaning so older reports are rejected instead of being rendered incorrectly.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// The results of analysing a project, independent of how they're going to be displayed.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub schema_version: u32,
+    /// Caveats about how the report was made which a reader needs to know
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    pub files: Vec<FileReport>,
+    /// How many source files were analysed, including the ones without findings
+    #[serde(default)]
+    pub files_checked: usize,
+    /// How many public items were checked in those files, including the ones without findings
+    #[serde(default)]
+    pub items_checked: usize,
+    /// Problems running the analysis, like source files which couldn't be read or parsed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// The crate level docs, like the `//!` comments of the crate root, mention panics
+    #[serde(default)]
+    pub crate_docs_mention_panics: bool,
+    /// The crate root has `#![no_std]`, so a panic halts or resets the target
+    #[serde(default)]
+    pub no_std: bool,
+    /// The release profile in the manifest sets `panic = "abort"`
+    #[serde(default)]
+    pub panic_abort: bool,
+    /// Files and items whose source has nothing which looks like a panic were skipped without
+    /// checking them, see `Options::prefilter`
+    #[serde(default)]
+    pub prefiltered: bool,
+    /// The findings are from `cargo expand` output, so their line numbers are in the expanded
+    /// file rather than the crate's own sources
+    #[serde(default)]
+    pub expanded_source: bool,
+    /// Counts for each top level module, only present if asked for
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modules: Vec<ModuleStats>,
+    /// What happened to the source files found in the project
+    #[serde(default)]
+    pub discovery: Discovery,
+    /// How many files or items weren't checked, or had their finding left out, for each reason
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub skipped: BTreeMap<SkipReason, usize>,
+}
+
+/// Why a file or item wasn't checked, or why its finding was left out of the report
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// A source file couldn't be read, counted per file
+    Unreadable,
+    /// A source file couldn't be parsed so none of its items were checked, counted per file
+    ParseError,
+    /// The item matched an `--allow-regex`
+    AllowRegex,
+    /// The item only delegates to another function and `--skip-delegations` was given
+    Delegation,
+    /// The item has fewer panic sites than `--min-panic-sites`
+    MinPanicSites,
+    /// The item's attributes, like `#[deprecated]`, were filtered by `--filter-attr` or
+    /// `--exclude-attr`
+    Attribute,
+    /// An impl generated by a derive macro in `cargo expand` output
+    DeriveResidue,
+}
+
+impl SkipReason {
+    pub fn description(self) -> &'static str {
+        match self {
+            SkipReason::Unreadable => "files which couldn't be read",
+            SkipReason::ParseError => "files which couldn't be parsed",
+            SkipReason::AllowRegex => "items matching --allow-regex",
+            SkipReason::Delegation => "delegations left out by --skip-delegations",
+            SkipReason::MinPanicSites => "items with fewer than --min-panic-sites",
+            SkipReason::Attribute => "items filtered by their attributes",
+            SkipReason::DeriveResidue => "derived impls in expanded source",
+        }
+    }
+}
+
+/// Counts of the source files found when walking the project and why the ones which weren't
+/// analysed were left out, so a run which analysed nothing can say why
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Discovery {
+    /// Source files found, including in build dependencies
+    pub files_walked: usize,
+    /// Files matching an `--exclude-files` pattern
+    pub excluded_by_glob: usize,
+    /// Integration tests under `tests`, which are never analysed
+    pub tests: usize,
+    /// Examples, which are only analysed with `--include-examples`
+    pub examples: usize,
+    /// Files which couldn't be read
+    pub unreadable: usize,
+}
+
+impl fmt::Display for Discovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} files walked, {} excluded by --exclude-files, {} tests and {} examples skipped, \
+             {} unreadable",
+            self.files_walked, self.excluded_by_glob, self.tests, self.examples, self.unreadable
+        )
+    }
+}
+
+/// Counts of the public items in a top level module, for tracking how dense the panics are. The
+/// module is the first part of the file's path under `src`, or the inline module for items in the
+/// crate root, and items are counted where they're defined even if they're re-exported elsewhere.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleStats {
+    /// The module name, `crate` for items directly in the crate root
+    pub module: String,
+    /// Public functions and methods which were checked
+    pub items: usize,
+    /// Items which contain something which can panic, whether or not it's documented
+    pub items_panicking: usize,
+    /// Panicking items with a finding other than a documented panic or thin panic docs, these
+    /// are counted whether or not the finding was suppressed
+    pub items_undocumented: usize,
+    /// Source lines of all the items including their docs and attributes, the findings per
+    /// thousand lines is `1000 * items_undocumented / lines`
+    pub lines: usize,
+}
+
+/// Totals for a report, shared by the outputs which show them
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Summary {
+    pub findings: usize,
+    /// Findings which are warnings or errors rather than notes
+    pub failures: usize,
+    pub files_checked: usize,
+    #[serde(default)]
+    pub items_checked: usize,
+    /// Problems running the analysis
+    pub warnings: usize,
+    /// Findings for unfinished items which shouldn't be released
+    pub release_blockers: usize,
+}
+
+impl fmt::Display for Summary {
+    /// The line printed when a run succeeds, which scripts can look for to check the tool ran.
+    /// It starts `OK` unless there are findings which aren't notes, like the status file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let outcome = if self.failures > 0 { "FINDINGS" } else { "OK" };
+        write!(
+            f,
+            "{}: {} ({} findings, {} items checked)",
+            env!("CARGO_PKG_NAME"),
+            outcome,
+            self.findings,
+            self.items_checked
+        )
+    }
+}
+
+/// All the findings for a single source file
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FileReport {
+    /// Path of the file relative to the project root
+    pub path: PathBuf,
+    /// The real file if `path` is a symlink
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symlink_target: Option<PathBuf>,
+    pub findings: Vec<Finding>,
+}
+
+/// A public item which can panic without documenting it
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub ident: String,
+    #[serde(default)]
+    pub kind: FindingKind,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// The line of the first expression which can panic
+    #[serde(default)]
+    pub panic_line: usize,
+    /// The line before, of and after `panic_line`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context: Vec<String>,
+    /// The display columns of the first expression which can panic in the middle line of
+    /// `context`, with tabs expanded to four columns
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underline: Option<Range<usize>>,
+    /// Attributes on the item which help triage it, like `must_use` or `deprecated`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<String>,
+    /// Short machine readable labels describing the context of the panics
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Explanations to help the user understand the finding
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Source code of the item, only present if snippets were embedded when the report was made
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    /// Edit adding a stub `# Panics` section to the docs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suggestion: Option<Suggestion>,
+    /// Shared by findings in the same file which panic on the same line of code, human readable
+    /// output shows each group as one entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<usize>,
+    /// What to do about the first panic, from its kind
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fix_hint: Option<String>,
+    /// The level set for the kind of finding by a `.doc-panic.toml`, if it's not the default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<String>,
+}
+
+/// A fix laid out like a span in rustc's JSON diagnostics so tools which apply compiler
+/// suggestions can apply it. Byte offsets are into the file the finding is in.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub byte_start: usize,
+    pub byte_end: usize,
+    pub line_start: usize,
+    pub line_end: usize,
+    pub column_start: usize,
+    pub column_end: usize,
+    pub suggested_replacement: String,
+    pub suggestion_applicability: Applicability,
+}
+
+/// How confident we are the suggestion is right, matches rustc's names
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Applicability {
+    /// The stub needs the panic conditions filling in by hand
+    MaybeIncorrect,
+}
+
+impl Report {
+    pub fn new(files: Vec<FileReport>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            notes: vec![],
+            files,
+            files_checked: 0,
+            items_checked: 0,
+            warnings: vec![],
+            crate_docs_mention_panics: false,
+            no_std: false,
+            panic_abort: false,
+            prefiltered: false,
+            expanded_source: false,
+            modules: vec![],
+            discovery: Discovery::default(),
+            skipped: BTreeMap::new(),
+        }
+    }
+
+    pub fn summary(&self) -> Summary {
+        let findings = self.files.iter().flat_map(|x| x.findings.iter());
+        Summary {
+            findings: findings.clone().count(),
+            failures: findings.clone().filter(|x| x.level() != "note").count(),
+            release_blockers: findings
+                .filter(|x| x.kind == FindingKind::ReleaseBlocker)
+                .count(),
+            files_checked: self.files_checked,
+            items_checked: self.items_checked,
+            warnings: self.warnings.len(),
+        }
+    }
+
+    /// Loads a report previously written with `--format json`
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let value: serde_json::Value = serde_json::from_reader(reader)?;
+        Self::from_json(value)
+    }
+
+    pub fn from_json(value: serde_json::Value) -> io::Result<Self> {
+        match value.get("schema_version").and_then(|x| x.as_u64()) {
+            Some(v) if v == u64::from(SCHEMA_VERSION) => {}
+            Some(v) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "report schema version {} is not supported (expected {})",
+                        v, SCHEMA_VERSION
+                    ),
+                ))
+            }
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "report is missing a schema_version",
+                ))
+            }
+        }
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Releases the spare capacity left over from building the report, which adds up in reports
+    /// with many findings
+    pub fn shrink_to_fit(&mut self) {
+        self.notes.shrink_to_fit();
+        self.warnings.shrink_to_fit();
+        self.modules.shrink_to_fit();
+        self.files.shrink_to_fit();
+        for file in &mut self.files {
+            file.findings.shrink_to_fit();
+            file.findings.iter_mut().for_each(Finding::shrink_to_fit);
+        }
+    }
+
+    /// Returns true if nothing was found
+    pub fn is_empty(&self) -> bool {
+        self.files.iter().all(|x| x.findings.is_empty())
+    }
+
+    /// Combines the reports of several projects analysed together, each given with the label its
+    /// file paths, modules, notes and warnings are prefixed with. The counts are added up and a
+    /// crate wide flag like `no_std` is set if it's set for any of them.
+    pub fn merge(reports: Vec<(String, Report)>) -> Self {
+        let mut merged = Report::new(vec![]);
+        for (label, report) in reports {
+            merged
+                .notes
+                .extend(report.notes.iter().map(|x| format!("{}: {}", label, x)));
+            merged.files.extend(report.files.into_iter().map(|mut x| {
+                x.path = Path::new(&label).join(&x.path);
+                x
+            }));
+            merged.files_checked += report.files_checked;
+            merged.items_checked += report.items_checked;
+            merged
+                .warnings
+                .extend(report.warnings.iter().map(|x| format!("{}: {}", label, x)));
+            merged.crate_docs_mention_panics |= report.crate_docs_mention_panics;
+            merged.no_std |= report.no_std;
+            merged.panic_abort |= report.panic_abort;
+            merged.prefiltered |= report.prefiltered;
+            merged.expanded_source |= report.expanded_source;
+            merged
+                .modules
+                .extend(report.modules.into_iter().map(|mut x| {
+                    x.module = format!("{}::{}", label, x.module);
+                    x
+                }));
+            let discovery = &mut merged.discovery;
+            discovery.files_walked += report.discovery.files_walked;
+            discovery.excluded_by_glob += report.discovery.excluded_by_glob;
+            discovery.tests += report.discovery.tests;
+            discovery.examples += report.discovery.examples;
+            discovery.unreadable += report.discovery.unreadable;
+            for (reason, count) in report.skipped {
+                *merged.skipped.entry(reason).or_default() += count;
+            }
+        }
+        merged
+    }
+}
+
+impl Finding {
+    /// Creates a finding for a panic location. If the source is provided the lines of the item
+    /// are embedded in the finding.
+    pub fn new(location: &PanicLocation, source: Option<&str>) -> Self {
+        let (start_line, end_line) = location.lines();
+        let snippet = source.map(|s| {
+            let lines = LineColRange {
+                start_line,
+                end_line,
+                ..Default::default()
+            };
+            extract(s, lines, 0).lines.join("\n")
+        });
+        let context = location.context();
+        Self {
+            ident: location.ident().to_string(),
+            kind: location.kind(),
+            start_line,
+            end_line,
+            panic_line: location.panic_line(),
+            context: context.window(context.span_line, 1),
+            underline: Some(context.underline.clone()),
+            attributes: location.attributes().to_vec(),
+            tags: location.tags().to_vec(),
+            notes: location.notes().to_vec(),
+            snippet,
+            suggestion: location.suggestion().cloned(),
+            group_id: None,
+            fix_hint: location.fix_hint().map(String::from),
+            level: None,
+        }
+    }
+
+    /// How serious the finding is, using SARIF's names for the levels
+    pub fn level(&self) -> &str {
+        self.level.as_deref().unwrap_or_else(|| self.kind.level())
+    }
+
+    fn shrink_to_fit(&mut self) {
+        self.ident.shrink_to_fit();
+        for strings in [
+            &mut self.context,
+            &mut self.attributes,
+            &mut self.tags,
+            &mut self.notes,
+        ] {
+            strings.shrink_to_fit();
+            strings.iter_mut().for_each(String::shrink_to_fit);
+        }
+        if let Some(snippet) = &mut self.snippet {
+            snippet.shrink_to_fit();
+        }
+        if let Some(suggestion) = &mut self.suggestion {
+            suggestion.suggested_replacement.shrink_to_fit();
+        }
+    }
+}
+
+/// Strings repeated across many findings, like file paths and the modules items are in, stored
+/// once and referred to by their index
+#[derive(Clone, Debug, Default)]
+struct StringTable {
+    strings: Vec<Box<str>>,
+    indices: HashMap<Box<str>, u32>,
+}
+
+impl StringTable {
+    fn intern(&mut self, s: &str) -> u32 {
+        if let Some(&index) = self.indices.get(s) {
+            return index;
+        }
+        let index = self.strings.len() as u32;
+        self.strings.push(s.into());
+        self.indices.insert(s.into(), index);
+        index
+    }
+
+    fn get(&self, index: u32) -> &str {
+        &self.strings[index as usize]
+    }
+}
+
+/// A finding whose ident has the path before its last `::` interned, `finding.ident` only holds
+/// the rest
+#[derive(Clone, Debug)]
+struct CompactFinding {
+    module: Option<u32>,
+    finding: Finding,
+}
+
+#[derive(Clone, Debug)]
+struct CompactFile {
+    path: u32,
+    symlink_target: Option<PathBuf>,
+    findings: Vec<CompactFinding>,
+}
+
+/// A report which stores each file path and module prefix once, for holding many findings while
+/// a large project is analysed. It serializes to the same JSON as the `Report` it holds, and
+/// `into_report` gives that back for rendering. Paths which aren't UTF-8 are stored lossily, they
+/// can't be written to JSON either.
+#[derive(Clone, Debug)]
+pub struct CompactReport {
+    strings: StringTable,
+    files: Vec<CompactFile>,
+    /// Everything but the files
+    report: Report,
+}
+
+impl From<Report> for CompactReport {
+    fn from(mut report: Report) -> Self {
+        let files = std::mem::take(&mut report.files);
+        let mut compact = Self {
+            strings: StringTable::default(),
+            files: Vec::with_capacity(files.len()),
+            report,
+        };
+        compact.extend(files);
+        compact
+    }
+}
+
+impl CompactReport {
+    /// Adds the findings of a file, interning its path and the modules of its idents
+    pub fn push(&mut self, file: FileReport) {
+        let strings = &mut self.strings;
+        let mut findings = file
+            .findings
+            .into_iter()
+            .map(|mut finding| {
+                let module = finding.ident.rfind("::").map(|i| {
+                    let module = strings.intern(&finding.ident[..i]);
+                    finding.ident.replace_range(..i + 2, "");
+                    module
+                });
+                finding.shrink_to_fit();
+                CompactFinding { module, finding }
+            })
+            .collect::<Vec<_>>();
+        findings.shrink_to_fit();
+        self.files.push(CompactFile {
+            path: strings.intern(&file.path.to_string_lossy()),
+            symlink_target: file.symlink_target,
+            findings,
+        });
+    }
+
+    /// The report with its strings expanded again
+    pub fn into_report(self) -> Report {
+        let strings = self.strings;
+        let files = self
+            .files
+            .into_iter()
+            .map(|file| FileReport {
+                path: PathBuf::from(strings.get(file.path)),
+                symlink_target: file.symlink_target,
+                findings: file
+                    .findings
+                    .into_iter()
+                    .map(|x| {
+                        let mut finding = x.finding;
+                        if let Some(module) = x.module {
+                            finding.ident = format!("{}::{}", strings.get(module), finding.ident);
+                        }
+                        finding
+                    })
+                    .collect(),
+            })
+            .collect();
+        Report {
+            files,
+            ..self.report
+        }
+    }
+}
+
+impl Extend<FileReport> for CompactReport {
+    fn extend<I: IntoIterator<Item = FileReport>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|x| self.push(x));
+    }
+}
+
+/// Serializes a field unless `skip` is true, like `#[serde(skip_serializing_if)]`
+macro_rules! serialize_field {
+    ($state:expr, $name:literal, $value:expr, $skip:expr) => {
+        if $skip {
+            $state.skip_field($name)?;
+        } else {
+            $state.serialize_field($name, $value)?;
+        }
+    };
+    ($state:expr, $name:literal, $value:expr) => {
+        $state.serialize_field($name, $value)?;
+    };
+}
+
+/// Borrows a compact file or finding along with the table its strings are in
+struct WithStrings<'a, T> {
+    strings: &'a StringTable,
+    value: &'a T,
+}
+
+/// An interned module and the rest of an ident, written as one string
+struct Ident<'a>(Option<&'a str>, &'a str);
+
+impl Serialize for Ident<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.0 {
+            Some(module) => serializer.collect_str(&format_args!("{}::{}", module, self.1)),
+            None => serializer.serialize_str(self.1),
+        }
+    }
+}
+
+impl Serialize for CompactReport {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let report = &self.report;
+        let files = self
+            .files
+            .iter()
+            .map(|value| WithStrings {
+                strings: &self.strings,
+                value,
+            })
+            .collect::<Vec<_>>();
+        let mut state = serializer.serialize_struct("Report", 14)?;
+        serialize_field!(state, "schema_version", &report.schema_version);
+        serialize_field!(state, "notes", &report.notes, report.notes.is_empty());
+        serialize_field!(state, "files", &files);
+        serialize_field!(state, "files_checked", &report.files_checked);
+        serialize_field!(state, "items_checked", &report.items_checked);
+        serialize_field!(
+            state,
+            "warnings",
+            &report.warnings,
+            report.warnings.is_empty()
+        );
+        serialize_field!(
+            state,
+            "crate_docs_mention_panics",
+            &report.crate_docs_mention_panics
+        );
+        serialize_field!(state, "no_std", &report.no_std);
+        serialize_field!(state, "panic_abort", &report.panic_abort);
+        serialize_field!(state, "prefiltered", &report.prefiltered);
+        serialize_field!(state, "expanded_source", &report.expanded_source);
+        serialize_field!(state, "modules", &report.modules, report.modules.is_empty());
+        serialize_field!(state, "discovery", &report.discovery);
+        serialize_field!(state, "skipped", &report.skipped, report.skipped.is_empty());
+        state.end()
+    }
+}
+
+impl Serialize for WithStrings<'_, CompactFile> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let file = self.value;
+        let findings = file
+            .findings
+            .iter()
+            .map(|value| WithStrings {
+                strings: self.strings,
+                value,
+            })
+            .collect::<Vec<_>>();
+        let mut state = serializer.serialize_struct("FileReport", 3)?;
+        serialize_field!(state, "path", self.strings.get(file.path));
+        serialize_field!(
+            state,
+            "symlink_target",
+            &file.symlink_target,
+            file.symlink_target.is_none()
+        );
+        serialize_field!(state, "findings", &findings);
+        state.end()
+    }
+}
+
+impl Serialize for WithStrings<'_, CompactFinding> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let x = &self.value.finding;
+        let module = self.value.module.map(|x| self.strings.get(x));
+        let mut state = serializer.serialize_struct("Finding", 15)?;
+        serialize_field!(state, "ident", &Ident(module, &x.ident));
+        serialize_field!(state, "kind", &x.kind);
+        serialize_field!(state, "start_line", &x.start_line);
+        serialize_field!(state, "end_line", &x.end_line);
+        serialize_field!(state, "panic_line", &x.panic_line);
+        serialize_field!(state, "context", &x.context, x.context.is_empty());
+        serialize_field!(state, "underline", &x.underline, x.underline.is_none());
+        serialize_field!(state, "attributes", &x.attributes, x.attributes.is_empty());
+        serialize_field!(state, "tags", &x.tags, x.tags.is_empty());
+        serialize_field!(state, "notes", &x.notes, x.notes.is_empty());
+        serialize_field!(state, "snippet", &x.snippet, x.snippet.is_none());
+        serialize_field!(state, "suggestion", &x.suggestion, x.suggestion.is_none());
+        serialize_field!(state, "group_id", &x.group_id, x.group_id.is_none());
+        serialize_field!(state, "fix_hint", &x.fix_hint, x.fix_hint.is_none());
+        serialize_field!(state, "level", &x.level, x.level.is_none());
+        state.end()
+    }
+}
+
+/// Removes the spaces `to_token_stream` puts between the tokens of an item's path, keeping those
+/// between words like in `<Config as From<RawConfig>>::from` or `Box<dyn Fn()>`, and before an
+/// `as` following generics like in `<Vec<u8> as Trait>::method`
+pub fn compact_ident(ident: &str) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut result = String::with_capacity(ident.len());
+    let mut chars = ident.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !c.is_whitespace() {
+            result.push(c);
+        } else if result.ends_with(is_word) && chars.peek().is_some_and(|x| is_word(*x))
+            || chars.clone().take(3).eq("as ".chars())
+        {
+            result.push(' ');
+        }
+    }
+    result
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}:{}",
+            compact_ident(&self.ident),
+            self.start_line,
+            self.end_line
+        )?;
+        for tag in &self.tags {
+            write!(f, " [{}]", tag)?;
+        }
+        for note in &self.notes {
+            write!(f, " {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reject_other_schema_versions() {
+        let old = serde_json::json!({"schema_version": 0, "files": []});
+        assert!(Report::from_json(old).is_err());
+        let missing = serde_json::json!({"files": []});
+        assert!(Report::from_json(missing).is_err());
+        let current = serde_json::json!({"schema_version": SCHEMA_VERSION, "files": []});
+        assert_eq!(Report::from_json(current).unwrap(), Report::new(vec![]));
+    }
+
+    #[test]
+    fn compacting_idents() {
+        assert_eq!(compact_ident("Buffer < u8 > :: push"), "Buffer<u8>::push");
+        assert_eq!(
+            compact_ident("< Config as From < RawConfig > > :: from"),
+            "<Config as From<RawConfig>>::from"
+        );
+        assert_eq!(
+            compact_ident("< Vec < u8 > as io :: Write > :: flush"),
+            "<Vec<u8> as io::Write>::flush"
+        );
+        assert_eq!(compact_ident("Box < dyn Fn () >"), "Box<dyn Fn()>");
+    }
+
+    #[test]
+    fn shrinking_keeps_findings() {
+        let json = include_str!("../tests/fixtures/report.json");
+        let report = Report::from_json(serde_json::from_str(json).unwrap()).unwrap();
+        let mut shrunk = report.clone();
+        shrunk.files[0].findings.reserve(100);
+        shrunk.files[0].findings[0].ident.reserve(100);
+        shrunk.shrink_to_fit();
+        assert_eq!(shrunk, report);
+        assert_eq!(shrunk.files[0].findings.capacity(), 1);
+    }
+
+    #[test]
+    fn compact_reports() {
+        let json = include_str!("../tests/fixtures/report.json");
+        let mut report = Report::from_json(serde_json::from_str(json).unwrap()).unwrap();
+        let mut other = report.files[0].clone();
+        other.path = PathBuf::from("src/other.rs");
+        other.symlink_target = Some(PathBuf::from("src/lib.rs"));
+        other.findings[0].ident = "Foo".to_string();
+        report.files.push(other);
+        report.files.push(report.files[0].clone());
+        let compact = CompactReport::from(report.clone());
+        assert_eq!(compact.files[0].path, compact.files[3].path);
+        assert_eq!(
+            serde_json::to_string_pretty(&compact).unwrap(),
+            serde_json::to_string_pretty(&report).unwrap()
+        );
+        assert_eq!(compact.into_report(), report);
+    }
+
+    #[test]
+    fn merging() {
+        let json = include_str!("../tests/fixtures/report.json");
+        let report = Report::from_json(serde_json::from_str(json).unwrap()).unwrap();
+        let mut other = report.clone();
+        other.notes = vec!["Checked with the embedded profile".to_string()];
+        other.no_std = true;
+        other.files_checked = 2;
+        other.discovery.files_walked = 3;
+        other.skipped.insert(SkipReason::AllowRegex, 1);
+        let mut merged = Report::merge(vec![
+            ("server".to_string(), report.clone()),
+            ("firmware".to_string(), other),
+        ]);
+        let expected = ["server", "firmware"]
+            .iter()
+            .flat_map(|label| {
+                report
+                    .files
+                    .iter()
+                    .map(move |x| Path::new(label).join(&x.path))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            merged
+                .files
+                .iter()
+                .map(|x| x.path.clone())
+                .collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(
+            merged.notes,
+            ["firmware: Checked with the embedded profile"]
+        );
+        assert!(merged.no_std);
+        assert_eq!(merged.summary().findings, 2 * report.summary().findings);
+        assert_eq!(merged.files_checked, report.files_checked + 2);
+        assert_eq!(
+            merged.discovery.files_walked,
+            report.discovery.files_walked + 3
+        );
+        assert_eq!(merged.skipped[&SkipReason::AllowRegex], 1);
+
+        // A single report keeps everything but the paths
+        merged = Report::merge(vec![("server".to_string(), report.clone())]);
+        merged.files = report.files.clone();
+        assert_eq!(merged, report);
+    }
+}
@@ -0,0 +1,277 @@
+//! Extracts the lines of source around a span for showing in a report. Columns are measured in
+//! display width, with tabs expanded and wide characters like CJK and emoji counting as two, so an
+//! underline lines up with the text above it however the line was indented or what's before it.
+use proc_macro2::Span;
+use std::ops::Range;
+use unicode_width::UnicodeWidthChar;
+
+/// How many columns a tab is expanded to unless told otherwise
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+/// A range of source code. Lines are 1-indexed and columns are 0-indexed counts of characters,
+/// the same as `proc_macro2` spans. Columns past the end of a line are clamped to it.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct LineColRange {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl From<Span> for LineColRange {
+    fn from(span: Span) -> Self {
+        Self {
+            start_line: span.start().line,
+            start_column: span.start().column,
+            end_line: span.end().line,
+            end_column: span.end().column,
+        }
+    }
+}
+
+impl LineColRange {
+    /// The part of the range on its first line
+    pub fn first_line(self) -> Self {
+        if self.end_line > self.start_line {
+            Self {
+                end_line: self.start_line,
+                end_column: usize::MAX,
+                ..self
+            }
+        } else {
+            self
+        }
+    }
+}
+
+/// Lines of source code with part of one underlined
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snippet {
+    /// The line number of the first line in `lines`
+    pub first_line: usize,
+    /// The source lines with tabs expanded and trailing whitespace removed
+    pub lines: Vec<String>,
+    /// The line the span starts on, which the underline goes under
+    pub span_line: usize,
+    /// The display columns to underline, a span covering several lines is underlined to the end of
+    /// its first line
+    pub underline: Range<usize>,
+    /// The span went past the end of the source so the snippet stops short of it
+    pub truncated: bool,
+}
+
+impl Snippet {
+    /// The `n` lines either side of `line`, with empty lines for any outside the snippet so
+    /// `line` is always in the middle
+    pub fn window(&self, line: usize, n: usize) -> Vec<String> {
+        (0..=2 * n)
+            .map(|i| {
+                (line + i)
+                    .checked_sub(n + self.first_line)
+                    .and_then(|i| self.lines.get(i))
+                    .cloned()
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+}
+
+/// Gets the lines of the span with `context` lines either side, expanding tabs to
+/// `DEFAULT_TAB_WIDTH` columns
+pub fn extract(src: &str, span: LineColRange, context: usize) -> Snippet {
+    extract_with_tab_width(src, span, context, DEFAULT_TAB_WIDTH)
+}
+
+pub fn extract_with_tab_width(
+    src: &str,
+    span: LineColRange,
+    context: usize,
+    tab_width: usize,
+) -> Snippet {
+    let lines = src.lines().collect::<Vec<_>>();
+    let truncated = span.end_line.max(span.start_line) > lines.len();
+    let last_line = lines.len().max(1);
+    let start_line = span.start_line.clamp(1, last_line);
+    let end_line = span.end_line.clamp(start_line, last_line);
+
+    let first_line = start_line.saturating_sub(context).max(1);
+    let last_line = (end_line + context).min(lines.len());
+    let snippet_lines = (first_line..=last_line)
+        .map(|i| expand_tabs(lines[i - 1], tab_width).trim_end().to_string())
+        .collect();
+
+    let line = lines.get(start_line - 1).copied().unwrap_or_default();
+    let line_width = display_column(line.trim_end(), usize::MAX, tab_width);
+    let underline = if span.start_line > lines.len() {
+        // Nothing left to underline, point just past the end of the source instead
+        line_width..line_width
+    } else {
+        let start = display_column(line, span.start_column, tab_width).min(line_width);
+        let end = if span.end_line > span.start_line {
+            line_width
+        } else {
+            display_column(line, span.end_column, tab_width).min(line_width)
+        };
+        start..end.max(start)
+    };
+    Snippet {
+        first_line,
+        lines: snippet_lines,
+        span_line: start_line,
+        underline,
+        truncated,
+    }
+}
+
+/// The display width of the first `chars` characters of the line
+fn display_column(line: &str, chars: usize, tab_width: usize) -> usize {
+    line.chars()
+        .take(chars)
+        .fold(0, |width, c| width + char_width(c, width, tab_width))
+}
+
+fn char_width(c: char, column: usize, tab_width: usize) -> usize {
+    if c == '\t' {
+        tab_width - column % tab_width.max(1)
+    } else {
+        c.width().unwrap_or_default()
+    }
+}
+
+fn expand_tabs(line: &str, tab_width: usize) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut width = 0;
+    for c in line.chars() {
+        let w = char_width(c, width, tab_width);
+        if c == '\t' {
+            result.extend(std::iter::repeat_n(' ', w));
+        } else {
+            result.push(c);
+        }
+        width += w;
+    }
+    result
+}
+
+/// Cuts the line down to at most `max_width` columns, ending it with `...` if anything was cut
+pub fn truncate(line: &str, max_width: usize) -> String {
+    if display_column(line, usize::MAX, DEFAULT_TAB_WIDTH) <= max_width {
+        return line.to_string();
+    }
+    let mut result = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        let w = char_width(c, width, DEFAULT_TAB_WIDTH);
+        if width + w > max_width.saturating_sub(3) {
+            break;
+        }
+        result.push(c);
+        width += w;
+    }
+    result.push_str("...");
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(line: usize, start_column: usize, end_column: usize) -> LineColRange {
+        LineColRange {
+            start_line: line,
+            start_column,
+            end_line: line,
+            end_column,
+        }
+    }
+
+    /// Draws the underline under the span's line like the text output does
+    fn underlined(snippet: &Snippet) -> String {
+        let line = &snippet.lines[snippet.span_line - snippet.first_line];
+        format!(
+            "{}\n{}{}",
+            line,
+            " ".repeat(snippet.underline.start),
+            "^".repeat(snippet.underline.len())
+        )
+    }
+
+    #[test]
+    fn after_tab_indented_block() {
+        let src = "fn foo() {\n\tif x {\n\t\ty();\n\t}\n\tz.unwrap();\n}";
+        let snippet = extract(src, range(5, 1, 11), 1);
+        assert_eq!(snippet.first_line, 4);
+        assert_eq!(snippet.lines, ["    }", "    z.unwrap();", "}"]);
+        assert_eq!(snippet.underline, 4..14);
+        assert_eq!(underlined(&snippet), "    z.unwrap();\n    ^^^^^^^^^^");
+
+        let snippet = extract_with_tab_width(src, range(5, 1, 11), 0, 8);
+        assert_eq!(snippet.lines, ["        z.unwrap();"]);
+        assert_eq!(snippet.underline, 8..18);
+
+        // A tab after other text only goes to the next tab stop
+        let snippet = extract("ab\tc.unwrap()", range(1, 3, 13), 0);
+        assert_eq!(snippet.lines, ["ab  c.unwrap()"]);
+        assert_eq!(snippet.underline, 4..14);
+    }
+
+    #[test]
+    fn emoji_before_unwrap() {
+        let src = "let x = f(\"🦀 数\").unwrap();";
+        let start = src.chars().position(|c| c == ')').unwrap() + 1;
+        let snippet = extract(src, range(1, start, start + 9), 0);
+        // The crab and the character are both two columns wide
+        assert_eq!(snippet.underline, 18..27);
+        assert_eq!(
+            underlined(&snippet),
+            format!("{}\n{}^^^^^^^^^", src, " ".repeat(18))
+        );
+    }
+
+    #[test]
+    fn span_ending_at_eof() {
+        let src = "fn foo() {\n    bar().unwrap()";
+        let snippet = extract(src, range(2, 4, 18), 2);
+        assert_eq!(snippet.first_line, 1);
+        assert_eq!(snippet.lines.len(), 2);
+        assert_eq!(snippet.underline, 4..18);
+        assert!(!snippet.truncated);
+
+        let snippet = extract(
+            src,
+            LineColRange {
+                start_line: 2,
+                start_column: 4,
+                end_line: 3,
+                end_column: 0,
+            },
+            1,
+        );
+        assert_eq!(snippet.lines.len(), 2);
+        assert_eq!(snippet.underline, 4..18);
+        assert!(snippet.truncated);
+
+        let snippet = extract(src, range(3, 0, 0), 1);
+        assert_eq!(snippet.span_line, 2);
+        assert_eq!(snippet.underline, 18..18);
+        assert!(snippet.truncated);
+
+        assert!(extract("", range(1, 0, 0), 1).lines.is_empty());
+    }
+
+    #[test]
+    fn windows() {
+        let src = "a\nb\nc";
+        let snippet = extract(src, range(1, 0, 1), 1);
+        assert_eq!(snippet.window(1, 1), ["", "a", "b"]);
+        let snippet = extract(src, range(3, 0, 1), 1);
+        assert_eq!(snippet.window(3, 1), ["b", "c", ""]);
+    }
+
+    #[test]
+    fn truncation_counts_wide_characters() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("数数数数数数", 10), "数数数...");
+        assert_eq!(truncate(&"a".repeat(20), 10), "aaaaaaa...");
+    }
+}
@@ -0,0 +1,170 @@
+//! A small JSON file saying how a run ended, for build systems which want to know whether the
+//! checker passed, found something or couldn't run without parsing its output. Unlike the report
+//! it's written even when the run fails before the analysis finishes.
+use crate::render::write_atomic;
+use crate::report::Report;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Version of the status file layout, bumped whenever a field is removed or changes meaning
+pub const STATUS_SCHEMA_VERSION: u32 = 1;
+
+/// The levels findings are counted at, always present in `Status::findings`
+const LEVELS: &[&str] = &["error", "warning", "note"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// The analysis ran and there's nothing which needs fixing
+    Ok,
+    /// The analysis ran and found warnings or errors, or failed the run because of its findings
+    Findings,
+    /// The run stopped before it could finish, the report may be missing or incomplete
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    pub schema_version: u32,
+    pub status: Outcome,
+    /// Why the run failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// The number of findings at each level
+    pub findings: BTreeMap<String, usize>,
+    pub files_checked: usize,
+}
+
+impl Status {
+    /// The status of a run which finished the analysis, `failure` is why its findings failed the
+    /// run if they did. A run without a report, like `info`, is ok.
+    pub fn finished(report: Option<&Report>, failure: Option<String>) -> Self {
+        let mut status = Self::counted(report, Outcome::Ok, failure);
+        if status.message.is_some() || report.is_some_and(|x| x.summary().failures > 0) {
+            status.status = Outcome::Findings;
+        }
+        status
+    }
+
+    /// The status of a run which stopped because of an error, counting the findings of its report
+    /// if it got as far as making one
+    pub fn error(report: Option<&Report>, message: String) -> Self {
+        Self::counted(report, Outcome::Error, Some(message))
+    }
+
+    fn counted(report: Option<&Report>, status: Outcome, message: Option<String>) -> Self {
+        let mut findings = LEVELS
+            .iter()
+            .map(|x| (x.to_string(), 0))
+            .collect::<BTreeMap<_, _>>();
+        let files = report.iter().flat_map(|x| x.files.iter());
+        for finding in files.flat_map(|x| x.findings.iter()) {
+            *findings.entry(finding.level().to_string()).or_default() += 1;
+        }
+        Self {
+            schema_version: STATUS_SCHEMA_VERSION,
+            status,
+            message,
+            findings,
+            files_checked: report.map(|x| x.files_checked).unwrap_or_default(),
+        }
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        write_atomic(path, |writer| {
+            serde_json::to_writer_pretty(&mut *writer, self)?;
+            writeln!(writer)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_walker::FindingKind;
+    use crate::report::{FileReport, Finding};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn report(levels: &[&str]) -> Report {
+        let finding = |level: &&str| Finding {
+            ident: "parse".to_string(),
+            kind: FindingKind::UndocumentedPanic,
+            start_line: 1,
+            end_line: 3,
+            panic_line: 2,
+            level: Some(level.to_string()),
+            ..Default::default()
+        };
+        let mut report = Report::new(vec![FileReport {
+            path: PathBuf::from("src/lib.rs"),
+            symlink_target: None,
+            findings: levels.iter().map(finding).collect(),
+        }]);
+        report.files_checked = 4;
+        report
+    }
+
+    fn written(status: &Status, name: &str) -> serde_json::Value {
+        let path = std::env::temp_dir().join(format!(
+            "doc_panic_checker_status_{}-{}.json",
+            name,
+            std::process::id()
+        ));
+        status.write(&path).unwrap();
+        let value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        value
+    }
+
+    #[test]
+    fn success() {
+        let status = Status::finished(Some(&report(&["note"])), None);
+        assert_eq!(
+            written(&status, "success"),
+            serde_json::json!({
+                "schema_version": STATUS_SCHEMA_VERSION,
+                "status": "ok",
+                "findings": {"error": 0, "warning": 0, "note": 1},
+                "files_checked": 4,
+            })
+        );
+        // Subcommands which don't analyse anything still pass
+        assert_eq!(Status::finished(None, None).status, Outcome::Ok);
+    }
+
+    #[test]
+    fn findings() {
+        let status = Status::finished(Some(&report(&["error", "warning", "warning"])), None);
+        let value = written(&status, "findings");
+        assert_eq!(value["status"], "findings");
+        assert_eq!(value["findings"]["warning"], 2);
+        assert_eq!(value["findings"]["error"], 1);
+        assert!(value.get("message").is_none());
+
+        // Notes alone only count when they failed the run, like with the ratchet
+        let failed = Status::finished(Some(&report(&["note"])), Some("ratchet".to_string()));
+        assert_eq!(failed.status, Outcome::Findings);
+        assert_eq!(failed.message.as_deref(), Some("ratchet"));
+    }
+
+    #[test]
+    fn errors() {
+        let status = Status::error(None, "Unable to read Cargo.toml".to_string());
+        let value = written(&status, "error");
+        assert_eq!(value["status"], "error");
+        assert_eq!(value["message"], "Unable to read Cargo.toml");
+        assert_eq!(value["files_checked"], 0);
+        assert_eq!(value["findings"]["error"], 0);
+
+        let missing = std::env::temp_dir()
+            .join(format!(
+                "doc_panic_checker_status_missing-{}",
+                std::process::id()
+            ))
+            .join("status.json");
+        assert!(status.write(&missing).is_err());
+    }
+}
@@ -0,0 +1,395 @@
+//! Works out which items of a crate can be reached from outside of it, following `mod`
+//! declarations from the crate root and `pub use` re-exports. This is the same set of items
+//! `cargo doc` shows, and each one is given the shortest path a user could name it by.
+use crate::imports::{flatten_use, UseEntry};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use syn::*;
+use tracing::debug;
+
+/// The publicly reachable items of a crate
+#[derive(Debug, Default)]
+pub struct Surface {
+    modules: HashMap<Vec<String>, Module>,
+    /// Module path of each source file in the crate
+    files: HashMap<PathBuf, Vec<String>>,
+    /// Shortest public path for each reachable item keyed by where it's defined
+    public_paths: HashMap<Vec<String>, Vec<String>>,
+    /// Whether `mod foo;` declarations are followed into their files
+    recurse_modules: bool,
+}
+
+#[derive(Debug, Default)]
+struct Module {
+    /// Items defined in the module and whether they're public
+    defs: HashMap<String, bool>,
+    uses: Vec<UseEntry>,
+}
+
+impl Surface {
+    /// Builds the surface of the crate with the given root file, typically `src/lib.rs`
+    pub fn new(crate_root: &Path) -> io::Result<Self> {
+        Self::build(crate_root, true)
+    }
+
+    /// Builds the surface from only the crate root file, modules declared with `mod foo;` are
+    /// left out and only inline modules are included
+    pub fn without_module_files(crate_root: &Path) -> io::Result<Self> {
+        Self::build(crate_root, false)
+    }
+
+    fn build(crate_root: &Path, recurse_modules: bool) -> io::Result<Self> {
+        let mut surface = Self {
+            recurse_modules,
+            ..Default::default()
+        };
+        let dir = crate_root
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        surface.add_file(crate_root, &dir, vec![])?;
+        surface.resolve_reachability();
+        Ok(surface)
+    }
+
+    fn add_file(&mut self, file: &Path, dir: &Path, path: Vec<String>) -> io::Result<()> {
+        let source = fs::read_to_string(file)?;
+        let parsed =
+            parse_file(&source).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.files.insert(file.to_path_buf(), path.clone());
+        self.add_module(&parsed.items, dir, path);
+        Ok(())
+    }
+
+    fn add_module(&mut self, items: &[Item], dir: &Path, path: Vec<String>) {
+        let mut module = Module::default();
+        for item in items {
+            let (name, vis) = match item {
+                Item::Fn(i) => (i.sig.ident.to_string(), &i.vis),
+                Item::Struct(i) => (i.ident.to_string(), &i.vis),
+                Item::Enum(i) => (i.ident.to_string(), &i.vis),
+                Item::Union(i) => (i.ident.to_string(), &i.vis),
+                Item::Trait(i) => (i.ident.to_string(), &i.vis),
+                Item::Type(i) => (i.ident.to_string(), &i.vis),
+                Item::Const(i) => (i.ident.to_string(), &i.vis),
+                Item::Static(i) => (i.ident.to_string(), &i.vis),
+                Item::Mod(i) => {
+                    let name = i.ident.to_string();
+                    let mut child = path.clone();
+                    child.push(name.clone());
+                    if let Some((_, items)) = &i.content {
+                        self.add_module(items, &dir.join(&name), child);
+                    } else if self.recurse_modules {
+                        self.add_module_file(i, dir, child);
+                    }
+                    (name, &i.vis)
+                }
+                Item::Use(i) => {
+                    let public = matches!(i.vis, Visibility::Public(_));
+                    flatten_use(&i.tree, vec![], public, &mut module.uses);
+                    continue;
+                }
+                _ => continue,
+            };
+            module
+                .defs
+                .insert(name, matches!(vis, Visibility::Public(_)));
+        }
+        self.modules.insert(path, module);
+    }
+
+    /// Finds the file for `mod foo;` either via a `#[path]` attribute, `foo.rs` or `foo/mod.rs`
+    fn add_module_file(&mut self, module: &ItemMod, dir: &Path, path: Vec<String>) {
+        let name = module.ident.to_string();
+        let explicit = module
+            .attrs
+            .iter()
+            .find_map(|attr| match attr.parse_meta() {
+                Ok(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(s),
+                    ..
+                })) if path.is_ident("path") => Some(dir.join(s.value())),
+                _ => None,
+            });
+        let candidates = match explicit {
+            Some(file) => vec![file],
+            None => vec![
+                dir.join(format!("{}.rs", name)),
+                dir.join(&name).join("mod.rs"),
+            ],
+        };
+        match candidates.into_iter().find(|x| x.is_file()) {
+            Some(file) => {
+                // Modules in `foo/mod.rs` have their children alongside them, in `foo.rs` the
+                // children are in `foo/`
+                let child_dir = if file.file_name() == Some("mod.rs".as_ref()) {
+                    file.parent().map(Path::to_path_buf).unwrap_or_default()
+                } else {
+                    dir.join(&name)
+                };
+                if let Err(e) = self.add_file(&file, &child_dir, path) {
+                    debug!("Unable to read module {}: {}", file.display(), e);
+                }
+            }
+            None => debug!("Couldn't find the file for module {}", name),
+        }
+    }
+
+    /// Follows a path used in module `from` to the definition it refers to
+    fn resolve(&self, from: &[String], segments: &[String], depth: usize) -> Option<Vec<String>> {
+        if depth > 16 || segments.is_empty() {
+            return None;
+        }
+        let mut current = from.to_vec();
+        let mut rest = segments;
+        match segments[0].as_str() {
+            "crate" => {
+                current.clear();
+                rest = &segments[1..];
+            }
+            "self" => rest = &segments[1..],
+            "super" => {
+                while rest.first().map(|x| x == "super").unwrap_or(false) {
+                    current.pop()?;
+                    rest = &rest[1..];
+                }
+            }
+            first => {
+                let in_scope = self
+                    .modules
+                    .get(from)
+                    .map(|m| m.defs.contains_key(first) || m.uses.iter().any(|u| u.name == first))
+                    .unwrap_or(false);
+                if !in_scope {
+                    current.clear();
+                }
+            }
+        }
+        for segment in rest {
+            let module = self.modules.get(&current)?;
+            if module.defs.contains_key(segment) {
+                current.push(segment.clone());
+            } else {
+                let entry = module.uses.iter().find(|u| !u.glob && &u.name == segment)?;
+                current = self.resolve(&current, &entry.path, depth + 1)?;
+            }
+        }
+        Some(current)
+    }
+
+    fn resolve_reachability(&mut self) {
+        let mut public_paths = HashMap::new();
+        public_paths.insert(vec![], vec![]);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            let reachable_modules = public_paths
+                .iter()
+                .filter(|(def, _)| self.modules.contains_key(*def))
+                .map(|(def, public)| (def.clone(), public.clone()))
+                .collect::<Vec<(Vec<String>, Vec<String>)>>();
+            for (def, public) in reachable_modules {
+                for (name, def, is_public) in self.exports(&def) {
+                    if is_public {
+                        let mut path = public.clone();
+                        path.push(name);
+                        changed |= update_shortest(&mut public_paths, def, path);
+                    }
+                }
+            }
+        }
+        self.public_paths = public_paths;
+    }
+
+    /// Everything a module makes available under its own path as (name, definition, public)
+    fn exports(&self, module_path: &[String]) -> Vec<(String, Vec<String>, bool)> {
+        let module = match self.modules.get(module_path) {
+            Some(m) => m,
+            None => return vec![],
+        };
+        let mut result = vec![];
+        for (name, is_public) in &module.defs {
+            let mut def = module_path.to_vec();
+            def.push(name.clone());
+            result.push((name.clone(), def, *is_public));
+        }
+        for entry in &module.uses {
+            if entry.glob {
+                if let Some(target) = self.resolve(module_path, &entry.path, 0) {
+                    if let Some(glob_module) = self.modules.get(&target) {
+                        for (name, is_public) in &glob_module.defs {
+                            let mut def = target.clone();
+                            def.push(name.clone());
+                            result.push((name.clone(), def, entry.public && *is_public));
+                        }
+                    }
+                }
+            } else if let Some(def) = self.resolve(module_path, &entry.path, 0) {
+                result.push((entry.name.clone(), def, entry.public));
+            }
+        }
+        result
+    }
+
+    /// Module path of a source file if it's part of the crate
+    pub fn module_path(&self, file: &Path) -> Option<&[String]> {
+        self.files.get(file).map(|x| x.as_slice())
+    }
+
+    /// Files which make up the crate
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.keys().map(|x| x.as_path())
+    }
+
+    /// The shortest public path for an item, given the module it's defined in and its name
+    pub fn public_path(&self, module: &[String], name: &str) -> Option<String> {
+        let mut def = module.to_vec();
+        def.push(name.to_string());
+        self.public_paths.get(&def).map(|x| x.join("::"))
+    }
+
+    /// The shortest public path for a type used in the given module, this is a guess based on
+    /// types defined in the module or brought in with a `use`, then any type in the crate with
+    /// the same name
+    pub fn public_type_path(&self, module: &[String], name: &str) -> Option<String> {
+        if let Some(def) = self.resolve(module, &[name.to_string()], 0) {
+            if let Some(path) = self.public_paths.get(&def) {
+                return Some(path.join("::"));
+            }
+        }
+        let mut candidates = self
+            .public_paths
+            .iter()
+            .filter(|(def, _)| def.last().map(|x| x == name).unwrap_or(false));
+        match (candidates.next(), candidates.next()) {
+            (Some((_, path)), None) => Some(path.join("::")),
+            _ => None,
+        }
+    }
+
+    /// Public items which are defined in the crate but can't be named from outside of it
+    pub fn unreachable(&self) -> Vec<String> {
+        let mut result = vec![];
+        for (path, module) in &self.modules {
+            for (name, _) in module.defs.iter().filter(|(_, is_public)| **is_public) {
+                let mut def = path.clone();
+                def.push(name.clone());
+                if !self.public_paths.contains_key(&def) {
+                    result.push(def.join("::"));
+                }
+            }
+        }
+        result.sort();
+        result
+    }
+}
+
+/// Keeps the path with the fewest segments, breaking ties alphabetically so the result doesn't
+/// depend on hashmap ordering. Returns true if the map changed.
+fn update_shortest(
+    paths: &mut HashMap<Vec<String>, Vec<String>>,
+    def: Vec<String>,
+    path: Vec<String>,
+) -> bool {
+    match paths.get(&def) {
+        Some(existing)
+            if (existing.len(), existing.join("::")) <= (path.len(), path.join("::")) =>
+        {
+            false
+        }
+        _ => {
+            paths.insert(def, path);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast_walker::{AstWalker, Options};
+    use std::sync::Arc;
+
+    fn fixture_root() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/surface")
+    }
+
+    fn path(s: &str) -> Vec<String> {
+        s.split("::").map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn shortest_public_paths() {
+        let surface = Surface::new(&fixture_root().join("src/lib.rs")).unwrap();
+
+        assert_eq!(
+            surface.module_path(&fixture_root().join("src/deep/nested.rs")),
+            Some(path("deep::nested").as_slice())
+        );
+        assert_eq!(
+            surface.public_path(&path("deep::nested"), "Widget"),
+            Some("Widget".to_string())
+        );
+        assert_eq!(
+            surface.public_path(&path("deep::nested"), "make_widget"),
+            Some("prelude::widget".to_string())
+        );
+        assert_eq!(
+            surface.public_path(&path("deep"), "Config"),
+            Some("prelude::Config".to_string())
+        );
+        assert_eq!(
+            surface.public_path(&path("deep::extra"), "Gadget"),
+            Some("prelude::Gadget".to_string())
+        );
+        assert_eq!(surface.public_path(&path("deep"), "hidden"), None);
+        assert_eq!(surface.public_path(&[], "private_helper"), None);
+        assert_eq!(
+            surface.unreachable(),
+            &["deep::extra", "deep::hidden", "deep::nested"]
+        );
+    }
+
+    #[test]
+    fn without_module_files() {
+        let surface = Surface::without_module_files(&fixture_root().join("src/lib.rs")).unwrap();
+        let files = surface.files().map(Path::to_path_buf).collect::<Vec<_>>();
+        assert_eq!(files, [fixture_root().join("src/lib.rs")]);
+        assert_eq!(
+            surface.module_path(&fixture_root().join("src/prelude.rs")),
+            None
+        );
+    }
+
+    #[test]
+    fn only_reachable_items_checked() {
+        let root = fixture_root();
+        let surface = Arc::new(Surface::new(&root.join("src/lib.rs")).unwrap());
+        let mut files = surface.files().map(Path::to_path_buf).collect::<Vec<_>>();
+        files.sort();
+
+        let mut idents = vec![];
+        for file in &files {
+            let options = Options {
+                surface: Some(surface.clone()),
+                ..Default::default()
+            };
+            let walker = AstWalker::new(file.clone()).unwrap().with_options(options);
+            idents.extend(walker.process().iter().map(|x| x.ident().to_string()));
+        }
+        idents.sort();
+        assert_eq!(
+            idents,
+            &[
+                "Widget::new",
+                "api::visible",
+                "prelude::Config::load",
+                "prelude::Gadget::spin",
+                "prelude::widget"
+            ]
+        );
+    }
+}
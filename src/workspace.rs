@@ -0,0 +1,171 @@
+//! Reports for each member of a workspace, so each crate's CI can fetch only its own, and an index
+//! of them with the totals for the whole workspace.
+use crate::render::OutputFormat;
+use crate::report::Summary;
+use cargo_toml::Manifest;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A package listed in the `members` of a workspace
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Member {
+    pub name: String,
+    /// The directory with the package's `Cargo.toml`
+    pub root: PathBuf,
+}
+
+/// The contents of `index.json`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Index {
+    pub packages: Vec<PackageSummary>,
+    /// The sum of the package summaries
+    pub totals: Summary,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageSummary {
+    pub name: String,
+    /// The package's directory relative to the workspace root
+    pub path: PathBuf,
+    /// The package's report relative to the output directory
+    pub report: PathBuf,
+    #[serde(flatten)]
+    pub summary: Summary,
+}
+
+impl Index {
+    pub fn new(packages: Vec<PackageSummary>) -> Self {
+        let totals = packages
+            .iter()
+            .fold(Summary::default(), |totals, package| Summary {
+                findings: totals.findings + package.summary.findings,
+                failures: totals.failures + package.summary.failures,
+                files_checked: totals.files_checked + package.summary.files_checked,
+                items_checked: totals.items_checked + package.summary.items_checked,
+                warnings: totals.warnings + package.summary.warnings,
+                release_blockers: totals.release_blockers + package.summary.release_blockers,
+            });
+        Self { packages, totals }
+    }
+}
+
+/// The members of the workspace defined in `manifest`, with their globs expanded and in the order
+/// they're listed. Empty if it doesn't define a workspace.
+pub fn members(manifest: &Path) -> io::Result<Vec<Member>> {
+    let workspace = match read_manifest(manifest)?.workspace {
+        Some(workspace) => workspace,
+        None => return Ok(vec![]),
+    };
+    let root = manifest.parent().unwrap_or_else(|| Path::new(""));
+    let excluded = workspace
+        .exclude
+        .iter()
+        .map(|x| root.join(x))
+        .collect::<Vec<_>>();
+    let mut members: Vec<Member> = vec![];
+    for pattern in &workspace.members {
+        let pattern = root.join(pattern);
+        let paths = glob::glob(&pattern.to_string_lossy())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut dirs = paths
+            .filter_map(Result::ok)
+            .filter(|x| x.join("Cargo.toml").is_file() && !excluded.contains(x))
+            .collect::<Vec<_>>();
+        dirs.sort();
+        for dir in dirs {
+            if members.iter().any(|x| x.root == dir) {
+                continue;
+            }
+            let package = read_manifest(&dir.join("Cargo.toml"))?.package;
+            if let Some(package) = package {
+                members.push(Member {
+                    name: package.name,
+                    root: dir,
+                });
+            }
+        }
+    }
+    Ok(members)
+}
+
+fn read_manifest(path: &Path) -> io::Result<Manifest> {
+    Manifest::from_slice(&fs::read(path)?).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{}: {}", path.display(), e),
+        )
+    })
+}
+
+/// The extension of the package reports in `format`, only formats which make sense as a file
+/// each are supported
+pub fn extension(format: OutputFormat) -> io::Result<&'static str> {
+    match format {
+        OutputFormat::Json => Ok("json"),
+        OutputFormat::Sarif => Ok("sarif"),
+        OutputFormat::Markdown => Ok("md"),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--output-dir only supports the json, sarif and markdown formats",
+        )),
+    }
+}
+
+/// The name of a package's report in the output directory. Anything but letters, digits, `-` and
+/// `_` is replaced so a name can't point outside the directory or clash with `index.json`.
+pub fn report_name(package: &str, extension: &str) -> String {
+    let name = package
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    let name = if name == "index" || name.is_empty() {
+        format!("{}_", name)
+    } else {
+        name
+    };
+    format!("{}.{}", name, extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitised_names() {
+        let json = |x| report_name(x, "json");
+        assert_eq!(json("doc_panic_checker"), "doc_panic_checker.json");
+        assert_eq!(json("tokio-util"), "tokio-util.json");
+        assert_eq!(json("../escape"), "___escape.json");
+        assert_eq!(json("index"), "index_.json");
+        assert_eq!(extension(OutputFormat::Markdown).unwrap(), "md");
+        assert!(extension(OutputFormat::Text).is_err());
+    }
+
+    #[test]
+    fn totals() {
+        let package = |name: &str, findings, files_checked| PackageSummary {
+            name: name.to_string(),
+            path: PathBuf::from(name),
+            report: PathBuf::from(format!("{}.json", name)),
+            summary: Summary {
+                findings,
+                failures: findings,
+                files_checked,
+                ..Default::default()
+            },
+        };
+        let index = Index::new(vec![package("a", 2, 3), package("b", 1, 4)]);
+        assert_eq!(index.totals.findings, 3);
+        assert_eq!(index.totals.failures, 3);
+        assert_eq!(index.totals.files_checked, 7);
+        assert_eq!(index.totals.warnings, 0);
+    }
+}
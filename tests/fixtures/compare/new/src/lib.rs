@@ -0,0 +1,21 @@
+pub fn stable(x: Option<u8>) -> u8 {
+    x.unwrap()
+}
+
+/// # Panics
+///
+/// If `x` is `None` or zero
+pub fn documented(x: Option<u8>) -> u8 {
+    if x == Some(0) {
+        panic!("zero");
+    }
+    x.expect("no x")
+}
+
+pub fn fixed(x: Option<u8>) -> u8 {
+    x.unwrap_or_default()
+}
+
+pub fn quiet(x: u8) -> u8 {
+    todo!()
+}
@@ -0,0 +1,18 @@
+pub fn stable(x: Option<u8>) -> u8 {
+    x.unwrap()
+}
+
+/// # Panics
+///
+/// If `x` is `None`
+pub fn documented(x: Option<u8>) -> u8 {
+    x.expect("no x")
+}
+
+pub fn fixed(x: Option<u8>) -> u8 {
+    x.unwrap()
+}
+
+pub fn quiet(x: u8) -> u8 {
+    x
+}
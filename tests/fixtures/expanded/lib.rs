@@ -0,0 +1,99 @@
+#![feature(prelude_import)]
+#[prelude_import]
+use std::prelude::rust_2021::*;
+#[macro_use]
+extern crate std;
+pub mod config {
+    use serde::Deserialize;
+    pub enum Level {
+        Low,
+        High(u8),
+    }
+    #[automatically_derived]
+    impl ::core::clone::Clone for Level {
+        #[inline]
+        fn clone(&self) -> Level {
+            match self {
+                Level::Low => Level::Low,
+                Level::High(__self_0) => Level::High(::core::clone::Clone::clone(__self_0)),
+            }
+        }
+    }
+    #[automatically_derived]
+    impl ::core::cmp::PartialOrd for Level {
+        #[inline]
+        fn partial_cmp(&self, other: &Level) -> ::core::option::Option<::core::cmp::Ordering> {
+            let __self_tag = ::core::intrinsics::discriminant_value(self);
+            let __arg1_tag = ::core::intrinsics::discriminant_value(other);
+            match (self, other) {
+                (Level::High(__self_0), Level::High(__arg1_0)) => {
+                    ::core::cmp::PartialOrd::partial_cmp(__self_0, __arg1_0)
+                }
+                (Level::Low, Level::Low) => ::core::option::Option::Some(::core::cmp::Ordering::Equal),
+                _ if __self_tag != __arg1_tag => {
+                    ::core::cmp::PartialOrd::partial_cmp(&__self_tag, &__arg1_tag)
+                }
+                _ => ::core::panicking::panic("internal error: entered unreachable code"),
+            }
+        }
+    }
+    pub enum Error {
+        Io(std::io::Error),
+    }
+    #[allow(unused_qualifications)]
+    #[automatically_derived]
+    impl std::error::Error for Error {}
+    #[allow(unused_qualifications)]
+    #[automatically_derived]
+    impl ::core::fmt::Display for Error {
+        fn fmt(&self, __formatter: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+            #[allow(unused_variables, deprecated, clippy::used_underscore_binding)]
+            match self {
+                Error::Io(_0) => ::core::fmt::Display::fmt(_0, __formatter),
+                #[allow(unreachable_patterns)]
+                _ => ::core::panicking::panic("internal error: entered unreachable code"),
+            }
+        }
+    }
+    pub struct Config {
+        pub port: u16,
+    }
+    #[doc(hidden)]
+    #[allow(non_upper_case_globals, unused_attributes, unused_qualifications)]
+    const _: () = {
+        #[allow(unused_extern_crates, clippy::useless_attribute)]
+        extern crate serde as _serde;
+        #[automatically_derived]
+        impl<'de> _serde::Deserialize<'de> for Config {
+            fn deserialize<__D>(
+                __deserializer: __D,
+            ) -> _serde::__private::Result<Self, __D::Error>
+            where
+                __D: _serde::Deserializer<'de>,
+            {
+                _serde::Deserializer::deserialize_struct(
+                    __deserializer,
+                    "Config",
+                    FIELDS,
+                    __Visitor {
+                        marker: _serde::__private::PhantomData::<Config>,
+                        lifetime: _serde::__private::PhantomData,
+                    },
+                )
+            }
+        }
+    };
+    impl Config {
+        pub fn port(raw: &str) -> u16 {
+            raw.parse().unwrap()
+        }
+        pub fn check(&self) {
+            if !(self.port != 0) {
+                ::core::panicking::panic("assertion failed: self.port != 0")
+            }
+        }
+    }
+}
+pub fn reload() -> config::Config {
+    ::core::panicking::panic("not yet implemented")
+}
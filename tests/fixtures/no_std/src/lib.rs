@@ -0,0 +1,15 @@
+//! Drives a status LED on a microcontroller.
+#![cfg_attr(not(test), no_std)]
+
+pub struct Led {
+    pin: Option<u8>,
+}
+
+impl Led {
+    pub fn on(&mut self) {
+        let pin = self.pin.expect("LED isn't wired up");
+        set_high(pin);
+    }
+}
+
+fn set_high(_pin: u8) {}
@@ -0,0 +1,5 @@
+//! Parses port numbers.
+
+pub fn port(s: &str) -> u16 {
+    s.parse().unwrap()
+}
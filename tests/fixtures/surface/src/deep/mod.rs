@@ -0,0 +1,23 @@
+pub mod nested;
+
+pub struct Config;
+
+impl Config {
+    pub fn load() -> Self {
+        todo!()
+    }
+}
+
+pub fn hidden() {
+    panic!("public but can't be named outside the crate");
+}
+
+pub mod extra {
+    pub struct Gadget;
+
+    impl Gadget {
+        pub fn spin(&self) {
+            panic!("dizzy");
+        }
+    }
+}
@@ -0,0 +1,11 @@
+pub struct Widget;
+
+impl Widget {
+    pub fn new() -> Self {
+        unimplemented!()
+    }
+}
+
+pub fn make_widget() -> Widget {
+    Some(Widget::new()).unwrap()
+}
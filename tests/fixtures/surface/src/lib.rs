@@ -0,0 +1,14 @@
+pub mod prelude;
+mod deep;
+
+pub use deep::nested::Widget;
+
+pub mod api {
+    pub fn visible() {
+        panic!("reachable as api::visible");
+    }
+}
+
+fn private_helper() {
+    todo!()
+}
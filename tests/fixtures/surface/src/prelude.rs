@@ -0,0 +1,3 @@
+pub use crate::deep::extra::*;
+pub use crate::deep::nested::{make_widget as widget, Widget};
+pub use crate::deep::Config;
@@ -0,0 +1,3 @@
+pub fn first(x: &[u8]) -> u8 {
+    *x.first().unwrap()
+}
@@ -0,0 +1,7 @@
+pub fn parse(s: &str) -> u32 {
+    s.parse().unwrap()
+}
+
+pub fn halve(x: Option<u32>) -> u32 {
+    x.expect("no value") / 2
+}